@@ -0,0 +1,1516 @@
+//! Opt-in localhost HTTP/JSON API for automations (editor plugins, scripts,
+//! launchers). Listens on 127.0.0.1 only and requires `Authorization: Bearer
+//! <token>` on every route except `GET /openapi.json`.
+//!
+//! Routing is kept free of Tauri types: `handle` works on an `AppDatabase` and
+//! reports which entities changed, so the caller can emit `data:changed`.
+
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tiny_http::{Header, Server};
+
+use crate::database::{self, AppDatabase, UndoStep};
+use crate::models::{
+    nullable, Agenda, GamificationRules, NewDebt, NewTask, NewTransaction, Priority,
+    ProjectDeletePolicy, ProjectPatch, Snapshot, Status, TaskBulkPatch, TaskPatch,
+    TemplateInstance, TimeEntryPatch, TimeRollupGroup,
+};
+
+pub const OPENAPI_SPEC: &str = include_str!("openapi.json");
+
+const API_PREFIX: &str = "/api/v1";
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl ApiResponse {
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        ApiResponse {
+            status,
+            body: json!({ "error": message.into() }),
+        }
+    }
+}
+
+/// An entity touched by a request, for the caller's `data:changed` event.
+pub struct ApiChange {
+    pub entity: &'static str,
+    pub action: &'static str,
+    pub id: Option<String>,
+    /// Snapshot from before the change (`None` for additions).
+    pub old: Option<Snapshot>,
+}
+
+struct ApiError(u16, String);
+
+impl From<String> for ApiError {
+    fn from(e: String) -> Self {
+        if e.contains("not found") {
+            ApiError(404, e)
+        } else {
+            ApiError(500, e)
+        }
+    }
+}
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    ApiError(400, message.into())
+}
+
+/// For database calls whose errors are validation failures rather than
+/// internal errors.
+fn rejected(e: String) -> ApiError {
+    if e.contains("not found") {
+        ApiError(404, e)
+    } else {
+        ApiError(400, e)
+    }
+}
+
+/// The tasks from `AppDatabase::blocked_dependents` that are no longer blocked.
+fn unblocked(
+    db: &AppDatabase,
+    waiting: Vec<(String, Option<Snapshot>)>,
+) -> Vec<(String, Option<Snapshot>)> {
+    waiting
+        .into_iter()
+        .filter(|(id, _)| db.get_task(id).is_ok_and(|t| !t.is_blocked))
+        .collect()
+}
+
+fn not_found(what: &str, id: &str) -> ApiError {
+    ApiError(404, format!("{} not found: {}", what, id))
+}
+
+type RouteResult = Result<(u16, Value), ApiError>;
+
+fn ok<T: Serialize>(status: u16, value: T) -> RouteResult {
+    serde_json::to_value(value)
+        .map(|v| (status, v))
+        .map_err(|e| ApiError(500, e.to_string()))
+}
+
+// --- REQUEST BODIES ---
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CreateTask {
+    title: String,
+    project_id: Option<String>,
+    description: Option<String>,
+    priority: Option<Priority>,
+    deadline: Option<i64>,
+    scheduled_at: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    repeat_mode: Option<String>,
+    repeat_days_mask: Option<i64>,
+    section_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BulkUpdate {
+    ids: Vec<String>,
+    patch: TaskBulkPatch,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LinkTask {
+    depends_on_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CreateSubtask {
+    title: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CreateProject {
+    name: String,
+    color: Option<String>,
+    priority: Option<Priority>,
+    parent_id: Option<String>,
+    #[serde(default)]
+    is_folder: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PatchTag {
+    name: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    color: Option<Option<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MergeTags {
+    sources: Vec<String>,
+    target: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DemoteTask {
+    parent_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlanTasks {
+    task_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CarryOverPlan {
+    to: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CreateTimeBlock {
+    task_id: Option<String>,
+    title: Option<String>,
+    start_at: i64,
+    end_at: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MoveTimeBlock {
+    start_at: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResizeTimeBlock {
+    start_at: i64,
+    end_at: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompleteTimeBlock {
+    #[serde(default)]
+    start_focus: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AutoSchedule {
+    days: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CreateTimeEntry {
+    task_id: String,
+    started_at: i64,
+    ended_at: i64,
+    note: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StartTimer {
+    task_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SaveTemplate {
+    name: String,
+    anchor: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReorderProjects {
+    ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MoveCard {
+    task_id: String,
+    column_id: String,
+    position: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SectionName {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReorderSections {
+    ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MoveProject {
+    parent_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CreateTransaction {
+    amount: f64,
+    category: String,
+    date: Option<i64>,
+    description: Option<String>,
+    #[serde(default = "default_true")]
+    is_expense: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CreateDebt {
+    person: String,
+    amount: f64,
+    currency: Option<String>,
+    is_owed_by_me: bool,
+    due_date: Option<i64>,
+    start_date: Option<i64>,
+    payment_day: Option<i32>,
+    initial_amount: Option<f64>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn parse_body<'a, T: Deserialize<'a>>(body: &'a str) -> Result<T, ApiError> {
+    serde_json::from_str(body).map_err(|e| bad_request(format!("Invalid request body: {}", e)))
+}
+
+fn non_empty(value: &str, field: &str) -> Result<String, ApiError> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Err(bad_request(format!("{} must not be empty", field)));
+    }
+    Ok(trimmed.to_string())
+}
+
+fn validate_repeat(mode: Option<&str>, mask: Option<i64>) -> Result<(), ApiError> {
+    database::validate_repeat(mode, mask).map_err(bad_request)
+}
+
+fn validate_amount(amount: f64) -> Result<(), ApiError> {
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err(bad_request("amount must be a positive number"));
+    }
+    Ok(())
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect()
+}
+
+/// `from`, `to` and the other parameters of a day range query.
+type DayRange = (chrono::NaiveDate, chrono::NaiveDate, Vec<(String, String)>);
+
+/// The required `from` and `to` days of a query, and the values of the
+/// `extra` parameters it may also carry.
+fn day_range_query(query: &str, extra: &[&str]) -> Result<DayRange, ApiError> {
+    let mut from = None;
+    let mut to = None;
+    let mut rest = Vec::new();
+    for (key, value) in parse_query(query) {
+        match key.as_str() {
+            "from" => from = Some(database::parse_day(&value).map_err(bad_request)?),
+            "to" => to = Some(database::parse_day(&value).map_err(bad_request)?),
+            other if extra.contains(&other) => rest.push((key, value)),
+            other => return Err(bad_request(format!("Unknown query parameter: {}", other))),
+        }
+    }
+    match (from, to) {
+        (Some(from), Some(to)) => Ok((from, to, rest)),
+        _ => Err(bad_request("from and to are required")),
+    }
+}
+
+/// Percent-decodes a path segment, for names such as tags.
+fn decode_segment(segment: &str) -> Result<String, ApiError> {
+    let invalid = || bad_request(format!("Invalid path segment: {}", segment));
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = tail.get(..2).ok_or_else(invalid)?;
+            let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+// --- ROUTING ---
+
+/// Routes one request. `url` is the request target (path and query string).
+pub fn handle(
+    db: &mut AppDatabase,
+    method: &str,
+    url: &str,
+    body: &str,
+) -> (ApiResponse, Vec<ApiChange>) {
+    let mut changes = Vec::new();
+    let result = if method == "GET" {
+        route(db, method, url, body, &mut changes)
+    } else {
+        // Every write request is one undo step
+        let label = format!("{} {}", method, url.split('?').next().unwrap_or(url));
+        UndoStep::begin(&mut *db, &label)
+            .map_err(ApiError::from)
+            .and_then(|mut db| route(&mut db, method, url, body, &mut changes))
+    };
+    match result {
+        Ok((status, body)) => (ApiResponse { status, body }, changes),
+        Err(ApiError(status, message)) => (ApiResponse::error(status, message), Vec::new()),
+    }
+}
+
+fn route(
+    db: &mut AppDatabase,
+    method: &str,
+    url: &str,
+    body: &str,
+    changes: &mut Vec<ApiChange>,
+) -> RouteResult {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let path = path
+        .strip_prefix(API_PREFIX)
+        .ok_or_else(|| ApiError(404, "Not found".to_string()))?;
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let mut changed =
+        |entity: &'static str, action: &'static str, id: Option<&str>, old: Option<Snapshot>| {
+            changes.push(ApiChange {
+                entity,
+                action,
+                id: id.map(String::from),
+                old,
+            })
+        };
+
+    match (method, segments.as_slice()) {
+        ("GET", ["health"]) => ok(200, json!({ "status": "ok" })),
+
+        // Tasks
+        ("GET", ["tasks"]) => {
+            let mut status = None;
+            let mut project_id = None;
+            let mut tag = None;
+            let mut limit = None;
+            let mut archived = None;
+            for (key, value) in parse_query(query) {
+                match key.as_str() {
+                    "status" => {
+                        status = Some(match value.as_str() {
+                            "todo" => Status::Todo,
+                            "doing" => Status::Doing,
+                            "done" => Status::Done,
+                            _ => return Err(bad_request(format!("Invalid status: {}", value))),
+                        })
+                    }
+                    "project_id" => project_id = Some(value),
+                    "tag" => tag = Some(value),
+                    "limit" => {
+                        limit = Some(
+                            value
+                                .parse::<usize>()
+                                .map_err(|_| bad_request(format!("Invalid limit: {}", value)))?,
+                        )
+                    }
+                    "archived" => {
+                        archived = Some(match value.as_str() {
+                            "true" => true,
+                            "false" => false,
+                            _ => return Err(bad_request(format!("Invalid archived: {}", value))),
+                        })
+                    }
+                    other => {
+                        return Err(bad_request(format!("Unknown query parameter: {}", other)))
+                    }
+                }
+            }
+            let mut tasks = db.get_tasks(None, status, project_id, tag)?;
+            if let Some(a) = archived {
+                tasks.retain(|t| t.is_archived == a);
+            }
+            if let Some(l) = limit {
+                tasks.truncate(l);
+            }
+            ok(200, tasks)
+        }
+        ("POST", ["tasks"]) => {
+            let req: CreateTask = parse_body(body)?;
+            validate_repeat(req.repeat_mode.as_deref(), req.repeat_days_mask)?;
+            let new_task = NewTask {
+                id: uuid::Uuid::new_v4().to_string(),
+                project_id: req.project_id,
+                title: non_empty(&req.title, "title")?,
+                description: req.description,
+                priority: req.priority.unwrap_or(Priority::Normal),
+                status: Status::Todo,
+                created_at: chrono::Utc::now().timestamp_millis(),
+                deadline: req.deadline,
+                estimated_minutes: None,
+                actual_minutes: None,
+                tags: req.tags,
+                remind_at: None,
+                repeat_mode: req.repeat_mode,
+                repeat_days_mask: req.repeat_days_mask,
+                section_id: req.section_id,
+                scheduled_at: req.scheduled_at,
+            };
+            let task = db.add_task(&new_task).map_err(rejected)?;
+            changed("tasks", "add", Some(&task.id), None);
+            changed("stats", "refresh", None, None);
+            ok(201, task)
+        }
+        ("POST", ["tasks", "bulk"]) => {
+            let mut req: BulkUpdate = parse_body(body)?;
+            let mut seen = std::collections::HashSet::new();
+            req.ids.retain(|id| seen.insert(id.clone()));
+            let old: Vec<Option<Snapshot>> = req
+                .ids
+                .iter()
+                .map(|id| db.snapshot("tasks", Some(id)))
+                .collect();
+            let waiting = req
+                .ids
+                .iter()
+                .flat_map(|id| db.blocked_dependents(id))
+                .collect();
+            let result = db
+                .bulk_update_tasks(&req.ids, &req.patch)
+                .map_err(rejected)?;
+            if !result.applied {
+                return ok(409, result);
+            }
+            for (r, old) in result.results.iter().zip(old) {
+                // "status" only where it changed, so completion hooks fire once
+                let action = match (req.patch.status, &old) {
+                    (Some(s), Some(Snapshot::Task(t))) if t.status != s => "status",
+                    _ => "edit",
+                };
+                changed("tasks", action, Some(&r.id), old);
+                if let Some(next_id) = &r.spawned_task_id {
+                    changed("tasks", "add", Some(next_id), None);
+                }
+            }
+            for (dep, dep_old) in unblocked(db, waiting) {
+                changed("tasks", "unblocked", Some(&dep), dep_old);
+            }
+            changed("stats", "refresh", None, None);
+            ok(200, result)
+        }
+        ("GET", ["agenda", agenda]) => {
+            let agenda = Agenda::parse(agenda).map_err(bad_request)?;
+            ok(200, db.get_agenda(agenda)?)
+        }
+        ("GET", ["tasks", id]) => {
+            let mut task = db.get_task(id)?;
+            task.subtasks = db.get_subtasks(id)?;
+            ok(200, task)
+        }
+        ("PATCH", ["tasks", id]) => {
+            let req: TaskPatch = parse_body(body)?;
+            let current = db.get_task(id)?;
+            let old = db.snapshot("tasks", Some(id));
+            let waiting = db.blocked_dependents(id);
+            let (task, spawned) = db.update_task(id, &req).map_err(|e| {
+                if e.starts_with("Revision conflict") {
+                    ApiError(409, e)
+                } else {
+                    rejected(e)
+                }
+            })?;
+            if task.status != current.status {
+                changed("tasks", "status", Some(id), old.clone());
+                for (dep, dep_old) in unblocked(db, waiting) {
+                    changed("tasks", "unblocked", Some(&dep), dep_old);
+                }
+                if let Some(next_id) = &spawned {
+                    changed("tasks", "add", Some(next_id), None);
+                }
+                changed("stats", "refresh", None, None);
+            }
+            changed("tasks", "edit", Some(id), old);
+            ok(200, task)
+        }
+        ("DELETE", ["tasks", id]) => {
+            let old = db.snapshot("tasks", Some(id));
+            if old.is_none() {
+                return Err(not_found("Task", id));
+            }
+            let waiting = db.blocked_dependents(id);
+            db.delete_task(id)?;
+            changed("tasks", "delete", Some(id), old);
+            for (dep, dep_old) in unblocked(db, waiting) {
+                changed("tasks", "unblocked", Some(&dep), dep_old);
+            }
+            changed("stats", "refresh", None, None);
+            ok(204, Value::Null)
+        }
+        ("GET", ["tasks", id, "history"]) => ok(200, db.get_task_history(id)?),
+        ("POST", ["tasks", id, "dependencies"]) => {
+            let req: LinkTask = parse_body(body)?;
+            let old = db.snapshot("tasks", Some(id));
+            db.add_task_dependency(id, &req.depends_on_id)
+                .map_err(rejected)?;
+            changed("tasks", "edit", Some(id), old);
+            ok(200, db.get_task(id)?)
+        }
+        ("DELETE", ["tasks", id, "dependencies", depends_on_id]) => {
+            let old = db
+                .snapshot("tasks", Some(id))
+                .ok_or_else(|| not_found("Task", id))?;
+            db.remove_task_dependency(id, depends_on_id)?;
+            changed("tasks", "edit", Some(id), Some(old));
+            ok(200, db.get_task(id)?)
+        }
+        ("POST", ["tasks", id, "duplicate"]) => {
+            let task = db.duplicate_task(id)?;
+            changed("tasks", "add", Some(&task.id), None);
+            changed("stats", "refresh", None, None);
+            ok(201, task)
+        }
+        ("POST", ["tasks", id, "demote"]) => {
+            let req: DemoteTask = parse_body(body)?;
+            let old = db.snapshot("tasks", Some(id));
+            let parent_old = db.snapshot("tasks", Some(&req.parent_id));
+            let waiting = db.blocked_dependents(id);
+            let subtask = db
+                .demote_task_to_subtask(id, &req.parent_id)
+                .map_err(rejected)?;
+            changed("tasks", "delete", Some(id), old);
+            changed("subtasks", "add", Some(&subtask.id), None);
+            changed("tasks", "edit", Some(&req.parent_id), parent_old);
+            for (dep, dep_old) in unblocked(db, waiting) {
+                changed("tasks", "unblocked", Some(&dep), dep_old);
+            }
+            changed("stats", "refresh", None, None);
+            ok(200, subtask)
+        }
+        ("POST", ["tasks", id, "archive"]) => {
+            let old = db.snapshot("tasks", Some(id));
+            if old.is_none() {
+                return Err(not_found("Task", id));
+            }
+            db.archive_task(id)?;
+            changed("tasks", "archive", Some(id), old);
+            ok(200, db.get_task(id)?)
+        }
+        ("POST", ["tasks", id, "unarchive"]) => {
+            let old = db.snapshot("tasks", Some(id));
+            if old.is_none() {
+                return Err(not_found("Task", id));
+            }
+            db.unarchive_task(id)?;
+            changed("tasks", "unarchive", Some(id), old);
+            ok(200, db.get_task(id)?)
+        }
+
+        // Subtasks
+        ("GET", ["tasks", id, "subtasks"]) => {
+            db.get_task(id)?;
+            ok(200, db.get_subtasks(id)?)
+        }
+        ("POST", ["tasks", id, "subtasks"]) => {
+            let req: CreateSubtask = parse_body(body)?;
+            db.get_task(id)?;
+            let subtask = db.add_subtask(id, &non_empty(&req.title, "title")?)?;
+            changed("subtasks", "add", Some(&subtask.id), None);
+            ok(201, subtask)
+        }
+        ("POST", ["subtasks", id, "toggle"]) => {
+            let old = db
+                .snapshot("subtasks", Some(id))
+                .ok_or_else(|| not_found("Subtask", id))?;
+            let completed = db.toggle_subtask(id)?;
+            changed("subtasks", "toggle", Some(id), Some(old));
+            ok(200, json!({ "id": id, "completed": completed }))
+        }
+        ("POST", ["subtasks", id, "promote"]) => {
+            let old = db
+                .snapshot("subtasks", Some(id))
+                .ok_or_else(|| not_found("Subtask", id))?;
+            let parent_id = db.get_subtask(id)?.task_id;
+            let parent_old = db.snapshot("tasks", Some(&parent_id));
+            let task = db.promote_subtask_to_task(id)?;
+            changed("subtasks", "delete", Some(id), Some(old));
+            changed("tasks", "edit", Some(&parent_id), parent_old);
+            changed("tasks", "add", Some(&task.id), None);
+            if task.status == Status::Done {
+                // Fires task.completed like any other completion
+                changed("tasks", "status", Some(&task.id), None);
+            }
+            changed("stats", "refresh", None, None);
+            ok(201, task)
+        }
+        ("DELETE", ["subtasks", id]) => {
+            let old = db.snapshot("subtasks", Some(id));
+            db.delete_subtask(id)?;
+            changed("subtasks", "delete", Some(id), old);
+            ok(204, Value::Null)
+        }
+
+        // Projects
+        ("GET", ["projects"]) => {
+            let mut archived = false;
+            for (key, value) in parse_query(query) {
+                match (key.as_str(), value.as_str()) {
+                    ("archived", "true") => archived = true,
+                    ("archived", "false") => archived = false,
+                    _ => return Err(bad_request(format!("Invalid query parameter: {}", key))),
+                }
+            }
+            if archived {
+                ok(200, db.get_all_projects()?)
+            } else {
+                ok(200, db.get_projects()?)
+            }
+        }
+        ("POST", ["projects", "reorder"]) => {
+            let req: ReorderProjects = parse_body(body)?;
+            let old = db.snapshots("projects", &req.ids);
+            db.reorder_projects(&req.ids).map_err(rejected)?;
+            changed("projects", "reorder", None, Some(old));
+            ok(200, db.get_projects()?)
+        }
+        ("POST", ["projects"]) => {
+            let req: CreateProject = parse_body(body)?;
+            let id = uuid::Uuid::new_v4().to_string();
+            let project = db.add_project(
+                id.clone(),
+                non_empty(&req.name, "name")?,
+                req.color.unwrap_or_else(|| "#3b82f6".to_string()),
+                req.priority.unwrap_or(Priority::Normal),
+                req.parent_id,
+                req.is_folder,
+            )?;
+            changed("projects", "add", Some(&id), None);
+            changed("stats", "refresh", None, None);
+            ok(201, project)
+        }
+        ("PATCH", ["projects", id]) => {
+            let req: ProjectPatch = parse_body(body)?;
+            let old = db
+                .snapshot("projects", Some(id))
+                .ok_or_else(|| not_found("Project", id))?;
+            let project = db.update_project_details(id, &req).map_err(rejected)?;
+            changed("projects", "edit", Some(id), Some(old));
+            ok(200, project)
+        }
+        ("GET", ["projects", "tree"]) => ok(200, db.get_project_tree()?),
+        ("POST", ["projects", id, "move"]) => {
+            let req: MoveProject = parse_body(body)?;
+            let old = db.snapshot("projects", Some(id));
+            db.move_project(id, req.parent_id.as_deref())
+                .map_err(rejected)?;
+            changed("projects", "move", Some(id), old);
+            ok(200, db.get_project(id)?)
+        }
+        ("POST", ["projects", id, action @ ("archive" | "unarchive")]) => {
+            let before = db.get_all_projects()?;
+            let ids = if *action == "archive" {
+                db.archive_project(id)?
+            } else {
+                db.unarchive_project(id)?
+            };
+            let action = if *action == "archive" {
+                "archive"
+            } else {
+                "unarchive"
+            };
+            for pid in &ids {
+                let old = before
+                    .iter()
+                    .find(|p| p.id == *pid)
+                    .map(|p| Snapshot::Project(p.clone()));
+                changed("projects", action, Some(pid), old);
+            }
+            changed("tasks", "refresh", None, None);
+            changed("stats", "refresh", None, None);
+            ok(200, json!({ "project_ids": ids }))
+        }
+        ("GET", ["projects", id, "sections"]) => {
+            let mut archived = false;
+            for (key, value) in parse_query(query) {
+                match (key.as_str(), value.as_str()) {
+                    ("archived", "true") => archived = true,
+                    ("archived", "false") => archived = false,
+                    _ => return Err(bad_request(format!("Invalid query parameter: {}", key))),
+                }
+            }
+            db.get_project(id)?;
+            ok(200, db.get_sections(id, archived)?)
+        }
+        ("POST", ["projects", id, "sections"]) => {
+            let req: SectionName = parse_body(body)?;
+            let section = db.add_section(id, &req.name).map_err(rejected)?;
+            changed("sections", "add", Some(&section.id), None);
+            ok(201, section)
+        }
+        ("GET", ["projects", id, "boards"]) => ok(200, db.get_boards(id)?),
+        ("GET", ["boards", id]) => ok(200, db.get_board(id)?),
+        ("POST", ["boards", id, "move"]) => {
+            let req: MoveCard = parse_body(body)?;
+            let old_board = db
+                .snapshot("boards", Some(id))
+                .ok_or_else(|| not_found("Board", id))?;
+            let old_task = db.snapshot("tasks", Some(&req.task_id));
+            let waiting = db.blocked_dependents(&req.task_id);
+            let moved = db
+                .move_card(id, &req.task_id, &req.column_id, req.position)
+                .map_err(rejected)?;
+            changed("boards", "move", Some(id), Some(old_board));
+            if moved.status_changed {
+                changed("tasks", "status", Some(&req.task_id), old_task);
+                for (dep, dep_old) in unblocked(db, waiting) {
+                    changed("tasks", "unblocked", Some(&dep), dep_old);
+                }
+                if let Some(next_id) = &moved.spawned_task_id {
+                    changed("tasks", "add", Some(next_id), None);
+                }
+                changed("stats", "refresh", None, None);
+            }
+            ok(200, moved.board)
+        }
+        ("POST", ["sections", "reorder"]) => {
+            let req: ReorderSections = parse_body(body)?;
+            let old = db.snapshots("sections", &req.ids);
+            db.reorder_sections(&req.ids).map_err(rejected)?;
+            changed("sections", "reorder", None, Some(old));
+            ok(200, Value::Null)
+        }
+        ("PATCH", ["sections", id]) => {
+            let req: SectionName = parse_body(body)?;
+            let old = db
+                .snapshot("sections", Some(id))
+                .ok_or_else(|| not_found("Section", id))?;
+            let section = db.rename_section(id, &req.name).map_err(rejected)?;
+            changed("sections", "edit", Some(id), Some(old));
+            ok(200, section)
+        }
+        ("POST", ["sections", id, action @ ("archive" | "unarchive")]) => {
+            let old = db
+                .snapshot("sections", Some(id))
+                .ok_or_else(|| not_found("Section", id))?;
+            let (task_ids, action) = if *action == "archive" {
+                (db.archive_section(id)?, "archive")
+            } else {
+                (db.unarchive_section(id)?, "unarchive")
+            };
+            changed("sections", action, Some(id), Some(old));
+            changed("tasks", "refresh", None, None);
+            ok(200, json!({ "task_ids": task_ids }))
+        }
+        ("DELETE", ["projects", id]) => {
+            let mut policy = ProjectDeletePolicy::Reparent;
+            for (key, value) in parse_query(query) {
+                match key.as_str() {
+                    "policy" => policy = ProjectDeletePolicy::parse(&value).map_err(bad_request)?,
+                    other => {
+                        return Err(bad_request(format!("Unknown query parameter: {}", other)))
+                    }
+                }
+            }
+            let before = db.get_all_projects()?;
+            let summary = db.delete_project(id, policy)?;
+            let old = |pid: &str| {
+                before
+                    .iter()
+                    .find(|p| p.id == pid)
+                    .map(|p| Snapshot::Project(p.clone()))
+            };
+            for pid in &summary.deleted_project_ids {
+                changed("projects", "delete", Some(pid), old(pid));
+            }
+            for pid in &summary.reparented_project_ids {
+                changed("projects", "move", Some(pid), old(pid));
+            }
+            changed("tasks", "refresh", None, None);
+            changed("stats", "refresh", None, None);
+            ok(200, summary)
+        }
+
+        // Finance
+        ("GET", ["finance"]) => {
+            let (transactions, debts) = db.get_finance_summary()?;
+            ok(200, json!({ "transactions": transactions, "debts": debts }))
+        }
+        ("POST", ["transactions"]) => {
+            let req: CreateTransaction = parse_body(body)?;
+            validate_amount(req.amount)?;
+            let id = uuid::Uuid::new_v4().to_string();
+            let created = db.add_transaction(NewTransaction {
+                id: id.clone(),
+                amount: req.amount,
+                category: non_empty(&req.category, "category")?,
+                date: req
+                    .date
+                    .unwrap_or_else(|| chrono::Utc::now().timestamp_millis()),
+                description: req.description,
+                is_expense: req.is_expense,
+            })?;
+            changed("finance", "add_transaction", Some(&id), None);
+            ok(201, created)
+        }
+        ("DELETE", ["transactions", id]) => {
+            let old = db.snapshot("finance", Some(id));
+            db.delete_transaction(id)?;
+            changed("finance", "delete_transaction", Some(id), old);
+            ok(204, Value::Null)
+        }
+        ("POST", ["debts"]) => {
+            let req: CreateDebt = parse_body(body)?;
+            validate_amount(req.amount)?;
+            if let Some(day) = req.payment_day {
+                if !(1..=31).contains(&day) {
+                    return Err(bad_request("payment_day must be between 1 and 31"));
+                }
+            }
+            let id = uuid::Uuid::new_v4().to_string();
+            let created = db.add_debt(NewDebt {
+                id: id.clone(),
+                person: non_empty(&req.person, "person")?,
+                amount: req.amount,
+                currency: req.currency.unwrap_or_else(|| "USD".to_string()),
+                is_owed_by_me: req.is_owed_by_me,
+                created_at: chrono::Utc::now().timestamp_millis(),
+                due_date: req.due_date,
+                start_date: req.start_date,
+                payment_day: req.payment_day,
+                initial_amount: req.initial_amount,
+            })?;
+            changed("finance", "add_debt", Some(&id), None);
+            ok(201, created)
+        }
+        ("POST", ["debts", id, "pay"]) => {
+            let old = db
+                .snapshot("finance", Some(id))
+                .ok_or_else(|| not_found("Debt", id))?;
+            db.pay_debt(id)?;
+            changed("finance", "pay_debt", Some(id), Some(old));
+            ok(200, json!({ "id": id, "status": "paid" }))
+        }
+        ("DELETE", ["debts", id]) => {
+            let old = db.snapshot("finance", Some(id));
+            db.delete_debt(id)?;
+            changed("finance", "delete_debt", Some(id), old);
+            ok(204, Value::Null)
+        }
+
+        // Trash
+        ("GET", ["tags"]) => ok(200, db.get_tags()?),
+        ("POST", ["tags", "merge"]) => {
+            let req: MergeTags = parse_body(body)?;
+            let tag = db.merge_tags(&req.sources, &req.target).map_err(rejected)?;
+            changed("tags", "refresh", None, None);
+            changed("tasks", "refresh", None, None);
+            ok(200, tag)
+        }
+        ("PATCH", ["tags", name]) => {
+            let req: PatchTag = parse_body(body)?;
+            let mut name = decode_segment(name)?;
+            if let Some(new_name) = &req.name {
+                name = db.rename_tag(&name, new_name).map_err(rejected)?.name;
+                changed("tasks", "refresh", None, None);
+            }
+            let tag = match req.color {
+                Some(color) => db.set_tag_color(&name, color).map_err(rejected)?,
+                None => db.get_tag(&name)?,
+            };
+            changed("tags", "refresh", None, None);
+            ok(200, tag)
+        }
+        ("DELETE", ["tags", name]) => {
+            db.delete_tag(&decode_segment(name)?)?;
+            changed("tags", "refresh", None, None);
+            changed("tasks", "refresh", None, None);
+            ok(204, Value::Null)
+        }
+        ("GET", ["templates"]) => ok(200, db.get_templates()?),
+        ("POST", [kind @ ("tasks" | "projects"), id, "template"]) => {
+            let req: SaveTemplate = parse_body(body)?;
+            let template = if *kind == "tasks" {
+                db.save_task_as_template(id, &req.name, req.anchor)
+            } else {
+                db.save_project_as_template(id, &req.name, req.anchor)
+            }
+            .map_err(rejected)?;
+            changed("templates", "add", Some(&template.id), None);
+            ok(201, template)
+        }
+        ("POST", ["templates", id, "instantiate"]) => {
+            let req: TemplateInstance = parse_body(body)?;
+            let created = db.instantiate_template(id, &req).map_err(rejected)?;
+            if let Some(project_id) = &created.project_id {
+                changed("projects", "add", Some(project_id), None);
+                changed("sections", "refresh", None, None);
+            }
+            for task_id in &created.task_ids {
+                changed("tasks", "add", Some(task_id), None);
+            }
+            changed("stats", "refresh", None, None);
+            ok(201, created)
+        }
+        ("DELETE", ["templates", id]) => {
+            let old = db.snapshot("templates", Some(id));
+            db.delete_template(id)?;
+            changed("templates", "delete", Some(id), old);
+            ok(204, Value::Null)
+        }
+        ("GET", ["trash"]) => ok(200, db.get_trash()?),
+        ("POST", ["trash", entity, id, "restore"]) => {
+            let restored = db.restore_from_trash(entity, id).map_err(rejected)?;
+            for item in &restored {
+                let entity = match item.entity.as_str() {
+                    "task" => "tasks",
+                    "subtask" => "subtasks",
+                    "project" => "projects",
+                    _ => "finance",
+                };
+                changed(entity, "restore", Some(&item.id), None);
+            }
+            changed("trash", "refresh", None, None);
+            changed("stats", "refresh", None, None);
+            ok(200, restored)
+        }
+        ("DELETE", ["trash", entity, id]) => {
+            db.purge_trash_item(entity, id).map_err(rejected)?;
+            changed("trash", "refresh", None, None);
+            ok(204, Value::Null)
+        }
+        ("DELETE", ["trash"]) => {
+            let purged = db.empty_trash()?;
+            changed("trash", "refresh", None, None);
+            ok(200, json!({ "purged": purged }))
+        }
+
+        // Daily plan
+        ("GET", ["plan", date]) => {
+            let date = database::parse_day(date).map_err(bad_request)?;
+            ok(200, db.get_daily_plan(date)?)
+        }
+        ("POST", ["plan", date, "tasks"]) => {
+            let day = database::parse_day(date).map_err(bad_request)?;
+            let req: PlanTasks = parse_body(body)?;
+            let old = db.snapshot("daily_plan", Some(date));
+            db.add_to_daily_plan(day, &req.task_ids).map_err(rejected)?;
+            changed("daily_plan", "edit", Some(date), old);
+            ok(200, db.get_daily_plan(day)?)
+        }
+        ("DELETE", ["plan", date, "tasks", task_id]) => {
+            let day = database::parse_day(date).map_err(bad_request)?;
+            let old = db.snapshot("daily_plan", Some(date));
+            db.remove_from_daily_plan(day, task_id).map_err(rejected)?;
+            changed("daily_plan", "edit", Some(date), old);
+            ok(204, Value::Null)
+        }
+        ("POST", ["plan", date, "reorder"]) => {
+            let day = database::parse_day(date).map_err(bad_request)?;
+            let req: PlanTasks = parse_body(body)?;
+            let old = db.snapshot("daily_plan", Some(date));
+            db.reorder_daily_plan(day, &req.task_ids)
+                .map_err(rejected)?;
+            changed("daily_plan", "reorder", Some(date), old);
+            ok(200, db.get_daily_plan(day)?)
+        }
+        ("POST", ["plan", date, "carry"]) => {
+            let from = database::parse_day(date).map_err(bad_request)?;
+            let req: CarryOverPlan = parse_body(body)?;
+            let to = match &req.to {
+                Some(to) => database::parse_day(to).map_err(bad_request)?,
+                None => from + chrono::Duration::days(1),
+            };
+            let to_key = to.format("%Y-%m-%d").to_string();
+            let old_from = db.snapshot("daily_plan", Some(date));
+            let old_to = db.snapshot("daily_plan", Some(&to_key));
+            let task_ids = db.carry_over_daily_plan(from, to).map_err(bad_request)?;
+            changed("daily_plan", "edit", Some(date), old_from);
+            changed("daily_plan", "edit", Some(&to_key), old_to);
+            ok(200, json!({ "to": to_key, "task_ids": task_ids }))
+        }
+
+        // Time blocks
+        ("GET", ["time-blocks"]) => {
+            let mut from = None;
+            let mut to = None;
+            for (key, value) in parse_query(query) {
+                let ms = value
+                    .parse::<i64>()
+                    .map_err(|_| bad_request(format!("Invalid {}: {}", key, value)))?;
+                match key.as_str() {
+                    "from" => from = Some(ms),
+                    "to" => to = Some(ms),
+                    other => {
+                        return Err(bad_request(format!("Unknown query parameter: {}", other)))
+                    }
+                }
+            }
+            let (Some(from), Some(to)) = (from, to) else {
+                return Err(bad_request("from and to are required"));
+            };
+            ok(200, db.get_time_blocks(from, to)?)
+        }
+        ("POST", ["time-blocks"]) => {
+            let req: CreateTimeBlock = parse_body(body)?;
+            let block = db
+                .add_time_block(
+                    req.task_id.as_deref(),
+                    req.title.as_deref(),
+                    req.start_at,
+                    req.end_at,
+                )
+                .map_err(rejected)?;
+            changed("time_blocks", "add", Some(&block.id), None);
+            ok(201, block)
+        }
+        ("POST", ["time-blocks", "auto-schedule"]) => {
+            let req: AutoSchedule = parse_body(body)?;
+            let now = chrono::Utc::now().timestamp_millis();
+            let result = db
+                .auto_schedule(now, req.days.unwrap_or(7))
+                .map_err(bad_request)?;
+            if !result.blocks.is_empty() {
+                changed("time_blocks", "refresh", None, None);
+            }
+            ok(200, result)
+        }
+        ("POST", ["time-blocks", id, "move"]) => {
+            let req: MoveTimeBlock = parse_body(body)?;
+            let old = db.snapshot("time_blocks", Some(id));
+            let block = db.move_time_block(id, req.start_at).map_err(rejected)?;
+            changed("time_blocks", "edit", Some(id), old);
+            ok(200, block)
+        }
+        ("POST", ["time-blocks", id, "resize"]) => {
+            let req: ResizeTimeBlock = parse_body(body)?;
+            let old = db.snapshot("time_blocks", Some(id));
+            let block = db
+                .resize_time_block(id, req.start_at, req.end_at)
+                .map_err(rejected)?;
+            changed("time_blocks", "edit", Some(id), old);
+            ok(200, block)
+        }
+        ("POST", ["time-blocks", id, "complete"]) => {
+            let req: CompleteTimeBlock = parse_body(body)?;
+            let old = db.snapshot("time_blocks", Some(id));
+            let completion = db
+                .complete_time_block(id, req.start_focus)
+                .map_err(rejected)?;
+            changed("time_blocks", "edit", Some(id), old);
+            ok(200, completion)
+        }
+        ("DELETE", ["time-blocks", id]) => {
+            let old = db.snapshot("time_blocks", Some(id));
+            db.delete_time_block(id)?;
+            changed("time_blocks", "delete", Some(id), old);
+            ok(204, Value::Null)
+        }
+
+        // Time tracking
+        ("GET", ["time-entries"]) => {
+            let (from, to, _) = day_range_query(query, &[])?;
+            ok(200, db.get_time_entries(from, to).map_err(bad_request)?)
+        }
+        ("POST", ["time-entries"]) => {
+            let req: CreateTimeEntry = parse_body(body)?;
+            let entry = db
+                .add_time_entry(
+                    &req.task_id,
+                    req.started_at,
+                    req.ended_at,
+                    req.note.as_deref(),
+                )
+                .map_err(rejected)?;
+            changed("time_entries", "add", Some(&entry.id), None);
+            ok(201, entry)
+        }
+        ("PATCH", ["time-entries", id]) => {
+            let req: TimeEntryPatch = parse_body(body)?;
+            let old = db.snapshot("time_entries", Some(id));
+            let entry = db.update_time_entry(id, &req).map_err(rejected)?;
+            changed("time_entries", "edit", Some(id), old);
+            ok(200, entry)
+        }
+        ("DELETE", ["time-entries", id]) => {
+            let old = db.snapshot("time_entries", Some(id));
+            db.delete_time_entry(id)?;
+            changed("time_entries", "delete", Some(id), old);
+            ok(204, Value::Null)
+        }
+        ("GET", ["timer"]) => ok(200, db.get_running_timer()?),
+        ("POST", ["timer", "start"]) => {
+            let req: StartTimer = parse_body(body)?;
+            let now = chrono::Utc::now().timestamp_millis();
+            let entry = db.start_timer(&req.task_id, now).map_err(|e| {
+                if e.starts_with("A timer is already running") {
+                    ApiError(409, e)
+                } else {
+                    rejected(e)
+                }
+            })?;
+            changed("time_entries", "add", Some(&entry.id), None);
+            ok(201, entry)
+        }
+        ("POST", ["timer", "stop"]) => {
+            let old = db.get_running_timer()?.map(Snapshot::TimeEntry);
+            let now = chrono::Utc::now().timestamp_millis();
+            let entry = db.stop_timer(now).map_err(|e| ApiError(409, e))?;
+            changed("time_entries", "edit", Some(&entry.id), old);
+            ok(200, entry)
+        }
+        ("GET", ["time-entries", "rollup"]) => {
+            let (from, to, rest) = day_range_query(query, &["by"])?;
+            let group = match rest.first() {
+                Some((_, by)) => TimeRollupGroup::parse(by).map_err(bad_request)?,
+                None => TimeRollupGroup::Task,
+            };
+            ok(
+                200,
+                db.get_time_rollup(from, to, group).map_err(bad_request)?,
+            )
+        }
+        ("GET", ["timesheet"]) => {
+            let (from, to, _) = day_range_query(query, &[])?;
+            ok(200, db.get_timesheet(from, to).map_err(bad_request)?)
+        }
+
+        // Stats
+        ("GET", ["stats"]) => ok(200, db.get_stats()?),
+        ("GET", ["stats", "estimates"]) => ok(200, db.get_estimate_accuracy()?),
+        ("GET", ["stats", "estimates", "correction"]) => {
+            let mut project_id = None;
+            for (key, value) in parse_query(query) {
+                match key.as_str() {
+                    "project_id" => project_id = Some(value),
+                    other => {
+                        return Err(bad_request(format!("Unknown query parameter: {}", other)))
+                    }
+                }
+            }
+            ok(200, db.get_estimate_correction(project_id.as_deref())?)
+        }
+
+        // Streaks
+        ("GET", ["streak"]) => ok(200, db.get_streak()?),
+        ("GET", ["activity"]) => {
+            let (from, to, _) = day_range_query(query, &[])?;
+            ok(200, db.get_daily_activity(from, to).map_err(bad_request)?)
+        }
+        ("POST", ["streak", "freezes", day]) => {
+            let date = database::parse_day(day).map_err(bad_request)?;
+            let old = db.snapshot("daily_activity", Some(day));
+            let activity = db.freeze_streak_day(date).map_err(|e| {
+                if e.starts_with("No streak freezes left") {
+                    ApiError(409, e)
+                } else {
+                    ApiError(400, e)
+                }
+            })?;
+            changed("daily_activity", "freeze", Some(day), old);
+            ok(201, activity)
+        }
+        ("DELETE", ["streak", "freezes", day]) => {
+            let date = database::parse_day(day).map_err(bad_request)?;
+            let old = db.snapshot("daily_activity", Some(day));
+            db.unfreeze_streak_day(date)?;
+            changed("daily_activity", "unfreeze", Some(day), old);
+            ok(204, Value::Null)
+        }
+
+        // Gamification
+        ("GET", ["gamification"]) => ok(200, db.get_gamification()?),
+        ("GET", ["gamification", "ledger"]) => {
+            let mut limit = 100;
+            for (key, value) in parse_query(query) {
+                match key.as_str() {
+                    "limit" => {
+                        limit = value
+                            .parse::<u32>()
+                            .map_err(|_| bad_request(format!("Invalid limit: {}", value)))?
+                    }
+                    other => {
+                        return Err(bad_request(format!("Unknown query parameter: {}", other)))
+                    }
+                }
+            }
+            ok(200, db.get_points_ledger(limit)?)
+        }
+        ("GET", ["gamification", "rules"]) => ok(200, db.get_gamification_rules()?),
+        ("PUT", ["gamification", "rules"]) => {
+            let rules: GamificationRules = parse_body(body)?;
+            let old = db.snapshot("gamification_rules", None);
+            db.save_gamification_rules(&rules).map_err(bad_request)?;
+            changed("gamification_rules", "edit", None, old);
+            ok(200, db.get_gamification_rules()?)
+        }
+
+        _ => Err(ApiError(404, format!("No route for {} {}", method, path))),
+    }
+}
+
+// --- SERVER ---
+
+pub struct ApiServer {
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+    pub addr: SocketAddr,
+}
+
+impl ApiServer {
+    pub fn stop(mut self) {
+        self.server.unblock();
+        if let Some(t) = self.thread.take() {
+            let _ = t.join();
+        }
+    }
+}
+
+fn is_authorized(headers: &[Header], token: &str) -> bool {
+    let presented = headers
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "));
+    match presented {
+        // Compare without short-circuiting on the first differing byte
+        Some(p) if p.len() == token.len() => {
+            p.bytes()
+                .zip(token.bytes())
+                .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+                == 0
+        }
+        _ => false,
+    }
+}
+
+fn serve<F>(request: &mut tiny_http::Request, token: &str, handler: &F) -> ApiResponse
+where
+    F: Fn(&str, &str, &str) -> ApiResponse,
+{
+    let method = request.method().as_str().to_uppercase();
+    let url = request.url().to_string();
+
+    if method == "GET" && url.split('?').next() == Some("/openapi.json") {
+        return ApiResponse {
+            status: 200,
+            body: serde_json::from_str(OPENAPI_SPEC).unwrap_or(Value::Null),
+        };
+    }
+    if token.is_empty() || !is_authorized(request.headers(), token) {
+        return ApiResponse::error(401, "Missing or invalid bearer token");
+    }
+
+    let mut body = String::new();
+    if request
+        .as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_string(&mut body)
+        .is_err()
+    {
+        return ApiResponse::error(400, "Request body must be UTF-8");
+    }
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return ApiResponse::error(413, "Request body too large");
+    }
+
+    handler(&method, &url, &body)
+}
+
+/// Binds 127.0.0.1:`port` (0 picks a free port) and serves requests on a
+/// background thread until `ApiServer::stop` is called.
+pub fn start<F>(port: u16, token: String, handler: F) -> Result<ApiServer, String>
+where
+    F: Fn(&str, &str, &str) -> ApiResponse + Send + 'static,
+{
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|e| format!("Failed to start API server on port {}: {}", port, e))?;
+    let addr = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| "API server is not listening on TCP".to_string())?;
+    let server = Arc::new(server);
+    let worker = server.clone();
+
+    let thread = std::thread::spawn(move || {
+        for mut request in worker.incoming_requests() {
+            let response = serve(&mut request, &token, &handler);
+            let json_header =
+                Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+            let body = if response.status == 204 {
+                String::new()
+            } else {
+                response.body.to_string()
+            };
+            let _ = request.respond(
+                tiny_http::Response::from_string(body)
+                    .with_status_code(response.status)
+                    .with_header(json_header),
+            );
+        }
+    });
+
+    Ok(ApiServer {
+        server,
+        thread: Some(thread),
+        addr,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::path::PathBuf;
+
+    fn memory_db() -> AppDatabase {
+        AppDatabase::open(PathBuf::from(":memory:")).unwrap()
+    }
+
+    fn call(db: &mut AppDatabase, method: &str, url: &str, body: &str) -> ApiResponse {
+        handle(db, method, url, body).0
+    }
+
+    #[test]
+    fn test_task_crud() {
+        let mut db = memory_db();
+        let (created, changes) = handle(
+            &mut db,
+            "POST",
+            "/api/v1/tasks",
+            r#"{"title":" Write report ","priority":"high","tags":["work"]}"#,
+        );
+        assert_eq!(created.status, 201);
+        assert_eq!(changes[0].entity, "tasks");
+        let id = created.body["id"].as_str().unwrap().to_string();
+        assert_eq!(created.body["title"], "Write report");
+
+        let patched = call(
+            &mut db,
+            "PATCH",
+            &format!("/api/v1/tasks/{}", id),
+            r#"{"status":"done","deadline":null}"#,
+        );
+        assert_eq!(patched.status, 200);
+        assert_eq!(patched.body["status"], "done");
+
+        let list = call(&mut db, "GET", "/api/v1/tasks?status=done", "");
+        assert_eq!(list.body.as_array().unwrap().len(), 1);
+
+        let sub = call(
+            &mut db,
+            "POST",
+            &format!("/api/v1/tasks/{}/subtasks", id),
+            r#"{"title":"Outline"}"#,
+        );
+        assert_eq!(sub.status, 201);
+        let task = call(&mut db, "GET", &format!("/api/v1/tasks/{}", id), "");
+        assert_eq!(task.body["subtasks"].as_array().unwrap().len(), 1);
+
+        let deleted = call(&mut db, "DELETE", &format!("/api/v1/tasks/{}", id), "");
+        assert_eq!(deleted.status, 204);
+        let missing = call(&mut db, "GET", &format!("/api/v1/tasks/{}", id), "");
+        assert_eq!(missing.status, 404);
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let mut db = memory_db();
+        assert_eq!(
+            call(&mut db, "POST", "/api/v1/tasks", r#"{"title":""}"#).status,
+            400
+        );
+        assert_eq!(
+            call(
+                &mut db,
+                "POST",
+                "/api/v1/tasks",
+                r#"{"title":"a","bogus":1}"#
+            )
+            .status,
+            400
+        );
+        assert_eq!(
+            call(
+                &mut db,
+                "POST",
+                "/api/v1/tasks",
+                r#"{"title":"a","repeat_mode":"custom"}"#
+            )
+            .status,
+            400
+        );
+        assert_eq!(
+            call(
+                &mut db,
+                "POST",
+                "/api/v1/transactions",
+                r#"{"amount":-5,"category":"food"}"#
+            )
+            .status,
+            400
+        );
+        assert_eq!(
+            call(&mut db, "GET", "/api/v1/tasks?colour=red", "").status,
+            400
+        );
+        assert_eq!(call(&mut db, "GET", "/api/v1/nothing", "").status, 404);
+        let (resp, changes) = handle(&mut db, "PATCH", "/api/v1/tasks/missing", "{}");
+        assert_eq!(resp.status, 404);
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn test_server_requires_token() {
+        let server = start(0, "secret".to_string(), |_, _, _| ApiResponse {
+            status: 200,
+            body: json!({ "ok": true }),
+        })
+        .unwrap();
+
+        let request = |auth: &str| {
+            let mut stream = TcpStream::connect(server.addr).unwrap();
+            write!(
+                stream,
+                "GET /api/v1/health HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+                auth
+            )
+            .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        assert!(request("").starts_with("HTTP/1.1 401"));
+        assert!(request("Authorization: Bearer wrong!\r\n").starts_with("HTTP/1.1 401"));
+        assert!(request("Authorization: Bearer secret\r\n").starts_with("HTTP/1.1 200"));
+        server.stop();
+    }
+}
@@ -0,0 +1,823 @@
+//! `focusflow-cli` — scriptable access to the FocusFlow database.
+//!
+//! Works on the same SQLite file as the app. The database runs in WAL mode with
+//! a busy timeout, so the CLI can be used from shell scripts and cron while the
+//! GUI is open. Run `focusflow-cli help` for usage.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use chrono::{Local, TimeZone};
+use focusflow_tauri_lib::database::{self, AppDatabase, DB_FILE_NAME};
+use focusflow_tauri_lib::deeplink;
+use focusflow_tauri_lib::models::{
+    Agenda, ExportBundle, NewTask, NewTransaction, Priority, Project, Status, Task,
+};
+
+/// Must match `identifier` in tauri.conf.json, which names the app data dir.
+const APP_IDENTIFIER: &str = "com.focusflow.focusflow";
+
+const USAGE: &str = "\
+Usage: focusflow-cli [--db PATH] [--json] <command> [args]
+
+Commands:
+  list [--status todo|doing|done] [--project ID] [--tag TAG] [--limit N] [--archived]
+       [--agenda today|upcoming|anytime]
+                                  List tasks (archived tasks only with --archived)
+  add <title> [--project ID|NAME] [--priority low|normal|high]
+              [--deadline DATE] [--scheduled DAY] [--tags a,b] [--description TEXT]
+                                  Create a task
+  complete <id>...                Mark tasks done (spawns next recurring instance)
+  archive <id>...                 Archive tasks
+  projects                        List projects
+  add-transaction <amount> <category> [--income] [--date DATE] [--description TEXT]
+                                  Record an expense (or income with --income)
+  stats                           Print statistics
+  estimates [--project ID|NAME]   Compare estimates with the time done tasks took,
+                                  and the factor to correct new estimates by
+  timer [start <id> | stop]       Show, start or stop the task timer
+  streak [freeze <DAY> | unfreeze <DAY>]
+                                  Show the streak and today's goal, or freeze a day
+  timesheet [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--format csv|json] [--output FILE]
+                                  Export tracked time and focus sessions
+                                  (today by default, CSV unless --json)
+  export [--output FILE]          Write a backup bundle (stdout by default)
+  import <FILE>                   Restore a backup bundle
+  recur                           Spawn missing next instances of recurring tasks
+
+Task ids may be abbreviated to any unique prefix.
+DATE is UNIX ms, RFC 3339, YYYY-MM-DDTHH:MM or YYYY-MM-DD (local time).
+DAY is YYYY-MM-DD or YYYY-MM-DDTHH:MM (local time).
+The database defaults to the app's data directory; override with --db or FOCUSFLOW_DB.";
+
+const VALUE_OPTIONS: &[&str] = &[
+    "db",
+    "status",
+    "project",
+    "limit",
+    "priority",
+    "deadline",
+    "scheduled",
+    "agenda",
+    "tags",
+    "tag",
+    "description",
+    "date",
+    "output",
+    "from",
+    "to",
+    "format",
+];
+const SWITCHES: &[&str] = &["json", "archived", "income", "help"];
+
+#[derive(Debug, Default)]
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    switches: Vec<String>,
+}
+
+impl Args {
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    fn switch(&self, name: &str) -> bool {
+        self.switches.iter().any(|s| s == name)
+    }
+
+    /// Rejects options the current command does not understand.
+    fn allow(&self, allowed: &[&str]) -> Result<(), String> {
+        let global = ["db", "json", "help"];
+        for name in self.options.keys().chain(self.switches.iter()) {
+            if !global.contains(&name.as_str()) && !allowed.contains(&name.as_str()) {
+                return Err(format!("Option --{} is not valid for this command", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_args(argv: &[String]) -> Result<Args, String> {
+    let mut args = Args::default();
+    let mut iter = argv.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            args.positional.extend(iter.by_ref().cloned());
+            break;
+        }
+        if let Some(name) = arg.strip_prefix("--") {
+            let (name, inline) = match name.split_once('=') {
+                Some((n, v)) => (n, Some(v.to_string())),
+                None => (name, None),
+            };
+            if VALUE_OPTIONS.contains(&name) {
+                let value = match inline {
+                    Some(v) => v,
+                    None => iter
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| format!("Option --{} needs a value", name))?,
+                };
+                if args.options.insert(name.to_string(), value).is_some() {
+                    return Err(format!("Option --{} given more than once", name));
+                }
+            } else if SWITCHES.contains(&name) {
+                if inline.is_some() {
+                    return Err(format!("Option --{} does not take a value", name));
+                }
+                args.switches.push(name.to_string());
+            } else {
+                return Err(format!("Unknown option: --{}", name));
+            }
+        } else {
+            args.positional.push(arg.clone());
+        }
+    }
+    Ok(args)
+}
+
+/// Mirrors Tauri's `app_data_dir()`: `<platform data dir>/<identifier>`.
+fn default_app_dir() -> Result<PathBuf, String> {
+    let env_dir = |key: &str| {
+        std::env::var_os(key)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    let home = || env_dir("HOME").ok_or_else(|| "HOME is not set".to_string());
+
+    let base = if cfg!(windows) {
+        env_dir("APPDATA").ok_or_else(|| "APPDATA is not set".to_string())?
+    } else if cfg!(target_os = "macos") {
+        home()?.join("Library").join("Application Support")
+    } else {
+        match env_dir("XDG_DATA_HOME") {
+            Some(dir) => dir,
+            None => home()?.join(".local").join("share"),
+        }
+    };
+    Ok(base.join(APP_IDENTIFIER))
+}
+
+fn open_database(args: &Args) -> Result<AppDatabase, String> {
+    let path = match args.option("db") {
+        Some(p) => PathBuf::from(p),
+        None => match std::env::var_os("FOCUSFLOW_DB").filter(|v| !v.is_empty()) {
+            Some(p) => PathBuf::from(p),
+            None => default_app_dir()?.join(DB_FILE_NAME),
+        },
+    };
+    AppDatabase::open(path)
+}
+
+fn parse_status(value: &str) -> Result<Status, String> {
+    match value {
+        "todo" => Ok(Status::Todo),
+        "doing" => Ok(Status::Doing),
+        "done" => Ok(Status::Done),
+        other => Err(format!("Invalid status: {}", other)),
+    }
+}
+
+fn parse_priority(value: &str) -> Result<Priority, String> {
+    match value {
+        "low" => Ok(Priority::Low),
+        "normal" => Ok(Priority::Normal),
+        "high" => Ok(Priority::High),
+        other => Err(format!("Invalid priority: {}", other)),
+    }
+}
+
+fn status_label(status: Status) -> &'static str {
+    match status {
+        Status::Todo => "todo",
+        Status::Doing => "doing",
+        Status::Done => "done",
+    }
+}
+
+fn priority_label(priority: Priority) -> &'static str {
+    match priority {
+        Priority::Low => "low",
+        Priority::Normal => "normal",
+        Priority::High => "high",
+    }
+}
+
+fn format_ms(ms: Option<i64>) -> String {
+    match ms.and_then(|ms| Local.timestamp_millis_opt(ms).single()) {
+        Some(dt) => dt.format("%Y-%m-%d %H:%M").to_string(),
+        None => "-".to_string(),
+    }
+}
+
+fn short_id(id: &str) -> &str {
+    id.get(..8).unwrap_or(id)
+}
+
+/// Resolves a full id or a unique prefix of one.
+fn resolve_task_id(db: &AppDatabase, prefix: &str) -> Result<String, String> {
+    let tasks = db.get_tasks(None, None, None, None)?;
+    let mut matches = tasks.iter().filter(|t| t.id.starts_with(prefix));
+    match (matches.next(), matches.next()) {
+        (Some(t), None) => Ok(t.id.clone()),
+        (Some(_), Some(_)) => Err(format!("Task id prefix is ambiguous: {}", prefix)),
+        (None, _) => Err(format!("Task not found: {}", prefix)),
+    }
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), String> {
+    let out = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", out);
+    Ok(())
+}
+
+fn print_tasks(tasks: &[Task], projects: &[Project]) {
+    let project_name = |id: &Option<String>| -> String {
+        id.as_ref()
+            .and_then(|id| projects.iter().find(|p| &p.id == id))
+            .map(|p| p.name.clone())
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let rows: Vec<[String; 7]> = tasks
+        .iter()
+        .map(|t| {
+            [
+                short_id(&t.id).to_string(),
+                status_label(t.status).to_string(),
+                priority_label(t.priority).to_string(),
+                format_ms(t.deadline),
+                t.scheduled_at.clone().unwrap_or_else(|| "-".to_string()),
+                project_name(&t.project_id),
+                t.title.clone(),
+            ]
+        })
+        .collect();
+    print_table(
+        &[
+            "ID",
+            "STATUS",
+            "PRIORITY",
+            "DEADLINE",
+            "SCHEDULED",
+            "PROJECT",
+            "TITLE",
+        ],
+        &rows,
+    );
+}
+
+fn print_table<const N: usize>(headers: &[&str; N], rows: &[[String; N]]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row.iter()) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(c, w)| format!("{:<width$}", c, width = *w))
+            .collect();
+        println!("{}", padded.join("  ").trim_end());
+    };
+    line(headers.to_vec());
+    for row in rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
+
+fn cmd_list(db: &AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&["status", "project", "tag", "limit", "archived", "agenda"])?;
+    let status = args.option("status").map(parse_status).transpose()?;
+    let limit = args
+        .option("limit")
+        .map(|l| {
+            l.parse::<i32>()
+                .map_err(|_| format!("Invalid limit: {}", l))
+        })
+        .transpose()?;
+    let archived = args.switch("archived");
+    let agenda = args.option("agenda").map(Agenda::parse).transpose()?;
+
+    let mut tasks = match agenda {
+        // Agenda lists only hold open, unarchived tasks
+        Some(agenda) => {
+            if status.is_some() || archived {
+                return Err("--agenda cannot be combined with --status or --archived".to_string());
+            }
+            let mut tasks = db.get_agenda(agenda)?;
+            if let Some(pid) = args.option("project") {
+                tasks.retain(|t| t.project_id.as_deref() == Some(pid));
+            }
+            if let Some(tag) = args.option("tag") {
+                tasks.retain(|t| t.tags.iter().any(|g| g == tag));
+            }
+            tasks
+        }
+        None => db.get_tasks(
+            None,
+            status,
+            args.option("project").map(String::from),
+            args.option("tag").map(String::from),
+        )?,
+    };
+    tasks.retain(|t| t.is_archived == archived);
+    if let Some(l) = limit {
+        tasks.truncate(l.max(0) as usize);
+    }
+
+    if json {
+        return print_json(&tasks);
+    }
+    print_tasks(&tasks, &db.get_projects()?);
+    Ok(())
+}
+
+fn cmd_add(db: &AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&[
+        "project",
+        "priority",
+        "deadline",
+        "scheduled",
+        "tags",
+        "description",
+    ])?;
+    let title = args.positional[1..].join(" ").trim().to_string();
+    if title.is_empty() {
+        return Err("Missing task title".to_string());
+    }
+
+    let project_id = match args.option("project") {
+        Some(key) => Some(deeplink::resolve_project(&db.get_projects()?, key)?),
+        None => None,
+    };
+    let tags: Vec<String> = args
+        .option("tags")
+        .map(|t| {
+            t.split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let new_task = NewTask {
+        id: uuid::Uuid::new_v4().to_string(),
+        project_id,
+        title,
+        description: args.option("description").map(String::from),
+        priority: args
+            .option("priority")
+            .map(parse_priority)
+            .transpose()?
+            .unwrap_or(Priority::Normal),
+        status: Status::Todo,
+        created_at: chrono::Utc::now().timestamp_millis(),
+        deadline: args
+            .option("deadline")
+            .map(deeplink::parse_deadline)
+            .transpose()?,
+        estimated_minutes: None,
+        actual_minutes: None,
+        tags,
+        remind_at: None,
+        repeat_mode: None,
+        repeat_days_mask: None,
+        section_id: None,
+        scheduled_at: args.option("scheduled").map(String::from),
+    };
+    let task = db.add_task(&new_task)?;
+
+    if json {
+        return print_json(&task);
+    }
+    println!("Added {} {}", short_id(&task.id), task.title);
+    Ok(())
+}
+
+fn cmd_complete(db: &mut AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&[])?;
+    if args.positional.len() < 2 {
+        return Err("Missing task id".to_string());
+    }
+
+    let mut completed = Vec::new();
+    for prefix in &args.positional[1..] {
+        let id = resolve_task_id(db, prefix)?;
+        let spawned = db.update_task_status(&id, Status::Done)?;
+        let task = db.get_task(&id)?;
+        if !json {
+            println!("Completed {} {}", short_id(&task.id), task.title);
+        }
+        completed.push(task);
+        if let Some(next_id) = spawned {
+            let next = db.get_task(&next_id)?;
+            if !json {
+                println!(
+                    "  next occurrence {} due {}",
+                    short_id(&next.id),
+                    format_ms(next.deadline)
+                );
+            }
+            completed.push(next);
+        }
+    }
+
+    if json {
+        return print_json(&completed);
+    }
+    Ok(())
+}
+
+fn cmd_archive(db: &AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&[])?;
+    if args.positional.len() < 2 {
+        return Err("Missing task id".to_string());
+    }
+
+    let mut archived = Vec::new();
+    for prefix in &args.positional[1..] {
+        let id = resolve_task_id(db, prefix)?;
+        db.archive_task(&id)?;
+        let task = db.get_task(&id)?;
+        if !json {
+            println!("Archived {} {}", short_id(&task.id), task.title);
+        }
+        archived.push(task);
+    }
+
+    if json {
+        return print_json(&archived);
+    }
+    Ok(())
+}
+
+fn cmd_projects(db: &AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&[])?;
+    let projects = db.get_projects()?;
+    if json {
+        return print_json(&projects);
+    }
+    let rows: Vec<[String; 4]> = projects
+        .iter()
+        .map(|p| {
+            [
+                p.id.clone(),
+                if p.is_folder { "folder" } else { "project" }.to_string(),
+                priority_label(p.priority).to_string(),
+                p.name.clone(),
+            ]
+        })
+        .collect();
+    print_table(&["ID", "KIND", "PRIORITY", "NAME"], &rows);
+    Ok(())
+}
+
+fn cmd_add_transaction(db: &AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&["income", "date", "description"])?;
+    let (amount, category) = match &args.positional[1..] {
+        [amount, category] => (amount, category),
+        _ => return Err("Expected <amount> <category>".to_string()),
+    };
+    let amount: f64 = amount
+        .parse()
+        .ok()
+        .filter(|a: &f64| a.is_finite() && *a > 0.0)
+        .ok_or_else(|| format!("Invalid amount: {}", amount))?;
+    let date = match args.option("date") {
+        Some(d) => deeplink::parse_deadline(d)?,
+        None => chrono::Utc::now().timestamp_millis(),
+    };
+
+    let created = db.add_transaction(NewTransaction {
+        id: uuid::Uuid::new_v4().to_string(),
+        amount,
+        category: category.clone(),
+        date,
+        description: args.option("description").map(String::from),
+        is_expense: !args.switch("income"),
+    })?;
+
+    if json {
+        return print_json(&created);
+    }
+    println!(
+        "Added {} {:.2} ({}) {}",
+        if created.is_expense {
+            "expense"
+        } else {
+            "income"
+        },
+        created.amount,
+        created.category,
+        short_id(&created.id)
+    );
+    Ok(())
+}
+
+fn cmd_stats(db: &AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&[])?;
+    let stats = db.get_stats()?;
+    if json {
+        return print_json(&stats);
+    }
+    let rows = [
+        ("Total tasks", stats.total_tasks.to_string()),
+        ("Completed", stats.completed_tasks.to_string()),
+        ("Completed today", stats.completed_today.to_string()),
+        ("Completed this week", stats.completed_week.to_string()),
+        ("Due today", stats.tasks_today.to_string()),
+        ("Due this week", stats.tasks_week.to_string()),
+        ("Current streak", stats.current_streak.to_string()),
+        ("Best streak", stats.best_streak.to_string()),
+        ("Focus minutes", stats.total_focus_time.to_string()),
+        ("Level", stats.level.to_string()),
+        ("Points", stats.points.to_string()),
+    ];
+    let rows: Vec<[String; 2]> = rows.into_iter().map(|(k, v)| [k.to_string(), v]).collect();
+    print_table(&["STAT", "VALUE"], &rows);
+    Ok(())
+}
+
+fn cmd_estimates(db: &AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&["project"])?;
+    let project_id = match args.option("project") {
+        Some(key) => Some(deeplink::resolve_project(&db.get_projects()?, key)?),
+        None => None,
+    };
+    let report = db.get_estimate_accuracy()?;
+    let correction = db.get_estimate_correction(project_id.as_deref())?;
+    if json {
+        return print_json(&serde_json::json!({ "report": report, "correction": correction }));
+    }
+    let ratio = |r: Option<f64>| r.map_or_else(|| "-".to_string(), |r| format!("{:.2}x", r));
+    let groups = [
+        ("", vec![report.overall]),
+        ("project: ", report.by_project),
+        ("tag: ", report.by_tag),
+        ("priority: ", report.by_priority),
+    ];
+    let rows: Vec<[String; 6]> = groups
+        .into_iter()
+        .flat_map(|(prefix, groups)| {
+            groups.into_iter().map(move |g| {
+                [
+                    format!("{}{}", prefix, g.label),
+                    g.tasks.to_string(),
+                    ratio(Some(g.median_ratio)),
+                    g.underestimated.to_string(),
+                    ratio(g.median_underestimate),
+                    ratio(g.median_overestimate),
+                ]
+            })
+        })
+        .collect();
+    print_table(
+        &["GROUP", "TASKS", "MEDIAN", "UNDER", "UNDER BY", "OVER BY"],
+        &rows,
+    );
+    println!(
+        "\nMultiply new estimates by {:.2} (from {} {})",
+        correction.factor,
+        correction.samples,
+        if correction.from_project {
+            "done tasks in the project"
+        } else {
+            "done tasks overall"
+        }
+    );
+    Ok(())
+}
+
+fn cmd_timer(db: &AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&[])?;
+    let now = chrono::Utc::now().timestamp_millis();
+    let entry = match &args.positional[1..] {
+        [] => db.get_running_timer()?,
+        [action, id] if action == "start" => Some(db.start_timer(&resolve_task_id(db, id)?, now)?),
+        [action] if action == "stop" => Some(db.stop_timer(now)?),
+        _ => return Err("Expected [start <id> | stop]".to_string()),
+    };
+    if json {
+        return print_json(&entry);
+    }
+    match entry {
+        None => println!("No timer is running"),
+        Some(e) if e.ended_at.is_none() => println!(
+            "Running on {} since {} ({} min)",
+            e.task_title,
+            format_ms(Some(e.started_at)),
+            e.minutes
+        ),
+        Some(e) => println!("Stopped {} after {} min", e.task_title, e.minutes),
+    }
+    Ok(())
+}
+
+fn cmd_streak(db: &AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&[])?;
+    let activity = match &args.positional[1..] {
+        [] => None,
+        [action, day] if action == "freeze" => {
+            Some(db.freeze_streak_day(database::parse_day(day)?)?)
+        }
+        [action, day] if action == "unfreeze" => {
+            Some(db.unfreeze_streak_day(database::parse_day(day)?)?)
+        }
+        _ => return Err("Expected [freeze <DAY> | unfreeze <DAY>]".to_string()),
+    };
+    if let Some(activity) = activity {
+        if json {
+            return print_json(&activity);
+        }
+        let action = if activity.frozen { "Froze" } else { "Unfroze" };
+        println!("{} {}", action, activity.day);
+        return Ok(());
+    }
+    let streak = db.get_streak()?;
+    if json {
+        return print_json(&streak);
+    }
+    let today = &streak.today;
+    let mut goal = Vec::new();
+    if today.goal_tasks > 0 {
+        goal.push(format!("{}/{} tasks", today.tasks_done, today.goal_tasks));
+    }
+    if today.goal_focus_minutes > 0 {
+        goal.push(format!(
+            "{}/{} focus min",
+            today.focus_minutes, today.goal_focus_minutes
+        ));
+    }
+    let state = match (today.goal_met, today.frozen) {
+        (true, _) => " (goal met)",
+        (false, true) => " (frozen)",
+        _ => "",
+    };
+    let rows = [
+        ("Current streak", streak.current.to_string()),
+        ("Best streak", streak.best.to_string()),
+        ("Today", format!("{}{}", goal.join(" or "), state)),
+        ("Freezes left", streak.freezes_left.to_string()),
+    ];
+    let rows: Vec<[String; 2]> = rows.into_iter().map(|(k, v)| [k.to_string(), v]).collect();
+    print_table(&["STREAK", "VALUE"], &rows);
+    Ok(())
+}
+
+fn cmd_timesheet(db: &AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&["from", "to", "format", "output"])?;
+    let day = |name: &str| match args.option(name) {
+        Some(value) => database::parse_day(value),
+        None => Ok(Local::now().date_naive()),
+    };
+    let format = args
+        .option("format")
+        .unwrap_or(if json { "json" } else { "csv" });
+    let out = db.export_timesheet(day("from")?, day("to")?, format)?;
+    match args.option("output") {
+        Some(path) => std::fs::write(path, out).map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            println!("{}", out.trim_end());
+            Ok(())
+        }
+    }
+}
+
+fn cmd_export(db: &AppDatabase, args: &Args) -> Result<(), String> {
+    args.allow(&["output"])?;
+    let bundle = db.export_bundle()?;
+    let out = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    match args.option("output") {
+        Some(path) => std::fs::write(path, out).map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            println!("{}", out);
+            Ok(())
+        }
+    }
+}
+
+fn cmd_import(db: &mut AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&[])?;
+    let path = match &args.positional[1..] {
+        [path] => path,
+        _ => return Err("Expected <FILE>".to_string()),
+    };
+    let raw = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let bundle: ExportBundle =
+        serde_json::from_str(&raw).map_err(|e| format!("Invalid backup file: {}", e))?;
+    let (projects, tasks) = (bundle.projects.len(), bundle.tasks.len());
+    db.import_data(
+        bundle.projects,
+        bundle.sections,
+        bundle.tasks,
+        bundle.tags,
+        bundle.boards,
+        bundle.settings,
+    )?;
+
+    if json {
+        return print_json(&serde_json::json!({ "projects": projects, "tasks": tasks }));
+    }
+    println!("Imported {} projects and {} tasks", projects, tasks);
+    Ok(())
+}
+
+fn cmd_recur(db: &mut AppDatabase, args: &Args, json: bool) -> Result<(), String> {
+    args.allow(&[])?;
+    let spawned = db.process_recurring_tasks()?;
+    if json {
+        return print_json(&spawned);
+    }
+    if spawned.is_empty() {
+        println!("No recurring tasks to spawn");
+        return Ok(());
+    }
+    print_tasks(&spawned, &db.get_projects()?);
+    Ok(())
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let json = args.switch("json");
+    let command = args
+        .positional
+        .first()
+        .map(String::as_str)
+        .unwrap_or("help");
+    if command == "help" || args.switch("help") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let mut db = open_database(args)?;
+    match command {
+        "list" => cmd_list(&db, args, json),
+        "add" => cmd_add(&db, args, json),
+        "complete" => cmd_complete(&mut db, args, json),
+        "archive" => cmd_archive(&db, args, json),
+        "projects" => cmd_projects(&db, args, json),
+        "add-transaction" => cmd_add_transaction(&db, args, json),
+        "stats" => cmd_stats(&db, args, json),
+        "estimates" => cmd_estimates(&db, args, json),
+        "timer" => cmd_timer(&db, args, json),
+        "streak" => cmd_streak(&db, args, json),
+        "timesheet" => cmd_timesheet(&db, args, json),
+        "export" => cmd_export(&db, args),
+        "import" => cmd_import(&mut db, args, json),
+        "recur" => cmd_recur(&mut db, args, json),
+        other => Err(format!("Unknown command: {}\n\n{}", other, USAGE)),
+    }
+}
+
+fn main() -> ExitCode {
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    let args = match parse_args(&argv) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("focusflow-cli: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("focusflow-cli: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse_args(&argv("--json add Buy milk --priority=high --tags a,b")).unwrap();
+        assert_eq!(args.positional, vec!["add", "Buy", "milk"]);
+        assert_eq!(args.option("priority"), Some("high"));
+        assert_eq!(args.option("tags"), Some("a,b"));
+        assert!(args.switch("json"));
+        assert!(args.allow(&["priority", "tags"]).is_ok());
+        assert!(args.allow(&["priority"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(parse_args(&argv("list --bogus")).is_err());
+        assert!(parse_args(&argv("list --status")).is_err());
+        assert!(parse_args(&argv("list --json=1")).is_err());
+        assert!(parse_args(&argv("list --limit 1 --limit 2")).is_err());
+
+        let args = parse_args(&argv("add -- --not-an-option")).unwrap();
+        assert_eq!(args.positional, vec!["add", "--not-an-option"]);
+    }
+}
@@ -1,14 +1,106 @@
+use rusqlite::functions::FunctionFlags;
 use rusqlite::{params, Connection};
+use std::collections::{HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
 
 // Трейт Timelike необходим для работы методов .hour() и .minute()
 use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Weekday};
 
 use crate::models::*;
 
+pub const DB_FILE_NAME: &str = "focusflow.db";
+
+/// Older hook deliveries are pruned so the log stays bounded.
+const HOOK_DELIVERY_LOG_LIMIT: i64 = 500;
+
+/// How many undo steps are kept; older ones are dropped.
+const UNDO_HISTORY_LIMIT: i64 = 100;
+
+/// Task columns whose changes are written to `task_events`.
+const TASK_EVENT_FIELDS: &[&str] = &[
+    "title",
+    "description",
+    "project_id",
+    "section_id",
+    "priority",
+    "status",
+    "deadline",
+    "scheduled_at",
+    "estimated_minutes",
+    "remind_at",
+    "repeat_mode",
+    "repeat_days_mask",
+    "is_archived",
+    "deleted_at",
+];
+
+/// Tables whose writes are journaled for undo/redo.
+const UNDO_TABLES: &[&str] = &[
+    "tasks",
+    "subtasks",
+    "projects",
+    "sections",
+    "boards",
+    "board_columns",
+    "board_cards",
+    "task_dependencies",
+    "tags",
+    "task_tags",
+    "templates",
+    "daily_plan",
+    "time_blocks",
+    "time_entries",
+    "transactions",
+    "debts",
+    "hooks",
+    "settings",
+    "gamification_rules",
+    "daily_activity",
+    "points_ledger",
+    "achievements",
+];
+
 pub struct AppDatabase {
     db_path: PathBuf,
     conn: Connection,
+    /// The undo step this connection journals under, 0 when none. Read by
+    /// the undo triggers through `undo_step()`.
+    undo_step: Arc<AtomicI64>,
+}
+
+/// A database handle whose writes form one undo step, closed when the guard
+/// is dropped. Wraps a `MutexGuard` in commands or a plain `&mut` in the API.
+pub struct UndoStep<G: DerefMut<Target = AppDatabase>>(G);
+
+impl<G: DerefMut<Target = AppDatabase>> UndoStep<G> {
+    pub fn begin(db: G, label: &str) -> Result<Self, String> {
+        db.begin_undo_step(label)?;
+        Ok(UndoStep(db))
+    }
+}
+
+impl<G: DerefMut<Target = AppDatabase>> Deref for UndoStep<G> {
+    type Target = AppDatabase;
+    fn deref(&self) -> &AppDatabase {
+        &self.0
+    }
+}
+
+impl<G: DerefMut<Target = AppDatabase>> DerefMut for UndoStep<G> {
+    fn deref_mut(&mut self) -> &mut AppDatabase {
+        &mut self.0
+    }
+}
+
+impl<G: DerefMut<Target = AppDatabase>> Drop for UndoStep<G> {
+    fn drop(&mut self) {
+        if let Err(e) = self.0.end_undo_step() {
+            eprintln!("[FocusFlow] Failed to close undo step: {}", e);
+        }
+    }
 }
 
 // Вспомогательные функции (внутренние)
@@ -62,6 +154,881 @@ fn pick_time_from_deadline(deadline_ms: Option<i64>) -> (u32, u32) {
     (9, 0)
 }
 
+/// Date of the next occurrence for a repeat rule, counted from the current deadline
+/// (or `now` for tasks without one).
+pub fn next_occurrence_ms(
+    repeat_mode: &str,
+    repeat_days_mask: Option<i64>,
+    deadline: Option<i64>,
+    now: i64,
+) -> Option<i64> {
+    let current_deadline_ms = deadline.unwrap_or(now);
+    let current_date = match Local.timestamp_millis_opt(current_deadline_ms) {
+        chrono::LocalResult::Single(dt) => dt.date_naive(),
+        _ => Local::now().date_naive(),
+    };
+
+    let next_date = match repeat_mode {
+        "daily" => Some(next_date_daily(current_date)),
+        "weekdays" => Some(next_date_weekdays(current_date)),
+        "custom" => repeat_days_mask.and_then(|mask| next_date_custom(current_date, mask)),
+        _ => None,
+    }?;
+
+    let (h, m) = pick_time_from_deadline(deadline);
+    Some(local_date_time_to_ms(next_date, h, m))
+}
+
+/// Sets the status and `completed_at`. Completing a task awards its points
+/// and spawns the next occurrence of a recurring one.
+fn set_task_status(
+    conn: &Connection,
+    task_id: &str,
+    new_status: Status,
+) -> Result<Option<String>, String> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let updated = conn.execute("UPDATE tasks SET status = ?1, completed_at = CASE WHEN ?1 = 2 THEN ?2 ELSE NULL END WHERE id = ?3 AND deleted_at IS NULL",
+        params![new_status as i32, now, task_id]).map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Task not found: {}", task_id));
+    }
+
+    // Logic for recurring tasks
+    if new_status == Status::Done {
+        record_completion(conn, task_id, now)?;
+        award_completion(conn, task_id, now)?;
+        spawn_next_occurrence(conn, task_id, now)
+    } else {
+        Ok(None)
+    }
+}
+
+/// A task's tag names, in order.
+fn task_tag_names(conn: &Connection, task_id: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT g.name FROM task_tags tt JOIN tags g ON g.id = tt.tag_id
+             WHERE tt.task_id = ?1 ORDER BY tt.position",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![task_id], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Replaces a task's tags, creating unknown ones. Names are trimmed and
+/// deduplicated; tags the task keeps are left in place so history only shows
+/// real additions and removals.
+fn set_task_tags(conn: &Connection, task_id: &str, tags: &[String]) -> Result<(), String> {
+    let mut names: Vec<&str> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !names.contains(&tag) {
+            names.push(tag);
+        }
+    }
+    for current in task_tag_names(conn, task_id)? {
+        if !names.contains(&current.as_str()) {
+            conn.execute(
+                "DELETE FROM task_tags WHERE task_id = ?1 AND tag_id = (SELECT id FROM tags WHERE name = ?2)",
+                params![task_id, current],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    let now = chrono::Utc::now().timestamp_millis();
+    for (position, name) in names.iter().enumerate() {
+        conn.execute(
+            "INSERT OR IGNORE INTO tags (name, created_at) VALUES (?1, ?2)",
+            params![name, now],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO task_tags (task_id, tag_id, position) SELECT ?1, id, ?3 FROM tags WHERE name = ?2
+             ON CONFLICT(task_id, tag_id) DO UPDATE SET position = excluded.position WHERE position != excluded.position",
+            params![task_id, name, position as i64],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Reads the pre-migration `tasks.tags` JSON. Unreadable values are salvaged
+/// as a comma-separated list rather than dropped.
+fn parse_legacy_tags(task_id: &str, raw: &str) -> Vec<String> {
+    match serde_json::from_str::<Vec<String>>(raw) {
+        Ok(tags) => tags,
+        Err(e) => {
+            eprintln!(
+                "Task {}: unreadable tags {:?} ({}), kept as text",
+                task_id, raw, e
+            );
+            raw.split(',')
+                .map(|t| t.trim_matches(|c: char| c.is_whitespace() || "[]\"".contains(c)))
+                .filter(|t| !t.is_empty())
+                .map(String::from)
+                .collect()
+        }
+    }
+}
+
+/// Inserts the next instance of a recurring task and strips the repeat rule from
+/// the completed one, so that unchecking and re-checking it does not duplicate.
+fn spawn_next_occurrence(
+    conn: &Connection,
+    task_id: &str,
+    now: i64,
+) -> Result<Option<String>, String> {
+    let (
+        repeat_mode,
+        repeat_days_mask,
+        deadline,
+        project_id,
+        title,
+        description,
+        priority,
+        section_id,
+    ) = {
+        let mut stmt = conn.prepare("SELECT repeat_mode, repeat_days_mask, deadline, project_id, title, description, priority, section_id FROM tasks WHERE id = ?1").map_err(|e| e.to_string())?;
+        stmt.query_row(params![task_id], |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, Option<i64>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, i32>(6)?,
+                row.get::<_, Option<String>>(7)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+    };
+
+    // NOTE: We only care if repeat_mode is present.
+    let next_ms = match repeat_mode
+        .as_deref()
+        .and_then(|mode| next_occurrence_ms(mode, repeat_days_mask, deadline, now))
+    {
+        Some(ms) => ms,
+        None => return Ok(None),
+    };
+    let new_id = uuid::Uuid::new_v4().to_string();
+
+    // Reset status to Todo (0)
+    conn.execute("INSERT INTO tasks (id, project_id, title, description, priority, status, created_at, deadline, repeat_mode, repeat_days_mask, section_id)
+        VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, ?7, ?8, ?9, ?10)",
+        params![new_id, project_id, title, description, priority, now, next_ms, repeat_mode, repeat_days_mask, section_id]
+    ).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO task_tags (task_id, tag_id, position) SELECT ?1, tag_id, position FROM task_tags WHERE task_id = ?2",
+        params![new_id, task_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "UPDATE tasks SET repeat_mode = NULL, repeat_days_mask = NULL WHERE id = ?1",
+        params![task_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(Some(new_id))
+}
+
+/// Two v4 UUIDs (244 random bits), hex encoded.
+fn new_api_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+const PROJECT_COLUMNS: &str = "id, name, color, priority, created_at, parent_id, is_folder, sort_order, description, icon, archived_at, default_priority, default_tags";
+
+fn project_from_row(row: &rusqlite::Row) -> rusqlite::Result<Project> {
+    let default_tags: String = row.get(12)?;
+    Ok(Project {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        color: row.get(2)?,
+        priority: Priority::from_int(row.get(3)?),
+        created_at: row.get(4)?,
+        parent_id: row.get(5)?,
+        is_folder: row.get::<_, i32>(6)? != 0,
+        sort_order: row.get(7)?,
+        description: row.get(8)?,
+        icon: row.get(9)?,
+        archived_at: row.get(10)?,
+        default_priority: row.get::<_, Option<i32>>(11)?.map(Priority::from_int),
+        default_tags: serde_json::from_str(&default_tags).unwrap_or_default(),
+    })
+}
+
+fn is_hex_color(value: &str) -> bool {
+    value.len() == 7 && value.starts_with('#') && value[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+const TASK_COLUMNS: &str = "id, project_id, title, description, priority, status, created_at, completed_at, deadline, estimated_minutes, actual_minutes, (SELECT json_group_array(g.name ORDER BY tt.position) FROM task_tags tt JOIN tags g ON g.id = tt.tag_id WHERE tt.task_id = tasks.id), remind_at, reminded_at, repeat_mode, repeat_days_mask, is_archived, sort_order, section_id, COALESCE(updated_at, created_at), revision, scheduled_at,
+    CASE WHEN status = 2 OR is_archived = 1 THEN NULL
+        WHEN substr(scheduled_at, 1, 10) <= date('now', 'localtime')
+            OR deadline < CAST(strftime('%s', date('now', 'localtime', '+1 day'), 'utc') AS INTEGER) * 1000 THEN 'today'
+        WHEN scheduled_at IS NOT NULL OR deadline IS NOT NULL THEN 'upcoming'
+        ELSE 'anytime' END AS agenda";
+
+fn task_from_row(row: &rusqlite::Row) -> rusqlite::Result<Task> {
+    let tags_raw: String = row.get(11)?;
+    Ok(Task {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        title: row.get(2)?,
+        description: row.get(3)?,
+        priority: Priority::from_int(row.get(4)?),
+        status: Status::from_int(row.get(5)?),
+        created_at: row.get(6)?,
+        completed_at: row.get(7)?,
+        deadline: row.get(8)?,
+        estimated_minutes: row.get::<_, Option<i64>>(9)?.map(|v| v as u32),
+        actual_minutes: row.get::<_, Option<i64>>(10)?.map(|v| v as u32),
+        tags: serde_json::from_str(&tags_raw).unwrap_or_default(),
+        remind_at: row.get(12)?,
+        reminded_at: row.get(13)?,
+        repeat_mode: row.get(14)?,
+        repeat_days_mask: row.get(15)?,
+        is_archived: row.get::<_, i32>(16)? != 0,
+        sort_order: row.get(17)?,
+        subtasks: Vec::new(),
+        section_id: row.get(18)?,
+        depends_on: Vec::new(),
+        is_blocked: false,
+        updated_at: row.get(19)?,
+        revision: row.get(20)?,
+        scheduled_at: row.get(21)?,
+        agenda: row
+            .get::<_, Option<String>>(22)?
+            .and_then(|a| Agenda::parse(&a).ok()),
+    })
+}
+
+/// Checks a "do on" value: a local date "YYYY-MM-DD" or date-time
+/// "YYYY-MM-DDTHH:MM".
+pub fn validate_scheduled(value: &str) -> Result<(), String> {
+    let valid = match value.len() {
+        10 => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+        16 => NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M").is_ok(),
+        _ => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid scheduled_at: {} (expected YYYY-MM-DD or YYYY-MM-DDTHH:MM)",
+            value
+        ))
+    }
+}
+
+/// Parses a local calendar day, "YYYY-MM-DD".
+pub fn parse_day(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date: {} (expected YYYY-MM-DD)", value))
+}
+
+/// UNIX ms of the local time `minutes` after midnight on `date`.
+fn local_ms(date: NaiveDate, minutes: u32) -> i64 {
+    let time = date.and_hms_opt(0, 0, 0).unwrap() + Duration::minutes(minutes as i64);
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|t| t.timestamp_millis())
+        .unwrap_or_else(|| time.and_utc().timestamp_millis())
+}
+
+const TIME_BLOCK_COLUMNS: &str = "b.id, b.task_id, COALESCE(b.title, t.title, ''), b.start_at, b.end_at, b.completed_at, b.created_at";
+
+fn time_block_from_row(row: &rusqlite::Row) -> rusqlite::Result<TimeBlock> {
+    Ok(TimeBlock {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        title: row.get(2)?,
+        start_at: row.get(3)?,
+        end_at: row.get(4)?,
+        completed_at: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+const TIME_ENTRY_COLUMNS: &str =
+    "e.id, e.task_id, t.title, e.note, e.started_at, e.ended_at, e.created_at";
+
+fn time_entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<TimeEntry> {
+    let started_at: i64 = row.get(4)?;
+    let ended_at: Option<i64> = row.get(5)?;
+    let end = ended_at.unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+    Ok(TimeEntry {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        task_title: row.get(2)?,
+        note: row.get(3)?,
+        started_at,
+        ended_at,
+        minutes: (((end - started_at).max(0) + 30_000) / 60_000) as u32,
+        created_at: row.get(6)?,
+    })
+}
+
+/// UNIX ms bounds `[start, end)` of the local days `from..=to`.
+fn day_range(from: NaiveDate, to: NaiveDate) -> Result<(i64, i64), String> {
+    if to < from {
+        return Err("Date range ends before it starts".to_string());
+    }
+    Ok((local_ms(from, 0), local_ms(to + Duration::days(1), 0)))
+}
+
+fn local_day(ms: i64) -> String {
+    Local
+        .timestamp_millis_opt(ms)
+        .single()
+        .map(|t| t.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// Quotes a CSV field when it holds a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Timesheet rows as CSV with a header line, times in local time.
+pub fn timesheet_csv(entries: &[TimesheetEntry]) -> String {
+    let time = |ms: i64| {
+        Local
+            .timestamp_millis_opt(ms)
+            .single()
+            .map(|t| t.format("%H:%M").to_string())
+            .unwrap_or_default()
+    };
+    let mut out = String::from("date,start,end,minutes,source,task,project,tags,note\n");
+    for e in entries {
+        let fields = [
+            local_day(e.started_at),
+            time(e.started_at),
+            time(e.ended_at),
+            e.minutes.to_string(),
+            e.source.clone(),
+            e.task_title.clone(),
+            e.project_name.clone().unwrap_or_default(),
+            e.tags.join(", "),
+            e.note.clone().unwrap_or_default(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Start of the first gap of `len` ms in `busy` (sorted by start) between
+/// `from` and `until`.
+fn first_free_slot(busy: &[(i64, i64)], from: i64, until: i64, len: i64) -> Option<i64> {
+    let mut start = from;
+    for &(busy_start, busy_end) in busy {
+        if busy_end <= start {
+            continue;
+        }
+        if busy_start >= start + len {
+            break;
+        }
+        start = busy_end;
+    }
+    (start + len <= until).then_some(start)
+}
+
+/// Median of `values`, which it sorts.
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    // The same element when the count is odd
+    let (lower, upper) = (values[(values.len() - 1) / 2], values[values.len() / 2]);
+    Some((lower + upper) / 2.0)
+}
+
+fn round_ratio(ratio: f64) -> f64 {
+    (ratio * 100.0).round() / 100.0
+}
+
+/// Accuracy of a group of actual / estimated ratios.
+fn estimate_accuracy(key: Option<String>, label: String, ratios: &[f64]) -> EstimateAccuracy {
+    let mut under: Vec<f64> = ratios.iter().copied().filter(|r| *r > 1.0).collect();
+    let mut over: Vec<f64> = ratios
+        .iter()
+        .filter(|r| **r < 1.0)
+        .map(|r| 1.0 / r)
+        .collect();
+    EstimateAccuracy {
+        key,
+        label,
+        tasks: ratios.len() as u32,
+        median_ratio: median(&mut ratios.to_vec()).map_or(1.0, round_ratio),
+        underestimated: under.len() as u32,
+        median_underestimate: median(&mut under).map(round_ratio),
+        overestimated: over.len() as u32,
+        median_overestimate: median(&mut over).map(round_ratio),
+    }
+}
+
+/// Done tasks a project needs before its own correction factor is used.
+const MIN_ESTIMATE_SAMPLES: usize = 3;
+
+/// A done task's actual / estimated ratio and what it is grouped by.
+struct EstimateSample {
+    project_id: Option<String>,
+    project_name: Option<String>,
+    priority: Priority,
+    tags: Vec<String>,
+    ratio: f64,
+}
+
+/// Key, label and ratios of one estimate accuracy group.
+type RatioGroup = (Option<String>, String, Vec<f64>);
+
+#[derive(Clone, Copy)]
+enum AchievementMetric {
+    Completed,
+    HighPriority,
+    OnTime,
+    FocusMinutes,
+    Streak,
+    Level,
+}
+
+struct AchievementDef {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    metric: AchievementMetric,
+    target: i64,
+}
+
+const ACHIEVEMENTS: &[AchievementDef] = &[
+    AchievementDef {
+        id: "first_task",
+        name: "First step",
+        description: "Complete a task",
+        metric: AchievementMetric::Completed,
+        target: 1,
+    },
+    AchievementDef {
+        id: "tasks_10",
+        name: "Getting things done",
+        description: "Complete 10 tasks",
+        metric: AchievementMetric::Completed,
+        target: 10,
+    },
+    AchievementDef {
+        id: "tasks_100",
+        name: "Centurion",
+        description: "Complete 100 tasks",
+        metric: AchievementMetric::Completed,
+        target: 100,
+    },
+    AchievementDef {
+        id: "high_priority_10",
+        name: "Firefighter",
+        description: "Complete 10 high-priority tasks",
+        metric: AchievementMetric::HighPriority,
+        target: 10,
+    },
+    AchievementDef {
+        id: "on_time_10",
+        name: "Punctual",
+        description: "Complete 10 tasks by their deadline",
+        metric: AchievementMetric::OnTime,
+        target: 10,
+    },
+    AchievementDef {
+        id: "focus_60",
+        name: "In the zone",
+        description: "Focus for an hour in total",
+        metric: AchievementMetric::FocusMinutes,
+        target: 60,
+    },
+    AchievementDef {
+        id: "focus_1000",
+        name: "Deep worker",
+        description: "Focus for 1000 minutes in total",
+        metric: AchievementMetric::FocusMinutes,
+        target: 1000,
+    },
+    AchievementDef {
+        id: "streak_7",
+        name: "On a roll",
+        description: "Complete tasks 7 days in a row",
+        metric: AchievementMetric::Streak,
+        target: 7,
+    },
+    AchievementDef {
+        id: "streak_30",
+        name: "Habit formed",
+        description: "Complete tasks 30 days in a row",
+        metric: AchievementMetric::Streak,
+        target: 30,
+    },
+    AchievementDef {
+        id: "level_5",
+        name: "Rising star",
+        description: "Reach level 5",
+        metric: AchievementMetric::Level,
+        target: 5,
+    },
+    AchievementDef {
+        id: "level_10",
+        name: "Veteran",
+        description: "Reach level 10",
+        metric: AchievementMetric::Level,
+        target: 10,
+    },
+];
+
+fn gamification_rules(conn: &Connection) -> Result<GamificationRules, String> {
+    let body = conn.query_row(
+        "SELECT body FROM gamification_rules WHERE id = 1",
+        [],
+        |row| row.get::<_, String>(0),
+    );
+    match body {
+        Ok(body) => serde_json::from_str(&body).map_err(|e| e.to_string()),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(GamificationRules::default()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Level 1 plus one for every threshold reached.
+fn level_for(points: i64, thresholds: &[i64]) -> u32 {
+    1 + thresholds.iter().filter(|t| points >= **t).count() as u32
+}
+
+fn award_points(
+    conn: &Connection,
+    event: &str,
+    ref_id: Option<&str>,
+    quantity: i64,
+    points: i64,
+    now: i64,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO points_ledger (event, ref_id, quantity, points, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![event, ref_id, quantity, points, now],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Whether a `daily_activity` row met the goal it was recorded under.
+const GOAL_MET: &str = "((goal_tasks > 0 AND tasks_done >= goal_tasks)
+    OR (goal_focus_minutes > 0 AND focus_minutes >= goal_focus_minutes))";
+
+const DAILY_ACTIVITY_COLUMNS: &str =
+    "day, tasks_done, focus_minutes, goal_tasks, goal_focus_minutes, frozen_at IS NOT NULL";
+
+fn daily_activity_from_row(row: &rusqlite::Row) -> rusqlite::Result<DailyActivity> {
+    let goal_tasks: u32 = row.get(3)?;
+    let goal_focus_minutes: u32 = row.get(4)?;
+    let mut activity = DailyActivity {
+        day: row.get(0)?,
+        tasks_done: row.get(1)?,
+        focus_minutes: row.get(2)?,
+        goal_tasks,
+        goal_focus_minutes,
+        goal_met: false,
+        frozen: row.get(5)?,
+    };
+    activity.goal_met = (goal_tasks > 0 && activity.tasks_done >= goal_tasks)
+        || (goal_focus_minutes > 0 && activity.focus_minutes >= goal_focus_minutes);
+    Ok(activity)
+}
+
+/// The daily goal currently set, as (tasks, focus minutes).
+fn daily_goal(conn: &Connection) -> Result<(u32, u32), String> {
+    match conn.query_row(
+        "SELECT daily_goal_tasks, daily_goal_focus_minutes FROM settings WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ) {
+        Ok(goal) => Ok(goal),
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            let defaults = AppSettings::default();
+            Ok((defaults.daily_goal_tasks, defaults.daily_goal_focus_minutes))
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Adds to the activity of the local day `at` falls on. The day keeps the
+/// goal in effect when it was first recorded, so a later goal change or a
+/// move to another time zone doesn't rewrite it. Meeting the goal hands back
+/// a freeze set on that day.
+fn record_activity(
+    conn: &Connection,
+    at: i64,
+    tasks: u32,
+    focus_minutes: u32,
+) -> Result<(), String> {
+    let day = local_day(at);
+    let (goal_tasks, goal_focus_minutes) = daily_goal(conn)?;
+    conn.execute(
+        "INSERT INTO daily_activity (day, tasks_done, focus_minutes, goal_tasks, goal_focus_minutes)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(day) DO UPDATE SET
+            tasks_done = tasks_done + excluded.tasks_done,
+            focus_minutes = focus_minutes + excluded.focus_minutes",
+        params![day, tasks, focus_minutes, goal_tasks, goal_focus_minutes],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        &format!(
+            "UPDATE daily_activity SET frozen_at = NULL WHERE day = ?1 AND frozen_at IS NOT NULL AND {}",
+            GOAL_MET
+        ),
+        params![day],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Counts a completed task toward its day, once per task however often it
+/// is reopened.
+fn record_completion(conn: &Connection, task_id: &str, now: i64) -> Result<(), String> {
+    let counted = conn
+        .execute(
+            "UPDATE tasks SET counted_on = ?1 WHERE id = ?2 AND counted_on IS NULL",
+            params![local_day(now), task_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if counted > 0 {
+        record_activity(conn, now, 1, 0)?;
+    }
+    Ok(())
+}
+
+/// Recorded days up to `until`, newest first, as (day, goal met, frozen).
+fn streak_days(
+    conn: &Connection,
+    until: NaiveDate,
+) -> Result<Vec<(NaiveDate, bool, bool)>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT day, {}, frozen_at IS NOT NULL FROM daily_activity WHERE day <= ?1 ORDER BY day DESC",
+            GOAL_MET
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![until.format("%Y-%m-%d").to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut days = Vec::new();
+    for row in rows {
+        let (day, met, frozen) = row.map_err(|e| e.to_string())?;
+        days.push((parse_day(&day)?, met, frozen));
+    }
+    Ok(days)
+}
+
+/// Goal-met days in the run reaching `today`. Frozen days keep the run going
+/// without adding to it, and today only breaks it once it's over.
+fn current_streak(days: &[(NaiveDate, bool, bool)], today: NaiveDate) -> u32 {
+    let mut expected = today;
+    let mut streak = 0;
+    for &(day, met, frozen) in days {
+        if day != expected {
+            if expected == today && Some(day) == today.pred_opt() {
+                expected = day;
+            } else {
+                break;
+            }
+        }
+        if met {
+            streak += 1;
+        } else if !frozen && day != today {
+            break;
+        }
+        expected -= Duration::days(1);
+    }
+    streak
+}
+
+/// The longest run of goal-met days, with frozen days bridging gaps.
+fn best_streak(days: &[(NaiveDate, bool, bool)]) -> u32 {
+    let mut best = 0;
+    let mut run = 0;
+    let mut next: Option<NaiveDate> = None;
+    for &(day, met, frozen) in days {
+        if next.is_some_and(|n| n.pred_opt() != Some(day)) {
+            run = 0;
+        }
+        if met {
+            run += 1;
+            best = best.max(run);
+        } else if !frozen {
+            run = 0;
+        }
+        next = Some(day);
+    }
+    best
+}
+
+/// Points for the day's goal once it's met and continues a streak, paid once
+/// a day.
+fn award_streak_day(conn: &Connection, now: i64) -> Result<(), String> {
+    let today = Local
+        .timestamp_millis_opt(now)
+        .single()
+        .map(|t| t.date_naive())
+        .ok_or_else(|| format!("Invalid time: {}", now))?;
+    let day = today.format("%Y-%m-%d").to_string();
+    let (met, awarded): (bool, bool) = conn
+        .query_row(
+            &format!(
+                "SELECT EXISTS(SELECT 1 FROM daily_activity WHERE day = ?1 AND {}),
+                        EXISTS(SELECT 1 FROM points_ledger WHERE event = 'streak_day' AND ref_id = ?1)",
+                GOAL_MET
+            ),
+            params![day],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    if !met || awarded {
+        return Ok(());
+    }
+    let streak = current_streak(&streak_days(conn, today)?, today);
+    if streak >= 2 {
+        let rules = gamification_rules(conn)?;
+        award_points(
+            conn,
+            "streak_day",
+            Some(&day),
+            streak as i64,
+            rules.streak_day,
+            now,
+        )?;
+    }
+    Ok(())
+}
+
+/// Points for completing a task: by priority and on time, once per task
+/// however often it is reopened, and for keeping a streak going.
+fn award_completion(conn: &Connection, task_id: &str, now: i64) -> Result<(), String> {
+    let awarded: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM points_ledger WHERE ref_id = ?1 AND event LIKE 'complete_%')",
+            params![task_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if awarded {
+        return Ok(());
+    }
+    let (priority, deadline): (i32, Option<i64>) = conn
+        .query_row(
+            "SELECT priority, deadline FROM tasks WHERE id = ?1",
+            params![task_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    let rules = gamification_rules(conn)?;
+    let (event, points) = match Priority::from_int(priority) {
+        Priority::Low => ("complete_low", rules.complete_low),
+        Priority::Normal => ("complete_normal", rules.complete_normal),
+        Priority::High => ("complete_high", rules.complete_high),
+    };
+    award_points(conn, event, Some(task_id), 1, points, now)?;
+    if deadline.is_some_and(|d| now <= d) {
+        award_points(conn, "on_time", Some(task_id), 1, rules.on_time_bonus, now)?;
+    }
+    award_streak_day(conn, now)?;
+    unlock_achievements(conn, now, false)
+}
+
+/// Unlocks every achievement whose goal the ledger now reaches. With
+/// `announced` they are not reported by `take_unannounced_achievements`.
+fn unlock_achievements(conn: &Connection, now: i64, announced: bool) -> Result<(), String> {
+    let rules = gamification_rules(conn)?;
+    let (completed, high, on_time, focus, streak, points): (i64, i64, i64, i64, i64, i64) = conn
+        .query_row(
+            "SELECT
+                (SELECT COUNT(*) FROM points_ledger WHERE event LIKE 'complete_%'),
+                (SELECT COUNT(*) FROM points_ledger WHERE event = 'complete_high'),
+                (SELECT COUNT(*) FROM points_ledger WHERE event = 'on_time'),
+                (SELECT COALESCE(SUM(quantity), 0) FROM points_ledger WHERE event = 'focus'),
+                (SELECT COALESCE(MAX(quantity), 0) FROM points_ledger WHERE event = 'streak_day'),
+                (SELECT COALESCE(SUM(points), 0) FROM points_ledger)",
+            [],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    let level = level_for(points, &rules.level_thresholds) as i64;
+    for def in ACHIEVEMENTS {
+        let value = match def.metric {
+            AchievementMetric::Completed => completed,
+            AchievementMetric::HighPriority => high,
+            AchievementMetric::OnTime => on_time,
+            AchievementMetric::FocusMinutes => focus,
+            AchievementMetric::Streak => streak,
+            AchievementMetric::Level => level,
+        };
+        if value >= def.target {
+            conn.execute(
+                "INSERT OR IGNORE INTO achievements (id, unlocked_at, announced_at) VALUES (?1, ?2, ?3)",
+                params![def.id, now, announced.then_some(now)],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks a repeat rule: "daily", "weekdays", or "custom" with a day mask.
+pub fn validate_repeat(mode: Option<&str>, mask: Option<i64>) -> Result<(), String> {
+    match mode {
+        None | Some("daily") | Some("weekdays") => Ok(()),
+        Some("custom") if matches!(mask, Some(1..=127)) => Ok(()),
+        Some("custom") => {
+            Err("repeat_days_mask must be between 1 and 127 for custom repeats".to_string())
+        }
+        Some(other) => Err(format!("Invalid repeat_mode: {}", other)),
+    }
+}
+
+const SECTION_COLUMNS: &str = "id, project_id, name, sort_order, archived_at, created_at";
+
+fn section_from_row(row: &rusqlite::Row) -> rusqlite::Result<Section> {
+    Ok(Section {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        sort_order: row.get(3)?,
+        archived_at: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
 fn local_date_time_to_ms(date: NaiveDate, hour: u32, minute: u32) -> i64 {
     let ndt = NaiveDateTime::new(
         date,
@@ -80,16 +1047,28 @@ fn local_date_time_to_ms(date: NaiveDate, hour: u32, minute: u32) -> i64 {
 
 impl AppDatabase {
     pub fn new(app_dir: PathBuf) -> Result<Self, String> {
-        let db_path = app_dir.join("focusflow.db");
-        if let Some(parent) = db_path.parent() {
+        Self::open(app_dir.join(DB_FILE_NAME))
+    }
+
+    /// Opens (and migrates) a database file directly. WAL and busy_timeout make it
+    /// safe for several processes (the app and `focusflow-cli`) to share one file.
+    pub fn open(db_path: PathBuf) -> Result<Self, String> {
+        if let Some(parent) = db_path.parent().filter(|p| !p.as_os_str().is_empty()) {
             std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
         }
 
         let conn = Connection::open(&db_path).map_err(|e| e.to_string())?;
         Self::configure_sqlite(&conn)?;
+        let undo_step = Arc::new(AtomicI64::new(0));
+        Self::register_undo_step(&conn, &undo_step)?;
         Self::migrate(&conn)?;
+        Self::install_undo_triggers(&conn)?;
 
-        Ok(AppDatabase { db_path, conn })
+        Ok(AppDatabase {
+            db_path,
+            conn,
+            undo_step,
+        })
     }
 
     // Метод для получения пути
@@ -111,7 +1090,26 @@ impl AppDatabase {
         Ok(())
     }
 
+    /// Registers `undo_step()`, the step this connection is journaling under
+    /// (NULL when none). It belongs to the connection rather than the file,
+    /// so writes from the CLI or the API on another connection never land in
+    /// the app's open step. Only this connection's TEMP undo triggers call it.
+    fn register_undo_step(conn: &Connection, step: &Arc<AtomicI64>) -> Result<(), String> {
+        let step = Arc::clone(step);
+        conn.create_scalar_function("undo_step", 0, FunctionFlags::SQLITE_UTF8, move |_| {
+            let current = step.load(Ordering::SeqCst);
+            Ok((current != 0).then_some(current))
+        })
+        .map_err(|e| e.to_string())
+    }
+
     fn migrate(conn: &Connection) -> Result<(), String> {
+        Self::migrate_to(conn, i32::MAX)
+    }
+
+    /// Runs the pending migrations up to and including `target`. Anything
+    /// short of `i32::MAX` is for tests that need an older schema
+    fn migrate_to(conn: &Connection, target: i32) -> Result<(), String> {
         // 1. Ensure migrations table exists
         conn.execute(
             "CREATE TABLE IF NOT EXISTS _migrations (
@@ -139,10 +1137,11 @@ impl AppDatabase {
             })
             .unwrap_or(None)
             .unwrap_or(0);
+        let pending = |ver: i32| current_version < ver && ver <= target;
 
         // 3. Define migrations
         // Version 1: Initial Schema
-        if current_version < 1 {
+        if pending(1) {
             // Check if legacy schema exists (projects table)
             let has_projects = conn
                 .query_row(
@@ -212,7 +1211,7 @@ impl AppDatabase {
         }
 
         // Version 2: Subtasks, Archive, Sort Order
-        if current_version < 2 {
+        if pending(2) {
             conn.execute_batch(
                 "CREATE TABLE IF NOT EXISTS subtasks (
                     id TEXT PRIMARY KEY,
@@ -267,7 +1266,7 @@ impl AppDatabase {
         }
 
         // Version 3: Folder hierarchy for projects
-        if current_version < 3 {
+        if pending(3) {
             // Add parent_id column to projects
             let has_parent_id = conn
                 .query_row(
@@ -308,7 +1307,7 @@ impl AppDatabase {
         }
 
         // Version 4: Finance (Transactions & Debts)
-        if current_version < 4 {
+        if pending(4) {
             conn.execute_batch(
                 "CREATE TABLE IF NOT EXISTS transactions (
                     id TEXT PRIMARY KEY,
@@ -335,7 +1334,7 @@ impl AppDatabase {
         }
 
         // Migration 5: Add detailed loan fields to debts (with existence checks)
-        if current_version < 5 {
+        if pending(5) {
             // Check and add start_date column
             let has_start_date = conn
                 .query_row(
@@ -385,75 +1384,835 @@ impl AppDatabase {
         }
 
         // Migration 6: Add last_reminded_date to debts
-        if current_version < 6 {
+        if pending(6) {
             conn.execute("ALTER TABLE debts ADD COLUMN last_reminded_date TEXT", [])
                 .map_err(|e| e.to_string())?;
 
             commit_migration(6)?;
         }
 
-        Ok(())
-    }
+        // Migration 7: Local HTTP API settings (kept out of `settings` so the
+        // token never ends up in exported backups)
+        if pending(7) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS api_settings (
+                    id INTEGER PRIMARY KEY CHECK (id = 1),
+                    enabled INTEGER NOT NULL DEFAULT 0,
+                    port INTEGER NOT NULL DEFAULT 17345,
+                    token TEXT NOT NULL,
+                    updated_at INTEGER NOT NULL
+                );",
+            )
+            .map_err(|e| e.to_string())?;
 
-    // --- ПУБЛИЧНЫЕ МЕТОДЫ ДЛЯ MAIN.RS ---
+            commit_migration(7)?;
+        }
 
-    pub fn import_data(
-        &mut self,
-        projects: Vec<Project>,
-        tasks: Vec<Task>,
-        settings: AppSettings,
-    ) -> Result<(), String> {
-        let conn = &mut self.conn;
-        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        // Migration 8: Outgoing hooks and their delivery log
+        if pending(8) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS hooks (
+                    id TEXT PRIMARY KEY,
+                    event TEXT NOT NULL,
+                    kind TEXT NOT NULL,
+                    target TEXT NOT NULL,
+                    enabled INTEGER NOT NULL DEFAULT 1,
+                    created_at INTEGER NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS hook_deliveries (
+                    id TEXT PRIMARY KEY,
+                    hook_id TEXT NOT NULL REFERENCES hooks(id) ON DELETE CASCADE,
+                    event TEXT NOT NULL,
+                    payload TEXT NOT NULL,
+                    attempts INTEGER NOT NULL,
+                    success INTEGER NOT NULL,
+                    status_code INTEGER,
+                    error TEXT,
+                    duration_ms INTEGER NOT NULL,
+                    created_at INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_hook_deliveries_created ON hook_deliveries(created_at);",
+            )
+            .map_err(|e| e.to_string())?;
 
-        // 1. Projects (Upsert)
-        for p in projects {
-            tx.execute(
-                "INSERT INTO projects (id, name, color, priority, created_at) VALUES (?1, ?2, ?3, ?4, ?5)
-                 ON CONFLICT(id) DO UPDATE SET name=excluded.name, color=excluded.color, priority=excluded.priority",
-                params![p.id, p.name, p.color, p.priority as i32, p.created_at]
-            ).map_err(|e| e.to_string())?;
+            commit_migration(8)?;
         }
 
-        // 2. Tasks (Upsert)
-        for t in tasks {
-            let tags = serde_json::to_string(&t.tags).unwrap_or_else(|_| "[]".to_string());
-            tx.execute(
-                "INSERT INTO tasks (id, project_id, title, description, priority, status, created_at, completed_at, deadline, estimated_minutes, actual_minutes, tags, remind_at, reminded_at, repeat_mode, repeat_days_mask) 
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
-                 ON CONFLICT(id) DO UPDATE SET 
-                    project_id=excluded.project_id, title=excluded.title, description=excluded.description, 
-                    priority=excluded.priority, status=excluded.status, completed_at=excluded.completed_at, 
-                    deadline=excluded.deadline, estimated_minutes=excluded.estimated_minutes, actual_minutes=excluded.actual_minutes, 
-                    tags=excluded.tags, remind_at=excluded.remind_at, repeat_mode=excluded.repeat_mode, repeat_days_mask=excluded.repeat_days_mask",
-                params![t.id, t.project_id, t.title, t.description, t.priority as i32, t.status as i32, t.created_at, t.completed_at, t.deadline, t.estimated_minutes, t.actual_minutes, tags, t.remind_at, t.reminded_at, t.repeat_mode, t.repeat_days_mask]
-            ).map_err(|e| e.to_string())?;
+        // Migration 9: Project metadata, manual order and archiving
+        if pending(9) {
+            let columns = [
+                ("projects", "sort_order", "INTEGER NOT NULL DEFAULT 0"),
+                ("projects", "description", "TEXT"),
+                ("projects", "icon", "TEXT"),
+                ("projects", "archived_at", "INTEGER"),
+                ("projects", "default_priority", "INTEGER"),
+                ("projects", "default_tags", "TEXT NOT NULL DEFAULT '[]'"),
+                // Set on tasks archived together with their project, so
+                // unarchiving the project doesn't revive tasks archived by hand
+                (
+                    "tasks",
+                    "archived_with_project",
+                    "INTEGER NOT NULL DEFAULT 0",
+                ),
+            ];
+            for (table, column, decl) in columns {
+                let exists = conn
+                    .query_row(
+                        &format!(
+                            "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name='{}'",
+                            table, column
+                        ),
+                        [],
+                        |row| row.get::<_, i32>(0),
+                    )
+                    .unwrap_or(0)
+                    > 0;
+                if !exists {
+                    conn.execute(
+                        &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl),
+                        [],
+                    )
+                    .map_err(|e| e.to_string())?;
+                }
+            }
+
+            commit_migration(9)?;
         }
 
-        // 3. Settings (Update)
-        let now = chrono::Utc::now().timestamp_millis();
-        tx.execute(
-            "INSERT INTO settings (id, pomodoro_length, short_break_length, long_break_length, pomodoros_until_long_break, sound_enabled, auto_start_breaks, auto_start_pomodoros, global_shortcuts_enabled, start_minimized, close_to_tray, reminder_lead_minutes, updated_at)
-             VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
-             ON CONFLICT(id) DO UPDATE SET 
-                pomodoro_length=excluded.pomodoro_length, short_break_length=excluded.short_break_length, long_break_length=excluded.long_break_length, 
-                pomodoros_until_long_break=excluded.pomodoros_until_long_break, sound_enabled=excluded.sound_enabled, 
-                auto_start_breaks=excluded.auto_start_breaks, auto_start_pomodoros=excluded.auto_start_pomodoros, 
-                global_shortcuts_enabled=excluded.global_shortcuts_enabled, start_minimized=excluded.start_minimized, 
-                close_to_tray=excluded.close_to_tray, reminder_lead_minutes=excluded.reminder_lead_minutes, updated_at=excluded.updated_at",
-            params![settings.pomodoro_length, settings.short_break_length, settings.long_break_length, settings.pomodoros_until_long_break, settings.sound_enabled, settings.auto_start_breaks, settings.auto_start_pomodoros, settings.global_shortcuts_enabled, settings.start_minimized, settings.close_to_tray, settings.reminder_lead_minutes, now]
-        ).map_err(|e| e.to_string())?;
+        // Migration 10: Sections (headings) inside projects
+        if pending(10) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS sections (
+                    id TEXT PRIMARY KEY,
+                    project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+                    name TEXT NOT NULL,
+                    sort_order INTEGER NOT NULL DEFAULT 0,
+                    archived_at INTEGER,
+                    created_at INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_sections_project ON sections(project_id);",
+            )
+            .map_err(|e| e.to_string())?;
+            let exists = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM pragma_table_info('tasks') WHERE name='section_id'",
+                    [],
+                    |row| row.get::<_, i32>(0),
+                )
+                .unwrap_or(0)
+                > 0;
+            if !exists {
+                conn.execute(
+                    "ALTER TABLE tasks ADD COLUMN section_id TEXT REFERENCES sections(id) ON DELETE SET NULL",
+                    [],
+                )
+                .map_err(|e| e.to_string())?;
+            }
 
-        tx.commit().map_err(|e| e.to_string())?;
-        Ok(())
-    }
+            commit_migration(10)?;
+        }
 
-    pub fn get_settings(&self) -> Result<AppSettings, String> {
-        let conn = &self.conn;
-        let mut stmt = conn.prepare("SELECT pomodoro_length, short_break_length, long_break_length, pomodoros_until_long_break, sound_enabled, auto_start_breaks, auto_start_pomodoros, global_shortcuts_enabled, start_minimized, close_to_tray, reminder_lead_minutes FROM settings WHERE id = 1").map_err(|e| e.to_string())?;
-        let res = stmt.query_row([], |row| {
-            Ok(AppSettings {
-                pomodoro_length: row.get::<_, i64>(0)? as u32,
+        // Migration 11: Kanban boards with custom columns
+        if pending(11) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS boards (
+                    id TEXT PRIMARY KEY,
+                    project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+                    name TEXT NOT NULL,
+                    created_at INTEGER NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS board_columns (
+                    id TEXT PRIMARY KEY,
+                    board_id TEXT NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+                    name TEXT NOT NULL,
+                    category INTEGER NOT NULL,
+                    wip_limit INTEGER,
+                    sort_order INTEGER NOT NULL DEFAULT 0
+                );
+                CREATE TABLE IF NOT EXISTS board_cards (
+                    board_id TEXT NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+                    task_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+                    column_id TEXT NOT NULL REFERENCES board_columns(id) ON DELETE CASCADE,
+                    position INTEGER NOT NULL,
+                    PRIMARY KEY (board_id, task_id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_boards_project ON boards(project_id);
+                CREATE INDEX IF NOT EXISTS idx_board_columns_board ON board_columns(board_id);",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(11)?;
+        }
+
+        // Migration 12: Task dependencies ("task_id can't start until depends_on_id is done")
+        if pending(12) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS task_dependencies (
+                    task_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+                    depends_on_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+                    created_at INTEGER NOT NULL,
+                    PRIMARY KEY (task_id, depends_on_id),
+                    CHECK (task_id != depends_on_id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_task_dependencies_depends_on ON task_dependencies(depends_on_id);",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(12)?;
+        }
+
+        // Migration 13: Trash (soft delete) and its retention setting
+        if pending(13) {
+            conn.execute_batch(
+                "ALTER TABLE tasks ADD COLUMN deleted_at INTEGER;
+                ALTER TABLE projects ADD COLUMN deleted_at INTEGER;
+                ALTER TABLE subtasks ADD COLUMN deleted_at INTEGER;
+                ALTER TABLE transactions ADD COLUMN deleted_at INTEGER;
+                ALTER TABLE debts ADD COLUMN deleted_at INTEGER;
+                ALTER TABLE settings ADD COLUMN trash_retention_days INTEGER NOT NULL DEFAULT 30;
+                CREATE INDEX IF NOT EXISTS idx_tasks_deleted ON tasks(deleted_at);
+                CREATE INDEX IF NOT EXISTS idx_projects_deleted ON projects(deleted_at);",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(13)?;
+        }
+
+        // Migration 14: Undo/redo journal. Triggers on UNDO_TABLES write the
+        // inverse SQL of every change into undo_log while undo_state.step is set
+        if pending(14) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS undo_steps (
+                    step INTEGER PRIMARY KEY AUTOINCREMENT,
+                    label TEXT NOT NULL,
+                    created_at INTEGER NOT NULL,
+                    undone INTEGER NOT NULL DEFAULT 0
+                );
+                CREATE TABLE IF NOT EXISTS undo_log (
+                    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                    step INTEGER NOT NULL REFERENCES undo_steps(step) ON DELETE CASCADE,
+                    sql TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_undo_log_step ON undo_log(step);
+                CREATE TABLE IF NOT EXISTS undo_state (
+                    id INTEGER PRIMARY KEY CHECK (id = 1),
+                    step INTEGER
+                );
+                INSERT OR IGNORE INTO undo_state (id, step) VALUES (1, NULL);",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(14)?;
+        }
+
+        // Migration 15: Task history. Rows are written by triggers, so they
+        // outlive hard deletes; purging the trash cleans them up
+        if pending(15) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS task_events (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    task_id TEXT NOT NULL,
+                    action TEXT NOT NULL,
+                    field TEXT,
+                    old_value TEXT,
+                    new_value TEXT,
+                    created_at INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_task_events_task ON task_events(task_id, id);",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(15)?;
+        }
+
+        // Migration 16: Task revisions. Any write that doesn't move the
+        // revision itself bumps it, so stale patches can be refused. Undo
+        // replays an older revision, which bumps it again: it never goes back
+        if pending(16) {
+            conn.execute_batch(
+                "ALTER TABLE tasks ADD COLUMN updated_at INTEGER;
+                ALTER TABLE tasks ADD COLUMN revision INTEGER NOT NULL DEFAULT 0;
+                CREATE TRIGGER IF NOT EXISTS task_revision AFTER UPDATE ON tasks
+                WHEN new.revision <= old.revision BEGIN
+                    UPDATE tasks SET revision = MAX(old.revision, new.revision) + 1,
+                        updated_at = CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)
+                    WHERE rowid = new.rowid;
+                END;",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(16)?;
+        }
+
+        // Migration 17: Tags move from the tasks.tags JSON to tags/task_tags.
+        // Undo history from before can't be replayed against the new schema
+        if pending(17) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS tags (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL UNIQUE,
+                    color TEXT,
+                    created_at INTEGER NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS task_tags (
+                    task_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+                    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+                    position INTEGER NOT NULL DEFAULT 0,
+                    PRIMARY KEY (task_id, tag_id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_task_tags_tag ON task_tags(tag_id);",
+            )
+            .map_err(|e| e.to_string())?;
+
+            let legacy: Vec<(String, String)> = {
+                let mut stmt = conn
+                    .prepare("SELECT id, tags FROM tasks")
+                    .map_err(|e| e.to_string())?;
+                let rows = stmt
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                    .map_err(|e| e.to_string())?;
+                rows.collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| e.to_string())?
+            };
+            for (id, raw) in legacy {
+                set_task_tags(conn, &id, &parse_legacy_tags(&id, &raw))?;
+            }
+
+            // Tag changes count as task changes for revisions
+            conn.execute_batch(
+                "DROP TRIGGER IF EXISTS undo_tasks_insert;
+                DROP TRIGGER IF EXISTS undo_tasks_update;
+                DROP TRIGGER IF EXISTS undo_tasks_delete;
+                DROP TRIGGER IF EXISTS task_events_update;
+                ALTER TABLE tasks DROP COLUMN tags;
+                DELETE FROM undo_steps;
+                CREATE TRIGGER IF NOT EXISTS task_tags_revision_insert AFTER INSERT ON task_tags BEGIN
+                    UPDATE tasks SET revision = revision WHERE id = new.task_id;
+                END;
+                CREATE TRIGGER IF NOT EXISTS task_tags_revision_delete AFTER DELETE ON task_tags BEGIN
+                    UPDATE tasks SET revision = revision WHERE id = old.task_id;
+                END;",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(17)?;
+        }
+
+        // Migration 18: Task and project templates, stored as JSON bodies
+        if pending(18) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS templates (
+                    id TEXT PRIMARY KEY,
+                    kind TEXT NOT NULL,
+                    name TEXT NOT NULL,
+                    body TEXT NOT NULL,
+                    created_at INTEGER NOT NULL
+                );",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(18)?;
+        }
+
+        // Migration 19: "Do on" dates, kept as local date strings so they
+        // don't move with the timezone
+        if pending(19) {
+            conn.execute_batch(
+                "ALTER TABLE tasks ADD COLUMN scheduled_at TEXT;
+                CREATE INDEX IF NOT EXISTS idx_tasks_scheduled_at ON tasks(scheduled_at);",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(19)?;
+        }
+
+        // Migration 20: Per-day plans with their own order, and the daily
+        // pomodoro budget they are measured against
+        if pending(20) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS daily_plan (
+                    date TEXT NOT NULL,
+                    task_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+                    position INTEGER NOT NULL,
+                    added_at INTEGER NOT NULL,
+                    PRIMARY KEY (date, task_id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_daily_plan_task ON daily_plan(task_id);
+                ALTER TABLE settings ADD COLUMN daily_pomodoros INTEGER NOT NULL DEFAULT 8;",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(20)?;
+        }
+
+        // Migration 21: Time blocks and the working hours they are
+        // auto-scheduled into
+        if pending(21) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS time_blocks (
+                    id TEXT PRIMARY KEY,
+                    task_id TEXT REFERENCES tasks(id) ON DELETE CASCADE,
+                    title TEXT,
+                    start_at INTEGER NOT NULL,
+                    end_at INTEGER NOT NULL,
+                    completed_at INTEGER,
+                    created_at INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_time_blocks_start ON time_blocks(start_at);
+                CREATE INDEX IF NOT EXISTS idx_time_blocks_task ON time_blocks(task_id);
+                ALTER TABLE settings ADD COLUMN work_start_minutes INTEGER NOT NULL DEFAULT 540;
+                ALTER TABLE settings ADD COLUMN work_end_minutes INTEGER NOT NULL DEFAULT 1020;
+                ALTER TABLE settings ADD COLUMN work_days_mask INTEGER NOT NULL DEFAULT 31;",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(21)?;
+        }
+
+        // Migration 22: Time entries from the start/stop timer or entered by
+        // hand. The partial unique index allows one running timer at most
+        if pending(22) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS time_entries (
+                    id TEXT PRIMARY KEY,
+                    task_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+                    note TEXT,
+                    started_at INTEGER NOT NULL,
+                    ended_at INTEGER,
+                    created_at INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_time_entries_started ON time_entries(started_at);
+                CREATE INDEX IF NOT EXISTS idx_time_entries_task ON time_entries(task_id);
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_time_entries_running
+                    ON time_entries((ended_at IS NULL)) WHERE ended_at IS NULL;",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(22)?;
+        }
+
+        // Migration 23: Points ledger, its rules, and unlocked achievements.
+        // Done tasks and finished focus sessions are credited with the
+        // default rules; streak bonuses start from here
+        if pending(23) {
+            let rules = GamificationRules::default();
+            conn.execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS points_ledger (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    event TEXT NOT NULL,
+                    ref_id TEXT,
+                    quantity INTEGER NOT NULL DEFAULT 1,
+                    points INTEGER NOT NULL,
+                    created_at INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_points_ledger_ref ON points_ledger(ref_id, event);
+                CREATE TABLE IF NOT EXISTS gamification_rules (
+                    id INTEGER PRIMARY KEY CHECK (id = 1),
+                    body TEXT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS achievements (
+                    id TEXT PRIMARY KEY,
+                    unlocked_at INTEGER NOT NULL,
+                    announced_at INTEGER
+                );
+                INSERT INTO points_ledger (event, ref_id, points, created_at)
+                    SELECT CASE priority WHEN 0 THEN 'complete_low' WHEN 2 THEN 'complete_high' ELSE 'complete_normal' END,
+                           id,
+                           CASE priority WHEN 0 THEN {} WHEN 2 THEN {} ELSE {} END,
+                           COALESCE(completed_at, created_at)
+                    FROM tasks WHERE status = 2 AND deleted_at IS NULL;
+                INSERT INTO points_ledger (event, ref_id, points, created_at)
+                    SELECT 'on_time', id, {}, completed_at FROM tasks
+                    WHERE status = 2 AND deleted_at IS NULL AND completed_at <= deadline;
+                INSERT INTO points_ledger (event, ref_id, quantity, points, created_at)
+                    SELECT 'focus', id, duration_minutes, duration_minutes * {}, COALESCE(ended_at, started_at)
+                    FROM focus_sessions WHERE completed = 1 AND duration_minutes > 0;",
+                rules.complete_low,
+                rules.complete_high,
+                rules.complete_normal,
+                rules.on_time_bonus,
+                rules.focus_minute
+            ))
+            .map_err(|e| e.to_string())?;
+            // Achievements earned before now unlock without an announcement
+            unlock_achievements(conn, chrono::Utc::now().timestamp_millis(), true)?;
+
+            commit_migration(23)?;
+        }
+
+        // Migration 24: Daily activity for streaks, with a daily goal and
+        // freeze days. Backfilled from past completions and focus sessions
+        if pending(24) {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS daily_activity (
+                    day TEXT PRIMARY KEY,
+                    tasks_done INTEGER NOT NULL DEFAULT 0,
+                    focus_minutes INTEGER NOT NULL DEFAULT 0,
+                    goal_tasks INTEGER NOT NULL,
+                    goal_focus_minutes INTEGER NOT NULL,
+                    frozen_at INTEGER
+                );
+                ALTER TABLE tasks ADD COLUMN counted_on TEXT;
+                ALTER TABLE settings ADD COLUMN daily_goal_tasks INTEGER NOT NULL DEFAULT 1;
+                ALTER TABLE settings ADD COLUMN daily_goal_focus_minutes INTEGER NOT NULL DEFAULT 0;
+                ALTER TABLE settings ADD COLUMN streak_freezes_per_month INTEGER NOT NULL DEFAULT 2;",
+            )
+            .map_err(|e| e.to_string())?;
+            // Past days are dated in the time zone at migration time, the
+            // best guess there is
+            let completions: Vec<(String, i64)> = {
+                let mut stmt = conn
+                    .prepare("SELECT id, completed_at FROM tasks WHERE status = 2 AND completed_at IS NOT NULL AND deleted_at IS NULL")
+                    .map_err(|e| e.to_string())?;
+                let rows = stmt
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                    .map_err(|e| e.to_string())?;
+                rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+            };
+            for (task_id, completed_at) in completions {
+                record_completion(conn, &task_id, completed_at)?;
+            }
+            let sessions: Vec<(i64, u32)> = {
+                let mut stmt = conn
+                    .prepare("SELECT COALESCE(ended_at, started_at), duration_minutes FROM focus_sessions WHERE completed = 1 AND duration_minutes > 0")
+                    .map_err(|e| e.to_string())?;
+                let rows = stmt
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                    .map_err(|e| e.to_string())?;
+                rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+            };
+            for (at, minutes) in sessions {
+                record_activity(conn, at, 0, minutes)?;
+            }
+
+            commit_migration(24)?;
+        }
+
+        // Migration 25: the step being journaled moved to the connection
+        // (`undo_step()`), so one connection can't record into another's
+        if pending(25) {
+            conn.execute_batch("DROP TABLE IF EXISTS undo_state;")
+                .map_err(|e| e.to_string())?;
+
+            commit_migration(25)?;
+        }
+
+        // Migration 26: the undo triggers became TEMP triggers installed by
+        // each app connection, so other SQLite clients (the sqlite3 shell,
+        // backup scripts) don't need `undo_step()` to write
+        if pending(26) {
+            for table in UNDO_TABLES {
+                conn.execute_batch(&format!(
+                    "DROP TRIGGER IF EXISTS undo_{t}_insert;
+                    DROP TRIGGER IF EXISTS undo_{t}_update;
+                    DROP TRIGGER IF EXISTS undo_{t}_delete;",
+                    t = table
+                ))
+                .map_err(|e| e.to_string())?;
+            }
+
+            commit_migration(26)?;
+        }
+
+        // Migration 27: Section archiving gets its own flag, so restoring a
+        // section doesn't revive tasks archived with the project or the other
+        // way round. Tasks of archived sections in live projects move over
+        if pending(27) {
+            conn.execute_batch(
+                "ALTER TABLE tasks ADD COLUMN archived_with_section INTEGER NOT NULL DEFAULT 0;
+                UPDATE tasks SET archived_with_section = 1, archived_with_project = 0
+                WHERE archived_with_project = 1
+                    AND section_id IN (SELECT id FROM sections WHERE archived_at IS NOT NULL)
+                    AND NOT EXISTS (SELECT 1 FROM projects p WHERE p.id = tasks.project_id AND p.archived_at IS NOT NULL);",
+            )
+            .map_err(|e| e.to_string())?;
+
+            commit_migration(27)?;
+        }
+
+        // The history triggers list every column, so rebuild them whenever
+        // the schema moved. A partial run may lack tables they cover
+        let latest: i32 = conn
+            .query_row("SELECT MAX(version) FROM _migrations", [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        if target == i32::MAX && latest != current_version {
+            Self::install_task_event_triggers(conn)?;
+        }
+
+        Ok(())
+    }
+
+    fn install_task_event_triggers(conn: &Connection) -> Result<(), String> {
+        const NOW_MS: &str = "CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)";
+        let changes = TASK_EVENT_FIELDS
+            .iter()
+            .map(|f| {
+                format!(
+                    "INSERT INTO task_events (task_id, action, field, old_value, new_value, created_at)
+                     SELECT new.id, 'updated', '{f}', old.{f}, new.{f}, {now} WHERE old.{f} IS NOT new.{f};",
+                    f = f,
+                    now = NOW_MS
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        conn.execute_batch(&format!(
+            "DROP TRIGGER IF EXISTS task_events_insert;
+            DROP TRIGGER IF EXISTS task_events_update;
+            DROP TRIGGER IF EXISTS task_events_tag_insert;
+            DROP TRIGGER IF EXISTS task_events_tag_delete;
+            CREATE TRIGGER task_events_insert AFTER INSERT ON tasks BEGIN
+                INSERT INTO task_events (task_id, action, field, old_value, new_value, created_at)
+                VALUES (new.id, 'created', NULL, NULL, new.title, {now});
+            END;
+            CREATE TRIGGER task_events_update AFTER UPDATE ON tasks BEGIN
+                {changes}
+            END;
+            CREATE TRIGGER task_events_tag_insert AFTER INSERT ON task_tags BEGIN
+                INSERT INTO task_events (task_id, action, field, old_value, new_value, created_at)
+                SELECT new.task_id, 'updated', 'tags', NULL, name, {now} FROM tags WHERE id = new.tag_id;
+            END;
+            CREATE TRIGGER task_events_tag_delete AFTER DELETE ON task_tags BEGIN
+                INSERT INTO task_events (task_id, action, field, old_value, new_value, created_at)
+                SELECT old.task_id, 'updated', 'tags', name, NULL, {now} FROM tags
+                WHERE id = old.tag_id AND EXISTS (SELECT 1 FROM tasks WHERE id = old.task_id);
+            END;",
+            now = NOW_MS,
+            changes = changes,
+        ))
+        .map_err(|e| e.to_string())
+    }
+
+    /// Journals writes to `UNDO_TABLES` while this connection records a step.
+    /// The triggers are TEMP: they live and die with the connection and list
+    /// the columns of the schema it opened.
+    fn install_undo_triggers(conn: &Connection) -> Result<(), String> {
+        const RECORDING: &str = "undo_step()";
+        for table in UNDO_TABLES {
+            // (name, position in the primary key or 0)
+            let info: Vec<(String, i64)> = {
+                let mut stmt = conn
+                    .prepare(&format!("PRAGMA table_info({})", table))
+                    .map_err(|e| e.to_string())?;
+                let rows = stmt
+                    .query_map([], |row| Ok((row.get(1)?, row.get(5)?)))
+                    .map_err(|e| e.to_string())?;
+                rows.collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| e.to_string())?
+            };
+            let columns: Vec<&String> = info.iter().map(|(c, _)| c).collect();
+            // Rows are found by primary key, not rowid: other connections
+            // write to the same tables, and a rowid can be handed out again
+            let mut primary_key: Vec<&(String, i64)> =
+                info.iter().filter(|(_, pk)| *pk > 0).collect();
+            primary_key.sort_by_key(|(_, pk)| *pk);
+            if primary_key.is_empty() {
+                return Err(format!("{} has no primary key to journal by", table));
+            }
+            let key = primary_key
+                .iter()
+                .map(|(c, _)| format!("'\"{c}\"=' || quote(new.\"{c}\")"))
+                .collect::<Vec<_>>()
+                .join(" || ' AND ' || ");
+            let assignments = columns
+                .iter()
+                .map(|c| format!("'\"{c}\"=' || quote(old.\"{c}\")"))
+                .collect::<Vec<_>>()
+                .join(" || ',' || ");
+            let names = columns
+                .iter()
+                .map(|c| format!("\"{}\"", c))
+                .collect::<Vec<_>>()
+                .join(",");
+            let values = columns
+                .iter()
+                .map(|c| format!("quote(old.\"{}\")", c))
+                .collect::<Vec<_>>()
+                .join(" || ',' || ");
+            conn.execute_batch(&format!(
+                "CREATE TEMP TRIGGER undo_{t}_insert AFTER INSERT ON {t} WHEN {rec} IS NOT NULL BEGIN
+                    INSERT INTO undo_log (step, sql) VALUES ({rec}, 'DELETE FROM {t} WHERE ' || {key});
+                END;
+                CREATE TEMP TRIGGER undo_{t}_update AFTER UPDATE ON {t} WHEN {rec} IS NOT NULL BEGIN
+                    INSERT INTO undo_log (step, sql) VALUES ({rec}, 'UPDATE {t} SET ' || {assignments} || ' WHERE ' || {key});
+                END;
+                CREATE TEMP TRIGGER undo_{t}_delete BEFORE DELETE ON {t} WHEN {rec} IS NOT NULL BEGIN
+                    INSERT INTO undo_log (step, sql) VALUES ({rec}, 'INSERT INTO {t} ({names}) VALUES (' || {values} || ')');
+                END;",
+                t = table,
+                rec = RECORDING,
+                key = key,
+                assignments = assignments,
+                names = names,
+                values = values,
+            ))
+            .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    // --- ПУБЛИЧНЫЕ МЕТОДЫ ДЛЯ MAIN.RS ---
+
+    pub fn export_bundle(&self) -> Result<ExportBundle, String> {
+        Ok(ExportBundle {
+            version: 1,
+            exported_at: chrono::Utc::now().timestamp_millis(),
+            projects: self.get_all_projects()?,
+            sections: self.get_all_sections()?,
+            tasks: self.get_tasks(None, None, None, None)?,
+            tags: self.get_tags()?,
+            boards: self.get_all_boards()?,
+            settings: self.get_settings()?,
+        })
+    }
+
+    pub fn import_data(
+        &mut self,
+        projects: Vec<Project>,
+        sections: Vec<Section>,
+        tasks: Vec<Task>,
+        tags: Vec<Tag>,
+        boards: Vec<Board>,
+        settings: AppSettings,
+    ) -> Result<(), String> {
+        let conn = &mut self.conn;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+        // 0. Tag colours; the tasks below attach the tags themselves
+        let now = chrono::Utc::now().timestamp_millis();
+        for tag in tags {
+            tx.execute(
+                "INSERT INTO tags (name, color, created_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(name) DO UPDATE SET color = excluded.color",
+                params![tag.name.trim(), tag.color, now],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+
+        // 1. Projects (Upsert)
+        for p in projects {
+            let default_tags =
+                serde_json::to_string(&p.default_tags).unwrap_or_else(|_| "[]".to_string());
+            tx.execute(
+                "INSERT INTO projects (id, name, color, priority, created_at, parent_id, is_folder, sort_order, description, icon, archived_at, default_priority, default_tags)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                 ON CONFLICT(id) DO UPDATE SET name=excluded.name, color=excluded.color, priority=excluded.priority,
+                    parent_id=excluded.parent_id, is_folder=excluded.is_folder, sort_order=excluded.sort_order,
+                    description=excluded.description, icon=excluded.icon, archived_at=excluded.archived_at,
+                    default_priority=excluded.default_priority, default_tags=excluded.default_tags",
+                params![p.id, p.name, p.color, p.priority as i32, p.created_at, p.parent_id, p.is_folder, p.sort_order, p.description, p.icon, p.archived_at, p.default_priority.map(|d| d as i32), default_tags]
+            ).map_err(|e| e.to_string())?;
+        }
+
+        // 2. Sections (Upsert)
+        for s in sections {
+            tx.execute(
+                "INSERT INTO sections (id, project_id, name, sort_order, archived_at, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(id) DO UPDATE SET project_id=excluded.project_id, name=excluded.name,
+                    sort_order=excluded.sort_order, archived_at=excluded.archived_at",
+                params![
+                    s.id,
+                    s.project_id,
+                    s.name,
+                    s.sort_order,
+                    s.archived_at,
+                    s.created_at
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+
+        // 3. Tasks (Upsert)
+        let mut dependencies = Vec::new();
+        for t in tasks {
+            if !t.depends_on.is_empty() {
+                dependencies.push((t.id.clone(), t.depends_on.clone()));
+            }
+            tx.execute(
+                "INSERT INTO tasks (id, project_id, title, description, priority, status, created_at, completed_at, deadline, estimated_minutes, actual_minutes, remind_at, reminded_at, repeat_mode, repeat_days_mask, section_id, scheduled_at) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+                 ON CONFLICT(id) DO UPDATE SET 
+                    project_id=excluded.project_id, title=excluded.title, description=excluded.description, 
+                    priority=excluded.priority, status=excluded.status, completed_at=excluded.completed_at, 
+                    deadline=excluded.deadline, estimated_minutes=excluded.estimated_minutes, actual_minutes=excluded.actual_minutes, 
+                    remind_at=excluded.remind_at, repeat_mode=excluded.repeat_mode, repeat_days_mask=excluded.repeat_days_mask,
+                    section_id=excluded.section_id, scheduled_at=excluded.scheduled_at",
+                params![t.id, t.project_id, t.title, t.description, t.priority as i32, t.status as i32, t.created_at, t.completed_at, t.deadline, t.estimated_minutes, t.actual_minutes, t.remind_at, t.reminded_at, t.repeat_mode, t.repeat_days_mask, t.section_id, t.scheduled_at]
+            ).map_err(|e| e.to_string())?;
+            set_task_tags(&tx, &t.id, &t.tags)?;
+        }
+
+        // Dependencies need every task in place first; edges to tasks that
+        // aren't in the database are dropped
+        let linked_at = chrono::Utc::now().timestamp_millis();
+        for (task_id, depends_on) in &dependencies {
+            for dep in depends_on {
+                tx.execute(
+                    "INSERT OR IGNORE INTO task_dependencies (task_id, depends_on_id, created_at)
+                     SELECT ?1, ?2, ?3 WHERE EXISTS (SELECT 1 FROM tasks WHERE id = ?2) AND ?1 != ?2",
+                    params![task_id, dep, linked_at],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+
+        // 4. Boards (Replace, so removed columns and cards don't linger)
+        for b in boards {
+            tx.execute(
+                "INSERT INTO boards (id, project_id, name, created_at) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(id) DO UPDATE SET project_id=excluded.project_id, name=excluded.name",
+                params![b.id, b.project_id, b.name, b.created_at],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.execute(
+                "DELETE FROM board_columns WHERE board_id = ?1",
+                params![b.id],
+            )
+            .map_err(|e| e.to_string())?;
+            for c in &b.columns {
+                tx.execute(
+                    "INSERT INTO board_columns (id, board_id, name, category, wip_limit, sort_order) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![c.id, b.id, c.name, c.category as i32, c.wip_limit, c.sort_order],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            for card in &b.cards {
+                tx.execute(
+                    "INSERT OR REPLACE INTO board_cards (board_id, task_id, column_id, position) VALUES (?1, ?2, ?3, ?4)",
+                    params![b.id, card.task_id, card.column_id, card.position],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+
+        // 5. Settings (Update)
+        let now = chrono::Utc::now().timestamp_millis();
+        tx.execute(
+            "INSERT INTO settings (id, pomodoro_length, short_break_length, long_break_length, pomodoros_until_long_break, sound_enabled, auto_start_breaks, auto_start_pomodoros, global_shortcuts_enabled, start_minimized, close_to_tray, reminder_lead_minutes, trash_retention_days, daily_pomodoros, work_start_minutes, work_end_minutes, work_days_mask, updated_at)
+             VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+             ON CONFLICT(id) DO UPDATE SET 
+                pomodoro_length=excluded.pomodoro_length, short_break_length=excluded.short_break_length, long_break_length=excluded.long_break_length, 
+                pomodoros_until_long_break=excluded.pomodoros_until_long_break, sound_enabled=excluded.sound_enabled, 
+                auto_start_breaks=excluded.auto_start_breaks, auto_start_pomodoros=excluded.auto_start_pomodoros, 
+                global_shortcuts_enabled=excluded.global_shortcuts_enabled, start_minimized=excluded.start_minimized, 
+                close_to_tray=excluded.close_to_tray, reminder_lead_minutes=excluded.reminder_lead_minutes,
+                trash_retention_days=excluded.trash_retention_days, daily_pomodoros=excluded.daily_pomodoros,
+                work_start_minutes=excluded.work_start_minutes, work_end_minutes=excluded.work_end_minutes,
+                work_days_mask=excluded.work_days_mask, updated_at=excluded.updated_at",
+            params![settings.pomodoro_length, settings.short_break_length, settings.long_break_length, settings.pomodoros_until_long_break, settings.sound_enabled, settings.auto_start_breaks, settings.auto_start_pomodoros, settings.global_shortcuts_enabled, settings.start_minimized, settings.close_to_tray, settings.reminder_lead_minutes, settings.trash_retention_days, settings.daily_pomodoros, settings.work_start_minutes, settings.work_end_minutes, settings.work_days_mask, now]
+        ).map_err(|e| e.to_string())?;
+
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn get_settings(&self) -> Result<AppSettings, String> {
+        let conn = &self.conn;
+        let mut stmt = conn.prepare("SELECT pomodoro_length, short_break_length, long_break_length, pomodoros_until_long_break, sound_enabled, auto_start_breaks, auto_start_pomodoros, global_shortcuts_enabled, start_minimized, close_to_tray, reminder_lead_minutes, trash_retention_days, daily_pomodoros, work_start_minutes, work_end_minutes, work_days_mask, daily_goal_tasks, daily_goal_focus_minutes, streak_freezes_per_month FROM settings WHERE id = 1").map_err(|e| e.to_string())?;
+        let res = stmt.query_row([], |row| {
+            Ok(AppSettings {
+                pomodoro_length: row.get::<_, i64>(0)? as u32,
                 short_break_length: row.get::<_, i64>(1)? as u32,
                 long_break_length: row.get::<_, i64>(2)? as u32,
                 pomodoros_until_long_break: row.get::<_, i64>(3)? as u32,
@@ -464,6 +2223,14 @@ impl AppDatabase {
                 start_minimized: row.get::<_, i64>(8)? != 0,
                 close_to_tray: row.get::<_, i64>(9)? != 0,
                 reminder_lead_minutes: row.get::<_, i64>(10)? as u32,
+                trash_retention_days: row.get::<_, i64>(11)? as u32,
+                daily_pomodoros: row.get::<_, i64>(12)? as u32,
+                work_start_minutes: row.get::<_, i64>(13)? as u32,
+                work_end_minutes: row.get::<_, i64>(14)? as u32,
+                work_days_mask: row.get(15)?,
+                daily_goal_tasks: row.get(16)?,
+                daily_goal_focus_minutes: row.get(17)?,
+                streak_freezes_per_month: row.get(18)?,
             })
         });
         res.map_err(|_| "Settings not found".to_string())
@@ -471,11 +2238,14 @@ impl AppDatabase {
     }
 
     pub fn save_settings(&self, settings: AppSettings) -> Result<(), String> {
+        if settings.daily_goal_tasks == 0 && settings.daily_goal_focus_minutes == 0 {
+            return Err("The daily goal needs a number of tasks or focus minutes".to_string());
+        }
         let conn = &self.conn;
         let now = chrono::Utc::now().timestamp_millis();
         conn.execute(
-            "INSERT INTO settings (id, pomodoro_length, short_break_length, long_break_length, pomodoros_until_long_break, sound_enabled, auto_start_breaks, auto_start_pomodoros, global_shortcuts_enabled, start_minimized, close_to_tray, reminder_lead_minutes, updated_at)
-             VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+            "INSERT INTO settings (id, pomodoro_length, short_break_length, long_break_length, pomodoros_until_long_break, sound_enabled, auto_start_breaks, auto_start_pomodoros, global_shortcuts_enabled, start_minimized, close_to_tray, reminder_lead_minutes, trash_retention_days, daily_pomodoros, work_start_minutes, work_end_minutes, work_days_mask, daily_goal_tasks, daily_goal_focus_minutes, streak_freezes_per_month, updated_at)
+             VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)
              ON CONFLICT(id) DO UPDATE SET 
                 pomodoro_length=excluded.pomodoro_length, 
                 short_break_length=excluded.short_break_length,
@@ -488,144 +2258,327 @@ impl AppDatabase {
                 start_minimized=excluded.start_minimized,
                 close_to_tray=excluded.close_to_tray,
                 reminder_lead_minutes=excluded.reminder_lead_minutes,
-                updated_at=excluded.updated_at",
-            params![settings.pomodoro_length, settings.short_break_length, settings.long_break_length, settings.pomodoros_until_long_break, settings.sound_enabled, settings.auto_start_breaks, settings.auto_start_pomodoros, settings.global_shortcuts_enabled, settings.start_minimized, settings.close_to_tray, settings.reminder_lead_minutes, now]
+                trash_retention_days=excluded.trash_retention_days,
+                daily_pomodoros=excluded.daily_pomodoros,
+                work_start_minutes=excluded.work_start_minutes, work_end_minutes=excluded.work_end_minutes,
+                work_days_mask=excluded.work_days_mask,
+                daily_goal_tasks=excluded.daily_goal_tasks, daily_goal_focus_minutes=excluded.daily_goal_focus_minutes,
+                streak_freezes_per_month=excluded.streak_freezes_per_month, updated_at=excluded.updated_at",
+            params![settings.pomodoro_length, settings.short_break_length, settings.long_break_length, settings.pomodoros_until_long_break, settings.sound_enabled, settings.auto_start_breaks, settings.auto_start_pomodoros, settings.global_shortcuts_enabled, settings.start_minimized, settings.close_to_tray, settings.reminder_lead_minutes, settings.trash_retention_days, settings.daily_pomodoros, settings.work_start_minutes, settings.work_end_minutes, settings.work_days_mask, settings.daily_goal_tasks, settings.daily_goal_focus_minutes, settings.streak_freezes_per_month, now]
         ).map_err(|e| e.to_string())?;
+        // Today is still open, so it moves to the new goal; earlier days
+        // keep theirs
+        conn.execute(
+            "UPDATE daily_activity SET goal_tasks = ?1, goal_focus_minutes = ?2 WHERE day = ?3",
+            params![
+                settings.daily_goal_tasks,
+                settings.daily_goal_focus_minutes,
+                local_day(now)
+            ],
+        )
+        .map_err(|e| e.to_string())?;
         Ok(())
     }
 
-    pub fn get_projects(&self) -> Result<Vec<Project>, String> {
+    // --- LOCAL API SETTINGS ---
+
+    /// Returns the API settings, creating the row with a fresh token on first use.
+    pub fn get_api_settings(&self) -> Result<ApiSettings, String> {
         let conn = &self.conn;
-        let mut stmt = conn
-            .prepare(
-                "SELECT id, name, color, priority, created_at, parent_id, is_folder FROM projects ORDER BY priority DESC",
+        let defaults = ApiSettings::default();
+        conn.execute(
+            "INSERT OR IGNORE INTO api_settings (id, enabled, port, token, updated_at) VALUES (1, ?1, ?2, ?3, ?4)",
+            params![defaults.enabled, defaults.port, new_api_token(), chrono::Utc::now().timestamp_millis()],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.query_row(
+            "SELECT enabled, port, token FROM api_settings WHERE id = 1",
+            [],
+            |row| {
+                Ok(ApiSettings {
+                    enabled: row.get::<_, i64>(0)? != 0,
+                    port: row.get::<_, i64>(1)? as u16,
+                    token: row.get(2)?,
+                })
+            },
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    pub fn save_api_settings(&self, enabled: bool, port: u16) -> Result<ApiSettings, String> {
+        if port < 1024 {
+            return Err("Port must be between 1024 and 65535".to_string());
+        }
+        self.get_api_settings()?;
+        self.conn
+            .execute(
+                "UPDATE api_settings SET enabled = ?1, port = ?2, updated_at = ?3 WHERE id = 1",
+                params![enabled, port, chrono::Utc::now().timestamp_millis()],
             )
             .map_err(|e| e.to_string())?;
-        let rows = stmt
-            .query_map([], |row| {
-                Ok(Project {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    color: row.get(2)?,
-                    priority: Priority::from_int(row.get(3)?),
-                    created_at: row.get(4)?,
-                    parent_id: row.get(5)?,
-                    is_folder: row.get::<_, i32>(6)? != 0,
-                })
-            })
+        self.get_api_settings()
+    }
+
+    pub fn regenerate_api_token(&self) -> Result<ApiSettings, String> {
+        self.get_api_settings()?;
+        self.conn
+            .execute(
+                "UPDATE api_settings SET token = ?1, updated_at = ?2 WHERE id = 1",
+                params![new_api_token(), chrono::Utc::now().timestamp_millis()],
+            )
             .map_err(|e| e.to_string())?;
-        let mut res = Vec::new();
-        for r in rows {
-            res.push(r.map_err(|e| e.to_string())?);
-        }
-        Ok(res)
+        self.get_api_settings()
     }
 
-    pub fn add_project(
-        &self,
-        id: String,
-        name: String,
-        color: String,
-        priority: Priority,
-        parent_id: Option<String>,
-        is_folder: bool,
-    ) -> Result<Project, String> {
+    // --- CHANGE SNAPSHOTS ---
+
+    /// Current state of one entity as sent in `data:changed` (`None` if it
+    /// doesn't exist, e.g. after a delete). `id` is ignored for "settings",
+    /// "api_settings" and "gamification_rules".
+    pub fn snapshot(&self, entity: &str, id: Option<&str>) -> Option<Snapshot> {
+        let snapshot = match (entity, id) {
+            ("settings", _) => Snapshot::Settings(self.get_settings().ok()?),
+            ("api_settings", _) => Snapshot::ApiSettings(self.get_api_settings().ok()?),
+            ("gamification_rules", _) => {
+                Snapshot::GamificationRules(self.get_gamification_rules().ok()?)
+            }
+            ("tasks", Some(id)) => {
+                let mut task = self.get_task(id).ok()?;
+                task.subtasks = self.get_subtasks(id).ok()?;
+                Snapshot::Task(task)
+            }
+            ("subtasks", Some(id)) => Snapshot::Subtask(self.get_subtask(id).ok()?),
+            ("projects", Some(id)) => Snapshot::Project(self.get_project(id).ok()?),
+            ("sections", Some(id)) => Snapshot::Section(self.get_section(id).ok()?),
+            ("boards", Some(id)) => Snapshot::Board(self.get_board(id).ok()?),
+            ("hooks", Some(id)) => Snapshot::Hook(self.get_hook(id).ok()?),
+            ("templates", Some(id)) => Snapshot::Template(self.get_template(id).ok()?),
+            ("time_blocks", Some(id)) => Snapshot::TimeBlock(self.get_time_block(id).ok()?),
+            ("time_entries", Some(id)) => Snapshot::TimeEntry(self.get_time_entry(id).ok()?),
+            ("daily_activity", Some(day)) => {
+                Snapshot::DailyActivity(self.get_daily_activity_day(parse_day(day).ok()?).ok()?)
+            }
+            ("daily_plan", Some(date)) => {
+                Snapshot::DailyPlan(self.get_daily_plan(parse_day(date).ok()?).ok()?)
+            }
+            ("finance", Some(id)) => {
+                let (transactions, debts) = self.get_finance_summary().ok()?;
+                match transactions.into_iter().find(|t| t.id == id) {
+                    Some(t) => Snapshot::Transaction(t),
+                    None => Snapshot::Debt(debts.into_iter().find(|d| d.id == id)?),
+                }
+            }
+            _ => return None,
+        };
+        Some(snapshot)
+    }
+
+    /// Snapshots of several entities for one batch event, in `ids` order.
+    pub fn snapshots(&self, entity: &str, ids: &[String]) -> Snapshot {
+        Snapshot::Many(
+            ids.iter()
+                .map(|id| self.snapshot(entity, Some(id)))
+                .collect(),
+        )
+    }
+
+    /// Positions of the given tasks or subtasks, for reorder events.
+    pub fn order_snapshot(&self, entity: &str, ids: &[String]) -> Option<Snapshot> {
+        let table = match entity {
+            "tasks" => "tasks",
+            "subtasks" => "subtasks",
+            _ => return None,
+        };
         let conn = &self.conn;
-        let now = chrono::Utc::now().timestamp_millis();
-        conn.execute("INSERT INTO projects (id, name, color, priority, created_at, parent_id, is_folder) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![id, name, color, priority as i32, now, parent_id, is_folder as i32]).map_err(|e| e.to_string())?;
-        Ok(Project {
-            id,
-            name,
-            color,
-            priority,
-            created_at: now,
-            parent_id,
-            is_folder,
-        })
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT sort_order FROM {} WHERE id = ?1 AND deleted_at IS NULL",
+                table
+            ))
+            .ok()?;
+        let mut res = Vec::new();
+        for id in ids {
+            let sort_order: i32 = stmt.query_row(params![id], |row| row.get(0)).ok()?;
+            res.push(SortOrder {
+                id: id.clone(),
+                sort_order,
+            });
+        }
+        Some(Snapshot::Order(res))
     }
 
-    pub fn update_project(&self, id: &str, name: String) -> Result<(), String> {
+    // --- UNDO JOURNAL ---
+
+    /// Starts journaling writes under a new undo step. Use `UndoStep::begin`
+    /// so the step is always closed.
+    fn begin_undo_step(&self, label: &str) -> Result<(), String> {
         let conn = &self.conn;
         conn.execute(
-            "UPDATE projects SET name = ?1 WHERE id = ?2",
-            params![name, id],
+            "INSERT INTO undo_steps (label, created_at) VALUES (?1, ?2)",
+            params![label, chrono::Utc::now().timestamp_millis()],
         )
         .map_err(|e| e.to_string())?;
+        self.undo_step
+            .store(conn.last_insert_rowid(), Ordering::SeqCst);
         Ok(())
     }
 
-    pub fn update_project_priority(&self, id: &str, priority: Priority) -> Result<(), String> {
+    /// Stops journaling. A step that wrote nothing is dropped; one that did
+    /// clears the redo history and pushes out the oldest steps past the limit.
+    fn end_undo_step(&self) -> Result<(), String> {
         let conn = &self.conn;
+        let step = self.undo_step.swap(0, Ordering::SeqCst);
+        if step == 0 {
+            return Ok(());
+        }
+        let wrote: bool = conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM undo_log WHERE step = ?1)",
+                params![step],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !wrote {
+            conn.execute("DELETE FROM undo_steps WHERE step = ?1", params![step])
+                .map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+        conn.execute("DELETE FROM undo_steps WHERE undone = 1", [])
+            .map_err(|e| e.to_string())?;
         conn.execute(
-            "UPDATE projects SET priority = ?1 WHERE id = ?2",
-            params![priority as i32, id],
+            "DELETE FROM undo_steps WHERE step <= ?1 - ?2",
+            params![step, UNDO_HISTORY_LIMIT],
         )
         .map_err(|e| e.to_string())?;
         Ok(())
     }
 
-    pub fn delete_project(&self, id: &str) -> Result<(), String> {
-        let conn = &self.conn;
-        conn.execute("DELETE FROM projects WHERE id = ?1", params![id])
-            .map_err(|e| e.to_string())?;
-        Ok(())
+    /// Labels of the steps `undo` and `redo` would apply next.
+    pub fn get_undo_status(&self) -> Result<UndoStatus, String> {
+        let label = |sql: &str| -> Result<Option<String>, String> {
+            match self.conn.query_row(sql, [], |row| row.get(0)) {
+                Ok(label) => Ok(Some(label)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(e.to_string()),
+            }
+        };
+        Ok(UndoStatus {
+            undo: label(
+                "SELECT label FROM undo_steps WHERE undone = 0 ORDER BY step DESC LIMIT 1",
+            )?,
+            redo: label("SELECT label FROM undo_steps WHERE undone = 1 ORDER BY step ASC LIMIT 1")?,
+        })
     }
 
-    pub fn get_tasks(
-        &self,
-        limit: Option<i32>,
-        status: Option<Status>,
-        project_id: Option<String>,
-    ) -> Result<Vec<Task>, String> {
-        let conn = &self.conn;
-        let mut query = "SELECT id, project_id, title, description, priority, status, created_at, completed_at, deadline, estimated_minutes, actual_minutes, tags, remind_at, reminded_at, repeat_mode, repeat_days_mask, is_archived, sort_order FROM tasks WHERE 1=1".to_string();
+    /// Reverts the latest step, side effects included (e.g. the next instance
+    /// spawned by completing a recurring task). Returns its label, or `None`
+    /// when there is nothing to undo.
+    pub fn undo(&mut self) -> Result<Option<String>, String> {
+        self.replay_undo_step(false)
+    }
 
-        // Build dynamic parameters
-        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+    /// Re-applies the most recently undone step.
+    pub fn redo(&mut self) -> Result<Option<String>, String> {
+        self.replay_undo_step(true)
+    }
 
-        if let Some(s) = &status {
-            query.push_str(" AND status = ?");
-            params_vec.push(Box::new(*s as i32));
-        }
-        if let Some(pid) = &project_id {
-            query.push_str(" AND project_id = ?");
-            params_vec.push(Box::new(pid.clone()));
-        }
-        query.push_str(" ORDER BY created_at DESC");
-        if let Some(l) = limit {
-            query.push_str(&format!(" LIMIT {}", l));
+    /// Applies the next step to undo or redo. A step that can't be replayed
+    /// (e.g. another connection has since written a row it would restore) is
+    /// dropped, so it doesn't block the history behind it.
+    fn replay_undo_step(&mut self, redo: bool) -> Result<Option<String>, String> {
+        let next = if redo {
+            "SELECT step, label FROM undo_steps WHERE undone = 1 ORDER BY step ASC LIMIT 1"
+        } else {
+            "SELECT step, label FROM undo_steps WHERE undone = 0 ORDER BY step DESC LIMIT 1"
+        };
+        let (step, label): (i64, String) = match self
+            .conn
+            .query_row(next, [], |row| Ok((row.get(0)?, row.get(1)?)))
+        {
+            Ok(found) => found,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        };
+        if let Err(e) = self.apply_undo_step(step, redo) {
+            self.conn
+                .execute("DELETE FROM undo_steps WHERE step = ?1", params![step])
+                .map_err(|e| e.to_string())?;
+            let verb = if redo { "redo" } else { "undo" };
+            return Err(format!(
+                "Could not {} \"{}\", dropped it: {}",
+                verb, label, e
+            ));
         }
+        Ok(Some(label))
+    }
 
-        let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+    /// Runs a step's journal backwards while journaling again under the same
+    /// step, so what gets recorded is exactly the way back. All or nothing.
+    fn apply_undo_step(&mut self, step: i64, redo: bool) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let statements: Vec<String> = {
+            let mut stmt = tx
+                .prepare("SELECT sql FROM undo_log WHERE step = ?1 ORDER BY seq DESC")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![step], |row| row.get::<_, String>(0))
+                .map_err(|e| e.to_string())?;
+            let mut res = Vec::new();
+            for r in rows {
+                res.push(r.map_err(|e| e.to_string())?);
+            }
+            res
+        };
 
-        // Convert params to references for rusqlite
-        let params_refs: Vec<&dyn rusqlite::ToSql> =
-            params_vec.iter().map(|p| p.as_ref()).collect();
+        // Children can come back before their parents; check keys at commit
+        tx.pragma_update(None, "defer_foreign_keys", "ON")
+            .map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM undo_log WHERE step = ?1", params![step])
+            .map_err(|e| e.to_string())?;
+        self.undo_step.store(step, Ordering::SeqCst);
+        let replayed = statements
+            .iter()
+            .try_for_each(|sql| tx.execute(sql, []).map(|_| ()));
+        self.undo_step.store(0, Ordering::SeqCst);
+        replayed.map_err(|e| e.to_string())?;
+        tx.execute(
+            "UPDATE undo_steps SET undone = ?1 WHERE step = ?2",
+            params![!redo, step],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    // --- HOOKS ---
+
+    pub fn get_hooks(&self) -> Result<Vec<Hook>, String> {
+        self.query_hooks(
+            "SELECT id, event, kind, target, enabled, created_at FROM hooks ORDER BY created_at",
+            params![],
+        )
+    }
+
+    /// Enabled hooks subscribed to `event` directly or via "*".
+    pub fn get_hooks_for_event(&self, event: &str) -> Result<Vec<Hook>, String> {
+        self.query_hooks(
+            "SELECT id, event, kind, target, enabled, created_at FROM hooks
+             WHERE enabled = 1 AND (event = ?1 OR event = '*') ORDER BY created_at",
+            params![event],
+        )
+    }
 
+    fn query_hooks(&self, sql: &str, args: &[&dyn rusqlite::ToSql]) -> Result<Vec<Hook>, String> {
+        let conn = &self.conn;
+        let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
         let rows = stmt
-            .query_map(params_refs.as_slice(), |row| {
-                let tags_raw: String = row.get(11)?;
-                Ok(Task {
+            .query_map(args, |row| {
+                Ok(Hook {
                     id: row.get(0)?,
-                    project_id: row.get(1)?,
-                    title: row.get(2)?,
-                    description: row.get(3)?,
-                    priority: Priority::from_int(row.get(4)?),
-                    status: Status::from_int(row.get(5)?),
-                    created_at: row.get(6)?,
-                    completed_at: row.get(7)?,
-                    deadline: row.get(8)?,
-                    estimated_minutes: row.get::<_, Option<i64>>(9)?.map(|v| v as u32),
-                    actual_minutes: row.get::<_, Option<i64>>(10)?.map(|v| v as u32),
-                    tags: serde_json::from_str(&tags_raw).unwrap_or_default(),
-                    remind_at: row.get(12)?,
-                    reminded_at: row.get(13)?,
-                    repeat_mode: row.get(14)?,
-                    repeat_days_mask: row.get(15)?,
-                    is_archived: row.get::<_, i32>(16)? != 0,
-                    sort_order: row.get(17)?,
-                    subtasks: Vec::new(),
+                    event: row.get(1)?,
+                    kind: row.get(2)?,
+                    target: row.get(3)?,
+                    enabled: row.get::<_, i32>(4)? != 0,
+                    created_at: row.get(5)?,
                 })
             })
             .map_err(|e| e.to_string())?;
@@ -636,163 +2589,88 @@ impl AppDatabase {
         Ok(res)
     }
 
-    pub fn add_task(&self, task: &NewTask) -> Result<Task, String> {
-        let conn = &self.conn;
-        let tags = serde_json::to_string(&task.tags).unwrap_or_else(|_| "[]".to_string());
-        conn.execute("INSERT INTO tasks (id, project_id, title, description, priority, status, created_at, deadline, tags, repeat_mode, repeat_days_mask) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
-            params![task.id, task.project_id, task.title, task.description, task.priority as i32, 0, task.created_at, task.deadline, tags, task.repeat_mode, task.repeat_days_mask]
-        ).map_err(|e| e.to_string())?;
-        // Возвращаем объект (упрощено)
-        Ok(Task {
-            id: task.id.clone(),
-            project_id: task.project_id.clone(),
-            title: task.title.clone(),
-            description: task.description.clone(),
-            priority: task.priority,
-            status: Status::Todo,
-            created_at: task.created_at,
-            completed_at: None,
-            deadline: task.deadline,
-            estimated_minutes: None,
-            actual_minutes: None,
-            tags: task.tags.clone(),
-            remind_at: None,
-            reminded_at: None,
-            repeat_mode: task.repeat_mode.clone(),
-            repeat_days_mask: task.repeat_days_mask,
-            is_archived: false,
-            sort_order: 0,
-            subtasks: Vec::new(),
-        })
-    }
-
-    pub fn update_task_title(&self, id: &str, title: String) -> Result<(), String> {
-        let conn = &self.conn;
-        conn.execute(
-            "UPDATE tasks SET title = ?1 WHERE id = ?2",
-            params![title, id],
-        )
-        .map_err(|e| e.to_string())?;
-        Ok(())
-    }
-
-    pub fn update_task_priority(&self, id: &str, priority: Priority) -> Result<(), String> {
-        let conn = &self.conn;
-        conn.execute(
-            "UPDATE tasks SET priority = ?1 WHERE id = ?2",
-            params![priority as i32, id],
-        )
-        .map_err(|e| e.to_string())?;
-        Ok(())
-    }
-
-    pub fn update_task_deadline(&self, id: &str, deadline: Option<i64>) -> Result<(), String> {
-        let conn = &self.conn;
-        conn.execute(
-            "UPDATE tasks SET deadline = ?1 WHERE id = ?2",
-            params![deadline, id],
-        )
-        .map_err(|e| e.to_string())?;
-        Ok(())
+    pub fn get_hook(&self, id: &str) -> Result<Hook, String> {
+        self.get_hooks()?
+            .into_iter()
+            .find(|h| h.id == id)
+            .ok_or_else(|| format!("Hook not found: {}", id))
     }
 
-    pub fn update_task_tags(&self, id: &str, tags: Vec<String>) -> Result<(), String> {
+    pub fn add_hook(&self, hook: &Hook) -> Result<(), String> {
         let conn = &self.conn;
-        let tags_json = serde_json::to_string(&tags).unwrap_or_else(|_| "[]".to_string());
         conn.execute(
-            "UPDATE tasks SET tags = ?1 WHERE id = ?2",
-            params![tags_json, id],
+            "INSERT INTO hooks (id, event, kind, target, enabled, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![hook.id, hook.event, hook.kind, hook.target, hook.enabled, hook.created_at],
         )
         .map_err(|e| e.to_string())?;
         Ok(())
     }
 
-    pub fn update_task_repeat(
-        &self,
-        id: &str,
-        mode: Option<String>,
-        mask: Option<i64>,
-    ) -> Result<(), String> {
+    pub fn set_hook_enabled(&self, id: &str, enabled: bool) -> Result<(), String> {
         let conn = &self.conn;
-        conn.execute(
-            "UPDATE tasks SET repeat_mode = ?1, repeat_days_mask = ?2 WHERE id = ?3",
-            params![mode, mask, id],
-        )
-        .map_err(|e| e.to_string())?;
+        let updated = conn
+            .execute(
+                "UPDATE hooks SET enabled = ?1 WHERE id = ?2",
+                params![enabled, id],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err(format!("Hook not found: {}", id));
+        }
         Ok(())
     }
 
-    pub fn delete_task(&self, id: &str) -> Result<(), String> {
+    pub fn delete_hook(&self, id: &str) -> Result<(), String> {
         let conn = &self.conn;
-        conn.execute("DELETE FROM tasks WHERE id = ?1", params![id])
+        conn.execute("DELETE FROM hooks WHERE id = ?1", params![id])
             .map_err(|e| e.to_string())?;
         Ok(())
     }
 
-    pub fn set_task_remind_at(&self, id: &str, remind_at: Option<i64>) -> Result<(), String> {
+    pub fn log_hook_delivery(&self, d: &HookDelivery) -> Result<(), String> {
         let conn = &self.conn;
         conn.execute(
-            "UPDATE tasks SET remind_at = ?1, reminded_at = NULL WHERE id = ?2",
-            params![remind_at, id],
+            "INSERT INTO hook_deliveries (id, hook_id, event, payload, attempts, success, status_code, error, duration_ms, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![d.id, d.hook_id, d.event, d.payload, d.attempts, d.success, d.status_code, d.error, d.duration_ms, d.created_at],
         )
         .map_err(|e| e.to_string())?;
-        Ok(())
-    }
-
-    pub fn snooze_task(&self, id: &str, minutes: i64) -> Result<(), String> {
-        let conn = &self.conn;
-        let next = chrono::Utc::now().timestamp_millis() + (minutes * 60_000);
         conn.execute(
-            "UPDATE tasks SET remind_at = ?1, reminded_at = NULL WHERE id = ?2",
-            params![next, id],
+            "DELETE FROM hook_deliveries WHERE id NOT IN
+             (SELECT id FROM hook_deliveries ORDER BY created_at DESC, rowid DESC LIMIT ?1)",
+            params![HOOK_DELIVERY_LOG_LIMIT],
         )
         .map_err(|e| e.to_string())?;
         Ok(())
     }
 
-    pub fn start_focus_session(&self, task_id: String) -> Result<String, String> {
-        let conn = &self.conn;
-        let id = uuid::Uuid::new_v4().to_string();
-        let now = chrono::Utc::now().timestamp_millis();
-        conn.execute("INSERT INTO focus_sessions (id, task_id, duration_minutes, completed, started_at) VALUES (?1, ?2, 0, 0, ?3)",
-            params![id, task_id, now]).map_err(|e| e.to_string())?;
-        Ok(id)
-    }
-
-    pub fn finish_focus_session(&self, id: String, mins: i32, comp: bool) -> Result<(), String> {
-        let conn = &self.conn;
-        let now = chrono::Utc::now().timestamp_millis();
-        conn.execute("UPDATE focus_sessions SET duration_minutes = ?1, completed = ?2, ended_at = ?3 WHERE id = ?4",
-            params![mins, comp, now, id]).map_err(|e| e.to_string())?;
-        Ok(())
-    }
-
-    pub fn get_due_reminders(&self, now: i64) -> Result<Vec<Task>, String> {
+    /// Newest first, optionally for a single hook.
+    pub fn get_hook_deliveries(
+        &self,
+        hook_id: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<HookDelivery>, String> {
         let conn = &self.conn;
-        let mut stmt = conn.prepare("SELECT id, project_id, title, description, priority, status, created_at, completed_at, deadline, estimated_minutes, actual_minutes, tags, remind_at, reminded_at, repeat_mode, repeat_days_mask FROM tasks WHERE status != 2 AND remind_at <= ?1").map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, hook_id, event, payload, attempts, success, status_code, error, duration_ms, created_at
+                 FROM hook_deliveries WHERE ?1 IS NULL OR hook_id = ?1
+                 ORDER BY created_at DESC, rowid DESC LIMIT ?2",
+            )
+            .map_err(|e| e.to_string())?;
         let rows = stmt
-            .query_map(params![now], |row| {
-                let tags_raw: String = row.get(11)?;
-                Ok(Task {
+            .query_map(params![hook_id, limit], |row| {
+                Ok(HookDelivery {
                     id: row.get(0)?,
-                    project_id: row.get(1)?,
-                    title: row.get(2)?,
-                    description: row.get(3)?,
-                    priority: Priority::from_int(row.get(4)?),
-                    status: Status::from_int(row.get(5)?),
-                    created_at: row.get(6)?,
-                    completed_at: row.get(7)?,
-                    deadline: row.get(8)?,
-                    estimated_minutes: row.get::<_, Option<i64>>(9)?.map(|v| v as u32),
-                    actual_minutes: row.get::<_, Option<i64>>(10)?.map(|v| v as u32),
-                    tags: serde_json::from_str(&tags_raw).unwrap_or_default(),
-                    remind_at: row.get(12)?,
-                    reminded_at: row.get(13)?,
-                    repeat_mode: row.get(14)?,
-                    repeat_days_mask: row.get(15)?,
-                    is_archived: false,
-                    sort_order: 0,
-                    subtasks: Vec::new(),
+                    hook_id: row.get(1)?,
+                    event: row.get(2)?,
+                    payload: row.get(3)?,
+                    attempts: row.get(4)?,
+                    success: row.get::<_, i32>(5)? != 0,
+                    status_code: row.get(6)?,
+                    error: row.get(7)?,
+                    duration_ms: row.get(8)?,
+                    created_at: row.get(9)?,
                 })
             })
             .map_err(|e| e.to_string())?;
@@ -803,25 +2681,26 @@ impl AppDatabase {
         Ok(res)
     }
 
-    pub fn mark_reminded(&mut self, ids: &[String], now: i64) -> Result<(), String> {
-        let conn = &mut self.conn;
-        let tx = conn.transaction().map_err(|e| e.to_string())?;
-        for id in ids {
-            tx.execute(
-                "UPDATE tasks SET reminded_at = ?1, remind_at = NULL WHERE id = ?2",
-                params![now, id],
-            )
-            .map_err(|e| e.to_string())?;
-        }
-        tx.commit().map_err(|e| e.to_string())?;
-        Ok(())
+    /// Active (non-archived) projects in manual order.
+    pub fn get_projects(&self) -> Result<Vec<Project>, String> {
+        self.query_projects("WHERE archived_at IS NULL AND deleted_at IS NULL")
     }
 
-    pub fn get_completion_series(&self, days: i32) -> Result<Vec<(String, i32)>, String> {
+    /// All projects, archived ones included.
+    pub fn get_all_projects(&self) -> Result<Vec<Project>, String> {
+        self.query_projects("WHERE deleted_at IS NULL")
+    }
+
+    fn query_projects(&self, filter: &str) -> Result<Vec<Project>, String> {
         let conn = &self.conn;
-        let mut stmt = conn.prepare("SELECT DATE(datetime(completed_at/1000, 'unixepoch', 'localtime')) as day, COUNT(*) FROM tasks WHERE status = 2 AND completed_at IS NOT NULL GROUP BY day ORDER BY day DESC LIMIT ?1").map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM projects {} ORDER BY sort_order ASC, priority DESC, created_at ASC",
+                PROJECT_COLUMNS, filter
+            ))
+            .map_err(|e| e.to_string())?;
         let rows = stmt
-            .query_map(params![days], |row| Ok((row.get(0)?, row.get(1)?)))
+            .query_map([], project_from_row)
             .map_err(|e| e.to_string())?;
         let mut res = Vec::new();
         for r in rows {
@@ -830,559 +2709,6455 @@ impl AppDatabase {
         Ok(res)
     }
 
-    pub fn update_task_status(&mut self, task_id: &str, new_status: Status) -> Result<(), String> {
-        let conn = &mut self.conn;
-        let tx = conn.transaction().map_err(|e| e.to_string())?;
-
-        let (
-            repeat_mode,
-            repeat_days_mask,
-            deadline,
-            project_id,
-            title,
-            description,
-            priority,
-            tags,
-        ) = {
-            let mut stmt = tx.prepare("SELECT repeat_mode, repeat_days_mask, deadline, project_id, title, description, priority, tags FROM tasks WHERE id = ?1").map_err(|e| e.to_string())?;
-            stmt.query_row(params![task_id], |row| {
-                Ok((
-                    row.get::<_, Option<String>>(0)?,
-                    row.get::<_, Option<i64>>(1)?,
-                    row.get::<_, Option<i64>>(2)?,
-                    row.get::<_, Option<String>>(3)?,
-                    row.get::<_, String>(4)?,
-                    row.get::<_, Option<String>>(5)?,
-                    row.get::<_, i32>(6)?,
-                    row.get::<_, String>(7)?,
-                ))
-            })
-            .map_err(|e| e.to_string())?
-        };
-
-        let now = chrono::Utc::now().timestamp_millis();
-        tx.execute("UPDATE tasks SET status = ?1, completed_at = CASE WHEN ?1 = 2 THEN ?2 ELSE NULL END WHERE id = ?3",
-            params![new_status as i32, now, task_id]).map_err(|e| e.to_string())?;
-
-        // Logic for recurring tasks
-        if new_status == Status::Done {
-            let mut next_date: Option<NaiveDate> = None;
-
-            // NOTE: We only care if repeat_mode is present.
-            if let Some(mode) = &repeat_mode {
-                let current_deadline_ms = deadline.unwrap_or(now);
-                let current_date = match Local.timestamp_millis_opt(current_deadline_ms) {
-                    chrono::LocalResult::Single(dt) => dt.date_naive(),
-                    _ => Local::now().date_naive(),
-                };
-
-                match mode.as_str() {
-                    "daily" => next_date = Some(next_date_daily(current_date)),
-                    "weekdays" => next_date = Some(next_date_weekdays(current_date)),
-                    "custom" => {
-                        if let Some(mask) = repeat_days_mask {
-                            next_date = next_date_custom(current_date, mask);
-                        }
-                    }
-                    _ => {}
-                }
+    pub fn get_project(&self, id: &str) -> Result<Project, String> {
+        let conn = &self.conn;
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM projects WHERE id = ?1 AND deleted_at IS NULL",
+                PROJECT_COLUMNS
+            ),
+            params![id],
+            project_from_row,
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Project not found: {}", id),
+            other => other.to_string(),
+        })
+    }
 
-                if let Some(nd) = next_date {
-                    let (h, m) = pick_time_from_deadline(deadline);
-                    let next_ms = local_date_time_to_ms(nd, h, m);
-                    let new_id = uuid::Uuid::new_v4().to_string();
-
-                    // Reset status to Todo (0)
-                    tx.execute("INSERT INTO tasks (id, project_id, title, description, priority, status, created_at, deadline, tags, repeat_mode, repeat_days_mask) 
-                        VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, ?7, ?8, ?9, ?10)",
-                        params![new_id, project_id, title, description, priority, now, next_ms, tags, repeat_mode, repeat_days_mask]
-                     ).map_err(|e| e.to_string())?;
-
-                    // Optional: Clean up repeat mode from completed task so it doesn't trigger again?
-                    // Or leave it as is. Leading choice: Leave it, so history reflects it was a recurring task.
-                    // But if user unchecks and re-checks, it duplicates.
-                    // Better to remove repeat settings from the OLD task.
-                    tx.execute("UPDATE tasks SET repeat_mode = NULL, repeat_days_mask = NULL WHERE id = ?1", params![task_id]).map_err(|e| e.to_string())?;
-                }
-            }
-        }
-
-        tx.commit().map_err(|e| e.to_string())?;
-        Ok(())
-    }
-
-    pub fn get_stats(&self) -> Result<UserStats, String> {
+    pub fn add_project(
+        &self,
+        id: String,
+        name: String,
+        color: String,
+        priority: Priority,
+        parent_id: Option<String>,
+        is_folder: bool,
+    ) -> Result<Project, String> {
         let conn = &self.conn;
-        let total = conn
-            .query_row("SELECT COUNT(*) FROM tasks", [], |row| row.get::<_, i32>(0))
-            .unwrap_or(0);
-        let done = conn
-            .query_row("SELECT COUNT(*) FROM tasks WHERE status = 2", [], |row| {
-                row.get::<_, i32>(0)
-            })
-            .unwrap_or(0);
-
-        let now_local = Local::now();
-        let start_of_day = now_local
-            .date_naive()
-            .and_hms_opt(0, 0, 0)
-            .unwrap()
-            .and_local_timezone(Local)
-            .unwrap()
-            .timestamp_millis();
-
-        // Start of week (Monday)
-        let days_from_mon = now_local.weekday().num_days_from_monday();
-        let start_of_week = (now_local - Duration::days(days_from_mon as i64))
-            .date_naive()
-            .and_hms_opt(0, 0, 0)
-            .unwrap()
-            .and_local_timezone(Local)
-            .unwrap()
-            .timestamp_millis();
-
-        let completed_today = conn
-            .query_row(
-                "SELECT COUNT(*) FROM tasks WHERE status = 2 AND completed_at >= ?1",
-                params![start_of_day],
-                |row| row.get::<_, i32>(0),
-            )
-            .unwrap_or(0);
-        let completed_week = conn
-            .query_row(
-                "SELECT COUNT(*) FROM tasks WHERE status = 2 AND completed_at >= ?1",
-                params![start_of_week],
-                |row| row.get::<_, i32>(0),
-            )
-            .unwrap_or(0);
-
-        // Focus time
-        let total_focus_time: u32 = conn
-            .query_row(
-                "SELECT COALESCE(SUM(duration_minutes), 0) FROM focus_sessions WHERE completed = 1",
-                [],
-                |row| row.get::<_, u32>(0),
-            )
-            .unwrap_or(0);
-
-        // Upcoming/Today counts
-        let tasks_today = conn
-            .query_row(
-                "SELECT COUNT(*) FROM tasks WHERE status != 2 AND deadline >= ?1 AND deadline < ?2",
-                params![start_of_day, start_of_day + 86400000],
-                |row| row.get::<_, i32>(0),
-            )
-            .unwrap_or(0);
-
-        let tasks_week = conn
+        let now = chrono::Utc::now().timestamp_millis();
+        // New projects go to the end of their siblings
+        let sort_order: i32 = conn
             .query_row(
-                "SELECT COUNT(*) FROM tasks WHERE status != 2 AND deadline >= ?1 AND deadline < ?2",
-                params![start_of_week, start_of_week + (7 * 86400000)],
-                |row| row.get::<_, i32>(0),
+                "SELECT COALESCE(MAX(sort_order) + 1, 0) FROM projects WHERE parent_id IS ?1",
+                params![parent_id],
+                |row| row.get(0),
             )
-            .unwrap_or(0);
-
-        // Streaks
-        let mut stmt = conn.prepare("SELECT DISTINCT DATE(datetime(completed_at/1000, 'unixepoch', 'localtime')) FROM tasks WHERE status = 2 ORDER BY completed_at DESC").map_err(|e| e.to_string())?;
-        let dates: Vec<NaiveDate> = stmt
-            .query_map([], |row| row.get::<_, String>(0))
-            .map_err(|e| e.to_string())?
-            .filter_map(|d| d.ok())
-            .filter_map(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").ok())
-            .collect();
-
-        let mut best_streak = 0;
-        let mut current_streak = 0;
-        let mut temp_streak;
-
-        // Calculate streaks (dates are DESC)
-        // For current streak: Check if today or yesterday is present
-        let today = now_local.date_naive();
+            .map_err(|e| e.to_string())?;
+        conn.execute("INSERT INTO projects (id, name, color, priority, created_at, parent_id, is_folder, sort_order) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![id, name, color, priority as i32, now, parent_id, is_folder as i32, sort_order]).map_err(|e| e.to_string())?;
+        Ok(Project {
+            id,
+            name,
+            color,
+            priority,
+            created_at: now,
+            parent_id,
+            is_folder,
+            sort_order,
+            description: None,
+            icon: None,
+            archived_at: None,
+            default_priority: None,
+            default_tags: Vec::new(),
+        })
+    }
 
-        // Simple streak logic
-        if !dates.is_empty() {
-            let mut iter = dates.iter();
-            let first = *iter.next().unwrap();
-
-            // If the latest completion is today or yesterday, streak is active
-            if first == today || first == today.pred_opt().unwrap() {
-                current_streak = 1;
-                let mut prev = first;
-                for &d in iter {
-                    if d == prev.pred_opt().unwrap() {
-                        current_streak += 1;
-                        prev = d;
-                    } else {
-                        break;
-                    }
-                }
+    pub fn update_project_details(
+        &self,
+        id: &str,
+        patch: &ProjectPatch,
+    ) -> Result<Project, String> {
+        let conn = &self.conn;
+        self.get_project(id)?;
+
+        let mut sets: Vec<&str> = Vec::new();
+        let mut values: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+        if let Some(name) = &patch.name {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err("Project name must not be empty".to_string());
             }
-
-            // Best streak (re-iterate everything)
-            // Need ASC or just iterate
-            temp_streak = 1;
-            best_streak = 1;
-
-            for i in 1..dates.len() {
-                let d = dates[i];
-                let prev = dates[i - 1]; // Prev in listing (which is later in time)
-
-                // dates are DESC: 2023-10-05, 2023-10-04
-                if d == prev.pred_opt().unwrap() {
-                    temp_streak += 1;
-                } else {
-                    temp_streak = 1;
-                }
-                if temp_streak > best_streak {
-                    best_streak = temp_streak;
-                }
+            sets.push("name = ?");
+            values.push(Box::new(name.to_string()));
+        }
+        if let Some(color) = &patch.color {
+            if !is_hex_color(color) {
+                return Err(format!("Invalid color: {}", color));
             }
+            sets.push("color = ?");
+            values.push(Box::new(color.clone()));
+        }
+        if let Some(priority) = patch.priority {
+            sets.push("priority = ?");
+            values.push(Box::new(priority as i32));
+        }
+        if let Some(description) = &patch.description {
+            sets.push("description = ?");
+            values.push(Box::new(description.clone()));
+        }
+        if let Some(icon) = &patch.icon {
+            sets.push("icon = ?");
+            values.push(Box::new(icon.clone()));
+        }
+        if let Some(default_priority) = patch.default_priority {
+            sets.push("default_priority = ?");
+            values.push(Box::new(default_priority.map(|p| p as i32)));
+        }
+        if let Some(tags) = &patch.default_tags {
+            sets.push("default_tags = ?");
+            values.push(Box::new(
+                serde_json::to_string(tags).unwrap_or_else(|_| "[]".to_string()),
+            ));
         }
 
-        Ok(UserStats {
-            total_tasks: total,
-            completed_tasks: done,
-            completed_today,
-            completed_week,
-            best_streak,
-            current_streak,
-            total_focus_time,
-            tasks_today,
-            tasks_week,
-            level: (done / 10) as u32 + 1,
-            points: done * 20,
-        })
+        if !sets.is_empty() {
+            values.push(Box::new(id.to_string()));
+            let sql = format!("UPDATE projects SET {} WHERE id = ?", sets.join(", "));
+            let refs: Vec<&dyn rusqlite::ToSql> = values.iter().map(|v| v.as_ref()).collect();
+            conn.execute(&sql, refs.as_slice())
+                .map_err(|e| e.to_string())?;
+        }
+        self.get_project(id)
     }
 
-    // --- SUBTASKS ---
-
-    pub fn get_subtasks(&self, task_id: &str) -> Result<Vec<Subtask>, String> {
-        let conn = &self.conn;
-        let mut stmt = conn
-            .prepare("SELECT id, task_id, title, completed, sort_order, created_at FROM subtasks WHERE task_id = ?1 ORDER BY sort_order ASC, created_at ASC")
-            .map_err(|e| e.to_string())?;
-        let rows = stmt
-            .query_map(params![task_id], |row| {
-                Ok(Subtask {
-                    id: row.get(0)?,
-                    task_id: row.get(1)?,
-                    title: row.get(2)?,
-                    completed: row.get::<_, i32>(3)? != 0,
-                    sort_order: row.get(4)?,
-                    created_at: row.get(5)?,
-                })
-            })
+    /// Sets the manual order of sibling projects to the order of `ids`.
+    pub fn reorder_projects(&mut self, ids: &[String]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let mut parent: Option<Option<String>> = None;
+        for (i, id) in ids.iter().enumerate() {
+            let (p, _) = Self::project_row(&tx, id)?;
+            match &parent {
+                Some(expected) if *expected != p => {
+                    return Err("Only projects with the same parent can be reordered".to_string())
+                }
+                Some(_) => {}
+                None => parent = Some(p),
+            }
+            tx.execute(
+                "UPDATE projects SET sort_order = ?1 WHERE id = ?2",
+                params![i as i32, id],
+            )
             .map_err(|e| e.to_string())?;
-        let mut res = Vec::new();
-        for r in rows {
-            res.push(r.map_err(|e| e.to_string())?);
         }
-        Ok(res)
+        tx.commit().map_err(|e| e.to_string())
     }
 
-    pub fn add_subtask(&self, task_id: &str, title: &str) -> Result<Subtask, String> {
-        let conn = &self.conn;
-        let id = uuid::Uuid::new_v4().to_string();
+    /// Archives the project, its descendants and their tasks. Returns the ids
+    /// of the projects that were archived.
+    pub fn archive_project(&mut self, id: &str) -> Result<Vec<String>, String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        Self::project_row(&tx, id)?;
         let now = chrono::Utc::now().timestamp_millis();
-
-        // Get next sort_order
-        let max_order: i32 = conn
-            .query_row(
-                "SELECT COALESCE(MAX(sort_order), -1) + 1 FROM subtasks WHERE task_id = ?1",
-                params![task_id],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
-
-        conn.execute(
-            "INSERT INTO subtasks (id, task_id, title, completed, sort_order, created_at) VALUES (?1, ?2, ?3, 0, ?4, ?5)",
-            params![id, task_id, title, max_order, now],
-        )
-        .map_err(|e| e.to_string())?;
-
-        Ok(Subtask {
-            id,
-            task_id: task_id.to_string(),
-            title: title.to_string(),
-            completed: false,
-            sort_order: max_order,
-            created_at: now,
-        })
-    }
-
-    pub fn toggle_subtask(&self, id: &str) -> Result<bool, String> {
-        let conn = &self.conn;
-        conn.execute(
-            "UPDATE subtasks SET completed = 1 - completed WHERE id = ?1",
-            params![id],
-        )
-        .map_err(|e| e.to_string())?;
-
-        // Return new state
-        let completed: i32 = conn
-            .query_row(
-                "SELECT completed FROM subtasks WHERE id = ?1",
-                params![id],
-                |row| row.get(0),
+        let mut archived = Vec::new();
+        for pid in Self::project_subtree(&tx, id)? {
+            let n = tx
+                .execute(
+                    "UPDATE projects SET archived_at = ?1 WHERE id = ?2 AND archived_at IS NULL",
+                    params![now, pid],
+                )
+                .map_err(|e| e.to_string())?;
+            tx.execute(
+                "UPDATE tasks SET is_archived = 1, archived_with_project = 1 WHERE project_id = ?1 AND is_archived = 0",
+                params![pid],
             )
             .map_err(|e| e.to_string())?;
-        Ok(completed != 0)
+            if n > 0 {
+                archived.push(pid);
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(archived)
     }
 
-    pub fn delete_subtask(&self, id: &str) -> Result<(), String> {
-        let conn = &self.conn;
-        conn.execute("DELETE FROM subtasks WHERE id = ?1", params![id])
+    /// Reverses `archive_project`; tasks archived on their own stay archived.
+    pub fn unarchive_project(&mut self, id: &str) -> Result<Vec<String>, String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        Self::project_row(&tx, id)?;
+        let mut restored = Vec::new();
+        for pid in Self::project_subtree(&tx, id)? {
+            let n = tx
+                .execute(
+                    "UPDATE projects SET archived_at = NULL WHERE id = ?1 AND archived_at IS NOT NULL",
+                    params![pid],
+                )
+                .map_err(|e| e.to_string())?;
+            tx.execute(
+                "UPDATE tasks SET is_archived = 0, archived_with_project = 0 WHERE project_id = ?1 AND archived_with_project = 1",
+                params![pid],
+            )
             .map_err(|e| e.to_string())?;
-        Ok(())
+            if n > 0 {
+                restored.push(pid);
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(restored)
     }
 
-    pub fn reorder_subtasks(&self, subtask_ids: &[String]) -> Result<(), String> {
-        let conn = &self.conn;
-        for (i, id) in subtask_ids.iter().enumerate() {
-            conn.execute(
-                "UPDATE subtasks SET sort_order = ?1 WHERE id = ?2",
-                params![i as i32, id],
-            )
-            .map_err(|e| e.to_string())?;
+    /// Fills in the project's default priority (if the task kept the Normal
+    /// default) and adds its default tags.
+    pub fn apply_project_defaults(&self, task: &mut NewTask) -> Result<(), String> {
+        let Some(project_id) = task.project_id.as_deref() else {
+            return Ok(());
+        };
+        let project = self.get_project(project_id)?;
+        if let Some(priority) = project.default_priority {
+            if task.priority == Priority::Normal {
+                task.priority = priority;
+            }
+        }
+        for tag in project.default_tags {
+            if !task.tags.contains(&tag) {
+                task.tags.push(tag);
+            }
         }
         Ok(())
     }
 
-    // --- ARCHIVE ---
-
-    pub fn archive_task(&self, id: &str) -> Result<(), String> {
+    pub fn update_project(&self, id: &str, name: String) -> Result<(), String> {
         let conn = &self.conn;
         conn.execute(
-            "UPDATE tasks SET is_archived = 1 WHERE id = ?1",
-            params![id],
+            "UPDATE projects SET name = ?1 WHERE id = ?2",
+            params![name, id],
         )
         .map_err(|e| e.to_string())?;
         Ok(())
     }
 
-    pub fn unarchive_task(&self, id: &str) -> Result<(), String> {
+    pub fn update_project_priority(&self, id: &str, priority: Priority) -> Result<(), String> {
         let conn = &self.conn;
         conn.execute(
-            "UPDATE tasks SET is_archived = 0 WHERE id = ?1",
-            params![id],
+            "UPDATE projects SET priority = ?1 WHERE id = ?2",
+            params![priority as i32, id],
         )
         .map_err(|e| e.to_string())?;
         Ok(())
     }
 
-    // --- REORDER TASKS ---
+    // --- PROJECT TREE ---
 
-    pub fn reorder_tasks(&self, task_ids: &[String]) -> Result<(), String> {
+    /// Projects nested by `parent_id`. Projects whose parent is missing are
+    /// returned as roots.
+    pub fn get_project_tree(&self) -> Result<Vec<ProjectNode>, String> {
         let conn = &self.conn;
-        for (i, id) in task_ids.iter().enumerate() {
-            conn.execute(
-                "UPDATE tasks SET sort_order = ?1 WHERE id = ?2",
-                params![i as i32, id],
+        let projects = self.get_projects()?;
+
+        let mut counts: HashMap<String, (i64, i64)> = HashMap::new();
+        let mut stmt = conn
+            .prepare(
+                "SELECT project_id, COUNT(*), SUM(CASE WHEN status != 2 THEN 1 ELSE 0 END)
+                 FROM tasks WHERE project_id IS NOT NULL AND deleted_at IS NULL GROUP BY project_id",
             )
             .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        for r in rows {
+            let (id, total, open) = r.map_err(|e| e.to_string())?;
+            counts.insert(id, (total, open));
         }
-        Ok(())
-    }
-    // --- FINANCE (TRANSACTIONS & DEBTS) ---
-
-    #[allow(dead_code)]
-    pub fn get_finance_summary(&self) -> Result<(Vec<Transaction>, Vec<Debt>), String> {
-        let conn = &self.conn;
 
-        // Transactions
-        let mut t_stmt = conn.prepare("SELECT id, amount, category, date, description, is_expense FROM transactions ORDER BY date DESC").map_err(|e| e.to_string())?;
-        let transactions = t_stmt
-            .query_map([], |row| {
-                Ok(Transaction {
-                    id: row.get(0)?,
-                    amount: row.get(1)?,
-                    category: row.get(2)?,
-                    date: row.get(3)?,
-                    description: row.get(4)?,
-                    is_expense: row.get::<_, i32>(5)? != 0,
-                })
-            })
-            .map_err(|e| e.to_string())?
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| e.to_string())?;
+        let ids: HashSet<&str> = projects.iter().map(|p| p.id.as_str()).collect();
+        let mut children: HashMap<&str, Vec<&Project>> = HashMap::new();
+        let mut roots = Vec::new();
+        for p in &projects {
+            match p.parent_id.as_deref() {
+                Some(parent) if parent != p.id && ids.contains(parent) => {
+                    children.entry(parent).or_default().push(p)
+                }
+                _ => roots.push(p),
+            }
+        }
 
-        // Debts
-        let mut d_stmt = conn.prepare("SELECT id, person, amount, currency, is_owed_by_me, created_at, due_date, status, start_date, payment_day, initial_amount FROM debts ORDER BY created_at DESC").map_err(|e| e.to_string())?;
-        let debts = d_stmt
-            .query_map([], |row| {
-                Ok(Debt {
-                    id: row.get(0)?,
-                    person: row.get(1)?,
-                    amount: row.get(2)?,
-                    currency: row.get(3)?,
-                    is_owed_by_me: row.get::<_, i32>(4)? != 0,
-                    created_at: row.get(5)?,
-                    due_date: row.get(6)?,
-                    status: row.get(7)?,
-                    start_date: row.get(8).unwrap_or(None),
-                    payment_day: row.get(9).unwrap_or(None),
-                    initial_amount: row.get(10).unwrap_or(None),
-                })
-            })
-            .map_err(|e| e.to_string())?
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| e.to_string())?;
+        fn build(
+            p: &Project,
+            children: &HashMap<&str, Vec<&Project>>,
+            counts: &HashMap<String, (i64, i64)>,
+            visited: &mut HashSet<String>,
+        ) -> ProjectNode {
+            visited.insert(p.id.clone());
+            let (task_count, open_task_count) = counts.get(&p.id).copied().unwrap_or((0, 0));
+            let mut kids = Vec::new();
+            for c in children.get(p.id.as_str()).into_iter().flatten() {
+                if !visited.contains(&c.id) {
+                    kids.push(build(c, children, counts, visited));
+                }
+            }
+            ProjectNode {
+                project: p.clone(),
+                task_count,
+                open_task_count,
+                total_task_count: task_count + kids.iter().map(|k| k.total_task_count).sum::<i64>(),
+                children: kids,
+            }
+        }
 
-        Ok((transactions, debts))
+        let mut visited = HashSet::new();
+        let mut tree: Vec<ProjectNode> = roots
+            .into_iter()
+            .map(|p| build(p, &children, &counts, &mut visited))
+            .collect();
+        // Projects stuck in a parent cycle aren't reachable from any root
+        for p in &projects {
+            if !visited.contains(&p.id) {
+                tree.push(build(p, &children, &counts, &mut visited));
+            }
+        }
+        Ok(tree)
     }
 
-    #[allow(dead_code)]
-    pub fn add_transaction(&self, t: NewTransaction) -> Result<Transaction, String> {
-        let conn = &self.conn;
-        conn.execute(
-            "INSERT INTO transactions (id, amount, category, date, description, is_expense) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![t.id, t.amount, t.category, t.date, t.description, t.is_expense as i32]
-        ).map_err(|e| e.to_string())?;
-
-        Ok(Transaction {
-            id: t.id,
-            amount: t.amount,
-            category: t.category,
-            date: t.date,
-            description: t.description,
-            is_expense: t.is_expense,
-        })
+    /// Ids of `id` and all its descendants.
+    fn project_subtree(conn: &Connection, id: &str) -> Result<Vec<String>, String> {
+        let mut stmt = conn
+            .prepare(
+                "WITH RECURSIVE subtree(id) AS (
+                    SELECT ?1
+                    UNION
+                    SELECT p.id FROM projects p JOIN subtree s ON p.parent_id = s.id
+                    WHERE p.deleted_at IS NULL
+                 )
+                 SELECT id FROM subtree",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![id], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?;
+        let mut res = Vec::new();
+        for r in rows {
+            res.push(r.map_err(|e| e.to_string())?);
+        }
+        Ok(res)
     }
 
-    #[allow(dead_code)]
-    pub fn delete_transaction(&self, id: &str) -> Result<(), String> {
-        let conn = &self.conn;
-        conn.execute("DELETE FROM transactions WHERE id = ?1", params![id])
-            .map_err(|e| e.to_string())?;
+    /// Checks that tasks can be put into project `id`: it exists and isn't a
+    /// folder, which only holds other projects.
+    fn task_project(conn: &Connection, id: &str) -> Result<(), String> {
+        let (_, is_folder) = Self::project_row(conn, id)?;
+        if is_folder {
+            return Err("Folders cannot hold tasks".to_string());
+        }
         Ok(())
     }
 
-    #[allow(dead_code)]
-    pub fn add_debt(&self, d: NewDebt) -> Result<Debt, String> {
-        let conn = &self.conn;
-        conn.execute(
-            "INSERT INTO debts (id, person, amount, currency, is_owed_by_me, created_at, due_date, status, start_date, payment_day, initial_amount) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'active', ?8, ?9, ?10)",
-            params![d.id, d.person, d.amount, d.currency, d.is_owed_by_me as i32, d.created_at, d.due_date, d.start_date, d.payment_day, d.initial_amount]
-        ).map_err(|e| e.to_string())?;
-
-        Ok(Debt {
-            id: d.id,
-            person: d.person,
-            amount: d.amount,
-            currency: d.currency,
-            is_owed_by_me: d.is_owed_by_me,
-            created_at: d.created_at,
-            due_date: d.due_date,
-            status: "active".to_string(),
-            start_date: d.start_date,
-            payment_day: d.payment_day,
-            initial_amount: d.initial_amount,
+    fn project_row(conn: &Connection, id: &str) -> Result<(Option<String>, bool), String> {
+        conn.query_row(
+            "SELECT parent_id, is_folder FROM projects WHERE id = ?1 AND deleted_at IS NULL",
+            params![id],
+            |row| Ok((row.get(0)?, row.get::<_, i32>(1)? != 0)),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Project not found: {}", id),
+            other => other.to_string(),
         })
     }
 
-    #[allow(dead_code)]
-    pub fn pay_debt(&self, id: &str) -> Result<(), String> {
-        let conn = &self.conn;
-        conn.execute(
-            "UPDATE debts SET status = 'paid' WHERE id = ?1",
-            params![id],
+    /// Moves a project or folder under `new_parent_id` (a folder), or to the
+    /// top level when `None`.
+    pub fn move_project(&mut self, id: &str, new_parent_id: Option<&str>) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        Self::project_row(&tx, id)?;
+        if let Some(parent) = new_parent_id {
+            let (_, is_folder) = Self::project_row(&tx, parent)?;
+            if !is_folder {
+                return Err("Projects can only be moved into folders".to_string());
+            }
+            if Self::project_subtree(&tx, id)?.iter().any(|d| d == parent) {
+                return Err("Cannot move a folder into itself or one of its subfolders".to_string());
+            }
+        }
+        tx.execute(
+            "UPDATE projects SET parent_id = ?1 WHERE id = ?2",
+            params![new_parent_id, id],
         )
         .map_err(|e| e.to_string())?;
-        Ok(())
+        tx.commit().map_err(|e| e.to_string())
     }
 
-    #[allow(dead_code)]
-    pub fn delete_debt(&self, id: &str) -> Result<(), String> {
-        let conn = &self.conn;
-        conn.execute("DELETE FROM debts WHERE id = ?1", params![id])
+    pub fn delete_project(
+        &mut self,
+        id: &str,
+        policy: ProjectDeletePolicy,
+    ) -> Result<ProjectDeleteSummary, String> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let (parent_id, _) = Self::project_row(&tx, id)?;
+
+        let doomed = match policy {
+            ProjectDeletePolicy::Reparent => vec![id.to_string()],
+            ProjectDeletePolicy::Cascade | ProjectDeletePolicy::Inbox => {
+                Self::project_subtree(&tx, id)?
+            }
+        };
+
+        let mut summary = ProjectDeleteSummary::default();
+        if policy == ProjectDeletePolicy::Reparent {
+            let mut stmt = tx
+                .prepare(
+                    "SELECT id FROM projects WHERE parent_id = ?1 AND id != ?1 AND deleted_at IS NULL",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![id], |row| row.get::<_, String>(0))
+                .map_err(|e| e.to_string())?;
+            for r in rows {
+                summary
+                    .reparented_project_ids
+                    .push(r.map_err(|e| e.to_string())?);
+            }
+            drop(stmt);
+            tx.execute(
+                "UPDATE projects SET parent_id = ?1 WHERE parent_id = ?2 AND id != ?2 AND deleted_at IS NULL",
+                params![parent_id, id],
+            )
             .map_err(|e| e.to_string())?;
-        Ok(())
+        }
+
+        for pid in &doomed {
+            let mut stmt = tx
+                .prepare("SELECT id FROM tasks WHERE project_id = ?1 AND deleted_at IS NULL")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![pid], |row| row.get::<_, String>(0))
+                .map_err(|e| e.to_string())?;
+            let mut task_ids = Vec::new();
+            for r in rows {
+                task_ids.push(r.map_err(|e| e.to_string())?);
+            }
+            drop(stmt);
+
+            // Everything trashed here shares one deleted_at, so restoring the
+            // project brings the whole batch back
+            if policy == ProjectDeletePolicy::Cascade {
+                tx.execute(
+                    "UPDATE tasks SET deleted_at = ?1 WHERE project_id = ?2 AND deleted_at IS NULL",
+                    params![now, pid],
+                )
+                .map_err(|e| e.to_string())?;
+                summary.deleted_task_ids.extend(task_ids);
+            } else {
+                tx.execute(
+                    "UPDATE tasks SET project_id = NULL, section_id = NULL WHERE project_id = ?1 AND deleted_at IS NULL",
+                    params![pid],
+                )
+                .map_err(|e| e.to_string())?;
+                summary.moved_task_ids.extend(task_ids);
+            }
+            tx.execute(
+                "UPDATE projects SET deleted_at = ?1 WHERE id = ?2",
+                params![now, pid],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        summary.deleted_project_ids = doomed;
+
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(summary)
     }
-    #[allow(dead_code)]
-    pub fn get_active_loans(&self) -> Result<Vec<(String, String, i32, Option<String>)>, String> {
-        // id, person, payment_day, last_reminded_date
-        let conn = &self.conn;
-        let mut stmt = conn.prepare("SELECT id, person, payment_day, last_reminded_date FROM debts WHERE status = 'active' AND payment_day IS NOT NULL").map_err(|e| e.to_string())?;
 
-        let loans = stmt
-            .query_map([], |row| {
-                Ok((
-                    row.get(0)?,
-                    row.get(1)?,
-                    row.get(2)?,
-                    row.get(3).unwrap_or(None),
-                ))
-            })
-            .map_err(|e| e.to_string())?
-            .collect::<Result<Vec<_>, _>>()
+    // --- SECTIONS ---
+
+    /// Sections of a project in manual order.
+    pub fn get_sections(
+        &self,
+        project_id: &str,
+        include_archived: bool,
+    ) -> Result<Vec<Section>, String> {
+        let filter = if include_archived {
+            ""
+        } else {
+            "AND archived_at IS NULL"
+        };
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM sections WHERE project_id = ?1 {} ORDER BY sort_order ASC, created_at ASC",
+                SECTION_COLUMNS, filter
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![project_id], section_from_row)
             .map_err(|e| e.to_string())?;
+        let mut res = Vec::new();
+        for r in rows {
+            res.push(r.map_err(|e| e.to_string())?);
+        }
+        Ok(res)
+    }
 
-        Ok(loans)
+    fn get_all_sections(&self) -> Result<Vec<Section>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM sections WHERE project_id IN (SELECT id FROM projects WHERE deleted_at IS NULL) ORDER BY project_id, sort_order ASC",
+                SECTION_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], section_from_row)
+            .map_err(|e| e.to_string())?;
+        let mut res = Vec::new();
+        for r in rows {
+            res.push(r.map_err(|e| e.to_string())?);
+        }
+        Ok(res)
     }
 
-    #[allow(dead_code)]
-    pub fn update_last_reminded(&self, id: &str, date: &str) -> Result<(), String> {
+    pub fn get_section(&self, id: &str) -> Result<Section, String> {
+        self.conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM sections WHERE id = ?1 AND project_id IN (SELECT id FROM projects WHERE deleted_at IS NULL)",
+                    SECTION_COLUMNS
+                ),
+                params![id],
+                section_from_row,
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Section not found: {}", id),
+                other => other.to_string(),
+            })
+    }
+
+    /// Adds a section at the end of the project's sections.
+    pub fn add_section(&self, project_id: &str, name: &str) -> Result<Section, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Section name must not be empty".to_string());
+        }
+        let (_, is_folder) = Self::project_row(&self.conn, project_id)?;
+        if is_folder {
+            return Err("Folders cannot have sections".to_string());
+        }
         let conn = &self.conn;
+        let sort_order: i32 = conn
+            .query_row(
+                "SELECT COALESCE(MAX(sort_order) + 1, 0) FROM sections WHERE project_id = ?1",
+                params![project_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        let section = Section {
+            id: uuid::Uuid::new_v4().to_string(),
+            project_id: project_id.to_string(),
+            name: name.to_string(),
+            sort_order,
+            archived_at: None,
+            created_at: chrono::Utc::now().timestamp_millis(),
+        };
         conn.execute(
-            "UPDATE debts SET last_reminded_date = ?1 WHERE id = ?2",
-            params![date, id],
+            "INSERT INTO sections (id, project_id, name, sort_order, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![section.id, section.project_id, section.name, section.sort_order, section.created_at],
         )
         .map_err(|e| e.to_string())?;
-        Ok(())
+        Ok(section)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::NaiveDate;
+    pub fn rename_section(&self, id: &str, name: &str) -> Result<Section, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Section name must not be empty".to_string());
+        }
+        self.get_section(id)?;
+        self.conn
+            .execute(
+                "UPDATE sections SET name = ?1 WHERE id = ?2",
+                params![name, id],
+            )
+            .map_err(|e| e.to_string())?;
+        self.get_section(id)
+    }
 
-    #[test]
-    fn test_next_date_daily() {
-        let d = NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(); // Sun
-        assert_eq!(
-            next_date_daily(d),
-            NaiveDate::from_ymd_opt(2023, 10, 2).unwrap()
-        );
+    /// Sets the manual order of one project's sections to the order of `ids`.
+    pub fn reorder_sections(&mut self, ids: &[String]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let mut project: Option<String> = None;
+        for (i, id) in ids.iter().enumerate() {
+            let pid: String = tx
+                .query_row(
+                    "SELECT project_id FROM sections WHERE id = ?1",
+                    params![id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| match e {
+                    rusqlite::Error::QueryReturnedNoRows => format!("Section not found: {}", id),
+                    other => other.to_string(),
+                })?;
+            match &project {
+                Some(expected) if *expected != pid => {
+                    return Err("Only sections of the same project can be reordered".to_string())
+                }
+                Some(_) => {}
+                None => project = Some(pid),
+            }
+            tx.execute(
+                "UPDATE sections SET sort_order = ?1 WHERE id = ?2",
+                params![i as i32, id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
     }
 
-    #[test]
-    fn test_next_date_weekdays() {
-        let fri = NaiveDate::from_ymd_opt(2023, 10, 6).unwrap();
-        assert_eq!(
-            next_date_weekdays(fri),
-            NaiveDate::from_ymd_opt(2023, 10, 9).unwrap()
-        ); // Mon
+    /// Archives a section together with its tasks. Like `archive_project` it
+    /// flags the tasks it archived (`archived_with_section`), so tasks archived
+    /// by hand or with the project stay archived on restore. Returns the ids
+    /// of the tasks that were archived.
+    pub fn archive_section(&mut self, id: &str) -> Result<Vec<String>, String> {
+        self.set_section_archived(id, true)
+    }
 
-        let sat = NaiveDate::from_ymd_opt(2023, 10, 7).unwrap();
-        assert_eq!(
-            next_date_weekdays(sat),
-            NaiveDate::from_ymd_opt(2023, 10, 9).unwrap()
-        ); // Mon
+    pub fn unarchive_section(&mut self, id: &str) -> Result<Vec<String>, String> {
+        self.set_section_archived(id, false)
+    }
 
-        let mon = NaiveDate::from_ymd_opt(2023, 10, 9).unwrap();
-        assert_eq!(
-            next_date_weekdays(mon),
-            NaiveDate::from_ymd_opt(2023, 10, 10).unwrap()
-        ); // Tue
+    fn set_section_archived(&mut self, id: &str, archived: bool) -> Result<Vec<String>, String> {
+        self.get_section(id)?;
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let filter = if archived {
+            "is_archived = 0"
+        } else {
+            "archived_with_section = 1"
+        };
+        let mut task_ids = Vec::new();
+        {
+            let mut stmt = tx
+                .prepare(&format!(
+                    "SELECT id FROM tasks WHERE section_id = ?1 AND deleted_at IS NULL AND {}",
+                    filter
+                ))
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![id], |row| row.get::<_, String>(0))
+                .map_err(|e| e.to_string())?;
+            for r in rows {
+                task_ids.push(r.map_err(|e| e.to_string())?);
+            }
+        }
+        tx.execute(
+            &format!(
+                "UPDATE tasks SET is_archived = ?1, archived_with_section = ?1 WHERE section_id = ?2 AND {}",
+                filter
+            ),
+            params![archived as i32, id],
+        )
+        .map_err(|e| e.to_string())?;
+        let archived_at = archived.then(|| chrono::Utc::now().timestamp_millis());
+        tx.execute(
+            "UPDATE sections SET archived_at = ?1 WHERE id = ?2",
+            params![archived_at, id],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(task_ids)
     }
 
-    #[test]
-    fn test_next_date_custom() {
-        let mon = NaiveDate::from_ymd_opt(2023, 10, 9).unwrap();
+    /// Puts a task into a section, moving it to the section's project if
+    /// needed. `None` takes it out of its section but keeps the project.
+    pub fn move_task_to_section(
+        &self,
+        task_id: &str,
+        section_id: Option<&str>,
+    ) -> Result<Task, String> {
+        self.get_task(task_id)?;
+        match section_id {
+            Some(sid) => {
+                let section = self.get_section(sid)?;
+                if section.archived_at.is_some() {
+                    return Err("Cannot move tasks into an archived section".to_string());
+                }
+                self.conn
+                    .execute(
+                        "UPDATE tasks SET section_id = ?1, project_id = ?2 WHERE id = ?3",
+                        params![sid, section.project_id, task_id],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+            None => {
+                self.conn
+                    .execute(
+                        "UPDATE tasks SET section_id = NULL WHERE id = ?1",
+                        params![task_id],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        self.get_task(task_id)
+    }
+
+    // --- BOARDS ---
+
+    pub fn get_boards(&self, project_id: &str) -> Result<Vec<Board>, String> {
+        self.get_project(project_id)?;
+        let ids = {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT id FROM boards WHERE project_id = ?1 ORDER BY created_at ASC")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![project_id], |row| row.get::<_, String>(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        };
+        ids.iter().map(|id| self.get_board(id)).collect()
+    }
+
+    fn get_all_boards(&self) -> Result<Vec<Board>, String> {
+        let ids = {
+            let mut stmt = self
+                .conn
+                .prepare(
+                    "SELECT id FROM boards WHERE project_id IN (SELECT id FROM projects WHERE deleted_at IS NULL) ORDER BY created_at ASC",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| row.get::<_, String>(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        };
+        ids.iter().map(|id| self.get_board(id)).collect()
+    }
+
+    /// Board with its columns and the current card layout.
+    pub fn get_board(&self, id: &str) -> Result<Board, String> {
+        let mut board = Self::board_row(&self.conn, id)?;
+        board.cards = Self::board_cards(&self.conn, &board)?;
+        Ok(board)
+    }
+
+    fn board_row(conn: &Connection, id: &str) -> Result<Board, String> {
+        let mut board = conn
+            .query_row(
+                "SELECT id, project_id, name, created_at FROM boards WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok(Board {
+                        id: row.get(0)?,
+                        project_id: row.get(1)?,
+                        name: row.get(2)?,
+                        created_at: row.get(3)?,
+                        columns: Vec::new(),
+                        cards: Vec::new(),
+                    })
+                },
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Board not found: {}", id),
+                other => other.to_string(),
+            })?;
+        let mut stmt = conn
+            .prepare("SELECT id, name, category, wip_limit, sort_order FROM board_columns WHERE board_id = ?1 ORDER BY sort_order ASC")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![id], |row| {
+                Ok(BoardColumn {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    category: Status::from_int(row.get(2)?),
+                    wip_limit: row.get::<_, Option<i64>>(3)?.map(|v| v as u32),
+                    sort_order: row.get(4)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        for r in rows {
+            board.columns.push(r.map_err(|e| e.to_string())?);
+        }
+        Ok(board)
+    }
+
+    /// Places every open (non-archived) task of the board's project. Stored
+    /// positions are kept while the column still matches the task's status;
+    /// other tasks go to the end of the first column for their status, so
+    /// status changes made outside the board show up on it.
+    fn board_cards(conn: &Connection, board: &Board) -> Result<Vec<BoardCard>, String> {
+        if board.columns.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut stored: HashMap<String, (String, i32)> = HashMap::new();
+        {
+            let mut stmt = conn
+                .prepare("SELECT task_id, column_id, position FROM board_cards WHERE board_id = ?1")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![board.id], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?))
+                })
+                .map_err(|e| e.to_string())?;
+            for r in rows {
+                let (task_id, column_id, position) = r.map_err(|e| e.to_string())?;
+                stored.insert(task_id, (column_id, position));
+            }
+        }
+
+        let mut stmt = conn
+            .prepare("SELECT id, status FROM tasks WHERE project_id = ?1 AND is_archived = 0 AND deleted_at IS NULL ORDER BY sort_order ASC, created_at ASC")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![board.project_id], |row| {
+                Ok((row.get::<_, String>(0)?, Status::from_int(row.get(1)?)))
+            })
+            .map_err(|e| e.to_string())?;
+
+        // (column index, stored position or MAX for new cards, arrival order)
+        let mut placed: Vec<(usize, i32, usize, String)> = Vec::new();
+        for (i, r) in rows.enumerate() {
+            let (task_id, status) = r.map_err(|e| e.to_string())?;
+            let kept = stored.get(&task_id).and_then(|(column_id, position)| {
+                board
+                    .columns
+                    .iter()
+                    .position(|c| c.id == *column_id && c.category == status)
+                    .map(|idx| (idx, *position))
+            });
+            let (idx, position) = kept.unwrap_or_else(|| {
+                let idx = board
+                    .columns
+                    .iter()
+                    .position(|c| c.category == status)
+                    .unwrap_or(0);
+                (idx, i32::MAX)
+            });
+            placed.push((idx, position, i, task_id));
+        }
+        placed.sort();
+
+        let mut cards = Vec::with_capacity(placed.len());
+        let mut next_position = vec![0; board.columns.len()];
+        for (idx, _, _, task_id) in placed {
+            cards.push(BoardCard {
+                task_id,
+                column_id: board.columns[idx].id.clone(),
+                position: next_position[idx],
+            });
+            next_position[idx] += 1;
+        }
+        Ok(cards)
+    }
+
+    fn validate_board_columns(columns: &[BoardColumnInput]) -> Result<(), String> {
+        if columns.is_empty() {
+            return Err("A board needs at least one column".to_string());
+        }
+        for c in columns {
+            if c.name.trim().is_empty() {
+                return Err("Column name must not be empty".to_string());
+            }
+            if c.wip_limit == Some(0) {
+                return Err("WIP limit must be at least 1".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates a board; without `columns` it gets To Do / In Progress / Done.
+    pub fn create_board(
+        &mut self,
+        project_id: &str,
+        name: &str,
+        columns: Option<Vec<BoardColumnInput>>,
+    ) -> Result<Board, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Board name must not be empty".to_string());
+        }
+        let (_, is_folder) = Self::project_row(&self.conn, project_id)?;
+        if is_folder {
+            return Err("Folders cannot have boards".to_string());
+        }
+        let columns = columns.unwrap_or_else(|| {
+            [
+                ("To Do", Status::Todo),
+                ("In Progress", Status::Doing),
+                ("Done", Status::Done),
+            ]
+            .into_iter()
+            .map(|(name, category)| BoardColumnInput {
+                id: None,
+                name: name.to_string(),
+                category,
+                wip_limit: None,
+            })
+            .collect()
+        });
+        Self::validate_board_columns(&columns)?;
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT INTO boards (id, project_id, name, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![id, project_id, name, chrono::Utc::now().timestamp_millis()],
+        )
+        .map_err(|e| e.to_string())?;
+        for (i, c) in columns.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO board_columns (id, board_id, name, category, wip_limit, sort_order) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![uuid::Uuid::new_v4().to_string(), id, c.name.trim(), c.category as i32, c.wip_limit, i as i32],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        self.get_board(&id)
+    }
+
+    pub fn rename_board(&self, id: &str, name: &str) -> Result<Board, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Board name must not be empty".to_string());
+        }
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE boards SET name = ?1 WHERE id = ?2",
+                params![name, id],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err(format!("Board not found: {}", id));
+        }
+        self.get_board(id)
+    }
+
+    /// Replaces the board's columns with `columns`, in that order. Columns
+    /// left out are removed; their cards are placed again by status.
+    pub fn save_board_columns(
+        &mut self,
+        board_id: &str,
+        columns: &[BoardColumnInput],
+    ) -> Result<Board, String> {
+        Self::validate_board_columns(columns)?;
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let existing = Self::board_row(&tx, board_id)?.columns;
+
+        let mut kept = Vec::new();
+        for (i, c) in columns.iter().enumerate() {
+            match &c.id {
+                Some(cid) => {
+                    if !existing.iter().any(|e| e.id == *cid) {
+                        return Err(format!("Column not found: {}", cid));
+                    }
+                    tx.execute(
+                        "UPDATE board_columns SET name = ?1, category = ?2, wip_limit = ?3, sort_order = ?4 WHERE id = ?5",
+                        params![c.name.trim(), c.category as i32, c.wip_limit, i as i32, cid],
+                    )
+                    .map_err(|e| e.to_string())?;
+                    kept.push(cid.clone());
+                }
+                None => {
+                    tx.execute(
+                        "INSERT INTO board_columns (id, board_id, name, category, wip_limit, sort_order) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        params![uuid::Uuid::new_v4().to_string(), board_id, c.name.trim(), c.category as i32, c.wip_limit, i as i32],
+                    )
+                    .map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        for column in existing.iter().filter(|e| !kept.contains(&e.id)) {
+            tx.execute(
+                "DELETE FROM board_columns WHERE id = ?1",
+                params![column.id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        self.get_board(board_id)
+    }
+
+    pub fn delete_board(&self, id: &str) -> Result<(), String> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM boards WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        if deleted == 0 {
+            return Err(format!("Board not found: {}", id));
+        }
+        Ok(())
+    }
+
+    /// Moves a card to `position` (default: the end) in `column_id`. Fails if
+    /// the column is at its WIP limit. When the column's status differs from
+    /// the task's, the status changes too, stamping `completed_at` when it
+    /// becomes done.
+    pub fn move_card(
+        &mut self,
+        board_id: &str,
+        task_id: &str,
+        column_id: &str,
+        position: Option<u32>,
+    ) -> Result<CardMove, String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let board = Self::board_row(&tx, board_id)?;
+        let column = board
+            .columns
+            .iter()
+            .find(|c| c.id == column_id)
+            .ok_or_else(|| format!("Column not found: {}", column_id))?;
+        let (project_id, status, is_archived): (Option<String>, Status, bool) = tx
+            .query_row(
+                "SELECT project_id, status, is_archived FROM tasks WHERE id = ?1 AND deleted_at IS NULL",
+                params![task_id],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        Status::from_int(row.get(1)?),
+                        row.get::<_, i32>(2)? != 0,
+                    ))
+                },
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Task not found: {}", task_id),
+                other => other.to_string(),
+            })?;
+        if project_id.as_deref() != Some(board.project_id.as_str()) {
+            return Err("Task is not in this board's project".to_string());
+        }
+        if is_archived {
+            return Err("Archived tasks are not shown on boards".to_string());
+        }
+
+        let mut cards = Self::board_cards(&tx, &board)?;
+        cards.retain(|c| c.task_id != task_id);
+        let mut target: Vec<String> = cards
+            .iter()
+            .filter(|c| c.column_id == column_id)
+            .map(|c| c.task_id.clone())
+            .collect();
+        if let Some(limit) = column.wip_limit {
+            if target.len() >= limit as usize {
+                return Err(format!(
+                    "Column \"{}\" is at its WIP limit ({})",
+                    column.name, limit
+                ));
+            }
+        }
+
+        let status_changed = column.category != status;
+        let spawned_task_id = if status_changed {
+            set_task_status(&tx, task_id, column.category)?
+        } else {
+            None
+        };
+
+        let at = position.map_or(target.len(), |p| (p as usize).min(target.len()));
+        target.insert(at, task_id.to_string());
+        cards.retain(|c| c.column_id != column_id);
+        tx.execute(
+            "DELETE FROM board_cards WHERE board_id = ?1",
+            params![board_id],
+        )
+        .map_err(|e| e.to_string())?;
+        let target_cards = target.into_iter().enumerate().map(|(i, id)| BoardCard {
+            task_id: id,
+            column_id: column_id.to_string(),
+            position: i as i32,
+        });
+        for card in cards.into_iter().chain(target_cards) {
+            tx.execute(
+                "INSERT INTO board_cards (board_id, task_id, column_id, position) VALUES (?1, ?2, ?3, ?4)",
+                params![board_id, card.task_id, card.column_id, card.position],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+
+        Ok(CardMove {
+            board: self.get_board(board_id)?,
+            status_changed,
+            spawned_task_id,
+        })
+    }
+
+    pub fn get_tasks(
+        &self,
+        limit: Option<i32>,
+        status: Option<Status>,
+        project_id: Option<String>,
+        tag: Option<String>,
+    ) -> Result<Vec<Task>, String> {
+        let conn = &self.conn;
+        let mut query = format!(
+            "SELECT {} FROM tasks WHERE deleted_at IS NULL",
+            TASK_COLUMNS
+        );
+
+        // Build dynamic parameters
+        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+        if let Some(s) = &status {
+            query.push_str(" AND status = ?");
+            params_vec.push(Box::new(*s as i32));
+        }
+        if let Some(pid) = &project_id {
+            query.push_str(" AND project_id = ?");
+            params_vec.push(Box::new(pid.clone()));
+        }
+        if let Some(tag) = &tag {
+            query.push_str(" AND id IN (SELECT tt.task_id FROM task_tags tt JOIN tags g ON g.id = tt.tag_id WHERE g.name = ?)");
+            params_vec.push(Box::new(tag.clone()));
+        }
+        query.push_str(" ORDER BY created_at DESC");
+        if let Some(l) = limit {
+            query.push_str(&format!(" LIMIT {}", l));
+        }
+
+        let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+
+        // Convert params to references for rusqlite
+        let params_refs: Vec<&dyn rusqlite::ToSql> =
+            params_vec.iter().map(|p| p.as_ref()).collect();
+
+        let rows = stmt
+            .query_map(params_refs.as_slice(), task_from_row)
+            .map_err(|e| e.to_string())?;
+        let mut res = Vec::new();
+        for r in rows {
+            res.push(r.map_err(|e| e.to_string())?);
+        }
+        drop(stmt);
+        self.fill_dependencies(&mut res)?;
+        Ok(res)
+    }
+
+    pub fn get_task(&self, id: &str) -> Result<Task, String> {
+        let conn = &self.conn;
+        let mut task = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM tasks WHERE id = ?1 AND deleted_at IS NULL",
+                    TASK_COLUMNS
+                ),
+                params![id],
+                task_from_row,
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Task not found: {}", id),
+                other => other.to_string(),
+            })?;
+        self.fill_dependencies(std::slice::from_mut(&mut task))?;
+        Ok(task)
+    }
+
+    /// Sets `depends_on` and `is_blocked` on each task.
+    fn fill_dependencies(&self, tasks: &mut [Task]) -> Result<(), String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT d.task_id, d.depends_on_id, b.status FROM task_dependencies d
+                 JOIN tasks b ON b.id = d.depends_on_id
+                 WHERE b.deleted_at IS NULL
+                 ORDER BY d.created_at ASC",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    Status::from_int(row.get(2)?),
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        let mut edges: HashMap<String, Vec<(String, Status)>> = HashMap::new();
+        for r in rows {
+            let (task_id, depends_on_id, status) = r.map_err(|e| e.to_string())?;
+            edges
+                .entry(task_id)
+                .or_default()
+                .push((depends_on_id, status));
+        }
+        for task in tasks {
+            if let Some(deps) = edges.get(&task.id) {
+                task.depends_on = deps.iter().map(|(id, _)| id.clone()).collect();
+                task.is_blocked = deps.iter().any(|(_, status)| *status != Status::Done);
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes `task_id` wait for `depends_on_id`. Rejects links that would
+    /// make a task (indirectly) wait for itself.
+    pub fn add_task_dependency(&self, task_id: &str, depends_on_id: &str) -> Result<(), String> {
+        if task_id == depends_on_id {
+            return Err("A task cannot depend on itself".to_string());
+        }
+        self.get_task(task_id)?;
+        self.get_task(depends_on_id)?;
+        let conn = &self.conn;
+        // Everything depends_on_id waits for, directly or not
+        let cycle: bool = conn
+            .query_row(
+                "WITH RECURSIVE upstream(id) AS (
+                    SELECT ?1
+                    UNION
+                    SELECT d.depends_on_id FROM task_dependencies d JOIN upstream u ON d.task_id = u.id
+                 )
+                 SELECT EXISTS (SELECT 1 FROM upstream WHERE id = ?2)",
+                params![depends_on_id, task_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if cycle {
+            return Err("This dependency would create a cycle".to_string());
+        }
+        conn.execute(
+            "INSERT OR IGNORE INTO task_dependencies (task_id, depends_on_id, created_at) VALUES (?1, ?2, ?3)",
+            params![task_id, depends_on_id, chrono::Utc::now().timestamp_millis()],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn remove_task_dependency(&self, task_id: &str, depends_on_id: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                "DELETE FROM task_dependencies WHERE task_id = ?1 AND depends_on_id = ?2",
+                params![task_id, depends_on_id],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Ids of the tasks that wait for `task_id`.
+    pub fn get_dependents(&self, task_id: &str) -> Result<Vec<String>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT d.task_id FROM task_dependencies d JOIN tasks t ON t.id = d.task_id
+                 WHERE d.depends_on_id = ?1 AND t.deleted_at IS NULL",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![task_id], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Snapshots of the currently blocked tasks waiting for `task_id`. Taken
+    /// before `task_id` changes; any of them no longer blocked afterwards was
+    /// unblocked by the change.
+    pub fn blocked_dependents(&self, task_id: &str) -> Vec<(String, Option<Snapshot>)> {
+        self.get_dependents(task_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|id| self.get_task(id).is_ok_and(|t| t.is_blocked))
+            .map(|id| {
+                let old = self.snapshot("tasks", Some(&id));
+                (id, old)
+            })
+            .collect()
+    }
+
+    /// Applies one patch to many tasks in a single transaction. Every id gets
+    /// a result; if any of them fails, the whole batch is rolled back.
+    pub fn bulk_update_tasks(
+        &mut self,
+        ids: &[String],
+        patch: &TaskBulkPatch,
+    ) -> Result<BulkUpdateResult, String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        if let Some(Some(project_id)) = &patch.project_id {
+            Self::task_project(&tx, project_id)?;
+        }
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut seen = HashSet::new();
+        let mut results = Vec::new();
+        for id in ids.iter().filter(|id| seen.insert(id.as_str())) {
+            let (error, spawned_task_id) = match Self::bulk_update_task(&tx, id, patch, now) {
+                Ok(spawned) => (None, spawned),
+                Err(e) => (Some(e), None),
+            };
+            results.push(BulkTaskResult {
+                id: id.clone(),
+                error,
+                spawned_task_id,
+            });
+        }
+
+        let applied = results.iter().all(|r| r.error.is_none());
+        if applied {
+            tx.commit().map_err(|e| e.to_string())?;
+        } else {
+            for r in &mut results {
+                r.spawned_task_id = None;
+            }
+        }
+        Ok(BulkUpdateResult { applied, results })
+    }
+
+    fn bulk_update_task(
+        conn: &Connection,
+        id: &str,
+        patch: &TaskBulkPatch,
+        now: i64,
+    ) -> Result<Option<String>, String> {
+        let status: i32 = conn
+            .query_row(
+                "SELECT status FROM tasks WHERE id = ?1 AND deleted_at IS NULL",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Task not found: {}", id),
+                other => other.to_string(),
+            })?;
+        let run = |sql: &str, args: &[&dyn rusqlite::ToSql]| -> Result<(), String> {
+            conn.execute(sql, args).map_err(|e| e.to_string())?;
+            Ok(())
+        };
+
+        if let Some(project_id) = &patch.project_id {
+            // The section belongs to the old project
+            run(
+                "UPDATE tasks SET section_id = CASE WHEN project_id IS ?1 THEN section_id ELSE NULL END, project_id = ?1 WHERE id = ?2",
+                &[project_id, &id],
+            )?;
+        }
+        if let Some(priority) = patch.priority {
+            run(
+                "UPDATE tasks SET priority = ?1 WHERE id = ?2",
+                &[&(priority as i32), &id],
+            )?;
+        }
+        if let Some(minutes) = patch.deadline_shift_minutes {
+            run(
+                "UPDATE tasks SET deadline = deadline + ?1,
+                    remind_at = CASE WHEN reminded_at IS NULL THEN remind_at + ?1 ELSE remind_at END
+                 WHERE id = ?2 AND deadline IS NOT NULL",
+                &[&(minutes * 60_000), &id],
+            )?;
+        }
+        if !patch.add_tags.is_empty() || !patch.remove_tags.is_empty() {
+            let mut current = task_tag_names(conn, id)?;
+            current.retain(|t| !patch.remove_tags.contains(t));
+            current.extend(patch.add_tags.iter().cloned());
+            set_task_tags(conn, id, &current)?;
+        }
+        if let Some(archived) = patch.archived {
+            run(
+                "UPDATE tasks SET is_archived = ?1 WHERE id = ?2",
+                &[&archived, &id],
+            )?;
+        }
+        // Last, so a spawned occurrence copies the new project, priority and tags
+        let mut spawned = None;
+        if let Some(new_status) = patch.status {
+            if new_status as i32 != status {
+                spawned = set_task_status(conn, id, new_status)?;
+            }
+        }
+        if patch.delete {
+            run(
+                "UPDATE tasks SET deleted_at = ?1 WHERE id = ?2",
+                &[&now, &id],
+            )?;
+        }
+        Ok(spawned)
+    }
+
+    /// Everything that happened to a task, oldest first. Works for trashed
+    /// tasks too.
+    pub fn get_task_history(&self, task_id: &str) -> Result<Vec<TaskEvent>, String> {
+        let conn = &self.conn;
+        let exists: bool = conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM tasks WHERE id = ?1)",
+                params![task_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !exists {
+            return Err(format!("Task not found: {}", task_id));
+        }
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, task_id, action, field, old_value, new_value, created_at
+                 FROM task_events WHERE task_id = ?1 ORDER BY id ASC",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![task_id], |row| {
+                Ok(TaskEvent {
+                    id: row.get(0)?,
+                    task_id: row.get(1)?,
+                    action: row.get(2)?,
+                    field: row.get(3)?,
+                    old_value: row.get(4)?,
+                    new_value: row.get(5)?,
+                    created_at: row.get(6)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        let mut res = Vec::new();
+        for r in rows {
+            res.push(r.map_err(|e| e.to_string())?);
+        }
+        Ok(res)
+    }
+
+    /// Inserts a task, filling in its project's default priority and tags.
+    pub fn add_task(&self, task: &NewTask) -> Result<Task, String> {
+        self.insert_task(task, true)
+    }
+
+    /// Template tasks skip the project defaults, since they were saved with
+    /// the project's defaults already applied or removed.
+    fn insert_task(&self, task: &NewTask, project_defaults: bool) -> Result<Task, String> {
+        let mut task = task.clone();
+        if let Some(section_id) = &task.section_id {
+            let section = self.get_section(section_id)?;
+            match &task.project_id {
+                Some(pid) if *pid != section.project_id => {
+                    return Err(format!(
+                        "Section {} does not belong to project {}",
+                        section_id, pid
+                    ))
+                }
+                Some(_) => {}
+                None => task.project_id = Some(section.project_id),
+            }
+        }
+        if let Some(project_id) = &task.project_id {
+            Self::task_project(&self.conn, project_id)?;
+        }
+        if let Some(scheduled_at) = &task.scheduled_at {
+            validate_scheduled(scheduled_at)?;
+        }
+        if project_defaults {
+            self.apply_project_defaults(&mut task)?;
+        }
+        let task = &task;
+        let conn = &self.conn;
+        conn.execute("INSERT INTO tasks (id, project_id, title, description, priority, status, created_at, deadline, repeat_mode, repeat_days_mask, section_id, estimated_minutes, actual_minutes, remind_at, scheduled_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![task.id, task.project_id, task.title, task.description, task.priority as i32, 0, task.created_at, task.deadline, task.repeat_mode, task.repeat_days_mask, task.section_id, task.estimated_minutes, task.actual_minutes, task.remind_at, task.scheduled_at]
+        ).map_err(|e| e.to_string())?;
+        set_task_tags(conn, &task.id, &task.tags)?;
+        self.get_task(&task.id)
+    }
+
+    /// Applies a partial update in one transaction and returns the task along
+    /// with the next occurrence spawned if it completed a recurring task.
+    pub fn update_task(
+        &mut self,
+        id: &str,
+        patch: &TaskPatch,
+    ) -> Result<(Task, Option<String>), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let current = tx
+            .query_row(
+                &format!(
+                    "SELECT {} FROM tasks WHERE id = ?1 AND deleted_at IS NULL",
+                    TASK_COLUMNS
+                ),
+                params![id],
+                task_from_row,
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Task not found: {}", id),
+                other => other.to_string(),
+            })?;
+        if let Some(expected) = patch.expected_revision {
+            if expected != current.revision {
+                return Err(format!(
+                    "Revision conflict: task {} is at revision {}, not {}",
+                    id, current.revision, expected
+                ));
+            }
+        }
+
+        let mut sets: Vec<&str> = Vec::new();
+        let mut values: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+        if let Some(title) = &patch.title {
+            let title = title.trim();
+            if title.is_empty() {
+                return Err("Task title must not be empty".to_string());
+            }
+            sets.push("title = ?");
+            values.push(Box::new(title.to_string()));
+        }
+        if let Some(description) = &patch.description {
+            sets.push("description = ?");
+            values.push(Box::new(description.clone()));
+        }
+
+        let mut project_id = match &patch.project_id {
+            Some(Some(pid)) => {
+                Self::task_project(&tx, pid)?;
+                Some(pid.clone())
+            }
+            Some(None) => None,
+            None => current.project_id.clone(),
+        };
+        let section_id = match &patch.section_id {
+            Some(Some(sid)) => {
+                let (section_project, archived_at): (String, Option<i64>) = tx
+                    .query_row(
+                        "SELECT project_id, archived_at FROM sections WHERE id = ?1 AND project_id IN (SELECT id FROM projects WHERE deleted_at IS NULL)",
+                        params![sid],
+                        |row| Ok((row.get(0)?, row.get(1)?)),
+                    )
+                    .map_err(|e| match e {
+                        rusqlite::Error::QueryReturnedNoRows => format!("Section not found: {}", sid),
+                        other => other.to_string(),
+                    })?;
+                if archived_at.is_some() {
+                    return Err("Cannot move tasks into an archived section".to_string());
+                }
+                match (&patch.project_id, &project_id) {
+                    (None, _) => project_id = Some(section_project),
+                    (Some(_), Some(pid)) if *pid == section_project => {}
+                    _ => {
+                        return Err(format!(
+                            "Section {} does not belong to project {}",
+                            sid,
+                            project_id.as_deref().unwrap_or("Inbox")
+                        ))
+                    }
+                }
+                Some(sid.clone())
+            }
+            Some(None) => None,
+            None if project_id == current.project_id => current.section_id.clone(),
+            None => None,
+        };
+        if project_id != current.project_id || section_id != current.section_id {
+            sets.push("project_id = ?");
+            values.push(Box::new(project_id));
+            sets.push("section_id = ?");
+            values.push(Box::new(section_id));
+        }
+
+        if let Some(priority) = patch.priority {
+            sets.push("priority = ?");
+            values.push(Box::new(priority as i32));
+        }
+        if let Some(deadline) = patch.deadline {
+            sets.push("deadline = ?");
+            values.push(Box::new(deadline));
+        }
+        if let Some(remind_at) = patch.remind_at {
+            sets.push("remind_at = ?");
+            values.push(Box::new(remind_at));
+            sets.push("reminded_at = NULL");
+        }
+        if let Some(scheduled_at) = &patch.scheduled_at {
+            if let Some(value) = scheduled_at {
+                validate_scheduled(value)?;
+            }
+            sets.push("scheduled_at = ?");
+            values.push(Box::new(scheduled_at.clone()));
+        }
+        if let Some(minutes) = patch.estimated_minutes {
+            sets.push("estimated_minutes = ?");
+            values.push(Box::new(minutes));
+        }
+        if let Some(minutes) = patch.actual_minutes {
+            sets.push("actual_minutes = ?");
+            values.push(Box::new(minutes));
+        }
+        if patch.repeat_mode.is_some() || patch.repeat_days_mask.is_some() {
+            let mode = patch
+                .repeat_mode
+                .clone()
+                .unwrap_or(current.repeat_mode.clone());
+            let mask = patch.repeat_days_mask.unwrap_or(current.repeat_days_mask);
+            validate_repeat(mode.as_deref(), mask)?;
+            sets.push("repeat_mode = ?");
+            values.push(Box::new(mode));
+            sets.push("repeat_days_mask = ?");
+            values.push(Box::new(mask));
+        }
+
+        if !sets.is_empty() {
+            values.push(Box::new(id.to_string()));
+            let sql = format!("UPDATE tasks SET {} WHERE id = ?", sets.join(", "));
+            let refs: Vec<&dyn rusqlite::ToSql> = values.iter().map(|v| v.as_ref()).collect();
+            tx.execute(&sql, refs.as_slice())
+                .map_err(|e| e.to_string())?;
+        }
+        if let Some(tags) = &patch.tags {
+            set_task_tags(&tx, id, tags)?;
+        }
+        // Last, so a spawned occurrence copies the updated fields
+        let mut spawned = None;
+        if let Some(status) = patch.status {
+            if status != current.status {
+                spawned = set_task_status(&tx, id, status)?;
+            }
+        }
+
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok((self.get_task(id)?, spawned))
+    }
+
+    pub fn update_task_title(&self, id: &str, title: String) -> Result<(), String> {
+        let conn = &self.conn;
+        conn.execute(
+            "UPDATE tasks SET title = ?1 WHERE id = ?2",
+            params![title, id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn update_task_priority(&self, id: &str, priority: Priority) -> Result<(), String> {
+        let conn = &self.conn;
+        conn.execute(
+            "UPDATE tasks SET priority = ?1 WHERE id = ?2",
+            params![priority as i32, id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn update_task_deadline(&self, id: &str, deadline: Option<i64>) -> Result<(), String> {
+        let conn = &self.conn;
+        conn.execute(
+            "UPDATE tasks SET deadline = ?1 WHERE id = ?2",
+            params![deadline, id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn update_task_tags(&self, id: &str, tags: Vec<String>) -> Result<(), String> {
+        self.get_task(id)?;
+        set_task_tags(&self.conn, id, &tags)
+    }
+
+    pub fn update_task_repeat(
+        &self,
+        id: &str,
+        mode: Option<String>,
+        mask: Option<i64>,
+    ) -> Result<(), String> {
+        let conn = &self.conn;
+        conn.execute(
+            "UPDATE tasks SET repeat_mode = ?1, repeat_days_mask = ?2 WHERE id = ?3",
+            params![mode, mask, id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Moves the task to the trash. Its subtasks, focus sessions and
+    /// dependency links stay in place (hidden) so a restore brings them back;
+    /// they are removed when the trash is purged.
+    pub fn delete_task(&self, id: &str) -> Result<(), String> {
+        self.soft_delete("tasks", id)
+    }
+
+    pub fn set_task_remind_at(&self, id: &str, remind_at: Option<i64>) -> Result<(), String> {
+        let conn = &self.conn;
+        conn.execute(
+            "UPDATE tasks SET remind_at = ?1, reminded_at = NULL WHERE id = ?2",
+            params![remind_at, id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn snooze_task(&self, id: &str, minutes: i64) -> Result<(), String> {
+        let conn = &self.conn;
+        let next = chrono::Utc::now().timestamp_millis() + (minutes * 60_000);
+        conn.execute(
+            "UPDATE tasks SET remind_at = ?1, reminded_at = NULL WHERE id = ?2",
+            params![next, id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn start_focus_session(&self, task_id: String) -> Result<String, String> {
+        let conn = &self.conn;
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp_millis();
+        conn.execute("INSERT INTO focus_sessions (id, task_id, duration_minutes, completed, started_at) VALUES (?1, ?2, 0, 0, ?3)",
+            params![id, task_id, now]).map_err(|e| e.to_string())?;
+        Ok(id)
+    }
+
+    pub fn finish_focus_session(&self, id: String, mins: i32, comp: bool) -> Result<(), String> {
+        let conn = &self.conn;
+        let now = chrono::Utc::now().timestamp_millis();
+        let was_completed: bool = conn
+            .query_row(
+                "SELECT completed FROM focus_sessions WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap_or(false);
+        conn.execute("UPDATE focus_sessions SET duration_minutes = ?1, completed = ?2, ended_at = ?3 WHERE id = ?4",
+            params![mins, comp, now, id]).map_err(|e| e.to_string())?;
+        if comp && mins > 0 && !was_completed {
+            record_activity(conn, now, 0, mins as u32)?;
+        }
+        if comp && mins > 0 {
+            self.award_focus(&id, mins as i64, now)?;
+        }
+        Ok(())
+    }
+
+    /// Points for a finished focus session, once per session.
+    fn award_focus(&self, session_id: &str, minutes: i64, now: i64) -> Result<(), String> {
+        let awarded: bool = self
+            .conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM points_ledger WHERE event = 'focus' AND ref_id = ?1)",
+                params![session_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if awarded {
+            return Ok(());
+        }
+        let rules = gamification_rules(&self.conn)?;
+        award_points(
+            &self.conn,
+            "focus",
+            Some(session_id),
+            minutes,
+            minutes * rules.focus_minute,
+            now,
+        )?;
+        award_streak_day(&self.conn, now)?;
+        unlock_achievements(&self.conn, now, false)
+    }
+
+    pub fn get_due_reminders(&self, now: i64) -> Result<Vec<Task>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM tasks
+                 WHERE status != 2 AND is_archived = 0 AND remind_at <= ?1 AND deleted_at IS NULL",
+                TASK_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![now], task_from_row)
+            .map_err(|e| e.to_string())?;
+        let mut tasks = Vec::new();
+        for r in rows {
+            tasks.push(r.map_err(|e| e.to_string())?);
+        }
+        drop(stmt);
+        self.fill_dependencies(&mut tasks)?;
+        Ok(tasks)
+    }
+
+    pub fn mark_reminded(&mut self, ids: &[String], now: i64) -> Result<(), String> {
+        let conn = &mut self.conn;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for id in ids {
+            tx.execute(
+                "UPDATE tasks SET reminded_at = ?1, remind_at = NULL WHERE id = ?2",
+                params![now, id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn get_completion_series(&self, days: i32) -> Result<Vec<(String, i32)>, String> {
+        let conn = &self.conn;
+        let mut stmt = conn.prepare("SELECT DATE(datetime(completed_at/1000, 'unixepoch', 'localtime')) as day, COUNT(*) FROM tasks WHERE status = 2 AND completed_at IS NOT NULL AND deleted_at IS NULL GROUP BY day ORDER BY day DESC LIMIT ?1").map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![days], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?;
+        let mut res = Vec::new();
+        for r in rows {
+            res.push(r.map_err(|e| e.to_string())?);
+        }
+        Ok(res)
+    }
+
+    /// Sets the status; completing a recurring task spawns its next occurrence,
+    /// whose id is returned.
+    pub fn update_task_status(
+        &mut self,
+        task_id: &str,
+        new_status: Status,
+    ) -> Result<Option<String>, String> {
+        let conn = &mut self.conn;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let spawned = set_task_status(&tx, task_id, new_status)?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(spawned)
+    }
+
+    /// Spawns the missing next occurrence for completed tasks that still carry a
+    /// repeat rule (e.g. restored from a backup or completed outside of
+    /// `update_task_status`). Safe to run repeatedly.
+    pub fn process_recurring_tasks(&mut self) -> Result<Vec<Task>, String> {
+        let conn = &mut self.conn;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let ids: Vec<String> = {
+            let mut stmt = tx
+                .prepare("SELECT id FROM tasks WHERE status = 2 AND repeat_mode IS NOT NULL AND deleted_at IS NULL")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        };
+
+        let mut spawned_ids = Vec::new();
+        for id in ids {
+            if let Some(new_id) = spawn_next_occurrence(&tx, &id, now)? {
+                spawned_ids.push(new_id);
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+
+        spawned_ids.iter().map(|id| self.get_task(id)).collect()
+    }
+
+    pub fn get_stats(&self) -> Result<UserStats, String> {
+        let conn = &self.conn;
+        let total = conn
+            .query_row(
+                "SELECT COUNT(*) FROM tasks WHERE deleted_at IS NULL",
+                [],
+                |row| row.get::<_, i32>(0),
+            )
+            .unwrap_or(0);
+        let done = conn
+            .query_row(
+                "SELECT COUNT(*) FROM tasks WHERE status = 2 AND deleted_at IS NULL",
+                [],
+                |row| row.get::<_, i32>(0),
+            )
+            .unwrap_or(0);
+
+        let now_local = Local::now();
+        let start_of_day = now_local
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap()
+            .timestamp_millis();
+
+        // Start of week (Monday)
+        let days_from_mon = now_local.weekday().num_days_from_monday();
+        let start_of_week = (now_local - Duration::days(days_from_mon as i64))
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap()
+            .timestamp_millis();
+
+        let completed_today = conn
+            .query_row(
+                "SELECT COUNT(*) FROM tasks WHERE status = 2 AND completed_at >= ?1 AND deleted_at IS NULL",
+                params![start_of_day],
+                |row| row.get::<_, i32>(0),
+            )
+            .unwrap_or(0);
+        let completed_week = conn
+            .query_row(
+                "SELECT COUNT(*) FROM tasks WHERE status = 2 AND completed_at >= ?1 AND deleted_at IS NULL",
+                params![start_of_week],
+                |row| row.get::<_, i32>(0),
+            )
+            .unwrap_or(0);
+
+        // Focus time
+        let total_focus_time: u32 = conn
+            .query_row(
+                "SELECT COALESCE(SUM(duration_minutes), 0) FROM focus_sessions WHERE completed = 1",
+                [],
+                |row| row.get::<_, u32>(0),
+            )
+            .unwrap_or(0);
+
+        // Upcoming/Today counts: the Today list, and open tasks scheduled or
+        // due this week
+        let tasks_today = conn
+            .query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM (SELECT {} FROM tasks WHERE deleted_at IS NULL) WHERE agenda = 'today'",
+                    TASK_COLUMNS
+                ),
+                [],
+                |row| row.get::<_, i32>(0),
+            )
+            .unwrap_or(0);
+
+        let week_start_date = (now_local - Duration::days(days_from_mon as i64)).date_naive();
+        let tasks_week = conn
+            .query_row(
+                "SELECT COUNT(*) FROM tasks WHERE status != 2 AND is_archived = 0 AND deleted_at IS NULL
+                 AND ((deadline >= ?1 AND deadline < ?2)
+                    OR (substr(scheduled_at, 1, 10) >= ?3 AND substr(scheduled_at, 1, 10) < ?4))",
+                params![
+                    start_of_week,
+                    start_of_week + (7 * 86400000),
+                    week_start_date.format("%Y-%m-%d").to_string(),
+                    (week_start_date + Duration::days(7)).format("%Y-%m-%d").to_string()
+                ],
+                |row| row.get::<_, i32>(0),
+            )
+            .unwrap_or(0);
+
+        // Streaks, from the days recorded as they happened
+        let today = now_local.date_naive();
+        let days = streak_days(conn, today)?;
+        let current_streak = current_streak(&days, today);
+        let best_streak = best_streak(&days);
+
+        let status = self.get_gamification()?;
+        Ok(UserStats {
+            total_tasks: total,
+            completed_tasks: done,
+            completed_today,
+            completed_week,
+            best_streak,
+            current_streak,
+            total_focus_time,
+            tasks_today,
+            tasks_week,
+            level: status.level,
+            points: status.points as i32,
+        })
+    }
+
+    // --- AGENDA ---
+
+    /// Open tasks in one agenda list. Today and Anytime follow the manual
+    /// order; Upcoming is by the date each task shows up on.
+    pub fn get_agenda(&self, agenda: Agenda) -> Result<Vec<Task>, String> {
+        let order = match agenda {
+            Agenda::Upcoming => {
+                "COALESCE(substr(scheduled_at, 1, 10), date(deadline / 1000, 'unixepoch', 'localtime')), scheduled_at, sort_order"
+            }
+            Agenda::Today | Agenda::Anytime => "sort_order, created_at",
+        };
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT * FROM (SELECT {} FROM tasks WHERE deleted_at IS NULL) WHERE agenda = ?1 ORDER BY {}",
+                TASK_COLUMNS, order
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![agenda.as_str()], task_from_row)
+            .map_err(|e| e.to_string())?;
+        let mut res = Vec::new();
+        for r in rows {
+            res.push(r.map_err(|e| e.to_string())?);
+        }
+        drop(stmt);
+        self.fill_dependencies(&mut res)?;
+        Ok(res)
+    }
+
+    /// Moves open tasks scheduled before `today` onto it, keeping any time
+    /// of day. Returns the ids that moved.
+    pub fn roll_over_scheduled(&self, today: NaiveDate) -> Result<Vec<String>, String> {
+        let today = today.format("%Y-%m-%d").to_string();
+        let mut stmt = self
+            .conn
+            .prepare(
+                "UPDATE tasks SET scheduled_at = ?1 || substr(scheduled_at, 11)
+                 WHERE substr(scheduled_at, 1, 10) < ?1
+                   AND status != 2 AND is_archived = 0 AND deleted_at IS NULL
+                 RETURNING id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![today], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    // --- DAILY PLAN ---
+
+    pub fn get_daily_plan(&self, date: NaiveDate) -> Result<DailyPlan, String> {
+        let day = date.format("%Y-%m-%d").to_string();
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM tasks
+                 WHERE deleted_at IS NULL AND id IN (SELECT task_id FROM daily_plan WHERE date = ?1)
+                 ORDER BY (SELECT position FROM daily_plan WHERE date = ?1 AND task_id = tasks.id)",
+                TASK_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![day], task_from_row)
+            .map_err(|e| e.to_string())?;
+        let mut tasks = Vec::new();
+        for r in rows {
+            tasks.push(r.map_err(|e| e.to_string())?);
+        }
+        drop(stmt);
+        self.fill_dependencies(&mut tasks)?;
+
+        let mut planned_minutes = 0;
+        let mut remaining_minutes = 0;
+        let mut unestimated_count = 0;
+        for task in &tasks {
+            let estimate = task.estimated_minutes.unwrap_or(0);
+            planned_minutes += estimate;
+            if task.status != Status::Done {
+                remaining_minutes += estimate;
+                if task.estimated_minutes.is_none() {
+                    unestimated_count += 1;
+                }
+            }
+        }
+        let settings = self.get_settings()?;
+        let focused_minutes = self
+            .conn
+            .query_row(
+                "SELECT COALESCE(SUM(duration_minutes), 0) FROM focus_sessions
+                 WHERE completed = 1 AND started_at >= ?1 AND started_at < ?2",
+                params![local_ms(date, 0), local_ms(date + Duration::days(1), 0)],
+                |row| row.get::<_, u32>(0),
+            )
+            .map_err(|e| e.to_string())?;
+
+        Ok(DailyPlan {
+            date: day,
+            tasks,
+            planned_minutes,
+            remaining_minutes,
+            unestimated_count,
+            available_minutes: settings.pomodoro_length * settings.daily_pomodoros,
+            focused_minutes,
+        })
+    }
+
+    /// Appends tasks to a day's plan; ones already planned keep their place.
+    pub fn add_to_daily_plan(
+        &mut self,
+        date: NaiveDate,
+        task_ids: &[String],
+    ) -> Result<(), String> {
+        let day = date.format("%Y-%m-%d").to_string();
+        let now = chrono::Utc::now().timestamp_millis();
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        for id in task_ids {
+            let exists: bool = tx
+                .query_row(
+                    "SELECT EXISTS(SELECT 1 FROM tasks WHERE id = ?1 AND deleted_at IS NULL)",
+                    params![id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| e.to_string())?;
+            if !exists {
+                return Err(format!("Task not found: {}", id));
+            }
+            tx.execute(
+                "INSERT OR IGNORE INTO daily_plan (date, task_id, position, added_at)
+                 SELECT ?1, ?2, COALESCE(MAX(position) + 1, 0), ?3 FROM daily_plan WHERE date = ?1",
+                params![day, id, now],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    pub fn remove_from_daily_plan(&self, date: NaiveDate, task_id: &str) -> Result<(), String> {
+        let day = date.format("%Y-%m-%d").to_string();
+        let removed = self
+            .conn
+            .execute(
+                "DELETE FROM daily_plan WHERE date = ?1 AND task_id = ?2",
+                params![day, task_id],
+            )
+            .map_err(|e| e.to_string())?;
+        if removed == 0 {
+            return Err(format!("Task {} is not planned for {}", task_id, day));
+        }
+        Ok(())
+    }
+
+    /// Puts the given tasks first, in this order; the rest of the plan
+    /// follows in its previous order.
+    pub fn reorder_daily_plan(
+        &mut self,
+        date: NaiveDate,
+        task_ids: &[String],
+    ) -> Result<(), String> {
+        let day = date.format("%Y-%m-%d").to_string();
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let current: Vec<String> = {
+            let mut stmt = tx
+                .prepare("SELECT task_id FROM daily_plan WHERE date = ?1 ORDER BY position")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![day], |row| row.get::<_, String>(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        };
+        let mut order: Vec<&String> = Vec::new();
+        for id in task_ids {
+            if !current.contains(id) {
+                return Err(format!("Task {} is not planned for {}", id, day));
+            }
+            if !order.contains(&id) {
+                order.push(id);
+            }
+        }
+        order.extend(current.iter().filter(|id| !task_ids.contains(id)));
+        for (i, id) in order.iter().enumerate() {
+            tx.execute(
+                "UPDATE daily_plan SET position = ?1 WHERE date = ?2 AND task_id = ?3",
+                params![i as i32, day, id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Moves the unfinished tasks of one day's plan to the end of another's,
+    /// keeping their order. Done tasks stay behind as a record of the day.
+    /// Returns the ids that moved.
+    pub fn carry_over_daily_plan(
+        &mut self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<String>, String> {
+        if from == to {
+            return Err("Cannot carry a plan over to the same day".to_string());
+        }
+        let from = from.format("%Y-%m-%d").to_string();
+        let to = to.format("%Y-%m-%d").to_string();
+        let now = chrono::Utc::now().timestamp_millis();
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let ids: Vec<String> = {
+            let mut stmt = tx
+                .prepare(
+                    "SELECT p.task_id FROM daily_plan p JOIN tasks t ON t.id = p.task_id
+                     WHERE p.date = ?1 AND t.status != 2 AND t.deleted_at IS NULL
+                     ORDER BY p.position",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![from], |row| row.get::<_, String>(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        };
+        for id in &ids {
+            tx.execute(
+                "DELETE FROM daily_plan WHERE date = ?1 AND task_id = ?2",
+                params![from, id],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.execute(
+                "INSERT OR IGNORE INTO daily_plan (date, task_id, position, added_at)
+                 SELECT ?1, ?2, COALESCE(MAX(position) + 1, 0), ?3 FROM daily_plan WHERE date = ?1",
+                params![to, id, now],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(ids)
+    }
+
+    // --- TIME BLOCKS ---
+
+    /// Blocks overlapping `[from, to)`, by start time. Blocks of trashed
+    /// tasks are hidden.
+    pub fn get_time_blocks(&self, from: i64, to: i64) -> Result<Vec<TimeBlock>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM time_blocks b LEFT JOIN tasks t ON t.id = b.task_id
+                 WHERE b.start_at < ?2 AND b.end_at > ?1 AND t.deleted_at IS NULL
+                 ORDER BY b.start_at",
+                TIME_BLOCK_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![from, to], time_block_from_row)
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn get_time_block(&self, id: &str) -> Result<TimeBlock, String> {
+        self.conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM time_blocks b LEFT JOIN tasks t ON t.id = b.task_id
+                     WHERE b.id = ?1 AND t.deleted_at IS NULL",
+                    TIME_BLOCK_COLUMNS
+                ),
+                params![id],
+                time_block_from_row,
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Time block not found: {}", id),
+                other => other.to_string(),
+            })
+    }
+
+    /// Rejects empty ranges and ones that overlap another block.
+    fn check_block_slot(
+        &self,
+        start_at: i64,
+        end_at: i64,
+        except: Option<&str>,
+    ) -> Result<(), String> {
+        if end_at <= start_at {
+            return Err("Time block must end after it starts".to_string());
+        }
+        let overlap = self
+            .get_time_blocks(start_at, end_at)?
+            .into_iter()
+            .find(|b| Some(b.id.as_str()) != except);
+        match overlap {
+            Some(b) => Err(format!("Time block overlaps \"{}\"", b.title)),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds a block for a task, or a free-standing one with a title.
+    pub fn add_time_block(
+        &self,
+        task_id: Option<&str>,
+        title: Option<&str>,
+        start_at: i64,
+        end_at: i64,
+    ) -> Result<TimeBlock, String> {
+        let title = title.map(str::trim).filter(|t| !t.is_empty());
+        match task_id {
+            Some(task_id) => {
+                self.get_task(task_id)?;
+            }
+            None if title.is_none() => {
+                return Err("A time block needs a task or a title".to_string())
+            }
+            None => {}
+        }
+        self.check_block_slot(start_at, end_at, None)?;
+        let id = uuid::Uuid::new_v4().to_string();
+        self.conn
+            .execute(
+                "INSERT INTO time_blocks (id, task_id, title, start_at, end_at, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    id,
+                    task_id,
+                    title,
+                    start_at,
+                    end_at,
+                    chrono::Utc::now().timestamp_millis()
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        self.get_time_block(&id)
+    }
+
+    /// Moves a block to start at `start_at`, keeping its length.
+    pub fn move_time_block(&self, id: &str, start_at: i64) -> Result<TimeBlock, String> {
+        let block = self.get_time_block(id)?;
+        self.resize_time_block(id, start_at, start_at + block.end_at - block.start_at)
+    }
+
+    pub fn resize_time_block(
+        &self,
+        id: &str,
+        start_at: i64,
+        end_at: i64,
+    ) -> Result<TimeBlock, String> {
+        self.get_time_block(id)?;
+        self.check_block_slot(start_at, end_at, Some(id))?;
+        self.conn
+            .execute(
+                "UPDATE time_blocks SET start_at = ?1, end_at = ?2 WHERE id = ?3",
+                params![start_at, end_at, id],
+            )
+            .map_err(|e| e.to_string())?;
+        self.get_time_block(id)
+    }
+
+    pub fn delete_time_block(&self, id: &str) -> Result<(), String> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM time_blocks WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        if deleted == 0 {
+            return Err(format!("Time block not found: {}", id));
+        }
+        Ok(())
+    }
+
+    /// Marks a block done and, when asked, starts a focus session on its
+    /// task.
+    pub fn complete_time_block(
+        &self,
+        id: &str,
+        start_focus: bool,
+    ) -> Result<TimeBlockCompletion, String> {
+        let block = self.get_time_block(id)?;
+        let session = match (&block.task_id, start_focus) {
+            (Some(task_id), true) => Some(self.start_focus_session(task_id.clone())?),
+            (None, true) => return Err("Time block has no task to focus on".to_string()),
+            (_, false) => None,
+        };
+        self.conn
+            .execute(
+                "UPDATE time_blocks SET completed_at = COALESCE(completed_at, ?1) WHERE id = ?2",
+                params![chrono::Utc::now().timestamp_millis(), id],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(TimeBlockCompletion {
+            block: self.get_time_block(id)?,
+            focus_session_id: session,
+        })
+    }
+
+    /// Places open tasks that have an estimate but no upcoming block into the
+    /// first free working-hours slots of the next `days` days, earliest
+    /// deadline first, then by priority. Tasks are not placed before their
+    /// scheduled day, and blocked tasks wait.
+    pub fn auto_schedule(&mut self, now: i64, days: u32) -> Result<AutoScheduleResult, String> {
+        let settings = self.get_settings()?;
+        let (work_start, work_end) = (settings.work_start_minutes, settings.work_end_minutes);
+        if work_start >= work_end || work_end > 24 * 60 {
+            return Err("Working hours must start before they end".to_string());
+        }
+        if settings.work_days_mask & 0x7f == 0 {
+            return Err("No working days are set".to_string());
+        }
+        let first_day = Local
+            .timestamp_millis_opt(now)
+            .single()
+            .ok_or_else(|| format!("Invalid time: {}", now))?
+            .date_naive();
+        // Slots start on a five minute mark
+        let step = 5 * 60_000;
+        let now = (now + step - 1) / step * step;
+
+        let mut tasks = {
+            let mut stmt = self
+                .conn
+                .prepare(&format!(
+                    "SELECT {} FROM tasks
+                     WHERE deleted_at IS NULL AND status != 2 AND is_archived = 0 AND estimated_minutes > 0
+                       AND NOT EXISTS (SELECT 1 FROM time_blocks b WHERE b.task_id = tasks.id
+                                       AND b.completed_at IS NULL AND b.end_at > ?1)
+                     ORDER BY deadline IS NULL, deadline, priority DESC, sort_order, created_at",
+                    TASK_COLUMNS
+                ))
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![now], task_from_row)
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        };
+        self.fill_dependencies(&mut tasks)?;
+
+        let horizon = local_ms(first_day + Duration::days(days as i64), 0);
+        let mut busy: Vec<(i64, i64)> = self
+            .get_time_blocks(now, horizon)?
+            .into_iter()
+            .map(|b| (b.start_at, b.end_at))
+            .collect();
+
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| e.to_string())?;
+        let mut result = AutoScheduleResult {
+            blocks: Vec::new(),
+            unscheduled_task_ids: Vec::new(),
+        };
+        for task in tasks.iter().filter(|t| !t.is_blocked) {
+            let len = task.estimated_minutes.unwrap_or(0) as i64 * 60_000;
+            let scheduled_day = task.scheduled_at.as_deref().and_then(|s| s.get(..10));
+            let earliest = match scheduled_day.map(parse_day) {
+                Some(Ok(day)) => now.max(local_ms(day, 0)),
+                _ => now,
+            };
+            let slot = (0..days as i64)
+                .map(|offset| first_day + Duration::days(offset))
+                .filter(|day| {
+                    settings.work_days_mask & (1 << day.weekday().num_days_from_monday()) != 0
+                })
+                .find_map(|day| {
+                    let from = earliest.max(local_ms(day, work_start));
+                    first_free_slot(&busy, from, local_ms(day, work_end), len)
+                });
+            match slot {
+                Some(start) => {
+                    let block = self.add_time_block(Some(&task.id), None, start, start + len)?;
+                    let at = busy.partition_point(|b| b.0 < start);
+                    busy.insert(at, (start, start + len));
+                    result.blocks.push(block);
+                }
+                None => result.unscheduled_task_ids.push(task.id.clone()),
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(result)
+    }
+
+    // --- TIME TRACKING ---
+
+    /// Time entries that started on the local days `from..=to`, by start,
+    /// including a running one. Entries of trashed tasks are hidden.
+    pub fn get_time_entries(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<TimeEntry>, String> {
+        let (start, end) = day_range(from, to)?;
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM time_entries e JOIN tasks t ON t.id = e.task_id
+                 WHERE e.started_at >= ?1 AND e.started_at < ?2 AND t.deleted_at IS NULL
+                 ORDER BY e.started_at",
+                TIME_ENTRY_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![start, end], time_entry_from_row)
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn get_time_entry(&self, id: &str) -> Result<TimeEntry, String> {
+        self.conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM time_entries e JOIN tasks t ON t.id = e.task_id
+                     WHERE e.id = ?1",
+                    TIME_ENTRY_COLUMNS
+                ),
+                params![id],
+                time_entry_from_row,
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Time entry not found: {}", id),
+                other => other.to_string(),
+            })
+    }
+
+    /// The running timer, even when its task was trashed meanwhile, so it
+    /// can still be stopped.
+    pub fn get_running_timer(&self) -> Result<Option<TimeEntry>, String> {
+        let res = self.conn.query_row(
+            &format!(
+                "SELECT {} FROM time_entries e JOIN tasks t ON t.id = e.task_id
+                 WHERE e.ended_at IS NULL",
+                TIME_ENTRY_COLUMNS
+            ),
+            [],
+            time_entry_from_row,
+        );
+        match res {
+            Ok(entry) => Ok(Some(entry)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Starts the timer on a task. Only one timer runs at a time, so a
+    /// running one has to be stopped first.
+    pub fn start_timer(&self, task_id: &str, now: i64) -> Result<TimeEntry, String> {
+        self.get_task(task_id)?;
+        if let Some(running) = self.get_running_timer()? {
+            return Err(format!(
+                "A timer is already running on \"{}\"",
+                running.task_title
+            ));
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        self.conn
+            .execute(
+                "INSERT INTO time_entries (id, task_id, started_at, created_at) VALUES (?1, ?2, ?3, ?3)",
+                params![id, task_id, now],
+            )
+            .map_err(|e| e.to_string())?;
+        self.get_time_entry(&id)
+    }
+
+    pub fn stop_timer(&self, now: i64) -> Result<TimeEntry, String> {
+        let running = self
+            .get_running_timer()?
+            .ok_or_else(|| "No timer is running".to_string())?;
+        self.conn
+            .execute(
+                "UPDATE time_entries SET ended_at = ?1 WHERE id = ?2",
+                params![now.max(running.started_at), running.id],
+            )
+            .map_err(|e| e.to_string())?;
+        self.get_time_entry(&running.id)
+    }
+
+    /// Records time worked on a task after the fact.
+    pub fn add_time_entry(
+        &self,
+        task_id: &str,
+        started_at: i64,
+        ended_at: i64,
+        note: Option<&str>,
+    ) -> Result<TimeEntry, String> {
+        self.get_task(task_id)?;
+        if ended_at <= started_at {
+            return Err("Time entry must end after it starts".to_string());
+        }
+        let note = note.map(str::trim).filter(|n| !n.is_empty());
+        let id = uuid::Uuid::new_v4().to_string();
+        self.conn
+            .execute(
+                "INSERT INTO time_entries (id, task_id, note, started_at, ended_at, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    id,
+                    task_id,
+                    note,
+                    started_at,
+                    ended_at,
+                    chrono::Utc::now().timestamp_millis()
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        self.get_time_entry(&id)
+    }
+
+    pub fn update_time_entry(&self, id: &str, patch: &TimeEntryPatch) -> Result<TimeEntry, String> {
+        let entry = self.get_time_entry(id)?;
+        let task_id = match &patch.task_id {
+            Some(task_id) => self.get_task(task_id)?.id,
+            None => entry.task_id,
+        };
+        let started_at = patch.started_at.unwrap_or(entry.started_at);
+        let ended_at = patch.ended_at.or(entry.ended_at);
+        if ended_at.is_some_and(|end| end <= started_at) {
+            return Err("Time entry must end after it starts".to_string());
+        }
+        let note = match &patch.note {
+            Some(note) => note
+                .as_deref()
+                .map(str::trim)
+                .filter(|n| !n.is_empty())
+                .map(str::to_string),
+            None => entry.note,
+        };
+        self.conn
+            .execute(
+                "UPDATE time_entries SET task_id = ?1, note = ?2, started_at = ?3, ended_at = ?4
+                 WHERE id = ?5",
+                params![task_id, note, started_at, ended_at, id],
+            )
+            .map_err(|e| e.to_string())?;
+        self.get_time_entry(id)
+    }
+
+    pub fn delete_time_entry(&self, id: &str) -> Result<(), String> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM time_entries WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        if deleted == 0 {
+            return Err(format!("Time entry not found: {}", id));
+        }
+        Ok(())
+    }
+
+    /// Finished time entries and focus sessions that started on the local
+    /// days `from..=to`, by start. Focus sessions count their focused
+    /// minutes rather than the time until they ended.
+    pub fn get_timesheet(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<TimesheetEntry>, String> {
+        let (start, end) = day_range(from, to)?;
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT s.source, s.id, s.task_id, t.title, t.project_id, p.name, s.note,
+                        s.started_at, s.ended_at, s.minutes
+                 FROM (
+                     SELECT 'time_entry' AS source, id, task_id, note, started_at, ended_at,
+                            (ended_at - started_at + 30000) / 60000 AS minutes
+                     FROM time_entries WHERE ended_at IS NOT NULL
+                     UNION ALL
+                     SELECT 'focus_session', id, task_id, NULL, started_at, ended_at, duration_minutes
+                     FROM focus_sessions WHERE ended_at IS NOT NULL AND duration_minutes > 0
+                 ) s
+                 JOIN tasks t ON t.id = s.task_id
+                 LEFT JOIN projects p ON p.id = t.project_id
+                 WHERE s.started_at >= ?1 AND s.started_at < ?2 AND t.deleted_at IS NULL
+                 ORDER BY s.started_at, s.source",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![start, end], |row| {
+                Ok(TimesheetEntry {
+                    source: row.get(0)?,
+                    id: row.get(1)?,
+                    task_id: row.get(2)?,
+                    task_title: row.get(3)?,
+                    project_id: row.get(4)?,
+                    project_name: row.get(5)?,
+                    tags: Vec::new(),
+                    note: row.get(6)?,
+                    started_at: row.get(7)?,
+                    ended_at: row.get(8)?,
+                    minutes: row.get(9)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        let mut entries = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+        for entry in &mut entries {
+            if !tags.contains_key(&entry.task_id) {
+                let names = task_tag_names(&self.conn, &entry.task_id)?;
+                tags.insert(entry.task_id.clone(), names);
+            }
+            entry.tags = tags[&entry.task_id].clone();
+        }
+        Ok(entries)
+    }
+
+    /// Timesheet minutes summed by task, project, tag or day. Days come in
+    /// date order, the rest with the most time first. An entry counts
+    /// toward every tag of its task.
+    pub fn get_time_rollup(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        group: TimeRollupGroup,
+    ) -> Result<Vec<TimeRollup>, String> {
+        let mut rollups: Vec<TimeRollup> = Vec::new();
+        for entry in self.get_timesheet(from, to)? {
+            let keys = match group {
+                TimeRollupGroup::Task => vec![(Some(entry.task_id), entry.task_title)],
+                TimeRollupGroup::Project => vec![(
+                    entry.project_id,
+                    entry.project_name.unwrap_or_else(|| "Inbox".to_string()),
+                )],
+                TimeRollupGroup::Tag if entry.tags.is_empty() => {
+                    vec![(None, "Untagged".to_string())]
+                }
+                TimeRollupGroup::Tag => entry
+                    .tags
+                    .into_iter()
+                    .map(|tag| (Some(tag.clone()), tag))
+                    .collect(),
+                TimeRollupGroup::Day => {
+                    let day = local_day(entry.started_at);
+                    vec![(Some(day.clone()), day)]
+                }
+            };
+            for (key, label) in keys {
+                match rollups.iter_mut().find(|r| r.key == key) {
+                    Some(rollup) => {
+                        rollup.minutes += entry.minutes;
+                        rollup.entries += 1;
+                    }
+                    None => rollups.push(TimeRollup {
+                        key,
+                        label,
+                        minutes: entry.minutes,
+                        entries: 1,
+                    }),
+                }
+            }
+        }
+        match group {
+            TimeRollupGroup::Day => rollups.sort_by(|a, b| a.key.cmp(&b.key)),
+            _ => rollups.sort_by(|a, b| b.minutes.cmp(&a.minutes).then(a.label.cmp(&b.label))),
+        }
+        Ok(rollups)
+    }
+
+    /// The timesheet for `from..=to` as "csv" or "json".
+    pub fn export_timesheet(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        format: &str,
+    ) -> Result<String, String> {
+        let entries = self.get_timesheet(from, to)?;
+        match format {
+            "csv" => Ok(timesheet_csv(&entries)),
+            "json" => serde_json::to_string_pretty(&entries).map_err(|e| e.to_string()),
+            other => Err(format!("Invalid timesheet format: {}", other)),
+        }
+    }
+
+    // --- GAMIFICATION ---
+
+    pub fn get_gamification_rules(&self) -> Result<GamificationRules, String> {
+        gamification_rules(&self.conn)
+    }
+
+    /// Replaces the rules. Points already in the ledger stay as they are;
+    /// levels follow the new thresholds.
+    pub fn save_gamification_rules(&self, rules: &GamificationRules) -> Result<(), String> {
+        let points = [
+            rules.complete_low,
+            rules.complete_normal,
+            rules.complete_high,
+            rules.on_time_bonus,
+            rules.focus_minute,
+            rules.streak_day,
+        ];
+        if points.iter().any(|p| *p < 0) {
+            return Err("Points must not be negative".to_string());
+        }
+        if rules.level_thresholds.first().is_some_and(|t| *t <= 0)
+            || rules.level_thresholds.windows(2).any(|w| w[1] <= w[0])
+        {
+            return Err("Level thresholds must be positive and increasing".to_string());
+        }
+        let body = serde_json::to_string(rules).map_err(|e| e.to_string())?;
+        self.conn
+            .execute(
+                "INSERT INTO gamification_rules (id, body) VALUES (1, ?1)
+                 ON CONFLICT(id) DO UPDATE SET body = excluded.body",
+                params![body],
+            )
+            .map_err(|e| e.to_string())?;
+        unlock_achievements(&self.conn, chrono::Utc::now().timestamp_millis(), false)
+    }
+
+    /// The newest `limit` ledger entries, newest first.
+    pub fn get_points_ledger(&self, limit: u32) -> Result<Vec<PointsEntry>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, event, ref_id, quantity, points, created_at FROM points_ledger
+                 ORDER BY created_at DESC, id DESC LIMIT ?1",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![limit], |row| {
+                Ok(PointsEntry {
+                    id: row.get(0)?,
+                    event: row.get(1)?,
+                    ref_id: row.get(2)?,
+                    quantity: row.get(3)?,
+                    points: row.get(4)?,
+                    created_at: row.get(5)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    fn unlocked_achievements(&self, sql: &str) -> Result<HashMap<String, i64>, String> {
+        let mut stmt = self.conn.prepare(sql).map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    fn achievement(def: &AchievementDef, unlocked_at: Option<i64>) -> Achievement {
+        Achievement {
+            id: def.id.to_string(),
+            name: def.name.to_string(),
+            description: def.description.to_string(),
+            unlocked_at,
+        }
+    }
+
+    /// Total points, the level they reach, and every achievement with its
+    /// unlock time.
+    pub fn get_gamification(&self) -> Result<GamificationStatus, String> {
+        let rules = gamification_rules(&self.conn)?;
+        let points: i64 = self
+            .conn
+            .query_row(
+                "SELECT COALESCE(SUM(points), 0) FROM points_ledger",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        let level = level_for(points, &rules.level_thresholds);
+        let unlocked = self.unlocked_achievements("SELECT id, unlocked_at FROM achievements")?;
+        Ok(GamificationStatus {
+            points,
+            level,
+            level_start: match level {
+                1 => 0,
+                _ => rules.level_thresholds[level as usize - 2],
+            },
+            next_level_at: rules.level_thresholds.get(level as usize - 1).copied(),
+            achievements: ACHIEVEMENTS
+                .iter()
+                .map(|def| Self::achievement(def, unlocked.get(def.id).copied()))
+                .collect(),
+        })
+    }
+
+    /// Achievements unlocked since the last call, oldest first; they are
+    /// marked as announced.
+    pub fn take_unannounced_achievements(&self) -> Result<Vec<Achievement>, String> {
+        let fresh = self.unlocked_achievements(
+            "SELECT id, unlocked_at FROM achievements WHERE announced_at IS NULL",
+        )?;
+        if fresh.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.conn
+            .execute(
+                "UPDATE achievements SET announced_at = ?1 WHERE announced_at IS NULL",
+                params![chrono::Utc::now().timestamp_millis()],
+            )
+            .map_err(|e| e.to_string())?;
+        let mut achievements: Vec<Achievement> = ACHIEVEMENTS
+            .iter()
+            .filter_map(|def| Some(Self::achievement(def, Some(*fresh.get(def.id)?))))
+            .collect();
+        achievements.sort_by_key(|a| a.unlocked_at);
+        Ok(achievements)
+    }
+
+    // --- STREAKS ---
+
+    /// A day's activity; a day with nothing recorded is measured against the
+    /// current goal.
+    pub fn get_daily_activity_day(&self, day: NaiveDate) -> Result<DailyActivity, String> {
+        let day = day.format("%Y-%m-%d").to_string();
+        let activity = self.conn.query_row(
+            &format!(
+                "SELECT {} FROM daily_activity WHERE day = ?1",
+                DAILY_ACTIVITY_COLUMNS
+            ),
+            params![day],
+            daily_activity_from_row,
+        );
+        match activity {
+            Ok(activity) => Ok(activity),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                let (goal_tasks, goal_focus_minutes) = daily_goal(&self.conn)?;
+                Ok(DailyActivity {
+                    day,
+                    tasks_done: 0,
+                    focus_minutes: 0,
+                    goal_tasks,
+                    goal_focus_minutes,
+                    goal_met: false,
+                    frozen: false,
+                })
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Recorded days from `from` to `to`, oldest first. Days left out had no
+    /// activity and weren't frozen.
+    pub fn get_daily_activity(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyActivity>, String> {
+        if to < from {
+            return Err("Date range ends before it starts".to_string());
+        }
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM daily_activity WHERE day >= ?1 AND day <= ?2 ORDER BY day",
+                DAILY_ACTIVITY_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(
+                params![
+                    from.format("%Y-%m-%d").to_string(),
+                    to.format("%Y-%m-%d").to_string()
+                ],
+                daily_activity_from_row,
+            )
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn get_streak(&self) -> Result<StreakStatus, String> {
+        let today = Local::now().date_naive();
+        let days = streak_days(&self.conn, today)?;
+        Ok(StreakStatus {
+            current: current_streak(&days, today),
+            best: best_streak(&days),
+            today: self.get_daily_activity_day(today)?,
+            freezes_left: self.streak_freezes_left(today)?,
+        })
+    }
+
+    /// Freezes left in the month `day` falls in.
+    fn streak_freezes_left(&self, day: NaiveDate) -> Result<u32, String> {
+        let used: u32 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM daily_activity WHERE frozen_at IS NOT NULL AND substr(day, 1, 7) = ?1",
+                params![day.format("%Y-%m").to_string()],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(self
+            .get_settings()?
+            .streak_freezes_per_month
+            .saturating_sub(used))
+    }
+
+    /// Freezes `day` so missing its goal doesn't break a streak. Yesterday,
+    /// today and days ahead can be frozen, within the month's allowance.
+    pub fn freeze_streak_day(&self, day: NaiveDate) -> Result<DailyActivity, String> {
+        let today = Local::now().date_naive();
+        if day < today - Duration::days(1) {
+            return Err("Only yesterday, today or a later day can be frozen".to_string());
+        }
+        let activity = self.get_daily_activity_day(day)?;
+        if activity.goal_met {
+            return Err(format!("The goal was already met on {}", activity.day));
+        }
+        if activity.frozen {
+            return Err(format!("Day is already frozen: {}", activity.day));
+        }
+        if self.streak_freezes_left(day)? == 0 {
+            return Err(format!("No streak freezes left in {}", day.format("%Y-%m")));
+        }
+        self.conn
+            .execute(
+                "INSERT INTO daily_activity (day, goal_tasks, goal_focus_minutes, frozen_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(day) DO UPDATE SET frozen_at = excluded.frozen_at",
+                params![
+                    activity.day,
+                    activity.goal_tasks,
+                    activity.goal_focus_minutes,
+                    chrono::Utc::now().timestamp_millis()
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        self.get_daily_activity_day(day)
+    }
+
+    /// Takes a freeze back, returning it to the month's allowance.
+    pub fn unfreeze_streak_day(&self, day: NaiveDate) -> Result<DailyActivity, String> {
+        let day_str = day.format("%Y-%m-%d").to_string();
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE daily_activity SET frozen_at = NULL WHERE day = ?1 AND frozen_at IS NOT NULL",
+                params![day_str],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err(format!("Frozen day not found: {}", day_str));
+        }
+        self.conn
+            .execute(
+                "DELETE FROM daily_activity WHERE day = ?1 AND tasks_done = 0 AND focus_minutes = 0",
+                params![day_str],
+            )
+            .map_err(|e| e.to_string())?;
+        self.get_daily_activity_day(day)
+    }
+
+    // --- ESTIMATES ---
+
+    /// Done tasks with an estimate and some tracked time. The actual time is
+    /// `actual_minutes` when set, otherwise the task's focus sessions and
+    /// time entries.
+    fn estimate_samples(&self) -> Result<Vec<EstimateSample>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT t.id, t.project_id, p.name, t.priority, t.estimated_minutes,
+                        COALESCE(t.actual_minutes,
+                            (SELECT COALESCE(SUM(duration_minutes), 0) FROM focus_sessions
+                             WHERE task_id = t.id AND ended_at IS NOT NULL)
+                            + (SELECT COALESCE(SUM((ended_at - started_at + 30000) / 60000), 0)
+                               FROM time_entries WHERE task_id = t.id AND ended_at IS NOT NULL))
+                 FROM tasks t LEFT JOIN projects p ON p.id = t.project_id
+                 WHERE t.deleted_at IS NULL AND t.status = 2 AND t.estimated_minutes > 0",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    Priority::from_int(row.get(3)?),
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, i64>(5)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        let mut samples = Vec::new();
+        for row in rows {
+            let (id, project_id, project_name, priority, estimate, actual) =
+                row.map_err(|e| e.to_string())?;
+            if actual <= 0 {
+                continue;
+            }
+            samples.push(EstimateSample {
+                project_id,
+                project_name,
+                priority,
+                tags: task_tag_names(&self.conn, &id)?,
+                ratio: actual as f64 / estimate as f64,
+            });
+        }
+        Ok(samples)
+    }
+
+    /// Estimate accuracy of done tasks overall and by project, tag and
+    /// priority. Groups with the most tasks come first; priorities go from
+    /// high to low.
+    pub fn get_estimate_accuracy(&self) -> Result<EstimateReport, String> {
+        let samples = self.estimate_samples()?;
+        let mut projects: Vec<RatioGroup> = Vec::new();
+        let mut tags: Vec<RatioGroup> = Vec::new();
+        let mut priorities: Vec<RatioGroup> = Vec::new();
+        let add =
+            |groups: &mut Vec<RatioGroup>, key: Option<String>, label: String, ratio| match groups
+                .iter_mut()
+                .find(|g| g.0 == key)
+            {
+                Some(group) => group.2.push(ratio),
+                None => groups.push((key, label, vec![ratio])),
+            };
+        for sample in &samples {
+            let name = sample
+                .project_name
+                .clone()
+                .unwrap_or_else(|| "Inbox".to_string());
+            add(&mut projects, sample.project_id.clone(), name, sample.ratio);
+            if sample.tags.is_empty() {
+                add(&mut tags, None, "Untagged".to_string(), sample.ratio);
+            }
+            for tag in &sample.tags {
+                add(&mut tags, Some(tag.clone()), tag.clone(), sample.ratio);
+            }
+            let key = match sample.priority {
+                Priority::High => "high",
+                Priority::Normal => "normal",
+                Priority::Low => "low",
+            };
+            add(
+                &mut priorities,
+                Some(key.to_string()),
+                key.to_string(),
+                sample.ratio,
+            );
+        }
+        let report = |mut groups: Vec<RatioGroup>| {
+            groups.sort_by(|a, b| b.2.len().cmp(&a.2.len()).then(a.1.cmp(&b.1)));
+            groups
+                .into_iter()
+                .map(|(key, label, ratios)| estimate_accuracy(key, label, &ratios))
+                .collect::<Vec<_>>()
+        };
+        let rank = |key: &Option<String>| match key.as_deref() {
+            Some("high") => 0,
+            Some("normal") => 1,
+            _ => 2,
+        };
+        let mut by_priority = report(priorities);
+        by_priority.sort_by_key(|a| rank(&a.key));
+        let all: Vec<f64> = samples.iter().map(|s| s.ratio).collect();
+        Ok(EstimateReport {
+            overall: estimate_accuracy(None, "All tasks".to_string(), &all),
+            by_project: report(projects),
+            by_tag: report(tags),
+            by_priority,
+        })
+    }
+
+    /// The factor to multiply new estimates in a project (or anywhere, for
+    /// `None`) by: the median actual / estimated ratio of its done tasks.
+    pub fn get_estimate_correction(
+        &self,
+        project_id: Option<&str>,
+    ) -> Result<EstimateCorrection, String> {
+        if let Some(id) = project_id {
+            self.get_project(id)?;
+        }
+        let samples = self.estimate_samples()?;
+        let mut own: Vec<f64> = samples
+            .iter()
+            .filter(|s| project_id.is_some() && s.project_id.as_deref() == project_id)
+            .map(|s| s.ratio)
+            .collect();
+        let from_project = own.len() >= MIN_ESTIMATE_SAMPLES;
+        let mut ratios = if from_project {
+            std::mem::take(&mut own)
+        } else {
+            samples.iter().map(|s| s.ratio).collect()
+        };
+        Ok(EstimateCorrection {
+            project_id: project_id.map(str::to_string),
+            factor: median(&mut ratios).map_or(1.0, round_ratio),
+            samples: ratios.len() as u32,
+            from_project,
+        })
+    }
+
+    // --- TAGS ---
+
+    /// All tags by name, including ones no task uses any more.
+    pub fn get_tags(&self) -> Result<Vec<Tag>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT g.name, g.color, COUNT(t.id),
+                    COUNT(CASE WHEN t.status != 2 AND t.is_archived = 0 THEN 1 END)
+                 FROM tags g
+                 LEFT JOIN task_tags tt ON tt.tag_id = g.id
+                 LEFT JOIN tasks t ON t.id = tt.task_id AND t.deleted_at IS NULL
+                 GROUP BY g.id ORDER BY g.name COLLATE NOCASE",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(Tag {
+                    name: row.get(0)?,
+                    color: row.get(1)?,
+                    task_count: row.get(2)?,
+                    open_count: row.get(3)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn get_tag(&self, name: &str) -> Result<Tag, String> {
+        self.get_tags()?
+            .into_iter()
+            .find(|t| t.name == name)
+            .ok_or_else(|| format!("Tag not found: {}", name))
+    }
+
+    fn tag_id(conn: &Connection, name: &str) -> Result<i64, String> {
+        conn.query_row(
+            "SELECT id FROM tags WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Tag not found: {}", name),
+            other => other.to_string(),
+        })
+    }
+
+    /// Swaps tags in every project's default tags: `from` become `to`, or are
+    /// dropped when it is `None`.
+    fn replace_default_tags(
+        conn: &Connection,
+        from: &[&str],
+        to: Option<&str>,
+    ) -> Result<(), String> {
+        let projects: Vec<(String, String)> = {
+            let mut stmt = conn
+                .prepare("SELECT id, default_tags FROM projects WHERE default_tags != '[]'")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        };
+        for (id, raw) in projects {
+            let tags: Vec<String> = serde_json::from_str(&raw).unwrap_or_default();
+            if !tags.iter().any(|t| from.contains(&t.as_str())) {
+                continue;
+            }
+            let mut replaced: Vec<String> = Vec::new();
+            for tag in tags {
+                let tag = match (from.contains(&tag.as_str()), to) {
+                    (false, _) => tag,
+                    (true, Some(to)) => to.to_string(),
+                    (true, None) => continue,
+                };
+                if !replaced.contains(&tag) {
+                    replaced.push(tag);
+                }
+            }
+            let json = serde_json::to_string(&replaced).unwrap_or_else(|_| "[]".to_string());
+            conn.execute(
+                "UPDATE projects SET default_tags = ?1 WHERE id = ?2",
+                params![json, id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Renames a tag on every task and project default. Fails if the new name
+    /// is taken; use `merge_tags` for that.
+    pub fn rename_tag(&mut self, name: &str, new_name: &str) -> Result<Tag, String> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err("Tag name must not be empty".to_string());
+        }
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let id = Self::tag_id(&tx, name)?;
+        if new_name != name && Self::tag_id(&tx, new_name).is_ok() {
+            return Err(format!(
+                "Tag already exists: {}; merge the tags instead",
+                new_name
+            ));
+        }
+        tx.execute(
+            "UPDATE tags SET name = ?1 WHERE id = ?2",
+            params![new_name, id],
+        )
+        .map_err(|e| e.to_string())?;
+        Self::replace_default_tags(&tx, &[name], Some(new_name))?;
+        tx.commit().map_err(|e| e.to_string())?;
+        self.get_tag(new_name)
+    }
+
+    /// Moves every task tagged with one of `sources` to `target` (created if
+    /// needed) and deletes the sources.
+    pub fn merge_tags(&mut self, sources: &[String], target: &str) -> Result<Tag, String> {
+        let target = target.trim();
+        if target.is_empty() {
+            return Err("Tag name must not be empty".to_string());
+        }
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT OR IGNORE INTO tags (name, created_at) VALUES (?1, ?2)",
+            params![target, chrono::Utc::now().timestamp_millis()],
+        )
+        .map_err(|e| e.to_string())?;
+        let target_id = Self::tag_id(&tx, target)?;
+        let mut merged: Vec<&str> = Vec::new();
+        for source in sources {
+            if source == target || merged.contains(&source.as_str()) {
+                continue;
+            }
+            let source_id = Self::tag_id(&tx, source)?;
+            tx.execute(
+                "INSERT OR IGNORE INTO task_tags (task_id, tag_id, position)
+                 SELECT task_id, ?1, position FROM task_tags WHERE tag_id = ?2",
+                params![target_id, source_id],
+            )
+            .map_err(|e| e.to_string())?;
+            // Unlinked first, so the task history names the source tag
+            tx.execute(
+                "DELETE FROM task_tags WHERE tag_id = ?1",
+                params![source_id],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM tags WHERE id = ?1", params![source_id])
+                .map_err(|e| e.to_string())?;
+            merged.push(source);
+        }
+        Self::replace_default_tags(&tx, &merged, Some(target))?;
+        tx.commit().map_err(|e| e.to_string())?;
+        self.get_tag(target)
+    }
+
+    pub fn set_tag_color(&self, name: &str, color: Option<String>) -> Result<Tag, String> {
+        if let Some(color) = color.as_deref().filter(|c| !is_hex_color(c)) {
+            return Err(format!("Invalid color: {}", color));
+        }
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE tags SET color = ?1 WHERE name = ?2",
+                params![color, name],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err(format!("Tag not found: {}", name));
+        }
+        self.get_tag(name)
+    }
+
+    /// Removes a tag from every task and project default, then deletes it.
+    pub fn delete_tag(&mut self, name: &str) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let id = Self::tag_id(&tx, name)?;
+        tx.execute("DELETE FROM task_tags WHERE tag_id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM tags WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        Self::replace_default_tags(&tx, &[name], None)?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    // --- TEMPLATES ---
+
+    pub fn get_templates(&self) -> Result<Vec<Template>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, name, body, created_at FROM templates ORDER BY name COLLATE NOCASE",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        let mut res = Vec::new();
+        for r in rows {
+            let (id, name, body, created_at) = r.map_err(|e| e.to_string())?;
+            let body = serde_json::from_str(&body)
+                .map_err(|e| format!("Template {} is unreadable: {}", id, e))?;
+            res.push(Template {
+                id,
+                name,
+                created_at,
+                body,
+            });
+        }
+        Ok(res)
+    }
+
+    pub fn get_template(&self, id: &str) -> Result<Template, String> {
+        self.get_templates()?
+            .into_iter()
+            .find(|t| t.id == id)
+            .ok_or_else(|| format!("Template not found: {}", id))
+    }
+
+    fn insert_template(&self, name: &str, body: TemplateBody) -> Result<Template, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Template name must not be empty".to_string());
+        }
+        let kind = match &body {
+            TemplateBody::Task { .. } => "task",
+            TemplateBody::Project { .. } => "project",
+        };
+        let template = Template {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            created_at: chrono::Utc::now().timestamp_millis(),
+            body,
+        };
+        let json = serde_json::to_string(&template.body).map_err(|e| e.to_string())?;
+        self.conn
+            .execute(
+                "INSERT INTO templates (id, kind, name, body, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![template.id, kind, template.name, json, template.created_at],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(template)
+    }
+
+    fn template_task(
+        &self,
+        task: &Task,
+        anchor: i64,
+        section: Option<(usize, String)>,
+    ) -> Result<TemplateTask, String> {
+        let offset = |ms: i64| (ms - anchor).div_euclid(60_000);
+        Ok(TemplateTask {
+            title: task.title.clone(),
+            description: task.description.clone(),
+            priority: task.priority,
+            tags: task.tags.clone(),
+            estimated_minutes: task.estimated_minutes,
+            repeat_mode: task.repeat_mode.clone(),
+            repeat_days_mask: task.repeat_days_mask,
+            deadline_offset_minutes: task.deadline.map(offset),
+            remind_offset_minutes: task.remind_at.map(offset),
+            subtasks: self
+                .get_subtasks(&task.id)?
+                .into_iter()
+                .map(|s| s.title)
+                .collect(),
+            section_index: section.as_ref().map(|(index, _)| *index),
+            section: section.map(|(_, name)| name),
+        })
+    }
+
+    /// Captures a task with its subtasks. Dates are stored relative to
+    /// `anchor`, which defaults to the task's deadline.
+    pub fn save_task_as_template(
+        &self,
+        task_id: &str,
+        name: &str,
+        anchor: Option<i64>,
+    ) -> Result<Template, String> {
+        let task = self.get_task(task_id)?;
+        let anchor = anchor
+            .or(task.deadline)
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+        let task = self.template_task(&task, anchor, None)?;
+        self.insert_template(name, TemplateBody::Task { task })
+    }
+
+    /// Captures a project with its open sections and unarchived tasks, done or
+    /// not. Dates are stored relative to `anchor`, which defaults to the
+    /// latest task deadline.
+    pub fn save_project_as_template(
+        &self,
+        project_id: &str,
+        name: &str,
+        anchor: Option<i64>,
+    ) -> Result<Template, String> {
+        let project = self.get_project(project_id)?;
+        if project.is_folder {
+            return Err("Folders cannot be saved as templates".to_string());
+        }
+        let sections = self.get_sections(project_id, false)?;
+        let mut tasks = self.get_tasks(None, None, Some(project_id.to_string()), None)?;
+        tasks.retain(|t| !t.is_archived);
+        tasks.sort_by_key(|t| (t.sort_order, t.created_at));
+        let anchor = anchor
+            .or(tasks.iter().filter_map(|t| t.deadline).max())
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+        let tasks = tasks
+            .iter()
+            .map(|t| {
+                let section = sections
+                    .iter()
+                    .position(|s| Some(&s.id) == t.section_id.as_ref())
+                    .map(|index| (index, sections[index].name.clone()));
+                self.template_task(t, anchor, section)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let project = TemplateProject {
+            name: project.name,
+            color: project.color,
+            priority: project.priority,
+            description: project.description,
+            icon: project.icon,
+            default_priority: project.default_priority,
+            default_tags: project.default_tags,
+            sections: sections.into_iter().map(|s| s.name).collect(),
+        };
+        self.insert_template(name, TemplateBody::Project { project, tasks })
+    }
+
+    fn instantiate_task(
+        &self,
+        template: &TemplateTask,
+        anchor: i64,
+        project_id: Option<String>,
+        section_id: Option<String>,
+        title: Option<&str>,
+        project_defaults: bool,
+    ) -> Result<String, String> {
+        let at = |minutes: i64| anchor + minutes * 60_000;
+        let task = self.insert_task(
+            &NewTask {
+                id: uuid::Uuid::new_v4().to_string(),
+                project_id,
+                title: title.unwrap_or(&template.title).to_string(),
+                description: template.description.clone(),
+                priority: template.priority,
+                status: Status::Todo,
+                created_at: chrono::Utc::now().timestamp_millis(),
+                deadline: template.deadline_offset_minutes.map(at),
+                estimated_minutes: template.estimated_minutes,
+                actual_minutes: None,
+                tags: template.tags.clone(),
+                remind_at: template.remind_offset_minutes.map(at),
+                repeat_mode: template.repeat_mode.clone(),
+                repeat_days_mask: template.repeat_days_mask,
+                section_id,
+                scheduled_at: None,
+            },
+            project_defaults,
+        )?;
+        for subtask in &template.subtasks {
+            self.add_subtask(&task.id, subtask)?;
+        }
+        Ok(task.id)
+    }
+
+    /// Creates the template's task or project in one transaction, placing
+    /// dates relative to `options.anchor` (now by default).
+    pub fn instantiate_template(
+        &self,
+        id: &str,
+        options: &TemplateInstance,
+    ) -> Result<InstantiatedTemplate, String> {
+        let template = self.get_template(id)?;
+        let anchor = options
+            .anchor
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+        let name = options
+            .name
+            .as_deref()
+            .map(str::trim)
+            .filter(|n| !n.is_empty());
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| e.to_string())?;
+        let created = match &template.body {
+            TemplateBody::Task { task } => {
+                if options.parent_id.is_some() {
+                    return Err("parent_id only applies to project templates".to_string());
+                }
+                let task_id = self.instantiate_task(
+                    task,
+                    anchor,
+                    options.project_id.clone(),
+                    None,
+                    name,
+                    true,
+                )?;
+                InstantiatedTemplate {
+                    project_id: None,
+                    task_ids: vec![task_id],
+                }
+            }
+            TemplateBody::Project { project, tasks } => {
+                if options.project_id.is_some() {
+                    return Err("project_id only applies to task templates".to_string());
+                }
+                let created = self.add_project(
+                    uuid::Uuid::new_v4().to_string(),
+                    name.unwrap_or(&project.name).to_string(),
+                    project.color.clone(),
+                    project.priority,
+                    options.parent_id.clone(),
+                    false,
+                )?;
+                self.update_project_details(
+                    &created.id,
+                    &ProjectPatch {
+                        description: Some(project.description.clone()),
+                        icon: Some(project.icon.clone()),
+                        default_priority: Some(project.default_priority),
+                        default_tags: Some(project.default_tags.clone()),
+                        ..Default::default()
+                    },
+                )?;
+                let mut sections = Vec::new();
+                for section in &project.sections {
+                    sections.push(self.add_section(&created.id, section)?);
+                }
+                let mut task_ids = Vec::new();
+                for task in tasks {
+                    // Templates saved before section_index fall back to the name
+                    let section = match task.section_index {
+                        Some(index) => sections.get(index),
+                        None => sections
+                            .iter()
+                            .find(|s| Some(&s.name) == task.section.as_ref()),
+                    };
+                    task_ids.push(self.instantiate_task(
+                        task,
+                        anchor,
+                        Some(created.id.clone()),
+                        section.map(|s| s.id.clone()),
+                        None,
+                        false,
+                    )?);
+                }
+                InstantiatedTemplate {
+                    project_id: Some(created.id),
+                    task_ids,
+                }
+            }
+        };
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(created)
+    }
+
+    pub fn delete_template(&self, id: &str) -> Result<(), String> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM templates WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        if deleted == 0 {
+            return Err(format!("Template not found: {}", id));
+        }
+        Ok(())
+    }
+
+    // --- TRASH ---
+
+    fn soft_delete(&self, table: &str, id: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                &format!(
+                    "UPDATE {} SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+                    table
+                ),
+                params![chrono::Utc::now().timestamp_millis(), id],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Table and trash-row query for each kind of trashable item. The query
+    /// yields (id, title, deleted_at, parent_id).
+    fn trash_source(entity: &str) -> Result<(&'static str, &'static str), String> {
+        Ok(match entity {
+            "task" => ("tasks", "SELECT id, title, deleted_at, project_id FROM tasks"),
+            "subtask" => ("subtasks", "SELECT id, title, deleted_at, task_id FROM subtasks"),
+            "project" => ("projects", "SELECT id, name, deleted_at, parent_id FROM projects"),
+            "transaction" => (
+                "transactions",
+                "SELECT id, COALESCE(NULLIF(description, ''), category), deleted_at, NULL FROM transactions",
+            ),
+            "debt" => ("debts", "SELECT id, person, deleted_at, NULL FROM debts"),
+            other => return Err(format!("Unknown trash entity: {}", other)),
+        })
+    }
+
+    fn trash_items(
+        conn: &Connection,
+        entity: &str,
+        filter: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<TrashItem>, String> {
+        let (_, select) = Self::trash_source(entity)?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "{} WHERE deleted_at IS NOT NULL {}",
+                select, filter
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params, |row| {
+                Ok(TrashItem {
+                    entity: entity.to_string(),
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    deleted_at: row.get(2)?,
+                    parent_id: row.get(3)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        let mut res = Vec::new();
+        for r in rows {
+            res.push(r.map_err(|e| e.to_string())?);
+        }
+        Ok(res)
+    }
+
+    /// The trashed item `entity`/`id`, or an error when it doesn't exist or
+    /// isn't in the trash.
+    fn trashed(conn: &Connection, entity: &str, id: &str) -> Result<TrashItem, String> {
+        let (table, _) = Self::trash_source(entity)?;
+        let exists: bool = conn
+            .query_row(
+                &format!("SELECT EXISTS (SELECT 1 FROM {} WHERE id = ?1)", table),
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !exists {
+            let mut kind = entity.to_string();
+            kind[..1].make_ascii_uppercase();
+            return Err(format!("{} not found: {}", kind, id));
+        }
+        Self::trash_items(conn, entity, "AND id = ?1", params![id])?
+            .pop()
+            .ok_or_else(|| format!("{} {} is not in the trash", entity, id))
+    }
+
+    /// Everything in the trash, most recently deleted first. Sub-projects and
+    /// tasks that went with a deleted project are folded into it.
+    pub fn get_trash(&self) -> Result<Vec<TrashItem>, String> {
+        let conn = &self.conn;
+        let mut items = Self::trash_items(
+            conn,
+            "task",
+            "AND NOT EXISTS (SELECT 1 FROM projects p WHERE p.id = tasks.project_id AND p.deleted_at = tasks.deleted_at)",
+            [],
+        )?;
+        items.extend(Self::trash_items(
+            conn,
+            "project",
+            "AND NOT EXISTS (SELECT 1 FROM projects p WHERE p.id = projects.parent_id AND p.deleted_at = projects.deleted_at)",
+            [],
+        )?);
+        for entity in ["subtask", "transaction", "debt"] {
+            items.extend(Self::trash_items(conn, entity, "", [])?);
+        }
+        items.sort_by_key(|item| std::cmp::Reverse(item.deleted_at));
+        Ok(items)
+    }
+
+    /// Clears deleted_at on one row, recording it in `restored` if it was trashed.
+    fn untrash(
+        conn: &Connection,
+        entity: &str,
+        id: &str,
+        restored: &mut Vec<TrashItem>,
+    ) -> Result<(), String> {
+        let (table, _) = Self::trash_source(entity)?;
+        if let Some(item) = Self::trash_items(conn, entity, "AND id = ?1", params![id])?.pop() {
+            conn.execute(
+                &format!("UPDATE {} SET deleted_at = NULL WHERE id = ?1", table),
+                params![id],
+            )
+            .map_err(|e| e.to_string())?;
+            restored.push(item);
+        }
+        Ok(())
+    }
+
+    /// Brings back the project `id` and any trashed folders above it, so the
+    /// tree is whole again. A parent that was purged in the meantime is
+    /// dropped and the project lands at the top level.
+    fn restore_project_chain(
+        conn: &Connection,
+        id: &str,
+        restored: &mut Vec<TrashItem>,
+    ) -> Result<(), String> {
+        let mut current = Some(id.to_string());
+        while let Some(pid) = current {
+            Self::untrash(conn, "project", &pid, restored)?;
+            let parent: Option<String> = conn
+                .query_row(
+                    "SELECT parent_id FROM projects WHERE id = ?1",
+                    params![pid],
+                    |row| row.get(0),
+                )
+                .map_err(|e| e.to_string())?;
+            current = match parent {
+                Some(parent) if parent != pid => {
+                    let exists: bool = conn
+                        .query_row(
+                            "SELECT EXISTS (SELECT 1 FROM projects WHERE id = ?1)",
+                            params![parent],
+                            |row| row.get(0),
+                        )
+                        .map_err(|e| e.to_string())?;
+                    if !exists {
+                        conn.execute(
+                            "UPDATE projects SET parent_id = NULL WHERE id = ?1",
+                            params![pid],
+                        )
+                        .map_err(|e| e.to_string())?;
+                    }
+                    exists.then_some(parent)
+                }
+                _ => None,
+            };
+        }
+        Ok(())
+    }
+
+    /// Restores a trashed item and re-links it: a subtask brings back its task,
+    /// a task its project (a purged project leaves it in the Inbox), and a
+    /// project its parent folders plus whatever was deleted along with it.
+    /// Returns every item taken out of the trash.
+    pub fn restore_from_trash(&mut self, entity: &str, id: &str) -> Result<Vec<TrashItem>, String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let item = Self::trashed(&tx, entity, id)?;
+        let mut restored = Vec::new();
+
+        let restore_task = |task_id: &str, restored: &mut Vec<TrashItem>| -> Result<(), String> {
+            let project_id: Option<String> = tx
+                .query_row(
+                    "SELECT project_id FROM tasks WHERE id = ?1",
+                    params![task_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| e.to_string())?;
+            if let Some(pid) = project_id {
+                Self::restore_project_chain(&tx, &pid, restored)?;
+            }
+            Self::untrash(&tx, "task", task_id, restored)
+        };
+
+        match entity {
+            "subtask" => {
+                if let Some(task_id) = &item.parent_id {
+                    restore_task(task_id, &mut restored)?;
+                }
+                Self::untrash(&tx, entity, id, &mut restored)?;
+            }
+            "task" => restore_task(id, &mut restored)?,
+            "project" => {
+                Self::restore_project_chain(&tx, id, &mut restored)?;
+                // Sub-projects and tasks deleted in the same batch
+                let batch: Vec<String> = {
+                    let mut stmt = tx
+                        .prepare(
+                            "WITH RECURSIVE subtree(id) AS (
+                                SELECT ?1
+                                UNION
+                                SELECT p.id FROM projects p JOIN subtree s ON p.parent_id = s.id
+                                WHERE p.deleted_at = ?2
+                             )
+                             SELECT id FROM subtree",
+                        )
+                        .map_err(|e| e.to_string())?;
+                    let rows = stmt
+                        .query_map(params![id, item.deleted_at], |row| row.get::<_, String>(0))
+                        .map_err(|e| e.to_string())?;
+                    let mut ids = Vec::new();
+                    for r in rows {
+                        ids.push(r.map_err(|e| e.to_string())?);
+                    }
+                    ids
+                };
+                for pid in &batch {
+                    Self::untrash(&tx, "project", pid, &mut restored)?;
+                    let task_ids = Self::trash_items(
+                        &tx,
+                        "task",
+                        "AND project_id = ?1 AND deleted_at = ?2",
+                        params![pid, item.deleted_at],
+                    )?;
+                    for task in task_ids {
+                        Self::untrash(&tx, "task", &task.id, &mut restored)?;
+                    }
+                }
+            }
+            _ => Self::untrash(&tx, entity, id, &mut restored)?,
+        }
+
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(restored)
+    }
+
+    /// Permanently deletes a trashed item. A project takes the sub-projects and
+    /// tasks that were deleted with it.
+    pub fn purge_trash_item(&mut self, entity: &str, id: &str) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let item = Self::trashed(&tx, entity, id)?;
+        Self::purge(&tx, &item)?;
+        Self::prune_task_events(&tx)?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Drops the history of tasks that no longer exist.
+    fn prune_task_events(conn: &Connection) -> Result<(), String> {
+        conn.execute(
+            "DELETE FROM task_events WHERE task_id NOT IN (SELECT id FROM tasks)",
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn purge(conn: &Connection, item: &TrashItem) -> Result<(), String> {
+        let (table, _) = Self::trash_source(&item.entity)?;
+        if item.entity == "project" {
+            let children = Self::trash_items(
+                conn,
+                "project",
+                "AND parent_id = ?1 AND id != ?1 AND deleted_at = ?2",
+                params![item.id, item.deleted_at],
+            )?;
+            for child in &children {
+                Self::purge(conn, child)?;
+            }
+            conn.execute(
+                "DELETE FROM tasks WHERE project_id = ?1 AND deleted_at = ?2",
+                params![item.id, item.deleted_at],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        conn.execute(
+            &format!("DELETE FROM {} WHERE id = ?1", table),
+            params![item.id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Permanently deletes everything in the trash. Returns how many rows went.
+    pub fn empty_trash(&mut self) -> Result<usize, String> {
+        self.purge_trashed_before(i64::MAX)
+    }
+
+    /// Purges items that have been in the trash longer than the configured
+    /// retention. Returns how many rows went; does nothing when retention is 0.
+    pub fn purge_expired_trash(&mut self, now: i64) -> Result<usize, String> {
+        let days = self.get_settings()?.trash_retention_days;
+        if days == 0 {
+            return Ok(0);
+        }
+        self.purge_trashed_before(now - days as i64 * 24 * 60 * 60 * 1000)
+    }
+
+    fn purge_trashed_before(&mut self, cutoff: i64) -> Result<usize, String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let mut purged = 0;
+        // Children before parents, so cascades don't hide what was removed
+        for table in ["subtasks", "transactions", "debts", "tasks", "projects"] {
+            purged += tx
+                .execute(
+                    &format!(
+                        "DELETE FROM {} WHERE deleted_at IS NOT NULL AND deleted_at < ?1",
+                        table
+                    ),
+                    params![cutoff],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        Self::prune_task_events(&tx)?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(purged)
+    }
+
+    // --- SUBTASKS ---
+
+    pub fn get_subtasks(&self, task_id: &str) -> Result<Vec<Subtask>, String> {
+        let conn = &self.conn;
+        let mut stmt = conn
+            .prepare("SELECT id, task_id, title, completed, sort_order, created_at FROM subtasks WHERE task_id = ?1 AND deleted_at IS NULL ORDER BY sort_order ASC, created_at ASC")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![task_id], |row| {
+                Ok(Subtask {
+                    id: row.get(0)?,
+                    task_id: row.get(1)?,
+                    title: row.get(2)?,
+                    completed: row.get::<_, i32>(3)? != 0,
+                    sort_order: row.get(4)?,
+                    created_at: row.get(5)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        let mut res = Vec::new();
+        for r in rows {
+            res.push(r.map_err(|e| e.to_string())?);
+        }
+        Ok(res)
+    }
+
+    pub fn add_subtask(&self, task_id: &str, title: &str) -> Result<Subtask, String> {
+        let conn = &self.conn;
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp_millis();
+
+        // Get next sort_order
+        let max_order: i32 = conn
+            .query_row(
+                "SELECT COALESCE(MAX(sort_order), -1) + 1 FROM subtasks WHERE task_id = ?1",
+                params![task_id],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+
+        conn.execute(
+            "INSERT INTO subtasks (id, task_id, title, completed, sort_order, created_at) VALUES (?1, ?2, ?3, 0, ?4, ?5)",
+            params![id, task_id, title, max_order, now],
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok(Subtask {
+            id,
+            task_id: task_id.to_string(),
+            title: title.to_string(),
+            completed: false,
+            sort_order: max_order,
+            created_at: now,
+        })
+    }
+
+    pub fn get_subtask(&self, id: &str) -> Result<Subtask, String> {
+        let conn = &self.conn;
+        conn.query_row(
+            "SELECT id, task_id, title, completed, sort_order, created_at FROM subtasks WHERE id = ?1 AND deleted_at IS NULL",
+            params![id],
+            |row| {
+                Ok(Subtask {
+                    id: row.get(0)?,
+                    task_id: row.get(1)?,
+                    title: row.get(2)?,
+                    completed: row.get::<_, i32>(3)? != 0,
+                    sort_order: row.get(4)?,
+                    created_at: row.get(5)?,
+                })
+            },
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Subtask not found: {}", id),
+            other => other.to_string(),
+        })
+    }
+
+    pub fn toggle_subtask(&self, id: &str) -> Result<bool, String> {
+        let conn = &self.conn;
+        conn.execute(
+            "UPDATE subtasks SET completed = 1 - completed WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+
+        // Return new state
+        let completed: i32 = conn
+            .query_row(
+                "SELECT completed FROM subtasks WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(completed != 0)
+    }
+
+    /// Moves the subtask to the trash.
+    pub fn delete_subtask(&self, id: &str) -> Result<(), String> {
+        self.soft_delete("subtasks", id)
+    }
+
+    pub fn reorder_subtasks(&self, subtask_ids: &[String]) -> Result<(), String> {
+        let conn = &self.conn;
+        for (i, id) in subtask_ids.iter().enumerate() {
+            conn.execute(
+                "UPDATE subtasks SET sort_order = ?1 WHERE id = ?2",
+                params![i as i32, id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Frees the slot right after `task_id` among its project's tasks and
+    /// returns its sort order.
+    fn sort_slot_after(conn: &Connection, task_id: &str) -> Result<i32, String> {
+        let (project_id, sort_order): (Option<String>, i32) = conn
+            .query_row(
+                "SELECT project_id, sort_order FROM tasks WHERE id = ?1 AND deleted_at IS NULL",
+                params![task_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Task not found: {}", task_id),
+                other => other.to_string(),
+            })?;
+        conn.execute(
+            "UPDATE tasks SET sort_order = sort_order + 1 WHERE project_id IS ?1 AND sort_order > ?2 AND deleted_at IS NULL",
+            params![project_id, sort_order],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(sort_order + 1)
+    }
+
+    /// Copies a task with its tags, reminder and checklist into a new todo
+    /// right after it. Subtasks start unchecked.
+    pub fn duplicate_task(&mut self, id: &str) -> Result<Task, String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let new_id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp_millis();
+        let sort_order = Self::sort_slot_after(&tx, id)?;
+        tx.execute(
+                "INSERT INTO tasks (id, project_id, section_id, title, description, priority, status, created_at, deadline, scheduled_at, estimated_minutes, remind_at, repeat_mode, repeat_days_mask, sort_order)
+                 SELECT ?1, project_id, section_id, title, description, priority, 0, ?2, deadline, scheduled_at, estimated_minutes, remind_at, repeat_mode, repeat_days_mask, ?3
+                 FROM tasks WHERE id = ?4 AND deleted_at IS NULL",
+            params![new_id, now, sort_order, id],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT INTO task_tags (task_id, tag_id, position) SELECT ?1, tag_id, position FROM task_tags WHERE task_id = ?2",
+            params![new_id, id],
+        )
+        .map_err(|e| e.to_string())?;
+        let subtasks: Vec<(String, i32)> = {
+            let mut stmt = tx
+                .prepare("SELECT title, sort_order FROM subtasks WHERE task_id = ?1 AND deleted_at IS NULL ORDER BY sort_order ASC, created_at ASC")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        };
+        for (title, sort_order) in subtasks {
+            tx.execute(
+                "INSERT INTO subtasks (id, task_id, title, completed, sort_order, created_at) VALUES (?1, ?2, ?3, 0, ?4, ?5)",
+                params![uuid::Uuid::new_v4().to_string(), new_id, title, sort_order, now],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        self.get_task(&new_id)
+    }
+
+    /// Turns a subtask into a task (same id) right after its parent, in the
+    /// parent's project and section. A checked subtask becomes a done task
+    /// through the usual completion (points, streak activity).
+    pub fn promote_subtask_to_task(&mut self, subtask_id: &str) -> Result<Task, String> {
+        let subtask = self.get_subtask(subtask_id)?;
+        let parent = self.get_task(&subtask.task_id)?;
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let sort_order = Self::sort_slot_after(&tx, &parent.id)?;
+        tx.execute("DELETE FROM subtasks WHERE id = ?1", params![subtask_id])
+            .map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT INTO tasks (id, project_id, section_id, title, priority, status, created_at, sort_order)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                subtask.id,
+                parent.project_id,
+                parent.section_id,
+                subtask.title,
+                parent.priority as i32,
+                Status::Todo as i32,
+                subtask.created_at,
+                sort_order
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        if subtask.completed {
+            set_task_status(&tx, &subtask.id, Status::Done)?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        self.get_task(subtask_id)
+    }
+
+    /// Turns a task into the last subtask (same id) of `parent_id`, checked if
+    /// the task was done. Its focus sessions and time entries move to the
+    /// parent; tasks that waited for it no longer do. Tasks with a checklist
+    /// of their own, or that are planned, time-blocked or on a board, can't
+    /// be demoted.
+    pub fn demote_task_to_subtask(
+        &mut self,
+        task_id: &str,
+        parent_id: &str,
+    ) -> Result<Subtask, String> {
+        if task_id == parent_id {
+            return Err("A task cannot become its own subtask".to_string());
+        }
+        let task = self.get_task(task_id)?;
+        self.get_task(parent_id)?;
+        if !self.get_subtasks(task_id)?.is_empty() {
+            return Err("Tasks with subtasks cannot become subtasks".to_string());
+        }
+        // These would go with the task row
+        let mut placed = Vec::new();
+        for (table, place) in [
+            ("daily_plan", "a daily plan"),
+            ("time_blocks", "a time block"),
+            ("board_cards", "a board"),
+        ] {
+            let found: bool = self
+                .conn
+                .query_row(
+                    &format!("SELECT EXISTS (SELECT 1 FROM {} WHERE task_id = ?1)", table),
+                    params![task_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| e.to_string())?;
+            if found {
+                placed.push(place);
+            }
+        }
+        if !placed.is_empty() {
+            return Err(format!(
+                "Task is on {}; take it off before making it a subtask",
+                placed.join(", ")
+            ));
+        }
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let sort_order: i32 = tx
+            .query_row(
+                "SELECT COALESCE(MAX(sort_order) + 1, 0) FROM subtasks WHERE task_id = ?1",
+                params![parent_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        for table in ["focus_sessions", "time_entries"] {
+            tx.execute(
+                &format!("UPDATE {} SET task_id = ?1 WHERE task_id = ?2", table),
+                params![parent_id, task_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.execute("DELETE FROM tasks WHERE id = ?1", params![task_id])
+            .map_err(|e| e.to_string())?;
+        // Trashed subtasks of the task went with it
+        tx.execute(
+            "INSERT INTO subtasks (id, task_id, title, completed, sort_order, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                task_id,
+                parent_id,
+                task.title,
+                task.status == Status::Done,
+                sort_order,
+                task.created_at
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Self::prune_task_events(&tx)?;
+        tx.commit().map_err(|e| e.to_string())?;
+        self.get_subtask(task_id)
+    }
+
+    // --- ARCHIVE ---
+
+    pub fn archive_task(&self, id: &str) -> Result<(), String> {
+        let conn = &self.conn;
+        conn.execute(
+            "UPDATE tasks SET is_archived = 1 WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn unarchive_task(&self, id: &str) -> Result<(), String> {
+        let conn = &self.conn;
+        conn.execute(
+            "UPDATE tasks SET is_archived = 0 WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    // --- REORDER TASKS ---
+
+    pub fn reorder_tasks(&self, task_ids: &[String]) -> Result<(), String> {
+        let conn = &self.conn;
+        for (i, id) in task_ids.iter().enumerate() {
+            conn.execute(
+                "UPDATE tasks SET sort_order = ?1 WHERE id = ?2",
+                params![i as i32, id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+    // --- FINANCE (TRANSACTIONS & DEBTS) ---
+
+    #[allow(dead_code)]
+    pub fn get_finance_summary(&self) -> Result<(Vec<Transaction>, Vec<Debt>), String> {
+        let conn = &self.conn;
+
+        // Transactions
+        let mut t_stmt = conn.prepare("SELECT id, amount, category, date, description, is_expense FROM transactions WHERE deleted_at IS NULL ORDER BY date DESC").map_err(|e| e.to_string())?;
+        let transactions = t_stmt
+            .query_map([], |row| {
+                Ok(Transaction {
+                    id: row.get(0)?,
+                    amount: row.get(1)?,
+                    category: row.get(2)?,
+                    date: row.get(3)?,
+                    description: row.get(4)?,
+                    is_expense: row.get::<_, i32>(5)? != 0,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        // Debts
+        let mut d_stmt = conn.prepare("SELECT id, person, amount, currency, is_owed_by_me, created_at, due_date, status, start_date, payment_day, initial_amount FROM debts WHERE deleted_at IS NULL ORDER BY created_at DESC").map_err(|e| e.to_string())?;
+        let debts = d_stmt
+            .query_map([], |row| {
+                Ok(Debt {
+                    id: row.get(0)?,
+                    person: row.get(1)?,
+                    amount: row.get(2)?,
+                    currency: row.get(3)?,
+                    is_owed_by_me: row.get::<_, i32>(4)? != 0,
+                    created_at: row.get(5)?,
+                    due_date: row.get(6)?,
+                    status: row.get(7)?,
+                    start_date: row.get(8).unwrap_or(None),
+                    payment_day: row.get(9).unwrap_or(None),
+                    initial_amount: row.get(10).unwrap_or(None),
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        Ok((transactions, debts))
+    }
+
+    #[allow(dead_code)]
+    pub fn add_transaction(&self, t: NewTransaction) -> Result<Transaction, String> {
+        let conn = &self.conn;
+        conn.execute(
+            "INSERT INTO transactions (id, amount, category, date, description, is_expense) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![t.id, t.amount, t.category, t.date, t.description, t.is_expense as i32]
+        ).map_err(|e| e.to_string())?;
+
+        Ok(Transaction {
+            id: t.id,
+            amount: t.amount,
+            category: t.category,
+            date: t.date,
+            description: t.description,
+            is_expense: t.is_expense,
+        })
+    }
+
+    /// Moves the transaction to the trash.
+    #[allow(dead_code)]
+    pub fn delete_transaction(&self, id: &str) -> Result<(), String> {
+        self.soft_delete("transactions", id)
+    }
+
+    #[allow(dead_code)]
+    pub fn add_debt(&self, d: NewDebt) -> Result<Debt, String> {
+        let conn = &self.conn;
+        conn.execute(
+            "INSERT INTO debts (id, person, amount, currency, is_owed_by_me, created_at, due_date, status, start_date, payment_day, initial_amount) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'active', ?8, ?9, ?10)",
+            params![d.id, d.person, d.amount, d.currency, d.is_owed_by_me as i32, d.created_at, d.due_date, d.start_date, d.payment_day, d.initial_amount]
+        ).map_err(|e| e.to_string())?;
+
+        Ok(Debt {
+            id: d.id,
+            person: d.person,
+            amount: d.amount,
+            currency: d.currency,
+            is_owed_by_me: d.is_owed_by_me,
+            created_at: d.created_at,
+            due_date: d.due_date,
+            status: "active".to_string(),
+            start_date: d.start_date,
+            payment_day: d.payment_day,
+            initial_amount: d.initial_amount,
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn pay_debt(&self, id: &str) -> Result<(), String> {
+        let conn = &self.conn;
+        conn.execute(
+            "UPDATE debts SET status = 'paid' WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Moves the debt to the trash.
+    #[allow(dead_code)]
+    pub fn delete_debt(&self, id: &str) -> Result<(), String> {
+        self.soft_delete("debts", id)
+    }
+    #[allow(dead_code)]
+    pub fn get_active_loans(&self) -> Result<Vec<(String, String, i32, Option<String>)>, String> {
+        // id, person, payment_day, last_reminded_date
+        let conn = &self.conn;
+        let mut stmt = conn.prepare("SELECT id, person, payment_day, last_reminded_date FROM debts WHERE status = 'active' AND payment_day IS NOT NULL AND deleted_at IS NULL").map_err(|e| e.to_string())?;
+
+        let loans = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3).unwrap_or(None),
+                ))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        Ok(loans)
+    }
+
+    #[allow(dead_code)]
+    pub fn update_last_reminded(&self, id: &str, date: &str) -> Result<(), String> {
+        let conn = &self.conn;
+        conn.execute(
+            "UPDATE debts SET last_reminded_date = ?1 WHERE id = ?2",
+            params![date, id],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn new_task(title: &str, repeat_mode: Option<&str>) -> NewTask {
+        NewTask {
+            id: uuid::Uuid::new_v4().to_string(),
+            project_id: None,
+            title: title.to_string(),
+            description: None,
+            priority: Priority::Normal,
+            status: Status::Todo,
+            created_at: chrono::Utc::now().timestamp_millis(),
+            deadline: None,
+            estimated_minutes: None,
+            actual_minutes: None,
+            tags: vec!["home".to_string()],
+            remind_at: None,
+            repeat_mode: repeat_mode.map(String::from),
+            repeat_days_mask: None,
+            section_id: None,
+            scheduled_at: None,
+        }
+    }
+
+    fn memory_db() -> AppDatabase {
+        AppDatabase::open(PathBuf::from(":memory:")).unwrap()
+    }
+
+    #[test]
+    fn test_update_task_status_spawns_next_occurrence() {
+        let mut db = memory_db();
+        let task = db
+            .add_task(&new_task("Water plants", Some("daily")))
+            .unwrap();
+
+        let next_id = db
+            .update_task_status(&task.id, Status::Done)
+            .unwrap()
+            .expect("daily task should recur");
+        let next = db.get_task(&next_id).unwrap();
+        assert_eq!(next.status, Status::Todo);
+        assert_eq!(next.repeat_mode.as_deref(), Some("daily"));
+        assert_eq!(next.tags, vec!["home".to_string()]);
+        assert!(db.get_task(&task.id).unwrap().repeat_mode.is_none());
+
+        // Re-completing the old task must not spawn a duplicate
+        assert!(db
+            .update_task_status(&task.id, Status::Done)
+            .unwrap()
+            .is_none());
+        assert!(db.update_task_status("missing", Status::Done).is_err());
+    }
+
+    #[test]
+    fn test_process_recurring_tasks_is_idempotent() {
+        let mut db = memory_db();
+        let task = db.add_task(&new_task("Standup", Some("weekdays"))).unwrap();
+        db.add_task(&new_task("One-off", None)).unwrap();
+        // Simulate a completion that bypassed update_task_status (e.g. an import)
+        db.get_connection()
+            .execute(
+                "UPDATE tasks SET status = 2 WHERE id = ?1",
+                params![task.id],
+            )
+            .unwrap();
+
+        let spawned = db.process_recurring_tasks().unwrap();
+        assert_eq!(spawned.len(), 1);
+        assert_eq!(spawned[0].title, "Standup");
+        assert!(db.process_recurring_tasks().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_next_date_daily() {
+        let d = NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(); // Sun
+        assert_eq!(
+            next_date_daily(d),
+            NaiveDate::from_ymd_opt(2023, 10, 2).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_next_date_weekdays() {
+        let fri = NaiveDate::from_ymd_opt(2023, 10, 6).unwrap();
+        assert_eq!(
+            next_date_weekdays(fri),
+            NaiveDate::from_ymd_opt(2023, 10, 9).unwrap()
+        ); // Mon
+
+        let sat = NaiveDate::from_ymd_opt(2023, 10, 7).unwrap();
+        assert_eq!(
+            next_date_weekdays(sat),
+            NaiveDate::from_ymd_opt(2023, 10, 9).unwrap()
+        ); // Mon
+
+        let mon = NaiveDate::from_ymd_opt(2023, 10, 9).unwrap();
+        assert_eq!(
+            next_date_weekdays(mon),
+            NaiveDate::from_ymd_opt(2023, 10, 10).unwrap()
+        ); // Tue
+    }
+
+    #[test]
+    fn test_next_date_custom() {
+        let mon = NaiveDate::from_ymd_opt(2023, 10, 9).unwrap();
         // Mask: Wed(4)
         // Mon(1), Tue(2), Wed(4)
         // next_date_custom checks d+1 (Tue/2) -> no. d+2 (Wed/4) -> yes.
         let next = next_date_custom(mon, 4);
         assert_eq!(next, Some(NaiveDate::from_ymd_opt(2023, 10, 11).unwrap()));
 
-        // Mask: Wed(4) | Fri(16) = 20
-        // From Wed -> Fri
-        let wed = NaiveDate::from_ymd_opt(2023, 10, 11).unwrap();
-        let next_fri = next_date_custom(wed, 20);
+        // Mask: Wed(4) | Fri(16) = 20
+        // From Wed -> Fri
+        let wed = NaiveDate::from_ymd_opt(2023, 10, 11).unwrap();
+        let next_fri = next_date_custom(wed, 20);
+        assert_eq!(
+            next_fri,
+            Some(NaiveDate::from_ymd_opt(2023, 10, 13).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_hooks_for_event_and_delivery_log() {
+        let db = memory_db();
+        let hook = |id: &str, event: &str, enabled: bool| Hook {
+            id: id.to_string(),
+            event: event.to_string(),
+            kind: "http".to_string(),
+            target: "http://127.0.0.1:9/hook".to_string(),
+            enabled,
+            created_at: 1,
+        };
+        db.add_hook(&hook("a", "task.completed", true)).unwrap();
+        db.add_hook(&hook("b", "*", true)).unwrap();
+        db.add_hook(&hook("c", "task.completed", false)).unwrap();
+        db.add_hook(&hook("d", "debt.paid", true)).unwrap();
+
+        let ids: Vec<String> = db
+            .get_hooks_for_event("task.completed")
+            .unwrap()
+            .into_iter()
+            .map(|h| h.id)
+            .collect();
+        assert_eq!(ids, vec!["a", "b"]);
+
+        db.log_hook_delivery(&HookDelivery {
+            id: "d1".to_string(),
+            hook_id: "a".to_string(),
+            event: "task.completed".to_string(),
+            payload: "{}".to_string(),
+            attempts: 3,
+            success: false,
+            status_code: Some(500),
+            error: Some("HTTP 500".to_string()),
+            duration_ms: 12,
+            created_at: 2,
+        })
+        .unwrap();
+        let log = db.get_hook_deliveries(Some("a"), 10).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].status_code, Some(500));
+
+        db.delete_hook("a").unwrap();
+        assert!(db.get_hook_deliveries(None, 10).unwrap().is_empty());
+        assert!(db.set_hook_enabled("a", true).is_err());
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let db = memory_db();
+        let a = db.add_task(&new_task("A", None)).unwrap();
+        let b = db.add_task(&new_task("B", None)).unwrap();
+        let sub = db.add_subtask(&a.id, "step").unwrap();
+
+        // Untagged: the event's `entity` already says what this is
+        let json = |snapshot: Option<Snapshot>| serde_json::to_value(snapshot.unwrap()).unwrap();
+        let snap = json(db.snapshot("tasks", Some(&a.id)));
+        assert_eq!(snap["title"], "A");
+        assert_eq!(snap["subtasks"][0]["id"], sub.id.as_str());
+        assert_eq!(
+            json(db.snapshot("subtasks", Some(&sub.id)))["completed"],
+            false
+        );
+        assert!(db.snapshot("settings", None).is_some());
+
+        db.delete_task(&b.id).unwrap();
+        assert!(db.snapshot("tasks", Some(&b.id)).is_none());
+        assert!(db.snapshot("stats", None).is_none());
+
+        let ids = vec![a.id.clone()];
+        db.reorder_tasks(&ids).unwrap();
+        let order = json(db.order_snapshot("tasks", &ids));
+        assert_eq!(order[0]["id"], a.id.as_str());
+        let batch = json(Some(db.snapshots("tasks", &[a.id.clone(), b.id.clone()])));
+        assert_eq!(batch[0]["id"], a.id.as_str());
+        assert!(batch[1].is_null());
+        assert!(db.order_snapshot("tasks", &[b.id]).is_none());
+    }
+
+    #[test]
+    fn test_project_tree_move_and_delete() {
+        let mut db = memory_db();
+        let add = |db: &AppDatabase, id: &str, parent: Option<&str>, folder: bool| {
+            db.add_project(
+                id.to_string(),
+                id.to_string(),
+                "#000".to_string(),
+                Priority::Normal,
+                parent.map(String::from),
+                folder,
+            )
+            .unwrap();
+        };
+        add(&db, "f", None, true);
+        add(&db, "g", Some("f"), true);
+        add(&db, "p", Some("g"), false);
+        let in_project = |pid: &str| {
+            let mut t = new_task(pid, None);
+            t.project_id = Some(pid.to_string());
+            db.add_task(&t).unwrap().id
+        };
+        let t_p = in_project("p");
+        let mut t = new_task("g", None);
+        t.project_id = Some("g".to_string());
+        assert!(db.add_task(&t).is_err());
+        // Data from before folders refused tasks can still have them
+        let t_g = in_project("p");
+        db.conn
+            .execute(
+                "UPDATE tasks SET project_id = 'g' WHERE id = ?1",
+                params![t_g],
+            )
+            .unwrap();
+
+        let tree = db.get_project_tree().unwrap();
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].total_task_count, 2);
+        assert_eq!(tree[0].children[0].project.id, "g");
+        assert_eq!(tree[0].children[0].task_count, 1);
+        assert_eq!(tree[0].children[0].children[0].project.id, "p");
+
+        assert!(db.move_project("f", Some("p")).is_err()); // not a folder
+        assert!(db
+            .move_project("f", Some("g"))
+            .unwrap_err()
+            .contains("subfolders"));
+        assert!(db.move_project("f", Some("f")).is_err());
+        assert!(db.move_project("missing", None).is_err());
+
+        // Reparent: p moves up to f, g's task goes to Inbox
+        let summary = db
+            .delete_project("g", ProjectDeletePolicy::Reparent)
+            .unwrap();
+        assert_eq!(summary.deleted_project_ids, vec!["g"]);
+        assert_eq!(summary.reparented_project_ids, vec!["p"]);
+        assert_eq!(summary.moved_task_ids, vec![t_g.clone()]);
+        assert_eq!(db.get_task(&t_g).unwrap().project_id, None);
+        let tree = db.get_project_tree().unwrap();
+        assert_eq!(tree[0].children[0].project.id, "p");
+
+        // Cascade removes the folder, its children and their tasks
+        let summary = db
+            .delete_project("f", ProjectDeletePolicy::Cascade)
+            .unwrap();
+        assert_eq!(summary.deleted_project_ids.len(), 2);
+        assert_eq!(summary.deleted_task_ids, vec![t_p.clone()]);
+        assert!(db.get_task(&t_p).is_err());
+        assert!(db.get_task(&t_g).is_ok());
+        assert!(db.get_projects().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_project_details_order_and_archive() {
+        let mut db = memory_db();
+        for id in ["a", "b", "c"] {
+            db.add_project(
+                id.to_string(),
+                id.to_string(),
+                "#000000".to_string(),
+                Priority::Normal,
+                None,
+                false,
+            )
+            .unwrap();
+        }
+
+        let patch: ProjectPatch = serde_json::from_str(
+            r##"{"description": "Home stuff", "icon": "🏠", "default_priority": "high", "default_tags": ["home"]}"##,
+        )
+        .unwrap();
+        let a = db.update_project_details("a", &patch).unwrap();
+        assert_eq!(a.icon.as_deref(), Some("🏠"));
+        assert_eq!(a.default_tags, vec!["home".to_string()]);
+        let bad: ProjectPatch = serde_json::from_str(r#"{"color": "red"}"#).unwrap();
+        assert!(db.update_project_details("a", &bad).is_err());
+        let clear: ProjectPatch = serde_json::from_str(r#"{"icon": null}"#).unwrap();
+        assert_eq!(db.update_project_details("a", &clear).unwrap().icon, None);
+
+        let mut t = new_task("Sweep", None);
+        t.project_id = Some("a".to_string());
+        let task = db.add_task(&t).unwrap();
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.tags, vec!["home".to_string()]);
+
+        db.reorder_projects(&["c".to_string(), "a".to_string(), "b".to_string()])
+            .unwrap();
+        let order: Vec<String> = db
+            .get_projects()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(order, ["c", "a", "b"]);
+
+        let mut own = new_task("Already archived", None);
+        own.project_id = Some("a".to_string());
+        let own = db.add_task(&own).unwrap();
+        db.archive_task(&own.id).unwrap();
+
+        assert_eq!(db.archive_project("a").unwrap(), vec!["a".to_string()]);
+        assert_eq!(db.get_projects().unwrap().len(), 2);
+        assert_eq!(db.get_all_projects().unwrap().len(), 3);
+        assert!(db.get_task(&task.id).unwrap().is_archived);
+
+        assert_eq!(db.unarchive_project("a").unwrap(), vec!["a".to_string()]);
+        assert!(!db.get_task(&task.id).unwrap().is_archived);
+        assert!(db.get_task(&own.id).unwrap().is_archived);
+    }
+
+    #[test]
+    fn test_sections() {
+        let mut db = memory_db();
+        for id in ["p", "q"] {
+            db.add_project(
+                id.to_string(),
+                id.to_string(),
+                "#000000".to_string(),
+                Priority::Normal,
+                None,
+                false,
+            )
+            .unwrap();
+        }
+        let todo = db.add_section("p", "Todo").unwrap();
+        let later = db.add_section("p", " Later ").unwrap();
+        let elsewhere = db.add_section("q", "Elsewhere").unwrap();
+        assert_eq!(later.name, "Later");
+        assert!(db.add_section("p", "  ").is_err());
+
+        db.reorder_sections(&[later.id.clone(), todo.id.clone()])
+            .unwrap();
+        let names: Vec<String> = db
+            .get_sections("p", false)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, ["Later", "Todo"]);
+        assert!(db
+            .reorder_sections(&[todo.id.clone(), elsewhere.id.clone()])
+            .is_err());
+
+        // Creating in a section fills in the project; a mismatch is rejected
+        let mut t = new_task("Plan", None);
+        t.section_id = Some(todo.id.clone());
+        let task = db.add_task(&t).unwrap();
+        assert_eq!(task.project_id.as_deref(), Some("p"));
+        let mut bad = new_task("Bad", None);
+        bad.project_id = Some("q".to_string());
+        bad.section_id = Some(todo.id.clone());
+        assert!(db.add_task(&bad).is_err());
+
+        let moved = db
+            .move_task_to_section(&task.id, Some(&elsewhere.id))
+            .unwrap();
+        assert_eq!(moved.project_id.as_deref(), Some("q"));
+        assert_eq!(moved.section_id.as_deref(), Some(elsewhere.id.as_str()));
+
+        assert_eq!(
+            db.archive_section(&elsewhere.id).unwrap(),
+            vec![task.id.clone()]
+        );
+        assert!(db.get_sections("q", false).unwrap().is_empty());
+        assert!(db.get_task(&task.id).unwrap().is_archived);
+        assert!(db
+            .move_task_to_section(&task.id, Some(&elsewhere.id))
+            .is_err());
+        db.unarchive_section(&elsewhere.id).unwrap();
+        assert!(!db.get_task(&task.id).unwrap().is_archived);
+
+        // Section and project archiving don't restore each other's tasks
+        db.archive_section(&elsewhere.id).unwrap();
+        db.archive_project("q").unwrap();
+        db.unarchive_project("q").unwrap();
+        assert!(db.get_task(&task.id).unwrap().is_archived);
+        db.unarchive_section(&elsewhere.id).unwrap();
+        assert!(!db.get_task(&task.id).unwrap().is_archived);
+        db.archive_project("q").unwrap();
+        db.archive_section(&elsewhere.id).unwrap();
+        db.unarchive_section(&elsewhere.id).unwrap();
+        assert!(db.get_task(&task.id).unwrap().is_archived);
+        db.unarchive_project("q").unwrap();
+        assert!(!db.get_task(&task.id).unwrap().is_archived);
+
+        // Sections survive a backup round trip
+        let bundle = db.export_bundle().unwrap();
+        let mut copy = memory_db();
+        copy.import_data(
+            bundle.projects,
+            bundle.sections,
+            bundle.tasks,
+            bundle.tags,
+            bundle.boards,
+            bundle.settings,
+        )
+        .unwrap();
+        assert_eq!(copy.get_sections("p", false).unwrap().len(), 2);
+        assert_eq!(
+            copy.get_task(&task.id).unwrap().section_id,
+            Some(elsewhere.id.clone())
+        );
+
+        // Deleting the project hides its sections and frees the tasks
+        db.delete_project("q", ProjectDeletePolicy::Inbox).unwrap();
+        assert!(db.get_section(&elsewhere.id).is_err());
+        assert_eq!(db.get_task(&task.id).unwrap().section_id, None);
+    }
+
+    #[test]
+    fn test_board_wip_limits_and_status() {
+        let mut db = memory_db();
+        db.add_project(
+            "p".to_string(),
+            "p".to_string(),
+            "#000000".to_string(),
+            Priority::Normal,
+            None,
+            false,
+        )
+        .unwrap();
+        let mut ids = Vec::new();
+        for title in ["a", "b", "c"] {
+            let mut t = new_task(title, None);
+            t.project_id = Some("p".to_string());
+            ids.push(db.add_task(&t).unwrap().id);
+        }
+        let board = db.create_board("p", "Sprint", None).unwrap();
+        let (todo, doing, done) = (
+            board.columns[0].id.clone(),
+            board.columns[1].id.clone(),
+            board.columns[2].id.clone(),
+        );
+        assert_eq!(board.cards.len(), 3);
+        assert!(board.cards.iter().all(|c| c.column_id == todo));
+
+        let mut columns: Vec<BoardColumnInput> = board
+            .columns
+            .iter()
+            .map(|c| BoardColumnInput {
+                id: Some(c.id.clone()),
+                name: c.name.clone(),
+                category: c.category,
+                wip_limit: None,
+            })
+            .collect();
+        columns[1].wip_limit = Some(1);
+        db.save_board_columns(&board.id, &columns).unwrap();
+
+        let moved = db.move_card(&board.id, &ids[0], &doing, None).unwrap();
+        assert!(moved.status_changed);
+        assert_eq!(db.get_task(&ids[0]).unwrap().status, Status::Doing);
+        let err = db.move_card(&board.id, &ids[1], &doing, None).unwrap_err();
+        assert!(err.contains("WIP limit"));
+        assert_eq!(db.get_task(&ids[1]).unwrap().status, Status::Todo);
+        // Reordering inside a full column is fine
+        db.move_card(&board.id, &ids[0], &doing, Some(0)).unwrap();
+
+        db.move_card(&board.id, &ids[2], &done, None).unwrap();
+        let task = db.get_task(&ids[2]).unwrap();
+        assert_eq!(task.status, Status::Done);
+        assert!(task.completed_at.is_some());
+
+        // Put b ahead of the others in To Do; a status change elsewhere moves c back
+        db.move_card(&board.id, &ids[1], &todo, Some(0)).unwrap();
+        db.update_task_status(&ids[2], Status::Todo).unwrap();
+        let board = db.get_board(&board.id).unwrap();
+        let todo_cards: Vec<&str> = board
+            .cards
+            .iter()
+            .filter(|c| c.column_id == todo)
+            .map(|c| c.task_id.as_str())
+            .collect();
+        assert_eq!(todo_cards, [ids[1].as_str(), ids[2].as_str()]);
+
+        let mut other = new_task("Inbox", None);
+        other.project_id = None;
+        let other = db.add_task(&other).unwrap();
+        assert!(db.move_card(&board.id, &other.id, &todo, None).is_err());
+    }
+
+    #[test]
+    fn test_task_dependencies() {
+        let mut db = memory_db();
+        let a = db.add_task(&new_task("Design", None)).unwrap().id;
+        let b = db.add_task(&new_task("Build", None)).unwrap().id;
+        let c = db.add_task(&new_task("Ship", None)).unwrap().id;
+
+        db.add_task_dependency(&b, &a).unwrap();
+        db.add_task_dependency(&c, &b).unwrap();
+        assert!(db
+            .add_task_dependency(&a, &c)
+            .unwrap_err()
+            .contains("cycle"));
+        assert!(db.add_task_dependency(&a, &a).is_err());
+
+        let tasks = db.get_tasks(None, None, None, None).unwrap();
+        let blocked: HashSet<&str> = tasks
+            .iter()
+            .filter(|t| t.is_blocked)
+            .map(|t| t.id.as_str())
+            .collect();
+        assert_eq!(blocked, HashSet::from([b.as_str(), c.as_str()]));
+        assert_eq!(db.get_task(&c).unwrap().depends_on, vec![b.clone()]);
+
+        let waiting = db.blocked_dependents(&a);
+        assert_eq!(waiting.len(), 1);
+        db.update_task_status(&a, Status::Done).unwrap();
+        assert!(!db.get_task(&b).unwrap().is_blocked);
+        assert!(db.get_task(&c).unwrap().is_blocked);
+
+        // Deleting a blocker removes its edges
+        db.delete_task(&b).unwrap();
+        let ship = db.get_task(&c).unwrap();
+        assert!(ship.depends_on.is_empty());
+        assert!(!ship.is_blocked);
+
+        db.add_task_dependency(&c, &a).unwrap();
+        db.remove_task_dependency(&c, &a).unwrap();
+        assert!(db.get_task(&c).unwrap().depends_on.is_empty());
+    }
+
+    #[test]
+    fn test_trash_restore_and_purge() {
+        let mut db = memory_db();
+        for (id, parent, folder) in [("f", None, true), ("p", Some("f"), false)] {
+            db.add_project(
+                id.to_string(),
+                id.to_string(),
+                "#000".to_string(),
+                Priority::Normal,
+                parent.map(String::from),
+                folder,
+            )
+            .unwrap();
+        }
+        let mut t = new_task("In p", None);
+        t.project_id = Some("p".to_string());
+        let task = db.add_task(&t).unwrap();
+        let sub = db.add_subtask(&task.id, "Step").unwrap();
+        let loose = db.add_task(&new_task("Loose", None)).unwrap();
+
+        // Cascade: the folder is listed once, with p and its task folded in
+        db.delete_project("f", ProjectDeletePolicy::Cascade)
+            .unwrap();
+        db.delete_task(&loose.id).unwrap();
+        assert!(db.get_task(&task.id).is_err());
+        assert!(db.get_project("p").is_err());
+        let trash = db.get_trash().unwrap();
+        let listed: Vec<(&str, &str)> = trash
+            .iter()
+            .map(|i| (i.entity.as_str(), i.id.as_str()))
+            .collect();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.contains(&("project", "f")));
+        assert!(listed.contains(&("task", loose.id.as_str())));
+
+        // Restoring the task alone brings back its project chain
+        let restored = db.restore_from_trash("task", &task.id).unwrap();
+        assert_eq!(restored.len(), 3);
         assert_eq!(
-            next_fri,
-            Some(NaiveDate::from_ymd_opt(2023, 10, 13).unwrap())
+            db.get_task(&task.id).unwrap().project_id,
+            Some("p".to_string())
+        );
+        assert_eq!(
+            db.get_project("p").unwrap().parent_id,
+            Some("f".to_string())
+        );
+        assert_eq!(db.get_subtasks(&task.id).unwrap()[0].id, sub.id);
+        assert!(db.restore_from_trash("task", &task.id).is_err());
+        assert!(db
+            .restore_from_trash("task", "missing")
+            .unwrap_err()
+            .contains("not found"));
+
+        // A subtask restores its trashed task
+        db.delete_subtask(&sub.id).unwrap();
+        db.delete_task(&task.id).unwrap();
+        db.restore_from_trash("subtask", &sub.id).unwrap();
+        assert_eq!(db.get_subtasks(&task.id).unwrap().len(), 1);
+
+        // A purged project leaves a restored task in the Inbox. Batches are
+        // told apart by deleted_at, so keep the two deletes a tick apart
+        db.delete_task(&task.id).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        db.delete_project("f", ProjectDeletePolicy::Cascade)
+            .unwrap();
+        db.purge_trash_item("project", "f").unwrap();
+        assert!(db
+            .get_trash()
+            .unwrap()
+            .iter()
+            .all(|i| i.entity != "project"));
+        db.restore_from_trash("task", &task.id).unwrap();
+        assert_eq!(db.get_task(&task.id).unwrap().project_id, None);
+
+        // Retention: nothing expires early, 0 disables purging
+        let now = chrono::Utc::now().timestamp_millis();
+        assert_eq!(db.purge_expired_trash(now).unwrap(), 0);
+        let mut settings = db.get_settings().unwrap();
+        settings.trash_retention_days = 0;
+        db.save_settings(settings.clone()).unwrap();
+        assert_eq!(db.purge_expired_trash(now + 365 * 86_400_000).unwrap(), 0);
+        settings.trash_retention_days = 1;
+        db.save_settings(settings).unwrap();
+        assert_eq!(db.purge_expired_trash(now + 2 * 86_400_000).unwrap(), 1);
+        assert!(db.get_trash().unwrap().is_empty());
+
+        db.delete_task(&task.id).unwrap();
+        assert_eq!(db.empty_trash().unwrap(), 1);
+        assert!(db.get_trash().unwrap().is_empty());
+        assert!(db.get_subtask(&sub.id).is_err());
+    }
+
+    #[test]
+    fn test_undo_redo_journal() {
+        let mut db = memory_db();
+        assert_eq!(db.undo().unwrap(), None);
+
+        let task = {
+            let db = UndoStep::begin(&mut db, "Add task").unwrap();
+            db.add_task(&new_task("Water plants", Some("daily")))
+                .unwrap()
+        };
+        {
+            let mut db = UndoStep::begin(&mut db, "Complete task").unwrap();
+            db.update_task_status(&task.id, Status::Done).unwrap();
+        }
+        // Reads and no-op steps don't land in the history
+        UndoStep::begin(&mut db, "Nothing").unwrap();
+        assert_eq!(db.get_tasks(None, None, None, None).unwrap().len(), 2);
+        assert_eq!(
+            db.get_undo_status().unwrap(),
+            UndoStatus {
+                undo: Some("Complete task".to_string()),
+                redo: None
+            }
+        );
+
+        // Undo drops the spawned occurrence and restores the repeat rule
+        assert_eq!(db.undo().unwrap(), Some("Complete task".to_string()));
+        let tasks = db.get_tasks(None, None, None, None).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].status, Status::Todo);
+        assert_eq!(tasks[0].repeat_mode.as_deref(), Some("daily"));
+
+        assert_eq!(db.redo().unwrap(), Some("Complete task".to_string()));
+        assert_eq!(db.get_tasks(None, None, None, None).unwrap().len(), 2);
+        assert_eq!(db.get_task(&task.id).unwrap().status, Status::Done);
+        db.undo().unwrap();
+
+        // Deleting a subtask row comes back with its values
+        let sub = db.add_subtask(&task.id, "Fill can").unwrap();
+        {
+            let db = UndoStep::begin(&mut db, "Purge").unwrap();
+            db.get_connection()
+                .execute("DELETE FROM subtasks WHERE id = ?1", params![sub.id])
+                .unwrap();
+        }
+        assert!(db.get_subtask(&sub.id).is_err());
+        db.undo().unwrap();
+        assert_eq!(db.get_subtask(&sub.id).unwrap().title, "Fill can");
+
+        // A new step discards what could be redone
+        assert!(db.get_undo_status().unwrap().redo.is_some());
+        {
+            let db = UndoStep::begin(&mut db, "Rename").unwrap();
+            db.update_task_title(&task.id, "Water ferns".to_string())
+                .unwrap();
+        }
+        assert_eq!(db.get_undo_status().unwrap().redo, None);
+
+        db.undo().unwrap();
+        db.undo().unwrap();
+        assert!(db.get_tasks(None, None, None, None).unwrap().is_empty());
+        assert_eq!(db.undo().unwrap(), None);
+    }
+
+    #[test]
+    fn test_task_history() {
+        let mut db = memory_db();
+        let task = db.add_task(&new_task("Report", None)).unwrap();
+        db.update_task_deadline(&task.id, Some(1_000)).unwrap();
+        db.update_task_deadline(&task.id, Some(2_000)).unwrap();
+        db.update_task_status(&task.id, Status::Done).unwrap();
+        db.delete_task(&task.id).unwrap();
+
+        let history = db.get_task_history(&task.id).unwrap();
+        let entries: Vec<_> = history
+            .iter()
+            .map(|e| {
+                (
+                    e.action.as_str(),
+                    e.field.as_deref(),
+                    e.old_value.as_deref(),
+                    e.new_value.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(entries[0], ("created", None, None, Some("Report")));
+        assert_eq!(entries[1], ("updated", Some("tags"), None, Some("home")));
+        assert_eq!(
+            entries[2],
+            ("updated", Some("deadline"), None, Some("1000"))
+        );
+        assert_eq!(
+            entries[3],
+            ("updated", Some("deadline"), Some("1000"), Some("2000"))
+        );
+        assert_eq!(
+            entries[4],
+            ("updated", Some("status"), Some("0"), Some("2"))
+        );
+        assert_eq!(entries[5].1, Some("deleted_at"));
+        assert_eq!(entries.len(), 6);
+        let postponed = history
+            .iter()
+            .filter(|e| e.field.as_deref() == Some("deadline") && e.old_value.is_some())
+            .count();
+        assert_eq!(postponed, 1);
+
+        // Unchanged values and untracked columns leave no trace
+        db.reorder_tasks(std::slice::from_ref(&task.id)).unwrap();
+        assert_eq!(db.get_task_history(&task.id).unwrap().len(), 6);
+
+        db.empty_trash().unwrap();
+        assert!(db.get_task_history(&task.id).is_err());
+        let left: i64 = db
+            .get_connection()
+            .query_row("SELECT COUNT(*) FROM task_events", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(left, 0);
+    }
+
+    #[test]
+    fn test_bulk_update_tasks() {
+        let mut db = memory_db();
+        db.add_project(
+            "p".to_string(),
+            "p".to_string(),
+            "#000".to_string(),
+            Priority::Normal,
+            None,
+            false,
+        )
+        .unwrap();
+        let mut daily = new_task("Stretch", Some("daily"));
+        daily.deadline = Some(60_000);
+        daily.tags = vec!["health".to_string(), "old".to_string()];
+        let a = db.add_task(&daily).unwrap();
+        let b = db.add_task(&new_task("Read", None)).unwrap();
+        let ids = vec![a.id.clone(), b.id.clone(), a.id.clone()];
+
+        let patch = TaskBulkPatch {
+            status: Some(Status::Done),
+            project_id: Some(Some("p".to_string())),
+            priority: Some(Priority::High),
+            deadline_shift_minutes: Some(10),
+            add_tags: vec!["batch".to_string()],
+            remove_tags: vec!["old".to_string()],
+            ..Default::default()
+        };
+        let result = db.bulk_update_tasks(&ids, &patch).unwrap();
+        assert!(result.applied);
+        assert_eq!(result.results.len(), 2);
+        let spawned = result.results[0].spawned_task_id.clone().unwrap();
+        assert_eq!(result.results[1].spawned_task_id, None);
+
+        let a = db.get_task(&a.id).unwrap();
+        assert_eq!(a.status, Status::Done);
+        assert_eq!(a.project_id.as_deref(), Some("p"));
+        assert_eq!(a.priority, Priority::High);
+        assert_eq!(a.deadline, Some(60_000 + 600_000));
+        assert_eq!(a.tags, vec!["health".to_string(), "batch".to_string()]);
+        assert_eq!(db.get_task(&b.id).unwrap().deadline, None);
+        // The next occurrence is cut from the updated task
+        let next = db.get_task(&spawned).unwrap();
+        assert_eq!(next.project_id.as_deref(), Some("p"));
+        assert_eq!(next.priority, Priority::High);
+
+        // One bad id rolls back the whole batch
+        let patch = TaskBulkPatch {
+            archived: Some(true),
+            delete: true,
+            ..Default::default()
+        };
+        let result = db
+            .bulk_update_tasks(&[b.id.clone(), "missing".to_string()], &patch)
+            .unwrap();
+        assert!(!result.applied);
+        assert!(result.results[0].error.is_none());
+        assert!(result.results[1]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("not found"));
+        assert!(!db.get_task(&b.id).unwrap().is_archived);
+
+        let result = db
+            .bulk_update_tasks(std::slice::from_ref(&b.id), &patch)
+            .unwrap();
+        assert!(result.applied);
+        assert!(db.get_task(&b.id).is_err());
+        assert_eq!(db.get_trash().unwrap()[0].id, b.id);
+
+        let patch = TaskBulkPatch {
+            project_id: Some(Some("missing".to_string())),
+            ..Default::default()
+        };
+        assert!(db
+            .bulk_update_tasks(std::slice::from_ref(&a.id), &patch)
+            .is_err());
+        db.add_project(
+            "f".to_string(),
+            "f".to_string(),
+            "#000".to_string(),
+            Priority::Normal,
+            None,
+            true,
+        )
+        .unwrap();
+        let patch = TaskBulkPatch {
+            project_id: Some(Some("f".to_string())),
+            ..Default::default()
+        };
+        assert!(db
+            .bulk_update_tasks(std::slice::from_ref(&a.id), &patch)
+            .is_err());
+        assert_eq!(db.get_task(&a.id).unwrap().project_id.as_deref(), Some("p"));
+    }
+
+    #[test]
+    fn test_update_task_patch() {
+        let mut db = memory_db();
+        db.add_project(
+            "p".to_string(),
+            "p".to_string(),
+            "#000".to_string(),
+            Priority::Normal,
+            None,
+            false,
+        )
+        .unwrap();
+        let section = db.add_section("p", "Later").unwrap();
+        let task = db.add_task(&new_task("Draft", Some("daily"))).unwrap();
+        let created = task.revision;
+
+        // Setting the section moves the task into its project
+        let patch = TaskPatch {
+            title: Some("  Draft post ".to_string()),
+            description: Some(Some("notes".to_string())),
+            section_id: Some(Some(section.id.clone())),
+            estimated_minutes: Some(Some(30)),
+            expected_revision: Some(created),
+            ..Default::default()
+        };
+        let (task, spawned) = db.update_task(&task.id, &patch).unwrap();
+        assert!(spawned.is_none());
+        assert_eq!(task.title, "Draft post");
+        assert_eq!(task.description.as_deref(), Some("notes"));
+        assert_eq!(task.project_id.as_deref(), Some("p"));
+        assert_eq!(task.section_id.as_deref(), Some(section.id.as_str()));
+        assert_eq!(task.estimated_minutes, Some(30));
+        assert_eq!(task.repeat_mode.as_deref(), Some("daily"));
+        assert!(task.revision > created);
+        assert!(task.updated_at >= task.created_at);
+
+        // Stale revisions and invalid values are refused without changes
+        let stale = TaskPatch {
+            title: Some("Lost".to_string()),
+            expected_revision: Some(created),
+            ..Default::default()
+        };
+        assert!(db
+            .update_task(&task.id, &stale)
+            .unwrap_err()
+            .starts_with("Revision conflict"));
+        for patch in [
+            TaskPatch {
+                title: Some(" ".to_string()),
+                ..Default::default()
+            },
+            TaskPatch {
+                repeat_mode: Some(Some("custom".to_string())),
+                ..Default::default()
+            },
+            TaskPatch {
+                project_id: Some(None),
+                section_id: Some(Some(section.id.clone())),
+                ..Default::default()
+            },
+            TaskPatch {
+                project_id: Some(Some("missing".to_string())),
+                ..Default::default()
+            },
+        ] {
+            assert!(db.update_task(&task.id, &patch).is_err());
+        }
+        assert_eq!(db.get_task(&task.id).unwrap().revision, task.revision);
+
+        // Moving back to the Inbox drops the section; completing spawns
+        let patch = TaskPatch {
+            project_id: Some(None),
+            actual_minutes: Some(Some(45)),
+            status: Some(Status::Done),
+            ..Default::default()
+        };
+        let (task, spawned) = db.update_task(&task.id, &patch).unwrap();
+        assert!(task.project_id.is_none() && task.section_id.is_none());
+        assert_eq!(task.actual_minutes, Some(45));
+        assert!(task.completed_at.is_some());
+        assert!(spawned.is_some());
+        assert!(db.update_task("missing", &TaskPatch::default()).is_err());
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut db = memory_db();
+        let mut a = new_task("a", None);
+        a.tags = vec![
+            " work ".to_string(),
+            "urgent".to_string(),
+            "work".to_string(),
+        ];
+        let a = db.add_task(&a).unwrap();
+        assert_eq!(a.tags, vec!["work".to_string(), "urgent".to_string()]);
+        let mut b = new_task("b", None);
+        b.tags = vec!["Work".to_string()];
+        let b = db.add_task(&b).unwrap();
+        db.update_task_status(&b.id, Status::Done).unwrap();
+
+        let counts: Vec<_> = db
+            .get_tags()
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.name, t.task_count, t.open_count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("urgent".to_string(), 1, 1),
+                ("work".to_string(), 1, 1),
+                ("Work".to_string(), 1, 0),
+            ]
+        );
+        let tagged = db
+            .get_tasks(None, None, None, Some("urgent".to_string()))
+            .unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].id, a.id);
+
+        // Renaming onto an existing tag is refused; merging folds it in
+        assert!(db.rename_tag("Work", "work").is_err());
+        let merged = db.merge_tags(&["Work".to_string()], "work").unwrap();
+        assert_eq!((merged.task_count, merged.open_count), (2, 1));
+        assert_eq!(db.get_task(&b.id).unwrap().tags, vec!["work".to_string()]);
+
+        let renamed = db.rename_tag("work", "office").unwrap();
+        assert_eq!(renamed.task_count, 2);
+        assert_eq!(
+            db.get_task(&a.id).unwrap().tags,
+            vec!["office".to_string(), "urgent".to_string()]
+        );
+        let colored = db
+            .set_tag_color("office", Some("#ff0000".to_string()))
+            .unwrap();
+        assert_eq!(colored.color.as_deref(), Some("#ff0000"));
+        assert!(db.set_tag_color("office", Some("red".to_string())).is_err());
+        assert_eq!(db.set_tag_color("office", None).unwrap().color, None);
+        assert!(db.set_tag_color("work", None).is_err());
+
+        db.delete_tag("urgent").unwrap();
+        assert_eq!(db.get_task(&a.id).unwrap().tags, vec!["office".to_string()]);
+        assert_eq!(db.get_tags().unwrap().len(), 1);
+        let removed = db
+            .get_task_history(&a.id)
+            .unwrap()
+            .into_iter()
+            .filter(|e| {
+                e.field.as_deref() == Some("tags") && e.old_value.as_deref() == Some("urgent")
+            })
+            .count();
+        assert_eq!(removed, 1);
+
+        // Pre-migration JSON that doesn't parse is salvaged, not dropped
+        assert_eq!(parse_legacy_tags("t", r#"["x","y"]"#), vec!["x", "y"]);
+        assert_eq!(parse_legacy_tags("t", r#"["x", y"#), vec!["x", "y"]);
+        assert!(parse_legacy_tags("t", "[]").is_empty());
+    }
+
+    #[test]
+    fn test_templates() {
+        let mut db = memory_db();
+        let day = 86_400_000;
+        db.add_project(
+            "p".to_string(),
+            "Release".to_string(),
+            "#0af".to_string(),
+            Priority::High,
+            None,
+            false,
+        )
+        .unwrap();
+        let qa = db.add_section("p", "QA").unwrap();
+        let mut freeze = new_task("Code freeze", None);
+        freeze.project_id = Some("p".to_string());
+        freeze.deadline = Some(10 * day - 2 * day);
+        freeze.estimated_minutes = Some(30);
+        let freeze = db.add_task(&freeze).unwrap();
+        db.add_subtask(&freeze.id, "Tag branch").unwrap();
+        db.add_subtask(&freeze.id, "Notify team").unwrap();
+        let mut ship = new_task("Ship", Some("weekdays"));
+        ship.section_id = Some(qa.id.clone());
+        ship.deadline = Some(10 * day);
+        ship.remind_at = Some(10 * day - 60_000);
+        let ship = db.add_task(&ship).unwrap();
+        db.update_task_status(&freeze.id, Status::Done).unwrap();
+
+        // Project templates anchor on the latest deadline by default
+        let template = db.save_project_as_template("p", " Release ", None).unwrap();
+        assert_eq!(template.name, "Release");
+        let TemplateBody::Project { project, tasks } = &template.body else {
+            panic!("expected a project template");
+        };
+        assert_eq!(project.sections, vec!["QA".to_string()]);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].deadline_offset_minutes, Some(-2 * 24 * 60));
+        assert_eq!(tasks[0].subtasks, vec!["Tag branch", "Notify team"]);
+        assert_eq!(tasks[1].section.as_deref(), Some("QA"));
+        assert_eq!(tasks[1].remind_offset_minutes, Some(-1));
+
+        let anchor = 100 * day;
+        let created = db
+            .instantiate_template(
+                &template.id,
+                &TemplateInstance {
+                    anchor: Some(anchor),
+                    name: Some("Release 2".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let project_id = created.project_id.unwrap();
+        assert_eq!(db.get_project(&project_id).unwrap().name, "Release 2");
+        let copy = db.get_task(&created.task_ids[0]).unwrap();
+        assert_eq!(copy.status, Status::Todo);
+        assert_eq!(copy.deadline, Some(anchor - 2 * day));
+        assert_eq!(copy.estimated_minutes, Some(30));
+        assert_eq!(copy.tags, vec!["home".to_string()]);
+        let subtasks = db.get_subtasks(&copy.id).unwrap();
+        assert_eq!(subtasks.len(), 2);
+        assert!(subtasks.iter().all(|s| !s.completed));
+        let copy = db.get_task(&created.task_ids[1]).unwrap();
+        let section = db.get_section(copy.section_id.as_ref().unwrap()).unwrap();
+        assert_eq!(
+            (section.project_id.as_str(), section.name.as_str()),
+            (project_id.as_str(), "QA")
+        );
+        assert_eq!(copy.remind_at, Some(anchor - 60_000));
+        assert_eq!(copy.repeat_mode.as_deref(), Some("weekdays"));
+
+        // Task templates anchor on the task's deadline
+        let template = db.save_task_as_template(&ship.id, "Ship", None).unwrap();
+        let created = db
+            .instantiate_template(
+                &template.id,
+                &TemplateInstance {
+                    anchor: Some(anchor),
+                    project_id: Some("p".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(created.project_id.is_none());
+        let copy = db.get_task(&created.task_ids[0]).unwrap();
+        assert_eq!(copy.deadline, Some(anchor));
+        assert_eq!(copy.project_id.as_deref(), Some("p"));
+
+        // Failures leave nothing behind
+        let before = db.get_tasks(None, None, None, None).unwrap().len();
+        let bad = TemplateInstance {
+            project_id: Some("missing".to_string()),
+            ..Default::default()
+        };
+        assert!(db.instantiate_template(&template.id, &bad).is_err());
+        assert_eq!(db.get_tasks(None, None, None, None).unwrap().len(), before);
+
+        assert_eq!(db.get_templates().unwrap().len(), 2);
+        db.delete_template(&template.id).unwrap();
+        assert!(db.get_template(&template.id).is_err());
+        assert!(db.save_task_as_template(&ship.id, " ", None).is_err());
+    }
+
+    #[test]
+    fn test_project_template_keeps_tasks_as_saved() {
+        let mut db = memory_db();
+        db.add_project(
+            "p".to_string(),
+            "Sprint".to_string(),
+            "#0af".to_string(),
+            Priority::Normal,
+            None,
+            false,
+        )
+        .unwrap();
+        db.update_project_details(
+            "p",
+            &ProjectPatch {
+                default_priority: Some(Some(Priority::High)),
+                default_tags: Some(vec!["work".to_string()]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        db.add_section("p", "Todo").unwrap();
+        let second = db.add_section("p", "Todo").unwrap();
+        let mut task = new_task("Review", None);
+        task.section_id = Some(second.id.clone());
+        let task = db.add_task(&task).unwrap();
+        assert_eq!(task.priority, Priority::High);
+        // Undo the defaults by hand; the template keeps that
+        db.update_task(
+            &task.id,
+            &TaskPatch {
+                priority: Some(Priority::Normal),
+                tags: Some(Vec::new()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let template = db.save_project_as_template("p", "Sprint", None).unwrap();
+        let created = db
+            .instantiate_template(&template.id, &TemplateInstance::default())
+            .unwrap();
+        let copy = db.get_task(&created.task_ids[0]).unwrap();
+        assert_eq!(copy.priority, Priority::Normal);
+        assert!(copy.tags.is_empty());
+        // Same-name sections stay apart
+        let sections = db
+            .get_sections(created.project_id.as_ref().unwrap(), false)
+            .unwrap();
+        assert_eq!(copy.section_id.as_ref(), Some(&sections[1].id));
+    }
+
+    #[test]
+    fn test_duplicate_promote_demote() {
+        let mut db = memory_db();
+        let mut original = new_task("Pack", None);
+        original.remind_at = Some(5_000);
+        let original = db.add_task(&original).unwrap();
+        let other = db.add_task(&new_task("Other", None)).unwrap();
+        db.reorder_tasks(&[original.id.clone(), other.id.clone()])
+            .unwrap();
+        let socks = db.add_subtask(&original.id, "Socks").unwrap();
+        db.add_subtask(&original.id, "Shirts").unwrap();
+        db.toggle_subtask(&socks.id).unwrap();
+        db.update_task_status(&original.id, Status::Doing).unwrap();
+
+        let copy = db.duplicate_task(&original.id).unwrap();
+        assert_ne!(copy.id, original.id);
+        assert_eq!(copy.title, "Pack");
+        assert_eq!(copy.status, Status::Todo);
+        assert_eq!(copy.tags, vec!["home".to_string()]);
+        assert_eq!(copy.remind_at, Some(5_000));
+        assert_eq!(copy.sort_order, 1);
+        assert_eq!(db.get_task(&other.id).unwrap().sort_order, 2);
+        let subtasks = db.get_subtasks(&copy.id).unwrap();
+        let titles: Vec<_> = subtasks.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, vec!["Socks", "Shirts"]);
+        assert!(subtasks.iter().all(|s| !s.completed));
+        assert!(db.duplicate_task("missing").is_err());
+
+        // Promotion keeps the id, title and completion, which counts as
+        // completing the task
+        let points = db.get_gamification().unwrap().points;
+        let promoted = db.promote_subtask_to_task(&socks.id).unwrap();
+        assert_eq!(promoted.id, socks.id);
+        assert_eq!(promoted.title, "Socks");
+        assert_eq!(promoted.status, Status::Done);
+        assert!(promoted.completed_at.is_some());
+        assert!(db.get_gamification().unwrap().points > points);
+        assert_eq!(promoted.sort_order, 1);
+        assert_eq!(db.get_subtasks(&original.id).unwrap().len(), 1);
+
+        // Demotion appends to the parent and takes focus time along
+        let session = db.start_focus_session(promoted.id.clone()).unwrap();
+        assert!(db.demote_task_to_subtask(&copy.id, &original.id).is_err());
+        let today = Local::now().date_naive();
+        db.add_to_daily_plan(today, std::slice::from_ref(&promoted.id))
+            .unwrap();
+        assert!(db
+            .demote_task_to_subtask(&promoted.id, &original.id)
+            .unwrap_err()
+            .contains("daily plan"));
+        db.remove_from_daily_plan(today, &promoted.id).unwrap();
+        assert!(db
+            .demote_task_to_subtask(&promoted.id, &promoted.id)
+            .is_err());
+        let demoted = db
+            .demote_task_to_subtask(&promoted.id, &original.id)
+            .unwrap();
+        assert_eq!(demoted.id, socks.id);
+        assert!(demoted.completed);
+        let titles: Vec<_> = db
+            .get_subtasks(&original.id)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.title)
+            .collect();
+        assert_eq!(titles, vec!["Shirts", "Socks"]);
+        assert!(db.get_task(&promoted.id).is_err());
+        let owner: String = db
+            .get_connection()
+            .query_row(
+                "SELECT task_id FROM focus_sessions WHERE id = ?1",
+                params![session],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(owner, original.id);
+    }
+
+    #[test]
+    fn test_scheduled_agenda() {
+        let mut db = memory_db();
+        let today = Local::now().date_naive();
+        let day = |offset: i64| {
+            (today + Duration::days(offset))
+                .format("%Y-%m-%d")
+                .to_string()
+        };
+
+        let mut planned = new_task("Planned", None);
+        planned.scheduled_at = Some(day(0));
+        let planned = db.add_task(&planned).unwrap();
+        let mut later = new_task("Later", None);
+        later.scheduled_at = Some(format!("{}T09:30", day(3)));
+        let later = db.add_task(&later).unwrap();
+        let mut soon = new_task("Soon", None);
+        soon.scheduled_at = Some(day(1));
+        let soon = db.add_task(&soon).unwrap();
+        let someday = db.add_task(&new_task("Someday", None)).unwrap();
+        let mut overdue = new_task("Overdue", None);
+        overdue.deadline = Some(chrono::Utc::now().timestamp_millis() - 60_000);
+        let overdue = db.add_task(&overdue).unwrap();
+
+        assert_eq!(planned.agenda, Some(Agenda::Today));
+        assert_eq!(later.agenda, Some(Agenda::Upcoming));
+        assert_eq!(someday.agenda, Some(Agenda::Anytime));
+        assert_eq!(overdue.agenda, Some(Agenda::Today));
+        let ids = |agenda| -> Vec<String> {
+            db.get_agenda(agenda)
+                .unwrap()
+                .into_iter()
+                .map(|t| t.id)
+                .collect()
+        };
+        assert_eq!(
+            ids(Agenda::Upcoming),
+            vec![soon.id.clone(), later.id.clone()]
+        );
+        assert_eq!(ids(Agenda::Anytime), vec![someday.id.clone()]);
+        assert_eq!(db.get_stats().unwrap().tasks_today, 2);
+
+        let mut bad = new_task("Bad", None);
+        bad.scheduled_at = Some("tomorrow".to_string());
+        assert!(db.add_task(&bad).is_err());
+        let patch = TaskPatch {
+            scheduled_at: Some(Some("2024-02-30".to_string())),
+            ..Default::default()
+        };
+        assert!(db.update_task(&someday.id, &patch).is_err());
+
+        // Past days roll over to today, keeping the time; done tasks stay put
+        let patch = TaskPatch {
+            scheduled_at: Some(Some(format!("{}T08:15", day(-2)))),
+            ..Default::default()
+        };
+        db.update_task(&someday.id, &patch).unwrap();
+        let patch = TaskPatch {
+            scheduled_at: Some(Some(day(-1))),
+            status: Some(Status::Done),
+            ..Default::default()
+        };
+        db.update_task(&planned.id, &patch).unwrap();
+        assert_eq!(
+            db.roll_over_scheduled(today).unwrap(),
+            vec![someday.id.clone()]
+        );
+        let moved = db.get_task(&someday.id).unwrap();
+        assert_eq!(moved.scheduled_at, Some(format!("{}T08:15", day(0))));
+        assert_eq!(moved.agenda, Some(Agenda::Today));
+        assert_eq!(
+            db.get_task(&planned.id).unwrap().scheduled_at,
+            Some(day(-1))
+        );
+        let history = db.get_task_history(&someday.id).unwrap();
+        assert!(history
+            .iter()
+            .any(|e| e.field.as_deref() == Some("scheduled_at")
+                && e.new_value.as_deref() == Some(format!("{}T08:15", day(0)).as_str())));
+        assert!(db.roll_over_scheduled(today).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_daily_plan() {
+        let mut db = memory_db();
+        let today = Local::now().date_naive();
+        let tomorrow = today + Duration::days(1);
+        let add = |db: &AppDatabase, title: &str, estimate: Option<u32>| {
+            let mut task = new_task(title, None);
+            task.estimated_minutes = estimate;
+            db.add_task(&task).unwrap().id
+        };
+        let a = add(&db, "A", Some(30));
+        let b = add(&db, "B", None);
+        let c = add(&db, "C", Some(45));
+        let d = add(&db, "D", Some(10));
+        let order = |db: &AppDatabase, date| -> Vec<String> {
+            db.get_daily_plan(date)
+                .unwrap()
+                .tasks
+                .into_iter()
+                .map(|t| t.id)
+                .collect()
+        };
+
+        // Re-adding a planned task keeps its place
+        db.add_to_daily_plan(today, &[a.clone(), b.clone()])
+            .unwrap();
+        db.add_to_daily_plan(today, &[c.clone(), a.clone()])
+            .unwrap();
+        assert_eq!(order(&db, today), vec![a.clone(), b.clone(), c.clone()]);
+        assert!(db
+            .add_to_daily_plan(today, &["missing".to_string()])
+            .is_err());
+
+        // The plan has its own order; the global one is untouched
+        let sort_before = db.get_task(&a).unwrap().sort_order;
+        db.reorder_daily_plan(today, std::slice::from_ref(&c))
+            .unwrap();
+        assert_eq!(order(&db, today), vec![c.clone(), a.clone(), b.clone()]);
+        assert_eq!(db.get_task(&a).unwrap().sort_order, sort_before);
+        assert!(db
+            .reorder_daily_plan(today, std::slice::from_ref(&d))
+            .is_err());
+
+        let plan = db.get_daily_plan(today).unwrap();
+        assert_eq!(plan.date, today.format("%Y-%m-%d").to_string());
+        assert_eq!(plan.planned_minutes, 75);
+        assert_eq!(plan.remaining_minutes, 75);
+        assert_eq!(plan.unestimated_count, 1);
+        assert_eq!(plan.available_minutes, 25 * 8);
+        assert_eq!(plan.focused_minutes, 0);
+
+        db.update_task_status(&a, Status::Done).unwrap();
+        let session = db.start_focus_session(a.clone()).unwrap();
+        db.finish_focus_session(session, 25, true).unwrap();
+        let plan = db.get_daily_plan(today).unwrap();
+        assert_eq!(plan.planned_minutes, 75);
+        assert_eq!(plan.remaining_minutes, 45);
+        assert_eq!(plan.focused_minutes, 25);
+
+        // Unfinished tasks go to the end of tomorrow's plan; done ones stay
+        db.add_to_daily_plan(tomorrow, std::slice::from_ref(&d))
+            .unwrap();
+        let moved = db.carry_over_daily_plan(today, tomorrow).unwrap();
+        assert_eq!(moved, vec![c.clone(), b.clone()]);
+        assert_eq!(order(&db, today), vec![a.clone()]);
+        assert_eq!(order(&db, tomorrow), vec![d.clone(), c.clone(), b.clone()]);
+        assert!(db.carry_over_daily_plan(today, today).is_err());
+
+        db.remove_from_daily_plan(tomorrow, &d).unwrap();
+        assert!(db.remove_from_daily_plan(tomorrow, &d).is_err());
+        db.delete_task(&b).unwrap();
+        assert_eq!(order(&db, tomorrow), vec![c.clone()]);
+        let key = tomorrow.format("%Y-%m-%d").to_string();
+        assert!(db.snapshot("daily_plan", Some(&key)).is_some());
+        assert!(parse_day("2024-13-01").is_err());
+    }
+
+    #[test]
+    fn test_time_blocks() {
+        let mut db = memory_db();
+        // A Monday; default working hours are 9:00 to 17:00, Monday to Friday
+        let monday = NaiveDate::from_ymd_opt(2030, 1, 7).unwrap();
+        let at = |day: i64, hour: u32, minute: u32| {
+            local_ms(monday + Duration::days(day), hour * 60 + minute)
+        };
+        let add = |db: &AppDatabase, title: &str, estimate: u32, priority: Priority| {
+            let mut task = new_task(title, None);
+            task.estimated_minutes = Some(estimate);
+            task.priority = priority;
+            db.add_task(&task).unwrap().id
+        };
+
+        let meeting = db
+            .add_time_block(None, Some("Stand-up"), at(0, 9, 0), at(0, 10, 0))
+            .unwrap();
+        assert_eq!(meeting.title, "Stand-up");
+        assert!(db
+            .add_time_block(None, Some("Clash"), at(0, 9, 30), at(0, 10, 30))
+            .is_err());
+        assert!(db
+            .add_time_block(None, None, at(0, 11, 0), at(0, 12, 0))
+            .is_err());
+        assert!(db
+            .add_time_block(None, Some("Backwards"), at(0, 12, 0), at(0, 11, 0))
+            .is_err());
+
+        let mut due = new_task("Due soon", None);
+        due.estimated_minutes = Some(60);
+        due.deadline = Some(at(2, 12, 0));
+        let due = db.add_task(&due).unwrap().id;
+        let urgent = add(&db, "Urgent", 120, Priority::High);
+        let huge = add(&db, "Huge", 600, Priority::High);
+        let low = add(&db, "Low", 30, Priority::Low);
+        let mut later = new_task("Wednesday", None);
+        later.estimated_minutes = Some(30);
+        later.scheduled_at = Some("2030-01-09".to_string());
+        let later = db.add_task(&later).unwrap().id;
+
+        // Deadlines first, then priority; a task longer than a working day
+        // never fits
+        let result = db.auto_schedule(at(0, 8, 2), 7).unwrap();
+        let placed: Vec<(String, i64, i64)> = result
+            .blocks
+            .iter()
+            .map(|b| (b.task_id.clone().unwrap(), b.start_at, b.end_at))
+            .collect();
+        assert_eq!(
+            placed,
+            vec![
+                (due.clone(), at(0, 10, 0), at(0, 11, 0)),
+                (urgent.clone(), at(0, 11, 0), at(0, 13, 0)),
+                (later.clone(), at(2, 9, 0), at(2, 9, 30)),
+                (low.clone(), at(0, 13, 0), at(0, 13, 30)),
+            ]
+        );
+        assert_eq!(result.unscheduled_task_ids, vec![huge.clone()]);
+        assert_eq!(result.blocks[0].title, "Due soon");
+
+        // Tasks with an upcoming block are left alone
+        let again = db.auto_schedule(at(0, 8, 2), 7).unwrap();
+        assert!(again.blocks.is_empty());
+
+        let low_block = result.blocks[3].id.clone();
+        assert!(db.move_time_block(&low_block, at(0, 12, 45)).is_err());
+        let moved = db.move_time_block(&low_block, at(0, 16, 30)).unwrap();
+        assert_eq!(moved.end_at, at(0, 17, 0));
+        let resized = db
+            .resize_time_block(&low_block, at(0, 16, 0), at(0, 17, 0))
+            .unwrap();
+        assert_eq!(resized.start_at, at(0, 16, 0));
+
+        let done = db.complete_time_block(&low_block, true).unwrap();
+        assert!(done.block.completed_at.is_some());
+        assert!(done.focus_session_id.is_some());
+        assert!(db.complete_time_block(&meeting.id, true).is_err());
+        assert!(db.complete_time_block(&meeting.id, false).is_ok());
+
+        assert_eq!(
+            db.get_time_blocks(at(0, 0, 0), at(1, 0, 0)).unwrap().len(),
+            4
+        );
+        db.delete_task(&urgent).unwrap();
+        assert_eq!(
+            db.get_time_blocks(at(0, 0, 0), at(1, 0, 0)).unwrap().len(),
+            3
+        );
+        db.delete_time_block(&meeting.id).unwrap();
+        assert!(db.get_time_block(&meeting.id).is_err());
+    }
+
+    #[test]
+    fn test_time_entries() {
+        let mut db = memory_db();
+        let monday = NaiveDate::from_ymd_opt(2030, 1, 7).unwrap();
+        let tuesday = monday + Duration::days(1);
+        let at = |day: i64, hour: u32, minute: u32| {
+            local_ms(monday + Duration::days(day), hour * 60 + minute)
+        };
+        db.add_project(
+            "p".to_string(),
+            "Client, Inc.".to_string(),
+            "#0af".to_string(),
+            Priority::Normal,
+            None,
+            false,
+        )
+        .unwrap();
+        let mut report = new_task("Write report", None);
+        report.project_id = Some("p".to_string());
+        let report = db.add_task(&report).unwrap().id;
+        let mut chores = new_task("Chores", None);
+        chores.tags = Vec::new();
+        let chores = db.add_task(&chores).unwrap().id;
+
+        // One timer at a time
+        let running = db.start_timer(&report, at(0, 9, 0)).unwrap();
+        assert!(running.ended_at.is_none());
+        assert_eq!(db.get_running_timer().unwrap().unwrap().id, running.id);
+        assert!(db.start_timer(&chores, at(0, 9, 5)).is_err());
+        let stopped = db.stop_timer(at(0, 9, 50)).unwrap();
+        assert_eq!(stopped.minutes, 50);
+        assert!(db.stop_timer(at(0, 10, 0)).is_err());
+        assert!(db.get_running_timer().unwrap().is_none());
+
+        // Backfilled by hand, then edited
+        let manual = db
+            .add_time_entry(&chores, at(1, 14, 0), at(1, 14, 45), Some("  Dishes "))
+            .unwrap();
+        assert_eq!(manual.note.as_deref(), Some("Dishes"));
+        assert!(db
+            .add_time_entry(&chores, at(1, 15, 0), at(1, 15, 0), None)
+            .is_err());
+        let patch = TimeEntryPatch {
+            ended_at: Some(at(1, 15, 0)),
+            note: Some(None),
+            ..Default::default()
+        };
+        let edited = db.update_time_entry(&manual.id, &patch).unwrap();
+        assert_eq!((edited.minutes, edited.note), (60, None));
+        let backwards = TimeEntryPatch {
+            started_at: Some(at(1, 16, 0)),
+            ..Default::default()
+        };
+        assert!(db.update_time_entry(&manual.id, &backwards).is_err());
+
+        // Focus sessions count their focused minutes
+        let session = db.start_focus_session(report.clone()).unwrap();
+        db.finish_focus_session(session.clone(), 25, true).unwrap();
+        db.conn
+            .execute(
+                "UPDATE focus_sessions SET started_at = ?1, ended_at = ?2 WHERE id = ?3",
+                params![at(1, 10, 0), at(1, 10, 30), session],
+            )
+            .unwrap();
+
+        let sheet = db.get_timesheet(monday, tuesday).unwrap();
+        let rows: Vec<(&str, &str, u32)> = sheet
+            .iter()
+            .map(|e| (e.source.as_str(), e.task_title.as_str(), e.minutes))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("time_entry", "Write report", 50),
+                ("focus_session", "Write report", 25),
+                ("time_entry", "Chores", 60),
+            ]
+        );
+        assert_eq!(db.get_timesheet(monday, monday).unwrap().len(), 1);
+        assert!(db.get_timesheet(tuesday, monday).is_err());
+        assert_eq!(db.get_time_entries(monday, tuesday).unwrap().len(), 2);
+
+        let by = |group| -> Vec<(String, u32)> {
+            db.get_time_rollup(monday, tuesday, group)
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.label, r.minutes))
+                .collect()
+        };
+        let pairs = |items: &[(&str, u32)]| -> Vec<(String, u32)> {
+            items.iter().map(|(l, m)| (l.to_string(), *m)).collect()
+        };
+        assert_eq!(
+            by(TimeRollupGroup::Task),
+            pairs(&[("Write report", 75), ("Chores", 60)])
+        );
+        assert_eq!(
+            by(TimeRollupGroup::Project),
+            pairs(&[("Client, Inc.", 75), ("Inbox", 60)])
+        );
+        assert_eq!(
+            by(TimeRollupGroup::Tag),
+            pairs(&[("home", 75), ("Untagged", 60)])
+        );
+        assert_eq!(
+            by(TimeRollupGroup::Day),
+            pairs(&[("2030-01-07", 50), ("2030-01-08", 85)])
+        );
+
+        let csv = db.export_timesheet(monday, monday, "csv").unwrap();
+        assert_eq!(
+            csv,
+            "date,start,end,minutes,source,task,project,tags,note\n\
+             2030-01-07,09:00,09:50,50,time_entry,Write report,\"Client, Inc.\",home,\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&db.export_timesheet(monday, tuesday, "json").unwrap()).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 3);
+        assert!(db.export_timesheet(monday, tuesday, "xlsx").is_err());
+
+        // Demoting a task moves its tracked time to the parent
+        db.demote_task_to_subtask(&chores, &report).unwrap();
+        assert_eq!(db.get_time_entry(&manual.id).unwrap().task_id, report);
+        assert_eq!(db.get_timesheet(monday, tuesday).unwrap().len(), 3);
+
+        db.delete_time_entry(&manual.id).unwrap();
+        assert!(db.delete_time_entry(&manual.id).is_err());
+    }
+
+    #[test]
+    fn test_estimate_accuracy() {
+        let mut db = memory_db();
+        for (id, name) in [("p", "Web"), ("q", "New")] {
+            db.add_project(
+                id.to_string(),
+                name.to_string(),
+                "#0af".to_string(),
+                Priority::Normal,
+                None,
+                false,
+            )
+            .unwrap();
+        }
+        let mut add = |title: &str, project: Option<&str>, priority, estimate, actual, done| {
+            let mut task = new_task(title, None);
+            task.project_id = project.map(str::to_string);
+            task.priority = priority;
+            task.estimated_minutes = Some(estimate);
+            task.actual_minutes = actual;
+            let id = db.add_task(&task).unwrap().id;
+            if done {
+                db.update_task_status(&id, Status::Done).unwrap();
+            }
+            id
+        };
+        add("Slow", Some("p"), Priority::High, 60, Some(90), true);
+        let focused = add("Focused", Some("p"), Priority::Normal, 60, None, true);
+        let tracked = add("Tracked", Some("p"), Priority::Normal, 30, None, true);
+        add("Inbox", None, Priority::Low, 20, Some(40), true);
+        add("Open", None, Priority::Low, 10, Some(50), false);
+        add("Untracked", None, Priority::Low, 30, None, true);
+        let session = db.start_focus_session(focused).unwrap();
+        db.finish_focus_session(session, 30, true).unwrap();
+        db.update_task_tags(&tracked, Vec::new()).unwrap();
+        db.add_time_entry(&tracked, 0, 30 * 60_000, None).unwrap();
+
+        // Ratios are 1.5, 0.5, 1.0 and 2.0
+        let report = db.get_estimate_accuracy().unwrap();
+        let overall = &report.overall;
+        assert_eq!((overall.tasks, overall.median_ratio), (4, 1.25));
+        assert_eq!(
+            (overall.underestimated, overall.median_underestimate),
+            (2, Some(1.75))
+        );
+        assert_eq!(
+            (overall.overestimated, overall.median_overestimate),
+            (1, Some(2.0))
+        );
+        let summary = |groups: &[EstimateAccuracy]| -> Vec<(String, u32, f64)> {
+            groups
+                .iter()
+                .map(|g| (g.label.clone(), g.tasks, g.median_ratio))
+                .collect()
+        };
+        assert_eq!(
+            summary(&report.by_project),
+            vec![("Web".to_string(), 3, 1.0), ("Inbox".to_string(), 1, 2.0)]
+        );
+        assert_eq!(
+            summary(&report.by_tag),
+            vec![
+                ("home".to_string(), 3, 1.5),
+                ("Untagged".to_string(), 1, 1.0)
+            ]
+        );
+        assert_eq!(
+            summary(&report.by_priority),
+            vec![
+                ("high".to_string(), 1, 1.5),
+                ("normal".to_string(), 2, 0.75),
+                ("low".to_string(), 1, 2.0),
+            ]
+        );
+
+        let own = db.get_estimate_correction(Some("p")).unwrap();
+        assert_eq!((own.factor, own.samples, own.from_project), (1.0, 3, true));
+        let fallback = db.get_estimate_correction(Some("q")).unwrap();
+        assert_eq!(
+            (fallback.factor, fallback.samples, fallback.from_project),
+            (1.25, 4, false)
+        );
+        assert_eq!(db.get_estimate_correction(None).unwrap().factor, 1.25);
+        assert!(db.get_estimate_correction(Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_gamification() {
+        let mut db = memory_db();
+        let now = chrono::Utc::now().timestamp_millis();
+        let day = 86_400_000;
+        let add = |title: &str, priority: Priority, deadline: Option<i64>| {
+            let mut task = new_task(title, None);
+            task.priority = priority;
+            task.deadline = deadline;
+            db.add_task(&task).unwrap().id
+        };
+        let ship = add("Ship", Priority::High, Some(now + day));
+        let late = add("Late", Priority::Low, Some(now - day));
+        let next = add("Next", Priority::Normal, None);
+        let points = |db: &AppDatabase| db.get_gamification().unwrap().points;
+
+        // High priority and on time; reopening doesn't pay out again
+        db.update_task_status(&ship, Status::Done).unwrap();
+        assert_eq!(points(&db), 40);
+        let unlocked = db.take_unannounced_achievements().unwrap();
+        assert_eq!(unlocked.len(), 1);
+        assert_eq!(unlocked[0].id, "first_task");
+        assert!(db.take_unannounced_achievements().unwrap().is_empty());
+        db.update_task_status(&ship, Status::Todo).unwrap();
+        db.update_task_status(&ship, Status::Done).unwrap();
+        assert_eq!(points(&db), 40);
+
+        let session = db.start_focus_session(ship.clone()).unwrap();
+        db.finish_focus_session(session, 60, true).unwrap();
+        let status = db.get_gamification().unwrap();
+        assert_eq!((status.points, status.level), (100, 2));
+        assert_eq!((status.level_start, status.next_level_at), (100, Some(250)));
+        let unlocked = db.take_unannounced_achievements().unwrap();
+        assert_eq!(unlocked[0].id, "focus_60");
+
+        db.update_task_status(&late, Status::Done).unwrap();
+        assert_eq!(points(&db), 110);
+
+        // Yesterday counts toward a streak, paid once a day
+        let yesterday = Local::now().date_naive() - Duration::days(1);
+        db.conn
+            .execute(
+                "INSERT INTO daily_activity (day, tasks_done, goal_tasks, goal_focus_minutes)
+                 VALUES (?1, 1, 1, 0)",
+                params![yesterday.format("%Y-%m-%d").to_string()],
+            )
+            .unwrap();
+        db.update_task_status(&next, Status::Done).unwrap();
+        assert_eq!(points(&db), 135);
+        let ledger = db.get_points_ledger(2).unwrap();
+        let events: Vec<(&str, i64, i64)> = ledger
+            .iter()
+            .map(|e| (e.event.as_str(), e.quantity, e.points))
+            .collect();
+        assert_eq!(
+            events,
+            vec![("streak_day", 2, 5), ("complete_normal", 1, 20)]
+        );
+
+        let mut rules = db.get_gamification_rules().unwrap();
+        assert_eq!(rules, GamificationRules::default());
+        rules.level_thresholds = vec![100, 90];
+        assert!(db.save_gamification_rules(&rules).is_err());
+        rules.level_thresholds = vec![100, 120];
+        rules.complete_low = -1;
+        assert!(db.save_gamification_rules(&rules).is_err());
+        rules.complete_low = 10;
+        db.save_gamification_rules(&rules).unwrap();
+        let status = db.get_gamification().unwrap();
+        assert_eq!((status.level, status.level_start), (3, 120));
+        assert_eq!(status.next_level_at, None);
+        let stats = db.get_stats().unwrap();
+        assert_eq!((stats.points, stats.level), (135, 3));
+        let unlocked: Vec<&str> = status
+            .achievements
+            .iter()
+            .filter(|a| a.unlocked_at.is_some())
+            .map(|a| a.id.as_str())
+            .collect();
+        assert_eq!(unlocked, vec!["first_task", "focus_60"]);
+    }
+
+    #[test]
+    fn test_streaks() {
+        let mut db = memory_db();
+        let today = Local::now().date_naive();
+        let ymd = |days: i64| {
+            (today + Duration::days(days))
+                .format("%Y-%m-%d")
+                .to_string()
+        };
+        let task = db.add_task(&new_task("Walk", None)).unwrap().id;
+
+        // A completion counts once toward its day, however often it's redone
+        db.update_task_status(&task, Status::Done).unwrap();
+        db.update_task_status(&task, Status::Todo).unwrap();
+        db.update_task_status(&task, Status::Done).unwrap();
+        let streak = db.get_streak().unwrap();
+        assert_eq!((streak.current, streak.today.tasks_done), (1, 1));
+        assert!(streak.today.goal_met);
+
+        // A frozen day bridges the gap without counting
+        for (day, frozen) in [(-3, None), (-2, Some(1)), (-1, None)] {
+            db.conn
+                .execute(
+                    "INSERT INTO daily_activity (day, tasks_done, goal_tasks, goal_focus_minutes, frozen_at)
+                     VALUES (?1, ?2, 1, 0, ?3)",
+                    params![ymd(day), if frozen.is_some() { 0 } else { 1 }, frozen],
+                )
+                .unwrap();
+        }
+        let streak = db.get_streak().unwrap();
+        assert_eq!((streak.current, streak.best), (3, 3));
+
+        assert!(db.freeze_streak_day(today - Duration::days(3)).is_err());
+        assert!(db.freeze_streak_day(today - Duration::days(1)).is_err()); // goal met
+        let tomorrow = today + Duration::days(1);
+        let left = db.streak_freezes_left(tomorrow).unwrap();
+        assert!(db.freeze_streak_day(tomorrow).unwrap().frozen);
+        assert_eq!(db.streak_freezes_left(tomorrow).unwrap(), left - 1);
+        assert!(db.freeze_streak_day(tomorrow).is_err());
+        assert!(!db.unfreeze_streak_day(tomorrow).unwrap().frozen);
+        assert!(db.unfreeze_streak_day(tomorrow).is_err());
+        assert_eq!(db.get_daily_activity(tomorrow, tomorrow).unwrap().len(), 0);
+
+        // A new goal applies from today; earlier days keep theirs
+        let mut settings = db.get_settings().unwrap();
+        settings.daily_goal_tasks = 0;
+        assert!(db.save_settings(settings.clone()).is_err());
+        settings.daily_goal_tasks = 5;
+        settings.daily_goal_focus_minutes = 30;
+        db.save_settings(settings).unwrap();
+        let history = db
+            .get_daily_activity(today - Duration::days(3), today)
+            .unwrap();
+        let met: Vec<(bool, bool)> = history.iter().map(|d| (d.goal_met, d.frozen)).collect();
+        assert_eq!(
+            met,
+            vec![(true, false), (false, true), (true, false), (false, false)]
         );
+        // Today's still open, so it doesn't break the streak yet
+        assert_eq!(db.get_streak().unwrap().current, 2);
+
+        let session = db.start_focus_session(task.clone()).unwrap();
+        db.finish_focus_session(session.clone(), 30, true).unwrap();
+        db.finish_focus_session(session, 30, true).unwrap();
+        let streak = db.get_streak().unwrap();
+        assert_eq!((streak.today.focus_minutes, streak.current), (30, 3));
+        assert_eq!(db.get_stats().unwrap().current_streak, 3);
+    }
+
+    #[test]
+    fn test_undo_step_is_per_connection() {
+        let path = std::env::temp_dir().join(format!("focusflow-{}.db", uuid::Uuid::new_v4()));
+        let mut app = AppDatabase::open(path.clone()).unwrap();
+        let task = app.add_task(&new_task("Water plants", None)).unwrap();
+        let other = app.add_task(&new_task("Repot cactus", None)).unwrap();
+        {
+            let step = UndoStep::begin(&mut app, "Rename").unwrap();
+            step.update_task_title(&task.id, "Water ferns".to_string())
+                .unwrap();
+
+            // Another process opening the file mid-step, e.g. the CLI
+            let mut cli = AppDatabase::open(path.clone()).unwrap();
+            cli.update_task_status(&other.id, Status::Done).unwrap();
+            cli.add_task(&new_task("Buy soil", None)).unwrap();
+
+            // ...doesn't stop the open step from journaling
+            step.update_task_priority(&task.id, Priority::High).unwrap();
+        }
+
+        assert_eq!(app.undo().unwrap(), Some("Rename".to_string()));
+        let undone = app.get_task(&task.id).unwrap();
+        assert_eq!(undone.title, "Water plants");
+        assert_eq!(undone.priority, Priority::Normal);
+        // The other connection's writes stay
+        assert_eq!(app.get_task(&other.id).unwrap().status, Status::Done);
+        assert_eq!(app.get_tasks(None, None, None, None).unwrap().len(), 3);
+        assert_eq!(app.undo().unwrap(), None);
+
+        // Plain SQLite clients have neither the triggers nor `undo_step()`
+        let shell = Connection::open(&path).unwrap();
+        shell
+            .execute(
+                "UPDATE tasks SET title = 'Water cacti' WHERE id = ?1",
+                params![task.id],
+            )
+            .unwrap();
+        drop(shell);
+        assert_eq!(app.get_task(&task.id).unwrap().title, "Water cacti");
+
+        drop(app);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_undo_reverts_points() {
+        let mut db = memory_db();
+        let task = db.add_task(&new_task("Ship", None)).unwrap();
+        {
+            let mut db = UndoStep::begin(&mut db, "Complete task").unwrap();
+            db.update_task_status(&task.id, Status::Done).unwrap();
+        }
+        assert_eq!(db.get_gamification().unwrap().points, 20);
+
+        db.undo().unwrap();
+        let status = db.get_gamification().unwrap();
+        assert_eq!(status.points, 0);
+        assert!(status.achievements.iter().all(|a| a.unlocked_at.is_none()));
+
+        // Completing it again pays again
+        db.update_task_status(&task.id, Status::Done).unwrap();
+        assert_eq!(db.get_gamification().unwrap().points, 20);
+    }
+
+    #[test]
+    fn test_migration_17_moves_legacy_tags() {
+        let conn = Connection::open_in_memory().unwrap();
+        AppDatabase::migrate_to(&conn, 16).unwrap();
+        let legacy = [
+            ("json", r#"["home", "work", "home"]"#),
+            ("malformed", r#"[home, "work"#),
+            ("empty", "[]"),
+        ];
+        for (id, tags) in legacy {
+            conn.execute(
+                "INSERT INTO tasks (id, title, priority, status, created_at, tags) VALUES (?1, ?1, 1, 0, 0, ?2)",
+                params![id, tags],
+            )
+            .unwrap();
+        }
+
+        AppDatabase::migrate(&conn).unwrap();
+        let db = AppDatabase {
+            db_path: PathBuf::from(":memory:"),
+            conn,
+            undo_step: Arc::new(AtomicI64::new(0)),
+        };
+        let home_work = vec!["home".to_string(), "work".to_string()];
+        assert_eq!(db.get_task("json").unwrap().tags, home_work);
+        assert_eq!(db.get_task("malformed").unwrap().tags, home_work);
+        assert!(db.get_task("empty").unwrap().tags.is_empty());
+        let has_tags_column: i64 = db
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('tasks') WHERE name = 'tags'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(has_tags_column, 0);
+    }
+
+    #[test]
+    fn test_undo_keys_rows_by_primary_key() {
+        let path = std::env::temp_dir().join(format!("focusflow-{}.db", uuid::Uuid::new_v4()));
+        let mut app = AppDatabase::open(path.clone()).unwrap();
+        let cli = AppDatabase::open(path.clone()).unwrap();
+        let hook = |id: &str| Hook {
+            id: id.to_string(),
+            event: "*".to_string(),
+            kind: "http".to_string(),
+            target: "http://127.0.0.1:9/hook".to_string(),
+            enabled: true,
+            created_at: 1,
+        };
+        let ids = |db: &AppDatabase| -> Vec<String> {
+            let mut ids: Vec<String> = db.get_hooks().unwrap().into_iter().map(|h| h.id).collect();
+            ids.sort();
+            ids
+        };
+
+        // The app adds a hook; the CLI removes it and adds another, which
+        // gets the same rowid. Undoing the add must leave that one alone
+        UndoStep::begin(&mut app, "Add hook")
+            .unwrap()
+            .add_hook(&hook("a"))
+            .unwrap();
+        cli.delete_hook("a").unwrap();
+        cli.add_hook(&hook("b")).unwrap();
+        app.undo().unwrap();
+        assert_eq!(ids(&app), vec!["b".to_string()]);
+
+        // Deleting "b" in the app, then a CLI hook reusing its rowid, must
+        // not block restoring it
+        UndoStep::begin(&mut app, "Delete hook")
+            .unwrap()
+            .delete_hook("b")
+            .unwrap();
+        cli.add_hook(&hook("c")).unwrap();
+        app.undo().unwrap();
+        assert_eq!(ids(&app), vec!["b".to_string(), "c".to_string()]);
+
+        // A step that can't replay any more is dropped with an error instead
+        // of blocking the steps behind it
+        UndoStep::begin(&mut app, "Add hook")
+            .unwrap()
+            .add_hook(&hook("d"))
+            .unwrap();
+        UndoStep::begin(&mut app, "Delete hook")
+            .unwrap()
+            .delete_hook("c")
+            .unwrap();
+        cli.add_hook(&hook("c")).unwrap();
+        let err = app.undo().unwrap_err();
+        assert!(err.contains("\"Delete hook\""), "{}", err);
+        assert_eq!(app.undo().unwrap(), Some("Add hook".to_string()));
+        assert_eq!(ids(&app), vec!["b".to_string(), "c".to_string()]);
+
+        drop((app, cli));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_due_reminders() {
+        let db = memory_db();
+        let mut t = new_task("Call back", None);
+        t.remind_at = Some(1_000);
+        t.scheduled_at = Some("2030-01-02".to_string());
+        let due = db.add_task(&t).unwrap();
+        db.update_task_title(&due.id, "Call Ann back".to_string())
+            .unwrap();
+        let mut t = new_task("Archived", None);
+        t.remind_at = Some(1_000);
+        let archived = db.add_task(&t).unwrap();
+        db.archive_task(&archived.id).unwrap();
+
+        let reminders = db.get_due_reminders(2_000).unwrap();
+        assert_eq!(reminders.len(), 1);
+        // The same task as everywhere else, revision included
+        let task = db.get_task(&due.id).unwrap();
+        assert_eq!(reminders[0].revision, task.revision);
+        assert_eq!(reminders[0].updated_at, task.updated_at);
+        assert_eq!(reminders[0].scheduled_at, task.scheduled_at);
+        assert!(db.get_due_reminders(500).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_update_task_refuses_folders() {
+        let mut db = memory_db();
+        db.add_project(
+            "f".to_string(),
+            "f".to_string(),
+            "#000000".to_string(),
+            Priority::Normal,
+            None,
+            true,
+        )
+        .unwrap();
+        let task = db.add_task(&new_task("Loose", None)).unwrap();
+        let patch: TaskPatch = serde_json::from_str(r#"{"project_id": "f"}"#).unwrap();
+        assert!(db
+            .update_task(&task.id, &patch)
+            .unwrap_err()
+            .contains("Folders"));
+        assert_eq!(db.get_task(&task.id).unwrap().project_id, None);
     }
 }
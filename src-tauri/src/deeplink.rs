@@ -0,0 +1,385 @@
+//! Parser for `focusflow://` deep links.
+//!
+//! Supported forms:
+//!   focusflow://add?title=...&deadline=...&scheduled=...&project=...&priority=...&tags=a,b&description=...
+//!   focusflow://task/<id>
+//!   focusflow://focus/<id>
+//!
+//! Parsing is strict: unknown hosts, unknown or repeated query keys and
+//! malformed values are rejected instead of being silently dropped.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use url::Url;
+
+use crate::database::validate_scheduled;
+use crate::models::{NewTask, Priority, Project, Status};
+
+pub const SCHEME: &str = "focusflow";
+
+const MAX_TITLE_LEN: usize = 500;
+const MAX_DESCRIPTION_LEN: usize = 10_000;
+const MAX_TAGS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddTaskLink {
+    pub title: String,
+    pub description: Option<String>,
+    pub deadline: Option<i64>,     // UNIX ms
+    pub scheduled: Option<String>, // "YYYY-MM-DD" or "YYYY-MM-DDTHH:MM"
+    pub project: Option<String>,   // project id or name, resolved by the caller
+    pub priority: Priority,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLink {
+    AddTask(AddTaskLink),
+    OpenTask(String),
+    Focus(String),
+}
+
+impl AddTaskLink {
+    /// Builds the payload for `AppDatabase::add_task` once the project has been resolved.
+    pub fn into_new_task(self, project_id: Option<String>) -> NewTask {
+        NewTask {
+            id: uuid::Uuid::new_v4().to_string(),
+            project_id,
+            title: self.title,
+            description: self.description,
+            priority: self.priority,
+            status: Status::Todo,
+            created_at: chrono::Utc::now().timestamp_millis(),
+            deadline: self.deadline,
+            estimated_minutes: None,
+            actual_minutes: None,
+            tags: self.tags,
+            remind_at: None,
+            repeat_mode: None,
+            repeat_days_mask: None,
+            section_id: None,
+            scheduled_at: self.scheduled,
+        }
+    }
+}
+
+/// Matches the `project` parameter against a project id first, then a
+/// case-insensitive project name. Folders cannot hold tasks and are skipped.
+pub fn resolve_project(projects: &[Project], key: &str) -> Result<String, String> {
+    if let Some(p) = projects.iter().find(|p| !p.is_folder && p.id == key) {
+        return Ok(p.id.clone());
+    }
+    let wanted = key.to_lowercase();
+    let mut matches = projects
+        .iter()
+        .filter(|p| !p.is_folder && p.name.to_lowercase() == wanted);
+    match (matches.next(), matches.next()) {
+        (Some(p), None) => Ok(p.id.clone()),
+        (Some(_), Some(_)) => Err(format!("Project name is ambiguous: {}", key)),
+        (None, _) => Err(format!("Project not found: {}", key)),
+    }
+}
+
+pub fn parse(raw: &str) -> Result<DeepLink, String> {
+    let url = Url::parse(raw.trim()).map_err(|e| format!("Invalid deep link: {}", e))?;
+
+    if url.scheme() != SCHEME {
+        return Err(format!("Unsupported scheme: {}", url.scheme()));
+    }
+    if url.fragment().is_some() {
+        return Err("Deep links must not contain a fragment".to_string());
+    }
+
+    let host = url
+        .host_str()
+        .ok_or_else(|| "Deep link has no action".to_string())?;
+    let segments: Vec<&str> = url.path().split('/').filter(|s| !s.is_empty()).collect();
+
+    match host {
+        "add" => {
+            if !segments.is_empty() {
+                return Err("focusflow://add does not take a path".to_string());
+            }
+            parse_add(&url).map(DeepLink::AddTask)
+        }
+        "task" => {
+            reject_query(&url)?;
+            parse_task_id(&segments).map(DeepLink::OpenTask)
+        }
+        "focus" => {
+            reject_query(&url)?;
+            parse_task_id(&segments).map(DeepLink::Focus)
+        }
+        other => Err(format!("Unknown deep link action: {}", other)),
+    }
+}
+
+fn reject_query(url: &Url) -> Result<(), String> {
+    match url.query() {
+        Some(q) if !q.is_empty() => {
+            Err("This deep link does not take query parameters".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn parse_task_id(segments: &[&str]) -> Result<String, String> {
+    match segments {
+        [id] => uuid::Uuid::parse_str(id)
+            .map(|u| u.to_string())
+            .map_err(|_| format!("Invalid task id: {}", id)),
+        [] => Err("Missing task id".to_string()),
+        _ => Err("Expected exactly one task id".to_string()),
+    }
+}
+
+fn parse_add(url: &Url) -> Result<AddTaskLink, String> {
+    let mut title: Option<String> = None;
+    let mut description: Option<String> = None;
+    let mut deadline: Option<i64> = None;
+    let mut scheduled: Option<String> = None;
+    let mut project: Option<String> = None;
+    let mut priority: Option<Priority> = None;
+    let mut tags: Option<Vec<String>> = None;
+
+    for (key, value) in url.query_pairs() {
+        let value = value.trim().to_string();
+        let duplicate = match key.as_ref() {
+            "title" => title.replace(value).is_some(),
+            "description" => description.replace(value).is_some(),
+            "deadline" => deadline.replace(parse_deadline(&value)?).is_some(),
+            "scheduled" => {
+                validate_scheduled(&value)?;
+                scheduled.replace(value).is_some()
+            }
+            "project" => project.replace(value).is_some(),
+            "priority" => priority.replace(parse_priority(&value)?).is_some(),
+            "tags" => tags.replace(parse_tags(&value)?).is_some(),
+            other => return Err(format!("Unknown parameter: {}", other)),
+        };
+        if duplicate {
+            return Err(format!("Parameter given more than once: {}", key));
+        }
+    }
+
+    let title = title.ok_or_else(|| "Missing required parameter: title".to_string())?;
+    if title.is_empty() {
+        return Err("Title must not be empty".to_string());
+    }
+    if title.chars().count() > MAX_TITLE_LEN {
+        return Err(format!("Title is longer than {} characters", MAX_TITLE_LEN));
+    }
+
+    let description = description.filter(|d| !d.is_empty());
+    if let Some(d) = &description {
+        if d.chars().count() > MAX_DESCRIPTION_LEN {
+            return Err(format!(
+                "Description is longer than {} characters",
+                MAX_DESCRIPTION_LEN
+            ));
+        }
+    }
+
+    let project = match project {
+        Some(p) if p.is_empty() => return Err("Project must not be empty".to_string()),
+        p => p,
+    };
+
+    Ok(AddTaskLink {
+        title,
+        description,
+        deadline,
+        scheduled,
+        project,
+        priority: priority.unwrap_or(Priority::Normal),
+        tags: tags.unwrap_or_default(),
+    })
+}
+
+fn parse_priority(value: &str) -> Result<Priority, String> {
+    match value {
+        "low" => Ok(Priority::Low),
+        "normal" => Ok(Priority::Normal),
+        "high" => Ok(Priority::High),
+        other => Err(format!("Invalid priority: {}", other)),
+    }
+}
+
+fn parse_tags(value: &str) -> Result<Vec<String>, String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in value.split(',').map(str::trim) {
+        if tag.is_empty() {
+            return Err("Tags must not be empty".to_string());
+        }
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    if tags.len() > MAX_TAGS {
+        return Err(format!("At most {} tags are allowed", MAX_TAGS));
+    }
+    Ok(tags)
+}
+
+/// Accepts UNIX milliseconds, an RFC 3339 timestamp, a local `YYYY-MM-DDTHH:MM`
+/// or a bare local `YYYY-MM-DD` (which defaults to 18:00, like the quick-add parser).
+pub fn parse_deadline(value: &str) -> Result<i64, String> {
+    if value.is_empty() {
+        return Err("Deadline must not be empty".to_string());
+    }
+    if value.bytes().all(|b| b.is_ascii_digit()) {
+        return value
+            .parse::<i64>()
+            .map_err(|_| format!("Invalid deadline: {}", value));
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.timestamp_millis());
+    }
+    let naive = if let Ok(ndt) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M") {
+        ndt
+    } else if let Ok(d) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        d.and_hms_opt(18, 0, 0).unwrap()
+    } else {
+        return Err(format!("Invalid deadline: {}", value));
+    };
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.timestamp_millis())
+        .ok_or_else(|| format!("Deadline does not exist in local time: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "5f0c6a52-1f2b-4a8e-9c43-2d7f1e6b9a10";
+
+    #[test]
+    fn test_parse_add_full() {
+        let link = parse(
+            "focusflow://add?title=Buy%20milk&deadline=1700000000000&scheduled=2023-11-13&project=Home&priority=high&tags=errands,%20shop",
+        )
+        .unwrap();
+        assert_eq!(
+            link,
+            DeepLink::AddTask(AddTaskLink {
+                title: "Buy milk".to_string(),
+                description: None,
+                deadline: Some(1_700_000_000_000),
+                scheduled: Some("2023-11-13".to_string()),
+                project: Some("Home".to_string()),
+                priority: Priority::High,
+                tags: vec!["errands".to_string(), "shop".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_add_defaults() {
+        match parse("focusflow://add?title=Call+mom").unwrap() {
+            DeepLink::AddTask(a) => {
+                assert_eq!(a.title, "Call mom");
+                assert_eq!(a.priority, Priority::Normal);
+                assert!(a.deadline.is_none());
+                assert!(a.project.is_none());
+                assert!(a.tags.is_empty());
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_add_rejects_bad_input() {
+        assert!(parse("focusflow://add").is_err());
+        assert!(parse("focusflow://add?title=").is_err());
+        assert!(parse("focusflow://add?title=a&title=b").is_err());
+        assert!(parse("focusflow://add?title=a&colour=red").is_err());
+        assert!(parse("focusflow://add?title=a&priority=urgent").is_err());
+        assert!(parse("focusflow://add?title=a&deadline=tomorrow").is_err());
+        assert!(parse("focusflow://add?title=a&scheduled=2024-05-01T25:00").is_err());
+        assert!(parse("focusflow://add?title=a&tags=x,,y").is_err());
+        assert!(parse("focusflow://add?title=a&project=").is_err());
+        assert!(parse("focusflow://add/extra?title=a").is_err());
+        let long = "x".repeat(MAX_TITLE_LEN + 1);
+        assert!(parse(&format!("focusflow://add?title={}", long)).is_err());
+    }
+
+    #[test]
+    fn test_parse_deadline_formats() {
+        assert_eq!(
+            parse_deadline("2024-05-01T10:00:00Z").unwrap(),
+            1_714_557_600_000
+        );
+        assert_eq!(
+            parse_deadline("2024-05-01T12:00:00+02:00").unwrap(),
+            1_714_557_600_000
+        );
+
+        let expected = Local
+            .from_local_datetime(
+                &NaiveDate::from_ymd_opt(2024, 5, 1)
+                    .unwrap()
+                    .and_hms_opt(18, 0, 0)
+                    .unwrap(),
+            )
+            .earliest()
+            .unwrap()
+            .timestamp_millis();
+        assert_eq!(parse_deadline("2024-05-01").unwrap(), expected);
+        assert!(parse_deadline("2024-13-01").is_err());
+    }
+
+    #[test]
+    fn test_parse_task_and_focus() {
+        assert_eq!(
+            parse(&format!("focusflow://task/{}", ID)).unwrap(),
+            DeepLink::OpenTask(ID.to_string())
+        );
+        assert_eq!(
+            parse(&format!("focusflow://focus/{}/", ID)).unwrap(),
+            DeepLink::Focus(ID.to_string())
+        );
+        assert!(parse("focusflow://task/").is_err());
+        assert!(parse("focusflow://task/not-a-uuid").is_err());
+        assert!(parse(&format!("focusflow://task/{}/{}", ID, ID)).is_err());
+        assert!(parse(&format!("focusflow://focus/{}?start=1", ID)).is_err());
+    }
+
+    #[test]
+    fn test_resolve_project() {
+        let project = |id: &str, name: &str, is_folder: bool| Project {
+            id: id.to_string(),
+            name: name.to_string(),
+            color: "#fff".to_string(),
+            priority: Priority::Normal,
+            created_at: 0,
+            parent_id: None,
+            is_folder,
+            sort_order: 0,
+            description: None,
+            icon: None,
+            archived_at: None,
+            default_priority: None,
+            default_tags: Vec::new(),
+        };
+        let projects = vec![
+            project("p1", "Home", false),
+            project("p2", "Work", false),
+            project("p3", "work", false),
+            project("f1", "Archive", true),
+        ];
+        assert_eq!(resolve_project(&projects, "p2").unwrap(), "p2");
+        assert_eq!(resolve_project(&projects, "home").unwrap(), "p1");
+        assert!(resolve_project(&projects, "Work").is_err()); // ambiguous
+        assert!(resolve_project(&projects, "Archive").is_err()); // folder
+        assert!(resolve_project(&projects, "Garden").is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_foreign_urls() {
+        assert!(parse("https://add?title=a").is_err());
+        assert!(parse("focusflow://delete/x").is_err());
+        assert!(parse(&format!("focusflow://task/{}#frag", ID)).is_err());
+        assert!(parse("not a url").is_err());
+    }
+}
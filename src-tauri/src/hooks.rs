@@ -0,0 +1,331 @@
+//! Outgoing hooks: on selected events the app POSTs a JSON payload to a URL or
+//! pipes it to a local command. Deliveries run on a background thread with
+//! retries and a timeout; the outcome of each one is handed back for logging.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::models::{Hook, HookDelivery};
+
+pub const EVENTS: &[&str] = &[
+    "task.created",
+    "task.completed",
+    "reminder.fired",
+    "focus.finished",
+    "debt.paid",
+    "achievement.unlocked",
+];
+
+/// Sent by "Test hook" in settings; not subscribable.
+pub const TEST_EVENT: &str = "hook.test";
+
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub timeout: Duration,
+    /// Delay before the second attempt; doubles after each failure.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            timeout: Duration::from_secs(10),
+            backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+pub fn validate(event: &str, kind: &str, target: &str) -> Result<(), String> {
+    if event != "*" && !EVENTS.contains(&event) {
+        return Err(format!("Unknown hook event: {}", event));
+    }
+    match kind {
+        "http" => {
+            let url = url::Url::parse(target).map_err(|e| format!("Invalid URL: {}", e))?;
+            if url.scheme() != "http" && url.scheme() != "https" {
+                return Err("Hook URL must use http or https".to_string());
+            }
+            Ok(())
+        }
+        "command" if target.trim().is_empty() => Err("Command must not be empty".to_string()),
+        "command" => Ok(()),
+        other => Err(format!("Unknown hook kind: {}", other)),
+    }
+}
+
+pub fn payload(event: &str, data: Value) -> String {
+    json!({
+        "event": event,
+        "timestamp": chrono::Utc::now().timestamp_millis(),
+        "data": data,
+    })
+    .to_string()
+}
+
+/// Outcome of one attempt: the HTTP status (if any) and an error on failure.
+type Attempt = (Option<u16>, Result<(), String>);
+
+fn post(url: &str, event: &str, body: &str, timeout: Duration) -> Attempt {
+    let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+    match agent
+        .post(url)
+        .set("Content-Type", "application/json")
+        .set("X-FocusFlow-Event", event)
+        .send_string(body)
+    {
+        Ok(resp) => (Some(resp.status()), Ok(())),
+        Err(ureq::Error::Status(code, _)) => (Some(code), Err(format!("HTTP {}", code))),
+        Err(e) => (None, Err(e.to_string())),
+    }
+}
+
+/// Runs `command_line` through the platform shell with the payload on stdin
+/// and the event name in `FOCUSFLOW_EVENT`.
+fn run_command(command_line: &str, event: &str, body: &str, timeout: Duration) -> Attempt {
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut c = Command::new("cmd");
+        c.args(["/C", command_line]);
+        c
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut c = Command::new("sh");
+        c.args(["-c", command_line]);
+        c
+    };
+    cmd.env("FOCUSFLOW_EVENT", event)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    let mut child = match cmd.spawn() {
+        Ok(c) => c,
+        Err(e) => return (None, Err(format!("Failed to start command: {}", e))),
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let body = body.to_string();
+        // Separate thread so a command that never reads stdin can't block us
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(body.as_bytes());
+        });
+    }
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return (None, Ok(())),
+            Ok(Some(status)) => return (None, Err(format!("Command failed: {}", status))),
+            Ok(None) => {}
+            Err(e) => return (None, Err(e.to_string())),
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return (
+                None,
+                Err(format!(
+                    "Command timed out after {} ms",
+                    timeout.as_millis()
+                )),
+            );
+        }
+        std::thread::sleep(Duration::from_millis(25));
+    }
+}
+
+/// Delivers `body` to one hook, retrying failed attempts.
+pub fn deliver(hook: &Hook, event: &str, body: &str, policy: &RetryPolicy) -> HookDelivery {
+    let started = Instant::now();
+    let mut backoff = policy.backoff;
+    let mut attempts = 0;
+    let (status_code, result) = loop {
+        attempts += 1;
+        let outcome = match hook.kind.as_str() {
+            "http" => post(&hook.target, event, body, policy.timeout),
+            "command" => run_command(&hook.target, event, body, policy.timeout),
+            other => (None, Err(format!("Unknown hook kind: {}", other))),
+        };
+        if outcome.1.is_ok() || attempts >= policy.attempts.max(1) {
+            break outcome;
+        }
+        std::thread::sleep(backoff);
+        backoff *= 2;
+    };
+
+    HookDelivery {
+        id: uuid::Uuid::new_v4().to_string(),
+        hook_id: hook.id.clone(),
+        event: event.to_string(),
+        payload: body.to_string(),
+        attempts,
+        success: result.is_ok(),
+        status_code,
+        error: result.err(),
+        duration_ms: started.elapsed().as_millis() as i64,
+        created_at: chrono::Utc::now().timestamp_millis(),
+    }
+}
+
+/// Delivers `event` to every hook on a background thread, calling
+/// `on_delivery` with each result.
+pub fn dispatch<F>(hooks: Vec<Hook>, event: &str, data: Value, policy: RetryPolicy, on_delivery: F)
+where
+    F: Fn(HookDelivery) + Send + 'static,
+{
+    if hooks.is_empty() {
+        return;
+    }
+    let event = event.to_string();
+    let body = payload(&event, data);
+    std::thread::spawn(move || {
+        for hook in &hooks {
+            on_delivery(deliver(hook, &event, &body, &policy));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+
+    fn hook(kind: &str, target: &str) -> Hook {
+        Hook {
+            id: "h1".to_string(),
+            event: "*".to_string(),
+            kind: kind.to_string(),
+            target: target.to_string(),
+            enabled: true,
+            created_at: 0,
+        }
+    }
+
+    fn quick_policy() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            timeout: Duration::from_millis(500),
+            backoff: Duration::from_millis(10),
+        }
+    }
+
+    /// Answers each connection with the next status code and returns the
+    /// request bodies it received.
+    fn stub_server(statuses: Vec<u16>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        content_length = v.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                bodies.push(String::from_utf8(body).unwrap());
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 {} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                )
+                .unwrap();
+            }
+            bodies
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("task.completed", "http", "https://example.com/x").is_ok());
+        assert!(validate("*", "command", "notify-send done").is_ok());
+        assert!(validate("task.deleted", "http", "https://example.com").is_err());
+        assert!(validate("task.created", "http", "file:///etc/passwd").is_err());
+        assert!(validate("task.created", "command", "  ").is_err());
+        assert!(validate("task.created", "email", "a@b.c").is_err());
+    }
+
+    #[test]
+    fn test_http_delivery_retries_until_success() {
+        let (url, server) = stub_server(vec![500, 503, 200]);
+        let body = payload("task.completed", json!({ "id": "t1" }));
+        let delivery = deliver(
+            &hook("http", &url),
+            "task.completed",
+            &body,
+            &quick_policy(),
+        );
+
+        assert!(delivery.success);
+        assert_eq!(delivery.attempts, 3);
+        assert_eq!(delivery.status_code, Some(200));
+        let received = server.join().unwrap();
+        let sent: Value = serde_json::from_str(&received[2]).unwrap();
+        assert_eq!(sent["event"], "task.completed");
+        assert_eq!(sent["data"]["id"], "t1");
+    }
+
+    #[test]
+    fn test_http_delivery_gives_up() {
+        let (url, server) = stub_server(vec![500, 500, 500]);
+        let delivery = deliver(&hook("http", &url), "debt.paid", "{}", &quick_policy());
+        server.join().unwrap();
+
+        assert!(!delivery.success);
+        assert_eq!(delivery.attempts, 3);
+        assert_eq!(delivery.status_code, Some(500));
+        assert_eq!(delivery.error.as_deref(), Some("HTTP 500"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_delivery() {
+        let out = std::env::temp_dir().join(format!("focusflow-hook-{}", uuid::Uuid::new_v4()));
+        let cmd = format!(
+            "cat > '{}'; echo \"$FOCUSFLOW_EVENT\" >> '{}'",
+            out.display(),
+            out.display()
+        );
+        let delivery = deliver(
+            &hook("command", &cmd),
+            "focus.finished",
+            "{\"a\":1}",
+            &quick_policy(),
+        );
+
+        assert!(delivery.success, "{:?}", delivery.error);
+        let written = std::fs::read_to_string(&out).unwrap();
+        let _ = std::fs::remove_file(&out);
+        assert_eq!(written, "{\"a\":1}focus.finished\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_timeout() {
+        let policy = RetryPolicy {
+            attempts: 1,
+            timeout: Duration::from_millis(100),
+            backoff: Duration::ZERO,
+        };
+        let delivery = deliver(&hook("command", "sleep 5"), "task.created", "{}", &policy);
+
+        assert!(!delivery.success);
+        assert!(delivery.error.unwrap().contains("timed out"));
+        assert!(delivery.duration_ms < 5000);
+    }
+}
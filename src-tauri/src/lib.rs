@@ -1,34 +1,112 @@
 //! Library entrypoint for Tauri mobile builds.
 //! On mobile, this is the entry point. On desktop, main.rs calls into this.
 
-// Re-export everything from main module
-mod database;
-mod models;
-
+// `database`, `deeplink` and `models` are public so `focusflow-cli` can reuse them.
+mod api;
+pub mod database;
+pub mod deeplink;
+mod hooks;
+pub mod models;
+
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
 use tauri::{Emitter, Manager, State, WindowEvent};
-
-use database::AppDatabase;
-use models::{AppSettings, NewTask, Priority, Project, Status, Subtask, Task, UserStats};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use chrono::Datelike;
+use database::{AppDatabase, UndoStep};
+use deeplink::DeepLink;
+use models::{
+    Agenda, ApiSettings, AppSettings, AutoScheduleResult, Board, BoardColumnInput,
+    BulkUpdateResult, DailyActivity, DailyPlan, EstimateCorrection, EstimateReport, ExportBundle,
+    GamificationRules, GamificationStatus, Hook, HookDelivery, InstantiatedTemplate, NewTask,
+    PointsEntry, Priority, Project, ProjectDeletePolicy, ProjectDeleteSummary, ProjectNode,
+    ProjectPatch, Section, Snapshot, Status, StreakStatus, Subtask, Tag, Task, TaskBulkPatch,
+    TaskEvent, TaskPatch, Template, TemplateInstance, TimeBlock, TimeBlockCompletion, TimeEntry,
+    TimeEntryPatch, TimeRollup, TimeRollupGroup, TimesheetEntry, TrashItem, UndoStatus, UserStats,
+};
 
 struct AppState {
     db: Mutex<AppDatabase>,
+    api: Mutex<Option<api::ApiServer>>,
 }
 
+/// Sequence number of the last `data:changed` event; a gap on the frontend
+/// means an event was missed and lists should be refetched.
+static CHANGE_SEQ: AtomicU64 = AtomicU64::new(0);
+
 #[derive(serde::Serialize, Clone)]
 struct DataChanged {
+    seq: u64,
+    entity: &'static str,
+    action: &'static str,
+    id: Option<String>,
+    old: Option<Snapshot>,
+    new: Option<Snapshot>,
+}
+
+fn emit_change(
+    app: &tauri::AppHandle,
     entity: &'static str,
     action: &'static str,
     id: Option<String>,
+    old: Option<Snapshot>,
+    new: Option<Snapshot>,
+) {
+    let seq = CHANGE_SEQ.fetch_add(1, Ordering::SeqCst) + 1;
+    let _ = app.emit(
+        "data:changed",
+        DataChanged {
+            seq,
+            entity,
+            action,
+            id,
+            old,
+            new,
+        },
+    );
 }
 
+/// Change without snapshots ("refresh" events and non-entity updates).
 fn emit_data_changed(
     app: &tauri::AppHandle,
     entity: &'static str,
     action: &'static str,
     id: Option<String>,
 ) {
-    let _ = app.emit("data:changed", DataChanged { entity, action, id });
+    emit_change(app, entity, action, id, None, None);
+}
+
+/// Change to a single entity; `old` is its snapshot from before the change.
+fn emit_entity_changed(
+    app: &tauri::AppHandle,
+    db: &AppDatabase,
+    entity: &'static str,
+    action: &'static str,
+    id: &str,
+    old: Option<Snapshot>,
+) {
+    let new = db.snapshot(entity, Some(id));
+    emit_change(app, entity, action, Some(id.to_string()), old, new);
+}
+
+/// Emits an `unblocked` change for each task from `blocked_dependents` that
+/// is no longer blocked.
+fn emit_unblocked(
+    app: &tauri::AppHandle,
+    db: &AppDatabase,
+    before: Vec<(String, Option<Snapshot>)>,
+) {
+    for (id, old) in before {
+        if db.get_task(&id).is_ok_and(|t| !t.is_blocked) {
+            emit_entity_changed(app, db, "tasks", "unblocked", &id, old);
+        }
+    }
+}
+
+#[tauri::command]
+fn get_change_seq() -> u64 {
+    CHANGE_SEQ.load(Ordering::SeqCst)
 }
 
 #[derive(serde::Serialize, Clone)]
@@ -38,7 +116,7 @@ struct ReminderPayload {
     deadline: Option<i64>,
 }
 
-// --- All tauri commands from main.rs ---
+// --- DB HEALTH ---
 
 #[derive(serde::Serialize)]
 struct DbHealth {
@@ -77,29 +155,12 @@ async fn db_health(state: State<'_, AppState>) -> Result<DbHealth, String> {
     })
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
-struct ExportBundle {
-    version: u32,
-    exported_at: i64,
-    projects: Vec<Project>,
-    tasks: Vec<Task>,
-    settings: AppSettings,
-}
+// --- EXPORT BACKUP ---
 
 #[tauri::command]
 async fn export_data(state: State<'_, AppState>) -> Result<ExportBundle, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    let projects = db.get_projects().map_err(|e| e.to_string())?;
-    let tasks = db.get_tasks(None, None, None).map_err(|e| e.to_string())?;
-    let settings = db.get_settings().map_err(|e| e.to_string())?;
-
-    Ok(ExportBundle {
-        version: 1,
-        exported_at: chrono::Utc::now().timestamp_millis(),
-        projects,
-        tasks,
-        settings,
-    })
+    db.export_bundle()
 }
 
 #[tauri::command]
@@ -111,17 +172,32 @@ async fn import_data(
     let bundle: ExportBundle =
         serde_json::from_str(&bundle_json).map_err(|e| format!("Invalid backup file: {}", e))?;
 
-    let mut db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.import_data(bundle.projects, bundle.tasks, bundle.settings)
-        .map_err(|e| e.to_string())?;
-
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Import data",
+    )?;
+    db.import_data(
+        bundle.projects,
+        bundle.sections,
+        bundle.tasks,
+        bundle.tags,
+        bundle.boards,
+        bundle.settings,
+    )
+    .map_err(|e| e.to_string())?;
+
+    // Refresh everything
     emit_data_changed(&app, "tasks", "refresh", None);
     emit_data_changed(&app, "projects", "refresh", None);
+    emit_data_changed(&app, "sections", "refresh", None);
+    emit_data_changed(&app, "tags", "refresh", None);
     emit_data_changed(&app, "settings", "refresh", None);
     emit_data_changed(&app, "stats", "refresh", None);
     Ok(())
 }
 
+// --- COMPLETION SERIES ---
+
 #[derive(serde::Serialize)]
 struct CompletionDay {
     day: String,
@@ -141,6 +217,8 @@ async fn get_completion_series(
         .collect())
 }
 
+// --- SETTINGS ---
+
 #[tauri::command]
 async fn get_settings(state: State<'_, AppState>) -> Result<AppSettings, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
@@ -153,260 +231,1921 @@ async fn save_settings(
     app: tauri::AppHandle,
     settings: AppSettings,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Save settings",
+    )?;
+    let old = db.snapshot("settings", None);
     db.save_settings(settings).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "settings", "edit", None);
+    emit_change(
+        &app,
+        "settings",
+        "edit",
+        None,
+        old,
+        db.snapshot("settings", None),
+    );
+    // The daily goal counts toward today's streak
+    emit_data_changed(&app, "stats", "refresh", None);
+    Ok(())
+}
+
+// --- LOCAL API ---
+
+fn start_api_server(
+    app: &tauri::AppHandle,
+    settings: &ApiSettings,
+) -> Result<api::ApiServer, String> {
+    let handle = app.clone();
+    api::start(
+        settings.port,
+        settings.token.clone(),
+        move |method, url, body| {
+            let state = handle.state::<AppState>();
+            let mut db = match state.db.lock() {
+                Ok(g) => g,
+                Err(_) => return api::ApiResponse::error(500, "Failed to lock db"),
+            };
+            let (response, changes) = api::handle(&mut db, method, url, body);
+            for change in changes {
+                fire_api_change_hooks(&handle, &db, &change);
+                let new = db.snapshot(change.entity, change.id.as_deref());
+                emit_change(
+                    &handle,
+                    change.entity,
+                    change.action,
+                    change.id,
+                    change.old,
+                    new,
+                );
+            }
+            emit_achievements(&handle, &db);
+            response
+        },
+    )
+}
+
+/// Stops the running server (if any) and starts it again when enabled.
+fn apply_api_settings(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    settings: &ApiSettings,
+) -> Result<(), String> {
+    let mut slot = state.api.lock().map_err(|_| "Failed to lock api")?;
+    if let Some(server) = slot.take() {
+        server.stop();
+    }
+    if settings.enabled {
+        let server = start_api_server(app, settings)?;
+        println!("[FocusFlow] Local API listening on http://{}", server.addr);
+        *slot = Some(server);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_api_settings(state: State<'_, AppState>) -> Result<ApiSettings, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_api_settings().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn save_api_settings(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    enabled: bool,
+    port: u16,
+) -> Result<ApiSettings, String> {
+    let (old, settings) = {
+        let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+        let old = db.snapshot("api_settings", None);
+        (
+            old,
+            db.save_api_settings(enabled, port)
+                .map_err(|e| e.to_string())?,
+        )
+    };
+    apply_api_settings(&app, &state, &settings)?;
+    emit_change(
+        &app,
+        "api_settings",
+        "edit",
+        None,
+        old,
+        Some(Snapshot::ApiSettings(settings.clone())),
+    );
+    Ok(settings)
+}
+
+#[tauri::command]
+async fn regenerate_api_token(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<ApiSettings, String> {
+    let (old, settings) = {
+        let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+        let old = db.snapshot("api_settings", None);
+        (old, db.regenerate_api_token().map_err(|e| e.to_string())?)
+    };
+    apply_api_settings(&app, &state, &settings)?;
+    emit_change(
+        &app,
+        "api_settings",
+        "edit",
+        None,
+        old,
+        Some(Snapshot::ApiSettings(settings.clone())),
+    );
+    Ok(settings)
+}
+
+// --- HOOKS ---
+
+/// Queues `event` for every enabled hook subscribed to it. Takes the already
+/// locked db; delivery happens on a background thread.
+fn fire_hook(app: &tauri::AppHandle, db: &AppDatabase, event: &str, data: serde_json::Value) {
+    let subscribed = match db.get_hooks_for_event(event) {
+        Ok(h) => h,
+        Err(_) => return,
+    };
+    let handle = app.clone();
+    hooks::dispatch(
+        subscribed,
+        event,
+        data,
+        hooks::RetryPolicy::default(),
+        move |delivery| {
+            let state = handle.state::<AppState>();
+            if let Ok(db) = state.db.lock() {
+                let _ = db.log_hook_delivery(&delivery);
+            }
+            emit_data_changed(&handle, "hooks", "delivery", Some(delivery.hook_id));
+        },
+    );
+}
+
+/// Announces achievements unlocked since the last call: a `data:changed`
+/// "unlock" and the `achievement.unlocked` hook for each.
+fn emit_achievements(app: &tauri::AppHandle, db: &AppDatabase) {
+    let Ok(unlocked) = db.take_unannounced_achievements() else {
+        return;
+    };
+    for achievement in unlocked {
+        fire_hook(
+            app,
+            db,
+            "achievement.unlocked",
+            serde_json::json!(achievement),
+        );
+        emit_change(
+            app,
+            "achievements",
+            "unlock",
+            Some(achievement.id.clone()),
+            None,
+            Some(Snapshot::Achievement(achievement)),
+        );
+    }
+}
+
+/// Hook events for changes made through the local API.
+fn fire_api_change_hooks(app: &tauri::AppHandle, db: &AppDatabase, change: &api::ApiChange) {
+    let Some(id) = change.id.as_deref() else {
+        return;
+    };
+    match (change.entity, change.action) {
+        ("tasks", "add") => {
+            if let Ok(task) = db.get_task(id) {
+                fire_hook(app, db, "task.created", serde_json::json!(task));
+            }
+        }
+        ("tasks", "status") => {
+            if let Ok(task) = db.get_task(id) {
+                if task.status == Status::Done {
+                    fire_hook(app, db, "task.completed", serde_json::json!(task));
+                }
+            }
+        }
+        ("finance", "pay_debt") => {
+            if let Ok((_, debts)) = db.get_finance_summary() {
+                if let Some(debt) = debts.into_iter().find(|d| d.id == id) {
+                    fire_hook(app, db, "debt.paid", serde_json::json!(debt));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[tauri::command]
+async fn get_hooks(state: State<'_, AppState>) -> Result<Vec<Hook>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_hooks()
+}
+
+#[tauri::command]
+async fn add_hook(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    event: String,
+    kind: String,
+    target: String,
+) -> Result<Hook, String> {
+    hooks::validate(&event, &kind, &target)?;
+    let hook = Hook {
+        id: uuid::Uuid::new_v4().to_string(),
+        event,
+        kind,
+        target: target.trim().to_string(),
+        enabled: true,
+        created_at: chrono::Utc::now().timestamp_millis(),
+    };
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Add hook",
+    )?;
+    db.add_hook(&hook)?;
+    emit_entity_changed(&app, &db, "hooks", "add", &hook.id, None);
+    Ok(hook)
+}
+
+#[tauri::command]
+async fn set_hook_enabled(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Set hook enabled",
+    )?;
+    let old = db.snapshot("hooks", Some(&id));
+    db.set_hook_enabled(&id, enabled)?;
+    emit_entity_changed(&app, &db, "hooks", "edit", &id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn delete_hook(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Delete hook",
+    )?;
+    let old = db.snapshot("hooks", Some(&id));
+    db.delete_hook(&id)?;
+    emit_entity_changed(&app, &db, "hooks", "delete", &id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_hook_deliveries(
+    state: State<'_, AppState>,
+    hook_id: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<HookDelivery>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_hook_deliveries(hook_id.as_deref(), limit.unwrap_or(50))
+}
+
+/// Sends a `hook.test` event to one hook, even if it is disabled.
+#[tauri::command]
+async fn test_hook(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<(), String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let hook = db.get_hook(&id)?;
+    let handle = app.clone();
+    hooks::dispatch(
+        vec![hook],
+        hooks::TEST_EVENT,
+        serde_json::json!({ "message": "FocusFlow test event" }),
+        hooks::RetryPolicy::default(),
+        move |delivery| {
+            let state = handle.state::<AppState>();
+            if let Ok(db) = state.db.lock() {
+                let _ = db.log_hook_delivery(&delivery);
+            }
+            emit_data_changed(&handle, "hooks", "delivery", Some(delivery.hook_id));
+        },
+    );
+    Ok(())
+}
+
+// --- REMINDERS ---
+
+#[tauri::command]
+async fn set_task_remind_at(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    remind_at: Option<i64>,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Set task remind at",
+    )?;
+    let old = db.snapshot("tasks", Some(&id));
+    db.set_task_remind_at(&id, remind_at)
+        .map_err(|e| e.to_string())?;
+    emit_entity_changed(&app, &db, "tasks", "edit", &id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn snooze_task_reminder(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    minutes: i64,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Snooze task reminder",
+    )?;
+    let old = db.snapshot("tasks", Some(&id));
+    db.snooze_task(&id, minutes).map_err(|e| e.to_string())?;
+    emit_entity_changed(&app, &db, "tasks", "edit", &id, old);
+    Ok(())
+}
+
+// --- PROJECTS ---
+
+#[tauri::command]
+async fn get_projects(
+    state: State<'_, AppState>,
+    include_archived: Option<bool>,
+) -> Result<Vec<Project>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    if include_archived.unwrap_or(false) {
+        db.get_all_projects()
+    } else {
+        db.get_projects()
+    }
+}
+
+#[tauri::command]
+async fn add_project(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    name: String,
+    color: String,
+    priority: String,
+    parent_id: Option<String>,
+    is_folder: bool,
+) -> Result<Project, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Add project",
+    )?;
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let priority_enum = match priority.as_str() {
+        "high" => Priority::High,
+        "low" => Priority::Low,
+        _ => Priority::Normal,
+    };
+
+    let project = db
+        .add_project(id.clone(), name, color, priority_enum, parent_id, is_folder)
+        .map_err(|e| e.to_string())?;
+
+    emit_entity_changed(&app, &db, "projects", "add", &id, None);
+    emit_data_changed(&app, "stats", "refresh", None);
+    Ok(project)
+}
+
+#[tauri::command]
+async fn edit_project(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    name: String,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Edit project",
+    )?;
+    let old = db.snapshot("projects", Some(&id));
+    db.update_project(&id, name).map_err(|e| e.to_string())?;
+    emit_entity_changed(&app, &db, "projects", "edit", &id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn update_project_priority(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    priority: String,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Update project priority",
+    )?;
+    let old = db.snapshot("projects", Some(&id));
+    let priority_enum = match priority.as_str() {
+        "high" => Priority::High,
+        "low" => Priority::Low,
+        _ => Priority::Normal,
+    };
+    db.update_project_priority(&id, priority_enum)
+        .map_err(|e| e.to_string())?;
+    emit_entity_changed(&app, &db, "projects", "edit", &id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn delete_project(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    policy: Option<String>,
+) -> Result<ProjectDeleteSummary, String> {
+    // Default keeps children visible by moving them up a level
+    let policy = ProjectDeletePolicy::parse(policy.as_deref().unwrap_or("reparent"))?;
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Delete project",
+    )?;
+    let before: Vec<Project> = db.get_all_projects()?;
+    let summary = db.delete_project(&id, policy)?;
+
+    let old = |pid: &str| {
+        before
+            .iter()
+            .find(|p| p.id == pid)
+            .map(|p| Snapshot::Project(p.clone()))
+    };
+    for pid in &summary.deleted_project_ids {
+        emit_change(
+            &app,
+            "projects",
+            "delete",
+            Some(pid.clone()),
+            old(pid),
+            None,
+        );
+    }
+    for pid in &summary.reparented_project_ids {
+        emit_entity_changed(&app, &db, "projects", "move", pid, old(pid));
+    }
+    emit_data_changed(&app, "tasks", "refresh", None);
+    emit_data_changed(&app, "stats", "refresh", None);
+    Ok(summary)
+}
+
+#[tauri::command]
+async fn get_project_tree(state: State<'_, AppState>) -> Result<Vec<ProjectNode>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_project_tree()
+}
+
+#[tauri::command]
+async fn move_project(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    parent_id: Option<String>,
+) -> Result<(), String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Move project",
+    )?;
+    let old = db.snapshot("projects", Some(&id));
+    db.move_project(&id, parent_id.as_deref())?;
+    emit_entity_changed(&app, &db, "projects", "move", &id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn update_project_details(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    patch: ProjectPatch,
+) -> Result<Project, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Update project details",
+    )?;
+    let old = db.snapshot("projects", Some(&id));
+    let project = db.update_project_details(&id, &patch)?;
+    emit_entity_changed(&app, &db, "projects", "edit", &id, old);
+    Ok(project)
+}
+
+#[tauri::command]
+async fn reorder_projects(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    project_ids: Vec<String>,
+) -> Result<(), String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Reorder projects",
+    )?;
+    let old = db.snapshots("projects", &project_ids);
+    db.reorder_projects(&project_ids)?;
+    let new = db.snapshots("projects", &project_ids);
+    emit_change(&app, "projects", "reorder", None, Some(old), Some(new));
+    Ok(())
+}
+
+#[tauri::command]
+async fn archive_project(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<Vec<String>, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Archive project",
+    )?;
+    let before = db.get_all_projects()?;
+    let archived = db.archive_project(&id)?;
+    emit_projects_changed(&app, &db, "archive", &archived, &before);
+    emit_data_changed(&app, "tasks", "refresh", None);
+    emit_data_changed(&app, "stats", "refresh", None);
+    Ok(archived)
+}
+
+#[tauri::command]
+async fn unarchive_project(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<Vec<String>, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Unarchive project",
+    )?;
+    let before = db.get_all_projects()?;
+    let restored = db.unarchive_project(&id)?;
+    emit_projects_changed(&app, &db, "unarchive", &restored, &before);
+    emit_data_changed(&app, "tasks", "refresh", None);
+    emit_data_changed(&app, "stats", "refresh", None);
+    Ok(restored)
+}
+
+/// One event per project touched by a bulk operation; `before` is the
+/// project list from before it.
+fn emit_projects_changed(
+    app: &tauri::AppHandle,
+    db: &AppDatabase,
+    action: &'static str,
+    ids: &[String],
+    before: &[Project],
+) {
+    for id in ids {
+        let old = before
+            .iter()
+            .find(|p| p.id == *id)
+            .map(|p| Snapshot::Project(p.clone()));
+        emit_entity_changed(app, db, "projects", action, id, old);
+    }
+}
+
+// --- SECTIONS ---
+
+#[tauri::command]
+async fn get_sections(
+    state: State<'_, AppState>,
+    project_id: String,
+    include_archived: Option<bool>,
+) -> Result<Vec<Section>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_sections(&project_id, include_archived.unwrap_or(false))
+}
+
+#[tauri::command]
+async fn add_section(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    project_id: String,
+    name: String,
+) -> Result<Section, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Add section",
+    )?;
+    let section = db.add_section(&project_id, &name)?;
+    emit_entity_changed(&app, &db, "sections", "add", &section.id, None);
+    Ok(section)
+}
+
+#[tauri::command]
+async fn rename_section(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    name: String,
+) -> Result<Section, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Rename section",
+    )?;
+    let old = db.snapshot("sections", Some(&id));
+    let section = db.rename_section(&id, &name)?;
+    emit_entity_changed(&app, &db, "sections", "edit", &id, old);
+    Ok(section)
+}
+
+#[tauri::command]
+async fn reorder_sections(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    section_ids: Vec<String>,
+) -> Result<(), String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Reorder sections",
+    )?;
+    let old = db.snapshots("sections", &section_ids);
+    db.reorder_sections(&section_ids)?;
+    let new = db.snapshots("sections", &section_ids);
+    emit_change(&app, "sections", "reorder", None, Some(old), Some(new));
+    Ok(())
+}
+
+#[tauri::command]
+async fn archive_section(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<Vec<String>, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Archive section",
+    )?;
+    let old = db.snapshot("sections", Some(&id));
+    let task_ids = db.archive_section(&id)?;
+    emit_entity_changed(&app, &db, "sections", "archive", &id, old);
+    emit_data_changed(&app, "tasks", "refresh", None);
+    Ok(task_ids)
+}
+
+#[tauri::command]
+async fn unarchive_section(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<Vec<String>, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Unarchive section",
+    )?;
+    let old = db.snapshot("sections", Some(&id));
+    let task_ids = db.unarchive_section(&id)?;
+    emit_entity_changed(&app, &db, "sections", "unarchive", &id, old);
+    emit_data_changed(&app, "tasks", "refresh", None);
+    Ok(task_ids)
+}
+
+#[tauri::command]
+async fn move_task_to_section(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_id: String,
+    section_id: Option<String>,
+) -> Result<Task, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Move task to section",
+    )?;
+    let old = db.snapshot("tasks", Some(&task_id));
+    let task = db.move_task_to_section(&task_id, section_id.as_deref())?;
+    emit_entity_changed(&app, &db, "tasks", "move", &task_id, old);
+    Ok(task)
+}
+
+// --- BOARDS ---
+
+#[tauri::command]
+async fn get_boards(state: State<'_, AppState>, project_id: String) -> Result<Vec<Board>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_boards(&project_id)
+}
+
+#[tauri::command]
+async fn get_board(state: State<'_, AppState>, id: String) -> Result<Board, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_board(&id)
+}
+
+#[tauri::command]
+async fn create_board(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    project_id: String,
+    name: String,
+    columns: Option<Vec<BoardColumnInput>>,
+) -> Result<Board, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Create board",
+    )?;
+    let board = db.create_board(&project_id, &name, columns)?;
+    emit_entity_changed(&app, &db, "boards", "add", &board.id, None);
+    Ok(board)
+}
+
+#[tauri::command]
+async fn rename_board(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    name: String,
+) -> Result<Board, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Rename board",
+    )?;
+    let old = db.snapshot("boards", Some(&id));
+    let board = db.rename_board(&id, &name)?;
+    emit_entity_changed(&app, &db, "boards", "edit", &id, old);
+    Ok(board)
+}
+
+#[tauri::command]
+async fn save_board_columns(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    board_id: String,
+    columns: Vec<BoardColumnInput>,
+) -> Result<Board, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Save board columns",
+    )?;
+    let old = db.snapshot("boards", Some(&board_id));
+    let board = db.save_board_columns(&board_id, &columns)?;
+    emit_entity_changed(&app, &db, "boards", "edit", &board_id, old);
+    Ok(board)
+}
+
+#[tauri::command]
+async fn delete_board(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Delete board",
+    )?;
+    let old = db.snapshot("boards", Some(&id));
+    db.delete_board(&id)?;
+    emit_change(&app, "boards", "delete", Some(id), old, None);
+    Ok(())
+}
+
+#[tauri::command]
+async fn move_card(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    board_id: String,
+    task_id: String,
+    column_id: String,
+    position: Option<u32>,
+) -> Result<Board, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Move card",
+    )?;
+    let old_board = db.snapshot("boards", Some(&board_id));
+    let old_task = db.snapshot("tasks", Some(&task_id));
+    let waiting = db.blocked_dependents(&task_id);
+    let moved = db.move_card(&board_id, &task_id, &column_id, position)?;
+    emit_entity_changed(&app, &db, "boards", "move", &board_id, old_board);
+    if moved.status_changed {
+        let task = db.get_task(&task_id)?;
+        if task.status == Status::Done {
+            fire_hook(&app, &db, "task.completed", serde_json::json!(task));
+            emit_achievements(&app, &db);
+        }
+        emit_entity_changed(&app, &db, "tasks", "status", &task_id, old_task);
+        emit_unblocked(&app, &db, waiting);
+        if let Some(next_id) = &moved.spawned_task_id {
+            emit_entity_changed(&app, &db, "tasks", "add", next_id, None);
+        }
+        emit_data_changed(&app, "stats", "refresh", None);
+    }
+    Ok(moved.board)
+}
+
+// --- TASKS ---
+
+#[tauri::command]
+async fn get_tasks(
+    state: State<'_, AppState>,
+    limit: Option<i32>,
+    status_filter: Option<i32>,
+    project_filter: Option<String>,
+    tag_filter: Option<String>,
+) -> Result<Vec<Task>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let status_enum = status_filter.map(Status::from_int);
+    db.get_tasks(limit, status_enum, project_filter, tag_filter)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_agenda(state: State<'_, AppState>, agenda: Agenda) -> Result<Vec<Task>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_agenda(agenda)
+}
+
+/// The plan day from a command argument, today when missing.
+fn plan_day(date: Option<&str>) -> Result<chrono::NaiveDate, String> {
+    match date {
+        Some(date) => database::parse_day(date),
+        None => Ok(chrono::Local::now().date_naive()),
+    }
+}
+
+#[tauri::command]
+async fn get_daily_plan(
+    state: State<'_, AppState>,
+    date: Option<String>,
+) -> Result<DailyPlan, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_daily_plan(plan_day(date.as_deref())?)
+}
+
+#[tauri::command]
+async fn add_to_daily_plan(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_ids: Vec<String>,
+    date: Option<String>,
+) -> Result<DailyPlan, String> {
+    let day = plan_day(date.as_deref())?;
+    let key = day.format("%Y-%m-%d").to_string();
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Plan tasks",
+    )?;
+    let old = db.snapshot("daily_plan", Some(&key));
+    db.add_to_daily_plan(day, &task_ids)?;
+    emit_entity_changed(&app, &db, "daily_plan", "edit", &key, old);
+    db.get_daily_plan(day)
+}
+
+#[tauri::command]
+async fn remove_from_daily_plan(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_id: String,
+    date: Option<String>,
+) -> Result<DailyPlan, String> {
+    let day = plan_day(date.as_deref())?;
+    let key = day.format("%Y-%m-%d").to_string();
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Remove from plan",
+    )?;
+    let old = db.snapshot("daily_plan", Some(&key));
+    db.remove_from_daily_plan(day, &task_id)?;
+    emit_entity_changed(&app, &db, "daily_plan", "edit", &key, old);
+    db.get_daily_plan(day)
+}
+
+#[tauri::command]
+async fn reorder_daily_plan(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_ids: Vec<String>,
+    date: Option<String>,
+) -> Result<DailyPlan, String> {
+    let day = plan_day(date.as_deref())?;
+    let key = day.format("%Y-%m-%d").to_string();
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Reorder plan",
+    )?;
+    let old = db.snapshot("daily_plan", Some(&key));
+    db.reorder_daily_plan(day, &task_ids)?;
+    emit_entity_changed(&app, &db, "daily_plan", "reorder", &key, old);
+    db.get_daily_plan(day)
+}
+
+/// Moves unfinished tasks from `from` (today) to `to` (the day after).
+#[tauri::command]
+async fn carry_over_daily_plan(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<String>, String> {
+    let from = plan_day(from.as_deref())?;
+    let to = match to {
+        Some(to) => database::parse_day(&to)?,
+        None => from + chrono::Duration::days(1),
+    };
+    let (from_key, to_key) = (
+        from.format("%Y-%m-%d").to_string(),
+        to.format("%Y-%m-%d").to_string(),
+    );
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Carry over plan",
+    )?;
+    let old_from = db.snapshot("daily_plan", Some(&from_key));
+    let old_to = db.snapshot("daily_plan", Some(&to_key));
+    let moved = db.carry_over_daily_plan(from, to)?;
+    emit_entity_changed(&app, &db, "daily_plan", "edit", &from_key, old_from);
+    emit_entity_changed(&app, &db, "daily_plan", "edit", &to_key, old_to);
+    Ok(moved)
+}
+
+#[tauri::command]
+async fn get_time_blocks(
+    state: State<'_, AppState>,
+    from: i64,
+    to: i64,
+) -> Result<Vec<TimeBlock>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_time_blocks(from, to)
+}
+
+#[tauri::command]
+async fn add_time_block(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_id: Option<String>,
+    title: Option<String>,
+    start_at: i64,
+    end_at: i64,
+) -> Result<TimeBlock, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Add time block",
+    )?;
+    let block = db.add_time_block(task_id.as_deref(), title.as_deref(), start_at, end_at)?;
+    emit_entity_changed(&app, &db, "time_blocks", "add", &block.id, None);
+    Ok(block)
+}
+
+#[tauri::command]
+async fn move_time_block(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    start_at: i64,
+) -> Result<TimeBlock, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Move time block",
+    )?;
+    let old = db.snapshot("time_blocks", Some(&id));
+    let block = db.move_time_block(&id, start_at)?;
+    emit_entity_changed(&app, &db, "time_blocks", "edit", &id, old);
+    Ok(block)
+}
+
+#[tauri::command]
+async fn resize_time_block(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    start_at: i64,
+    end_at: i64,
+) -> Result<TimeBlock, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Resize time block",
+    )?;
+    let old = db.snapshot("time_blocks", Some(&id));
+    let block = db.resize_time_block(&id, start_at, end_at)?;
+    emit_entity_changed(&app, &db, "time_blocks", "edit", &id, old);
+    Ok(block)
+}
+
+#[tauri::command]
+async fn delete_time_block(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Delete time block",
+    )?;
+    let old = db.snapshot("time_blocks", Some(&id));
+    db.delete_time_block(&id)?;
+    emit_entity_changed(&app, &db, "time_blocks", "delete", &id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn complete_time_block(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    start_focus: bool,
+) -> Result<TimeBlockCompletion, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Complete time block",
+    )?;
+    let old = db.snapshot("time_blocks", Some(&id));
+    let completion = db.complete_time_block(&id, start_focus)?;
+    emit_entity_changed(&app, &db, "time_blocks", "edit", &id, old);
+    Ok(completion)
+}
+
+/// Fills free working hours over the next `days` days (a week by default).
+#[tauri::command]
+async fn auto_schedule(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    days: Option<u32>,
+) -> Result<AutoScheduleResult, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Auto-schedule",
+    )?;
+    let now = chrono::Utc::now().timestamp_millis();
+    let result = db.auto_schedule(now, days.unwrap_or(7))?;
+    if !result.blocks.is_empty() {
+        emit_data_changed(&app, "time_blocks", "refresh", None);
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+async fn get_time_entries(
+    state: State<'_, AppState>,
+    from: String,
+    to: String,
+) -> Result<Vec<TimeEntry>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_time_entries(database::parse_day(&from)?, database::parse_day(&to)?)
+}
+
+#[tauri::command]
+async fn get_running_timer(state: State<'_, AppState>) -> Result<Option<TimeEntry>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_running_timer()
+}
+
+#[tauri::command]
+async fn start_timer(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_id: String,
+) -> Result<TimeEntry, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Start timer",
+    )?;
+    let entry = db.start_timer(&task_id, chrono::Utc::now().timestamp_millis())?;
+    emit_entity_changed(&app, &db, "time_entries", "add", &entry.id, None);
+    Ok(entry)
+}
+
+#[tauri::command]
+async fn stop_timer(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<TimeEntry, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Stop timer",
+    )?;
+    let old = db.get_running_timer()?.map(Snapshot::TimeEntry);
+    let entry = db.stop_timer(chrono::Utc::now().timestamp_millis())?;
+    emit_entity_changed(&app, &db, "time_entries", "edit", &entry.id, old);
+    Ok(entry)
+}
+
+#[tauri::command]
+async fn add_time_entry(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_id: String,
+    started_at: i64,
+    ended_at: i64,
+    note: Option<String>,
+) -> Result<TimeEntry, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Add time entry",
+    )?;
+    let entry = db.add_time_entry(&task_id, started_at, ended_at, note.as_deref())?;
+    emit_entity_changed(&app, &db, "time_entries", "add", &entry.id, None);
+    Ok(entry)
+}
+
+#[tauri::command]
+async fn update_time_entry(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    patch: TimeEntryPatch,
+) -> Result<TimeEntry, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Edit time entry",
+    )?;
+    let old = db.snapshot("time_entries", Some(&id));
+    let entry = db.update_time_entry(&id, &patch)?;
+    emit_entity_changed(&app, &db, "time_entries", "edit", &id, old);
+    Ok(entry)
+}
+
+#[tauri::command]
+async fn delete_time_entry(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Delete time entry",
+    )?;
+    let old = db.snapshot("time_entries", Some(&id));
+    db.delete_time_entry(&id)?;
+    emit_entity_changed(&app, &db, "time_entries", "delete", &id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_time_rollup(
+    state: State<'_, AppState>,
+    from: String,
+    to: String,
+    group: TimeRollupGroup,
+) -> Result<Vec<TimeRollup>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_time_rollup(
+        database::parse_day(&from)?,
+        database::parse_day(&to)?,
+        group,
+    )
+}
+
+#[tauri::command]
+async fn get_timesheet(
+    state: State<'_, AppState>,
+    from: String,
+    to: String,
+) -> Result<Vec<TimesheetEntry>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_timesheet(database::parse_day(&from)?, database::parse_day(&to)?)
+}
+
+/// The timesheet as "csv" or "json" text, for the frontend to save.
+#[tauri::command]
+async fn export_timesheet(
+    state: State<'_, AppState>,
+    from: String,
+    to: String,
+    format: String,
+) -> Result<String, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.export_timesheet(
+        database::parse_day(&from)?,
+        database::parse_day(&to)?,
+        &format,
+    )
+}
+
+#[tauri::command]
+async fn add_task(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    new_task: NewTask,
+) -> Result<Task, String> {
+    let mut db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    insert_task(&app, &mut db, &new_task)
+}
+
+/// Adds a task as one undo step, announcing it and firing `task.created`.
+/// Shared by the command and deep links.
+fn insert_task(
+    app: &tauri::AppHandle,
+    db: &mut AppDatabase,
+    new_task: &NewTask,
+) -> Result<Task, String> {
+    let db = UndoStep::begin(db, "Add task")?;
+    let task = db.add_task(new_task)?;
+    emit_entity_changed(app, &db, "tasks", "add", &task.id, None);
+    emit_data_changed(app, "stats", "refresh", None);
+    fire_hook(app, &db, "task.created", serde_json::json!(task));
+    Ok(task)
+}
+
+#[tauri::command]
+async fn edit_task_title(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    title: String,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Edit task title",
+    )?;
+    let old = db.snapshot("tasks", Some(&id));
+    db.update_task_title(&id, title)
+        .map_err(|e| e.to_string())?;
+    emit_entity_changed(&app, &db, "tasks", "edit", &id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn update_task_priority(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    priority: String,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Update task priority",
+    )?;
+    let old = db.snapshot("tasks", Some(&id));
+    let priority_enum = match priority.as_str() {
+        "high" => Priority::High,
+        "low" => Priority::Low,
+        _ => Priority::Normal,
+    };
+    db.update_task_priority(&id, priority_enum)
+        .map_err(|e| e.to_string())?;
+    emit_entity_changed(&app, &db, "tasks", "edit", &id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn update_task_deadline(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    deadline: Option<i64>,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Update task deadline",
+    )?;
+    let old = db.snapshot("tasks", Some(&id));
+    db.update_task_deadline(&id, deadline)
+        .map_err(|e| e.to_string())?;
+    emit_entity_changed(&app, &db, "tasks", "edit", &id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn update_task_tags(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    tags: Vec<String>,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Update task tags",
+    )?;
+    let old = db.snapshot("tasks", Some(&id));
+    db.update_task_tags(&id, tags).map_err(|e| e.to_string())?;
+    emit_entity_changed(&app, &db, "tasks", "edit", &id, old);
+    emit_data_changed(&app, "tags", "refresh", None);
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_tags(state: State<'_, AppState>) -> Result<Vec<Tag>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_tags()
+}
+
+#[tauri::command]
+async fn rename_tag(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    name: String,
+    new_name: String,
+) -> Result<Tag, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Rename tag",
+    )?;
+    let tag = db.rename_tag(&name, &new_name)?;
+    emit_data_changed(&app, "tags", "refresh", None);
+    emit_data_changed(&app, "tasks", "refresh", None);
+    emit_data_changed(&app, "projects", "refresh", None);
+    Ok(tag)
+}
+
+#[tauri::command]
+async fn merge_tags(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    sources: Vec<String>,
+    target: String,
+) -> Result<Tag, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Merge tags",
+    )?;
+    let tag = db.merge_tags(&sources, &target)?;
+    emit_data_changed(&app, "tags", "refresh", None);
+    emit_data_changed(&app, "tasks", "refresh", None);
+    emit_data_changed(&app, "projects", "refresh", None);
+    Ok(tag)
+}
+
+#[tauri::command]
+async fn set_tag_color(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    name: String,
+    color: Option<String>,
+) -> Result<Tag, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Set tag color",
+    )?;
+    let tag = db.set_tag_color(&name, color)?;
+    emit_data_changed(&app, "tags", "refresh", None);
+    Ok(tag)
+}
+
+#[tauri::command]
+async fn delete_tag(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    name: String,
+) -> Result<(), String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Delete tag",
+    )?;
+    db.delete_tag(&name)?;
+    emit_data_changed(&app, "tags", "refresh", None);
+    emit_data_changed(&app, "tasks", "refresh", None);
+    emit_data_changed(&app, "projects", "refresh", None);
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_templates(state: State<'_, AppState>) -> Result<Vec<Template>, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_templates()
+}
+
+#[tauri::command]
+async fn save_task_as_template(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_id: String,
+    name: String,
+    anchor: Option<i64>,
+) -> Result<Template, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Save task as template",
+    )?;
+    let template = db.save_task_as_template(&task_id, &name, anchor)?;
+    emit_entity_changed(&app, &db, "templates", "add", &template.id, None);
+    Ok(template)
+}
+
+#[tauri::command]
+async fn save_project_as_template(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    project_id: String,
+    name: String,
+    anchor: Option<i64>,
+) -> Result<Template, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Save project as template",
+    )?;
+    let template = db.save_project_as_template(&project_id, &name, anchor)?;
+    emit_entity_changed(&app, &db, "templates", "add", &template.id, None);
+    Ok(template)
+}
+
+#[tauri::command]
+async fn instantiate_template(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    template_id: String,
+    options: TemplateInstance,
+) -> Result<InstantiatedTemplate, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Instantiate template",
+    )?;
+    let created = db.instantiate_template(&template_id, &options)?;
+    if let Some(project_id) = &created.project_id {
+        emit_entity_changed(&app, &db, "projects", "add", project_id, None);
+        emit_data_changed(&app, "sections", "refresh", None);
+    }
+    for task_id in &created.task_ids {
+        emit_entity_changed(&app, &db, "tasks", "add", task_id, None);
+        if let Ok(task) = db.get_task(task_id) {
+            fire_hook(&app, &db, "task.created", serde_json::json!(task));
+        }
+    }
+    emit_data_changed(&app, "tags", "refresh", None);
+    emit_data_changed(&app, "stats", "refresh", None);
+    Ok(created)
+}
+
+#[tauri::command]
+async fn delete_template(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    template_id: String,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Delete template",
+    )?;
+    let old = db.snapshot("templates", Some(&template_id));
+    db.delete_template(&template_id)?;
+    emit_entity_changed(&app, &db, "templates", "delete", &template_id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn update_task_repeat(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+    repeat_mode: Option<String>,
+    repeat_days_mask: Option<i64>,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Update task repeat",
+    )?;
+    let old = db.snapshot("tasks", Some(&id));
+    db.update_task_repeat(&id, repeat_mode, repeat_days_mask)
+        .map_err(|e| e.to_string())?;
+    emit_entity_changed(&app, &db, "tasks", "edit", &id, old);
+    Ok(())
+}
+
+#[tauri::command]
+async fn update_task_status(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_id: String,
+    new_status: String,
+) -> Result<(), String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Update task status",
+    )?;
+    let old = db.snapshot("tasks", Some(&task_id));
+    let waiting = db.blocked_dependents(&task_id);
+    let status_enum = match new_status.as_str() {
+        "todo" => Status::Todo,
+        "doing" => Status::Doing,
+        "done" => Status::Done,
+        _ => Status::Todo,
+    };
+    let spawned = db
+        .update_task_status(&task_id, status_enum)
+        .map_err(|e| e.to_string())?;
+    if status_enum == Status::Done {
+        if let Ok(task) = db.get_task(&task_id) {
+            fire_hook(&app, &db, "task.completed", serde_json::json!(task));
+        }
+        emit_achievements(&app, &db);
+    }
+    emit_entity_changed(&app, &db, "tasks", "status", &task_id, old);
+    emit_unblocked(&app, &db, waiting);
+    if let Some(next_id) = spawned {
+        emit_entity_changed(&app, &db, "tasks", "add", &next_id, None);
+    }
+    emit_data_changed(&app, "stats", "refresh", None);
+    Ok(())
+}
+
+#[tauri::command]
+async fn update_task(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_id: String,
+    patch: TaskPatch,
+) -> Result<Task, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Update task",
+    )?;
+    let old = db.snapshot("tasks", Some(&task_id));
+    let waiting = db.blocked_dependents(&task_id);
+    let previous = db.get_task(&task_id)?.status;
+    let (task, spawned) = db.update_task(&task_id, &patch)?;
+    if task.status != previous {
+        if task.status == Status::Done {
+            fire_hook(&app, &db, "task.completed", serde_json::json!(task));
+            emit_achievements(&app, &db);
+        }
+        emit_entity_changed(&app, &db, "tasks", "status", &task_id, old.clone());
+        emit_unblocked(&app, &db, waiting);
+        if let Some(next_id) = spawned {
+            emit_entity_changed(&app, &db, "tasks", "add", &next_id, None);
+        }
+        emit_data_changed(&app, "stats", "refresh", None);
+    }
+    emit_entity_changed(&app, &db, "tasks", "edit", &task_id, old);
+    if patch.tags.is_some() {
+        emit_data_changed(&app, "tags", "refresh", None);
+    }
+    Ok(task)
+}
+
+#[tauri::command]
+async fn delete_task(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_id: String,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Delete task",
+    )?;
+    let old = db.snapshot("tasks", Some(&task_id));
+    let waiting = db.blocked_dependents(&task_id);
+    db.delete_task(&task_id).map_err(|e| e.to_string())?;
+    emit_entity_changed(&app, &db, "tasks", "delete", &task_id, old);
+    emit_unblocked(&app, &db, waiting);
+    emit_data_changed(&app, "stats", "refresh", None);
+    Ok(())
+}
+
+#[tauri::command]
+async fn duplicate_task(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_id: String,
+) -> Result<Task, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Duplicate task",
+    )?;
+    let task = db.duplicate_task(&task_id)?;
+    emit_entity_changed(&app, &db, "tasks", "add", &task.id, None);
+    emit_data_changed(&app, "tags", "refresh", None);
+    emit_data_changed(&app, "stats", "refresh", None);
+    fire_hook(&app, &db, "task.created", serde_json::json!(task));
+    Ok(task)
+}
+
+#[tauri::command]
+async fn bulk_update_tasks(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_ids: Vec<String>,
+    patch: TaskBulkPatch,
+) -> Result<BulkUpdateResult, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Bulk update tasks",
+    )?;
+    let before: Vec<(String, Option<Snapshot>)> = task_ids
+        .iter()
+        .map(|id| (id.clone(), db.snapshot("tasks", Some(id))))
+        .collect();
+    let waiting: Vec<_> = task_ids
+        .iter()
+        .flat_map(|id| db.blocked_dependents(id))
+        .collect();
+    let result = db.bulk_update_tasks(&task_ids, &patch)?;
+    if !result.applied {
+        return Ok(result);
+    }
+
+    if patch.status == Some(Status::Done) {
+        for (id, old) in &before {
+            let was_done = matches!(old, Some(Snapshot::Task(t)) if t.status == Status::Done);
+            if let (false, Ok(task)) = (was_done, db.get_task(id)) {
+                fire_hook(&app, &db, "task.completed", serde_json::json!(task));
+            }
+        }
+        emit_achievements(&app, &db);
+    }
+    // One event for the whole batch: changed tasks, spawned occurrences and
+    // tasks that are no longer blocked
+    let mut old = Vec::new();
+    let mut new = Vec::new();
+    let spawned = result
+        .results
+        .iter()
+        .filter_map(|r| r.spawned_task_id.clone())
+        .map(|id| (id, None));
+    let unblocked = waiting
+        .into_iter()
+        .filter(|(id, _)| db.get_task(id).is_ok_and(|t| !t.is_blocked));
+    for (id, snapshot) in before.into_iter().chain(spawned).chain(unblocked) {
+        old.push(snapshot);
+        new.push(db.snapshot("tasks", Some(&id)));
+    }
+    emit_change(
+        &app,
+        "tasks",
+        "bulk",
+        None,
+        Some(Snapshot::Many(old)),
+        Some(Snapshot::Many(new)),
+    );
+    Ok(result)
+}
+
+#[tauri::command]
+async fn link_tasks(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_id: String,
+    depends_on_id: String,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Link tasks",
+    )?;
+    let old = db.snapshot("tasks", Some(&task_id));
+    db.add_task_dependency(&task_id, &depends_on_id)?;
+    emit_entity_changed(&app, &db, "tasks", "edit", &task_id, old);
     Ok(())
 }
 
 #[tauri::command]
-async fn set_task_remind_at(
+async fn unlink_tasks(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
-    id: String,
-    remind_at: Option<i64>,
+    task_id: String,
+    depends_on_id: String,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.set_task_remind_at(&id, remind_at)
-        .map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "tasks", "edit", Some(id));
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Unlink tasks",
+    )?;
+    let old = db.snapshot("tasks", Some(&task_id));
+    db.remove_task_dependency(&task_id, &depends_on_id)?;
+    emit_entity_changed(&app, &db, "tasks", "edit", &task_id, old);
     Ok(())
 }
 
 #[tauri::command]
-async fn snooze_task_reminder(
+async fn get_task_history(
     state: State<'_, AppState>,
-    app: tauri::AppHandle,
-    id: String,
-    minutes: i64,
-) -> Result<(), String> {
+    task_id: String,
+) -> Result<Vec<TaskEvent>, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.snooze_task(&id, minutes).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "tasks", "edit", Some(id));
-    Ok(())
+    db.get_task_history(&task_id)
+}
+
+// --- TRASH ---
+
+/// Event entity for a kind of trashable item.
+fn trash_entity(entity: &str) -> &'static str {
+    match entity {
+        "task" => "tasks",
+        "subtask" => "subtasks",
+        "project" => "projects",
+        _ => "finance",
+    }
 }
 
 #[tauri::command]
-async fn get_projects(state: State<'_, AppState>) -> Result<Vec<Project>, String> {
+async fn get_trash(state: State<'_, AppState>) -> Result<Vec<TrashItem>, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.get_projects().map_err(|e| e.to_string())
+    db.get_trash()
 }
 
 #[tauri::command]
-async fn add_project(
+async fn restore_from_trash(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
-    name: String,
-    color: String,
-    priority: String,
-    parent_id: Option<String>,
-    is_folder: bool,
-) -> Result<Project, String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    let id = uuid::Uuid::new_v4().to_string();
-
-    let priority_enum = match priority.as_str() {
-        "high" => Priority::High,
-        "low" => Priority::Low,
-        _ => Priority::Normal,
-    };
-
-    let project = db
-        .add_project(id.clone(), name, color, priority_enum, parent_id, is_folder)
-        .map_err(|e| e.to_string())?;
-
-    emit_data_changed(&app, "projects", "add", Some(id));
+    entity: String,
+    id: String,
+) -> Result<Vec<TrashItem>, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Restore from trash",
+    )?;
+    let restored = db.restore_from_trash(&entity, &id)?;
+    let mut touched: Vec<&'static str> = restored.iter().map(|i| trash_entity(&i.entity)).collect();
+    touched.dedup();
+    for entity in touched {
+        emit_data_changed(&app, entity, "refresh", None);
+    }
+    emit_data_changed(&app, "trash", "refresh", None);
     emit_data_changed(&app, "stats", "refresh", None);
-    Ok(project)
+    Ok(restored)
 }
 
 #[tauri::command]
-async fn edit_project(
+async fn purge_trash_item(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
+    entity: String,
     id: String,
-    name: String,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.update_project(&id, name).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "projects", "edit", Some(id));
+    let mut db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.purge_trash_item(&entity, &id)?;
+    emit_data_changed(&app, "trash", "refresh", None);
     Ok(())
 }
 
 #[tauri::command]
-async fn update_project_priority(
-    state: State<'_, AppState>,
-    app: tauri::AppHandle,
-    id: String,
-    priority: String,
-) -> Result<(), String> {
+async fn empty_trash(state: State<'_, AppState>, app: tauri::AppHandle) -> Result<usize, String> {
+    let mut db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let purged = db.empty_trash()?;
+    emit_data_changed(&app, "trash", "refresh", None);
+    Ok(purged)
+}
+
+// --- UNDO ---
+
+/// Entities an undo or redo step may have touched.
+const UNDO_REFRESH: &[&str] = &[
+    "tasks",
+    "subtasks",
+    "projects",
+    "sections",
+    "boards",
+    "finance",
+    "hooks",
+    "settings",
+    "stats",
+    "trash",
+    "tags",
+    "templates",
+    "daily_plan",
+    "time_blocks",
+    "time_entries",
+    "gamification_rules",
+    "daily_activity",
+    "achievements",
+];
+
+#[tauri::command]
+async fn get_undo_status(state: State<'_, AppState>) -> Result<UndoStatus, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    let priority_enum = match priority.as_str() {
-        "high" => Priority::High,
-        "low" => Priority::Low,
-        _ => Priority::Normal,
-    };
-    db.update_project_priority(&id, priority_enum)
-        .map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "projects", "edit", Some(id));
-    Ok(())
+    db.get_undo_status()
 }
 
 #[tauri::command]
-async fn delete_project(
-    state: State<'_, AppState>,
-    app: tauri::AppHandle,
-    id: String,
-) -> Result<(), String> {
+async fn undo(state: State<'_, AppState>, app: tauri::AppHandle) -> Result<Option<String>, String> {
+    let mut db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let label = db.undo()?;
+    if label.is_some() {
+        for entity in UNDO_REFRESH {
+            emit_data_changed(&app, entity, "refresh", None);
+        }
+    }
+    Ok(label)
+}
+
+#[tauri::command]
+async fn redo(state: State<'_, AppState>, app: tauri::AppHandle) -> Result<Option<String>, String> {
+    let mut db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let label = db.redo()?;
+    if label.is_some() {
+        for entity in UNDO_REFRESH {
+            emit_data_changed(&app, entity, "refresh", None);
+        }
+    }
+    Ok(label)
+}
+
+// --- STATS ---
+
+#[tauri::command]
+async fn get_stats(state: State<'_, AppState>) -> Result<UserStats, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.delete_project(&id).map_err(|e| e.to_string())?;
+    db.get_stats().map_err(|e| e.to_string())
+}
 
-    emit_data_changed(&app, "projects", "delete", Some(id));
-    emit_data_changed(&app, "tasks", "refresh", None);
-    emit_data_changed(&app, "stats", "refresh", None);
-    Ok(())
+#[tauri::command]
+async fn get_estimate_accuracy(state: State<'_, AppState>) -> Result<EstimateReport, String> {
+    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    db.get_estimate_accuracy()
 }
 
+/// The factor to multiply new estimates in `project_id` (any project when
+/// missing) by.
 #[tauri::command]
-async fn get_tasks(
+async fn get_estimate_correction(
     state: State<'_, AppState>,
-    limit: Option<i32>,
-    status_filter: Option<i32>,
-    project_filter: Option<String>,
-) -> Result<Vec<Task>, String> {
+    project_id: Option<String>,
+) -> Result<EstimateCorrection, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    let status_enum = status_filter.map(Status::from_int);
-    db.get_tasks(limit, status_enum, project_filter)
-        .map_err(|e| e.to_string())
+    db.get_estimate_correction(project_id.as_deref())
 }
 
 #[tauri::command]
-async fn add_task(
-    state: State<'_, AppState>,
-    app: tauri::AppHandle,
-    new_task: NewTask,
-) -> Result<Task, String> {
+async fn get_gamification(state: State<'_, AppState>) -> Result<GamificationStatus, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    let task = db.add_task(&new_task).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "tasks", "add", Some(task.id.clone()));
-    emit_data_changed(&app, "stats", "refresh", None);
-    Ok(task)
+    db.get_gamification()
 }
 
+/// The newest ledger entries (100 by default), newest first.
 #[tauri::command]
-async fn edit_task_title(
+async fn get_points_ledger(
     state: State<'_, AppState>,
-    app: tauri::AppHandle,
-    id: String,
-    title: String,
-) -> Result<(), String> {
+    limit: Option<u32>,
+) -> Result<Vec<PointsEntry>, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.update_task_title(&id, title)
-        .map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "tasks", "edit", Some(id));
-    Ok(())
+    db.get_points_ledger(limit.unwrap_or(100))
 }
 
 #[tauri::command]
-async fn update_task_priority(
-    state: State<'_, AppState>,
-    app: tauri::AppHandle,
-    id: String,
-    priority: String,
-) -> Result<(), String> {
+async fn get_gamification_rules(state: State<'_, AppState>) -> Result<GamificationRules, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    let priority_enum = match priority.as_str() {
-        "high" => Priority::High,
-        "low" => Priority::Low,
-        _ => Priority::Normal,
-    };
-    db.update_task_priority(&id, priority_enum)
-        .map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "tasks", "edit", Some(id));
-    Ok(())
+    db.get_gamification_rules()
 }
 
 #[tauri::command]
-async fn update_task_deadline(
+async fn save_gamification_rules(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
-    id: String,
-    deadline: Option<i64>,
+    rules: GamificationRules,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.update_task_deadline(&id, deadline)
-        .map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "tasks", "edit", Some(id));
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Save gamification rules",
+    )?;
+    let old = db.snapshot("gamification_rules", None);
+    db.save_gamification_rules(&rules)?;
+    emit_change(
+        &app,
+        "gamification_rules",
+        "edit",
+        None,
+        old,
+        db.snapshot("gamification_rules", None),
+    );
+    emit_achievements(&app, &db);
+    emit_data_changed(&app, "stats", "refresh", None);
     Ok(())
 }
 
 #[tauri::command]
-async fn update_task_tags(
-    state: State<'_, AppState>,
-    app: tauri::AppHandle,
-    id: String,
-    tags: Vec<String>,
-) -> Result<(), String> {
+async fn get_streak(state: State<'_, AppState>) -> Result<StreakStatus, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.update_task_tags(&id, tags).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "tasks", "edit", Some(id));
-    Ok(())
+    db.get_streak()
 }
 
 #[tauri::command]
-async fn update_task_repeat(
+async fn get_daily_activity(
     state: State<'_, AppState>,
-    app: tauri::AppHandle,
-    id: String,
-    repeat_mode: Option<String>,
-    repeat_days_mask: Option<i64>,
-) -> Result<(), String> {
+    from: String,
+    to: String,
+) -> Result<Vec<DailyActivity>, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.update_task_repeat(&id, repeat_mode, repeat_days_mask)
-        .map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "tasks", "edit", Some(id));
-    Ok(())
+    db.get_daily_activity(database::parse_day(&from)?, database::parse_day(&to)?)
 }
 
 #[tauri::command]
-async fn update_task_status(
+async fn freeze_streak_day(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
-    task_id: String,
-    new_status: String,
-) -> Result<(), String> {
-    let mut db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    let status_enum = match new_status.as_str() {
-        "todo" => Status::Todo,
-        "doing" => Status::Doing,
-        "done" => Status::Done,
-        _ => Status::Todo,
-    };
-    db.update_task_status(&task_id, status_enum)
-        .map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "tasks", "status", Some(task_id));
+    day: String,
+) -> Result<DailyActivity, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Freeze day",
+    )?;
+    let old = db.snapshot("daily_activity", Some(&day));
+    let activity = db.freeze_streak_day(database::parse_day(&day)?)?;
+    emit_entity_changed(&app, &db, "daily_activity", "freeze", &day, old);
     emit_data_changed(&app, "stats", "refresh", None);
-    Ok(())
+    Ok(activity)
 }
 
 #[tauri::command]
-async fn delete_task(
+async fn unfreeze_streak_day(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
-    task_id: String,
-) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.delete_task(&task_id).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "tasks", "delete", Some(task_id));
+    day: String,
+) -> Result<DailyActivity, String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Unfreeze day",
+    )?;
+    let old = db.snapshot("daily_activity", Some(&day));
+    let activity = db.unfreeze_streak_day(database::parse_day(&day)?)?;
+    emit_entity_changed(&app, &db, "daily_activity", "unfreeze", &day, old);
     emit_data_changed(&app, "stats", "refresh", None);
-    Ok(())
+    Ok(activity)
 }
 
-#[tauri::command]
-async fn get_stats(state: State<'_, AppState>) -> Result<UserStats, String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.get_stats().map_err(|e| e.to_string())
-}
+// --- FOCUS ---
 
 #[tauri::command]
 async fn start_focus_session(
@@ -425,8 +2164,15 @@ async fn complete_focus_session(
     duration_minutes: i32,
 ) -> Result<(), String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.finish_focus_session(session_id, duration_minutes, true)
+    db.finish_focus_session(session_id.clone(), duration_minutes, true)
         .map_err(|e| e.to_string())?;
+    fire_hook(
+        &app,
+        &db,
+        "focus.finished",
+        serde_json::json!({ "session_id": session_id, "duration_minutes": duration_minutes }),
+    );
+    emit_achievements(&app, &db);
     emit_data_changed(&app, "stats", "refresh", None);
     Ok(())
 }
@@ -445,6 +2191,8 @@ async fn cancel_focus_session(
     Ok(())
 }
 
+// --- SUBTASKS ---
+
 #[tauri::command]
 async fn get_subtasks(state: State<'_, AppState>, task_id: String) -> Result<Vec<Subtask>, String> {
     let db = state.db.lock().map_err(|_| "Failed to lock db")?;
@@ -458,11 +2206,14 @@ async fn add_subtask(
     task_id: String,
     title: String,
 ) -> Result<Subtask, String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Add subtask",
+    )?;
     let subtask = db
         .add_subtask(&task_id, &title)
         .map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "subtasks", "add", Some(task_id));
+    emit_entity_changed(&app, &db, "subtasks", "add", &subtask.id, None);
     Ok(subtask)
 }
 
@@ -472,9 +2223,13 @@ async fn toggle_subtask(
     app: tauri::AppHandle,
     id: String,
 ) -> Result<bool, String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Toggle subtask",
+    )?;
+    let old = db.snapshot("subtasks", Some(&id));
     let completed = db.toggle_subtask(&id).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "subtasks", "toggle", Some(id));
+    emit_entity_changed(&app, &db, "subtasks", "toggle", &id, old);
     Ok(completed)
 }
 
@@ -484,30 +2239,100 @@ async fn delete_subtask(
     app: tauri::AppHandle,
     id: String,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Delete subtask",
+    )?;
+    let old = db.snapshot("subtasks", Some(&id));
     db.delete_subtask(&id).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "subtasks", "delete", Some(id));
+    emit_entity_changed(&app, &db, "subtasks", "delete", &id, old);
     Ok(())
 }
 
+#[tauri::command]
+async fn promote_subtask_to_task(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<Task, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Promote subtask to task",
+    )?;
+    let old = db.snapshot("subtasks", Some(&id));
+    let parent_id = db.get_subtask(&id)?.task_id;
+    let parent_old = db.snapshot("tasks", Some(&parent_id));
+    let task = db.promote_subtask_to_task(&id)?;
+    emit_entity_changed(&app, &db, "subtasks", "delete", &id, old);
+    emit_entity_changed(&app, &db, "tasks", "edit", &parent_id, parent_old);
+    emit_entity_changed(&app, &db, "tasks", "add", &task.id, None);
+    emit_data_changed(&app, "stats", "refresh", None);
+    fire_hook(&app, &db, "task.created", serde_json::json!(task));
+    if task.status == Status::Done {
+        fire_hook(&app, &db, "task.completed", serde_json::json!(task));
+        emit_achievements(&app, &db);
+    }
+    Ok(task)
+}
+
+#[tauri::command]
+async fn demote_task_to_subtask(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_id: String,
+    parent_id: String,
+) -> Result<Subtask, String> {
+    let mut db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Demote task to subtask",
+    )?;
+    let old = db.snapshot("tasks", Some(&task_id));
+    let parent_old = db.snapshot("tasks", Some(&parent_id));
+    let waiting = db.blocked_dependents(&task_id);
+    let subtask = db.demote_task_to_subtask(&task_id, &parent_id)?;
+    emit_entity_changed(&app, &db, "tasks", "delete", &task_id, old);
+    emit_entity_changed(&app, &db, "subtasks", "add", &subtask.id, None);
+    emit_entity_changed(&app, &db, "tasks", "edit", &parent_id, parent_old);
+    emit_unblocked(&app, &db, waiting);
+    emit_data_changed(&app, "tags", "refresh", None);
+    emit_data_changed(&app, "stats", "refresh", None);
+    emit_data_changed(&app, "time_entries", "refresh", None);
+    Ok(subtask)
+}
+
 #[tauri::command]
 async fn reorder_subtasks(
     state: State<'_, AppState>,
+    app: tauri::AppHandle,
     subtask_ids: Vec<String>,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.reorder_subtasks(&subtask_ids).map_err(|e| e.to_string())
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Reorder subtasks",
+    )?;
+    let old = db.order_snapshot("subtasks", &subtask_ids);
+    db.reorder_subtasks(&subtask_ids)
+        .map_err(|e| e.to_string())?;
+    let new = db.order_snapshot("subtasks", &subtask_ids);
+    emit_change(&app, "subtasks", "reorder", None, old, new);
+    Ok(())
 }
 
+// --- ARCHIVE ---
+
 #[tauri::command]
 async fn archive_task(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
     id: String,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Archive task",
+    )?;
+    let old = db.snapshot("tasks", Some(&id));
     db.archive_task(&id).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "tasks", "archive", Some(id));
+    emit_entity_changed(&app, &db, "tasks", "archive", &id, old);
     Ok(())
 }
 
@@ -517,18 +2342,106 @@ async fn unarchive_task(
     app: tauri::AppHandle,
     id: String,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Unarchive task",
+    )?;
+    let old = db.snapshot("tasks", Some(&id));
     db.unarchive_task(&id).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "tasks", "unarchive", Some(id));
+    emit_entity_changed(&app, &db, "tasks", "unarchive", &id, old);
     Ok(())
 }
 
+// --- REORDER TASKS ---
+
 #[tauri::command]
-async fn reorder_tasks(state: State<'_, AppState>, task_ids: Vec<String>) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
-    db.reorder_tasks(&task_ids).map_err(|e| e.to_string())
+async fn reorder_tasks(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+    task_ids: Vec<String>,
+) -> Result<(), String> {
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Reorder tasks",
+    )?;
+    let old = db.order_snapshot("tasks", &task_ids);
+    db.reorder_tasks(&task_ids).map_err(|e| e.to_string())?;
+    let new = db.order_snapshot("tasks", &task_ids);
+    emit_change(&app, "tasks", "reorder", None, old, new);
+    Ok(())
+}
+
+// --- DEEP LINKS ---
+
+#[derive(serde::Serialize, Clone)]
+struct DeepLinkPayload {
+    action: &'static str, // "add" | "open" | "focus"
+    task_id: String,
+    session_id: Option<String>,
+}
+
+fn handle_deep_link(app: &tauri::AppHandle, raw: &str) -> Result<DeepLinkPayload, String> {
+    let link = deeplink::parse(raw)?;
+    let state = app.state::<AppState>();
+    let mut db = state.db.lock().map_err(|_| "Failed to lock db")?;
+
+    let payload = match link {
+        DeepLink::AddTask(add) => {
+            let project_id = match add.project.as_deref() {
+                Some(key) => Some(deeplink::resolve_project(&db.get_projects()?, key)?),
+                None => None,
+            };
+            let task = insert_task(app, &mut db, &add.into_new_task(project_id))?;
+            DeepLinkPayload {
+                action: "add",
+                task_id: task.id,
+                session_id: None,
+            }
+        }
+        DeepLink::OpenTask(id) => {
+            db.get_task(&id)?;
+            DeepLinkPayload {
+                action: "open",
+                task_id: id,
+                session_id: None,
+            }
+        }
+        DeepLink::Focus(id) => {
+            db.get_task(&id)?;
+            let session_id = db.start_focus_session(id.clone())?;
+            DeepLinkPayload {
+                action: "focus",
+                task_id: id,
+                session_id: Some(session_id),
+            }
+        }
+    };
+    drop(db);
+
+    if let Some(w) = app.get_webview_window("main") {
+        let _ = w.show();
+        let _ = w.set_focus();
+    }
+    let _ = app.emit("deeplink:open", payload.clone());
+    Ok(payload)
+}
+
+fn handle_deep_links(app: &tauri::AppHandle, urls: Vec<url::Url>) {
+    for url in urls {
+        if let Err(e) = handle_deep_link(app, url.as_str()) {
+            eprintln!("[FocusFlow] Deep link {} rejected: {}", url, e);
+            let _ = app.emit("deeplink:error", e);
+        }
+    }
+}
+
+#[tauri::command]
+async fn open_deep_link(app: tauri::AppHandle, url: String) -> Result<DeepLinkPayload, String> {
+    handle_deep_link(&app, &url)
 }
 
+// --- WINDOW ---
+
 #[cfg(not(mobile))]
 #[tauri::command]
 async fn toggle_window(window: tauri::Window) {
@@ -549,8 +2462,23 @@ async fn minimize_window(window: tauri::Window) {
 // Mobile entry point
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    let builder = tauri::Builder::default();
+    // Registered first: on Windows and Linux every focusflow:// link starts a
+    // new process, which hands its arguments to the running app and exits
+    // before it opens the database. With the `deep-link` feature the plugin
+    // passes the links on to `on_open_url` below, and so to
+    // `handle_deep_links`; handling `argv` here as well would run each link
+    // twice.
+    #[cfg(not(mobile))]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+        if let Some(w) = app.get_webview_window("main") {
+            let _ = w.show();
+            let _ = w.set_focus();
+        }
+    }));
+    builder
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_deep_link::init())
         .setup(|app| {
             let app_handle = app.handle();
             let app_dir = app_handle
@@ -559,8 +2487,39 @@ pub fn run() {
                 .expect("failed to get app data dir");
 
             let db = AppDatabase::new(app_dir).expect("failed to initialize database");
+            println!("[FocusFlow] DB path: {}", db.db_path().display());
+
+            let api_settings = db.get_api_settings();
+
+            app.manage(AppState {
+                db: Mutex::new(db),
+                api: Mutex::new(None),
+            });
+
+            // Local HTTP API (opt-in, 127.0.0.1 only)
+            match api_settings {
+                Ok(settings) => {
+                    if let Err(e) =
+                        apply_api_settings(app.handle(), &app.state::<AppState>(), &settings)
+                    {
+                        eprintln!("[FocusFlow] Local API not started: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("[FocusFlow] Failed to load API settings: {}", e),
+            }
 
-            app.manage(AppState { db: Mutex::new(db) });
+            // Deep links (focusflow://add, focusflow://task/<id>, focusflow://focus/<id>)
+            #[cfg(any(windows, target_os = "linux"))]
+            {
+                app.deep_link().register_all()?;
+                if let Ok(Some(urls)) = app.deep_link().get_current() {
+                    handle_deep_links(app.handle(), urls);
+                }
+            }
+            let link_handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                handle_deep_links(&link_handle, event.urls());
+            });
 
             // Reminder loop (background thread) - skip on mobile for battery saving
             #[cfg(not(mobile))]
@@ -577,6 +2536,25 @@ pub fn run() {
                         Err(_) => continue,
                     };
 
+                    // Old trash goes first: the rest of the tick bails out early
+                    if let Ok(purged) = db_guard.purge_expired_trash(now_ms) {
+                        if purged > 0 {
+                            emit_data_changed(&app_handle2, "trash", "refresh", None);
+                        }
+                    }
+
+                    // Open tasks scheduled for a past day move onto today
+                    let today = chrono::Local::now().date_naive();
+                    if let Ok(moved) = db_guard.roll_over_scheduled(today) {
+                        if !moved.is_empty() {
+                            emit_data_changed(&app_handle2, "tasks", "refresh", None);
+                            emit_data_changed(&app_handle2, "stats", "refresh", None);
+                        }
+                    }
+
+                    // Unlocks from elsewhere, e.g. the CLI
+                    emit_achievements(&app_handle2, &db_guard);
+
                     let due = match db_guard.get_due_reminders(now_ms) {
                         Ok(d) => d,
                         Err(_) => continue,
@@ -595,6 +2573,12 @@ pub fn run() {
                     }
 
                     for t in due {
+                        fire_hook(
+                            &app_handle2,
+                            &db_guard,
+                            "reminder.fired",
+                            serde_json::json!({ "kind": "task", "task": &t }),
+                        );
                         let _ = app_handle2.emit(
                             "reminder:due",
                             ReminderPayload {
@@ -604,6 +2588,99 @@ pub fn run() {
                             },
                         );
                     }
+                    // Loan Reminders
+                    // ---------------------------------------------------------
+                    let loans = match db_guard.get_active_loans() {
+                        Ok(l) => l,
+                        Err(_) => Vec::new(), // ignore error
+                    };
+
+                    let today = chrono::Local::now().date_naive();
+                    let today_str = today.format("%Y-%m-%d").to_string();
+
+                    for (id, person, payment_day, last_reminded) in loans {
+                        if let Some(last) = last_reminded {
+                            if last == today_str {
+                                continue; // Already reminded today
+                            }
+                        }
+
+                        // Check if due in 1, 2, or 3 days
+                        // Simple logic: Construct target date for this month
+                        let target_date = match chrono::NaiveDate::from_ymd_opt(
+                            today.year(),
+                            today.month(),
+                            payment_day as u32,
+                        ) {
+                            Some(d) => d,
+                            None => continue, // Invalid date (e.g. Feb 30), skip for now
+                        };
+
+                        // If target is in past, maybe it's next month?
+                        // E.g. Today 25th, Payment 10th. Target (ThisMonth-10) is past.
+                        // We only care about UPCOMING.
+                        // Cases:
+                        // Payment 25. Today 22. Diff 3.
+                        // Payment 2. Today 30. Diff 2 (approx).
+
+                        let mut days_diff = (target_date - today).num_days();
+
+                        if days_diff < 0 {
+                            // Try next month
+                            let next_month_date = if today.month() == 12 {
+                                chrono::NaiveDate::from_ymd_opt(
+                                    today.year() + 1,
+                                    1,
+                                    payment_day as u32,
+                                )
+                            } else {
+                                chrono::NaiveDate::from_ymd_opt(
+                                    today.year(),
+                                    today.month() + 1,
+                                    payment_day as u32,
+                                )
+                            };
+
+                            if let Some(nm) = next_month_date {
+                                days_diff = (nm - today).num_days();
+                            }
+                        }
+
+                        if days_diff >= 1 && days_diff <= 3 {
+                            // Trigger reminder
+                            let _ = app_handle2.emit(
+                                "reminder:due",
+                                ReminderPayload {
+                                    task_id: id.clone(),
+                                    title: format!(
+                                        "Платеж по кредиту: {} (через {} дн.)",
+                                        person, days_diff
+                                    ),
+                                    deadline: None,
+                                },
+                            );
+                            fire_hook(
+                                &app_handle2,
+                                &db_guard,
+                                "reminder.fired",
+                                serde_json::json!({
+                                    "kind": "loan",
+                                    "debt_id": id,
+                                    "person": person,
+                                    "days_until_payment": days_diff,
+                                }),
+                            );
+                            // Mark as reminded
+                            let _ = db_guard.update_last_reminded(&id, &today_str);
+
+                            // Also show window if not shown
+                            if let Some(w) = app_handle2.get_webview_window("main") {
+                                let _ = w.show();
+                                let _ = w.set_focus();
+                            }
+                        }
+                    }
+                    // ---------------------------------------------------------
                 });
             }
 
@@ -632,8 +2709,18 @@ pub fn run() {
                     export_data,
                     import_data,
                     get_completion_series,
+                    get_change_seq,
                     get_settings,
                     save_settings,
+                    get_api_settings,
+                    save_api_settings,
+                    regenerate_api_token,
+                    get_hooks,
+                    add_hook,
+                    set_hook_enabled,
+                    delete_hook,
+                    get_hook_deliveries,
+                    test_hook,
                     set_task_remind_at,
                     snooze_task_reminder,
                     get_projects,
@@ -641,15 +2728,81 @@ pub fn run() {
                     edit_project,
                     update_project_priority,
                     delete_project,
+                    get_project_tree,
+                    move_project,
+                    update_project_details,
+                    reorder_projects,
+                    archive_project,
+                    unarchive_project,
+                    get_sections,
+                    add_section,
+                    rename_section,
+                    reorder_sections,
+                    archive_section,
+                    unarchive_section,
+                    move_task_to_section,
+                    get_boards,
+                    get_board,
+                    create_board,
+                    rename_board,
+                    save_board_columns,
+                    delete_board,
+                    move_card,
                     get_tasks,
+                    get_agenda,
+                    get_daily_plan,
+                    add_to_daily_plan,
+                    remove_from_daily_plan,
+                    reorder_daily_plan,
+                    carry_over_daily_plan,
+                    get_time_blocks,
+                    add_time_block,
+                    move_time_block,
+                    resize_time_block,
+                    delete_time_block,
+                    complete_time_block,
+                    auto_schedule,
+                    get_time_entries,
+                    get_running_timer,
+                    start_timer,
+                    stop_timer,
+                    add_time_entry,
+                    update_time_entry,
+                    delete_time_entry,
+                    get_time_rollup,
+                    get_timesheet,
+                    export_timesheet,
                     add_task,
                     edit_task_title,
                     update_task_priority,
                     update_task_deadline,
                     update_task_tags,
+                    get_tags,
+                    rename_tag,
+                    merge_tags,
+                    set_tag_color,
+                    delete_tag,
+                    get_templates,
+                    save_task_as_template,
+                    save_project_as_template,
+                    instantiate_template,
+                    delete_template,
                     update_task_repeat,
                     update_task_status,
+                    update_task,
                     delete_task,
+                    duplicate_task,
+                    bulk_update_tasks,
+                    link_tasks,
+                    unlink_tasks,
+                    get_task_history,
+                    get_trash,
+                    restore_from_trash,
+                    purge_trash_item,
+                    empty_trash,
+                    get_undo_status,
+                    undo,
+                    redo,
                     reorder_tasks,
                     archive_task,
                     unarchive_task,
@@ -657,8 +2810,20 @@ pub fn run() {
                     add_subtask,
                     toggle_subtask,
                     delete_subtask,
+                    promote_subtask_to_task,
+                    demote_task_to_subtask,
                     reorder_subtasks,
                     get_stats,
+                    get_estimate_accuracy,
+                    get_estimate_correction,
+                    get_gamification,
+                    get_points_ledger,
+                    get_gamification_rules,
+                    save_gamification_rules,
+                    get_streak,
+                    get_daily_activity,
+                    freeze_streak_day,
+                    unfreeze_streak_day,
                     start_focus_session,
                     complete_focus_session,
                     cancel_focus_session,
@@ -668,7 +2833,8 @@ pub fn run() {
                     delete_transaction,
                     add_debt,
                     pay_debt,
-                    delete_debt
+                    delete_debt,
+                    open_deep_link
                 ]
             }
             #[cfg(not(mobile))]
@@ -678,8 +2844,18 @@ pub fn run() {
                     export_data,
                     import_data,
                     get_completion_series,
+                    get_change_seq,
                     get_settings,
                     save_settings,
+                    get_api_settings,
+                    save_api_settings,
+                    regenerate_api_token,
+                    get_hooks,
+                    add_hook,
+                    set_hook_enabled,
+                    delete_hook,
+                    get_hook_deliveries,
+                    test_hook,
                     set_task_remind_at,
                     snooze_task_reminder,
                     get_projects,
@@ -687,15 +2863,81 @@ pub fn run() {
                     edit_project,
                     update_project_priority,
                     delete_project,
+                    get_project_tree,
+                    move_project,
+                    update_project_details,
+                    reorder_projects,
+                    archive_project,
+                    unarchive_project,
+                    get_sections,
+                    add_section,
+                    rename_section,
+                    reorder_sections,
+                    archive_section,
+                    unarchive_section,
+                    move_task_to_section,
+                    get_boards,
+                    get_board,
+                    create_board,
+                    rename_board,
+                    save_board_columns,
+                    delete_board,
+                    move_card,
                     get_tasks,
+                    get_agenda,
+                    get_daily_plan,
+                    add_to_daily_plan,
+                    remove_from_daily_plan,
+                    reorder_daily_plan,
+                    carry_over_daily_plan,
+                    get_time_blocks,
+                    add_time_block,
+                    move_time_block,
+                    resize_time_block,
+                    delete_time_block,
+                    complete_time_block,
+                    auto_schedule,
+                    get_time_entries,
+                    get_running_timer,
+                    start_timer,
+                    stop_timer,
+                    add_time_entry,
+                    update_time_entry,
+                    delete_time_entry,
+                    get_time_rollup,
+                    get_timesheet,
+                    export_timesheet,
                     add_task,
                     edit_task_title,
                     update_task_priority,
                     update_task_deadline,
                     update_task_tags,
+                    get_tags,
+                    rename_tag,
+                    merge_tags,
+                    set_tag_color,
+                    delete_tag,
+                    get_templates,
+                    save_task_as_template,
+                    save_project_as_template,
+                    instantiate_template,
+                    delete_template,
                     update_task_repeat,
                     update_task_status,
+                    update_task,
                     delete_task,
+                    duplicate_task,
+                    bulk_update_tasks,
+                    link_tasks,
+                    unlink_tasks,
+                    get_task_history,
+                    get_trash,
+                    restore_from_trash,
+                    purge_trash_item,
+                    empty_trash,
+                    get_undo_status,
+                    undo,
+                    redo,
                     reorder_tasks,
                     archive_task,
                     unarchive_task,
@@ -703,8 +2945,20 @@ pub fn run() {
                     add_subtask,
                     toggle_subtask,
                     delete_subtask,
+                    promote_subtask_to_task,
+                    demote_task_to_subtask,
                     reorder_subtasks,
                     get_stats,
+                    get_estimate_accuracy,
+                    get_estimate_correction,
+                    get_gamification,
+                    get_points_ledger,
+                    get_gamification_rules,
+                    save_gamification_rules,
+                    get_streak,
+                    get_daily_activity,
+                    freeze_streak_day,
+                    unfreeze_streak_day,
                     start_focus_session,
                     complete_focus_session,
                     cancel_focus_session,
@@ -715,6 +2969,7 @@ pub fn run() {
                     add_debt,
                     pay_debt,
                     delete_debt,
+                    open_deep_link,
                     toggle_window,
                     minimize_window
                 ]
@@ -724,7 +2979,7 @@ pub fn run() {
         .expect("error while running tauri application");
 }
 
-// --- Finance Commands ---
+// --- FINANCE ---
 
 #[derive(serde::Serialize)]
 struct FinanceSummary {
@@ -752,7 +3007,10 @@ async fn add_transaction(
     description: Option<String>,
     is_expense: bool,
 ) -> Result<models::Transaction, String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Add transaction",
+    )?;
     let id = uuid::Uuid::new_v4().to_string();
     let t = models::NewTransaction {
         id: id.clone(),
@@ -764,7 +3022,7 @@ async fn add_transaction(
     };
     let created = db.add_transaction(t).map_err(|e| e.to_string())?;
 
-    emit_data_changed(&app, "finance", "add_transaction", Some(id));
+    emit_entity_changed(&app, &db, "finance", "add_transaction", &id, None);
     Ok(created)
 }
 
@@ -774,9 +3032,13 @@ async fn delete_transaction(
     app: tauri::AppHandle,
     id: String,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Delete transaction",
+    )?;
+    let old = db.snapshot("finance", Some(&id));
     db.delete_transaction(&id).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "finance", "delete_transaction", Some(id));
+    emit_entity_changed(&app, &db, "finance", "delete_transaction", &id, old);
     Ok(())
 }
 
@@ -793,7 +3055,10 @@ async fn add_debt(
     payment_day: Option<i32>,
     initial_amount: Option<f64>,
 ) -> Result<models::Debt, String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Add debt",
+    )?;
     let id = uuid::Uuid::new_v4().to_string();
     let d = models::NewDebt {
         id: id.clone(),
@@ -809,7 +3074,7 @@ async fn add_debt(
     };
     let created = db.add_debt(d).map_err(|e| e.to_string())?;
 
-    emit_data_changed(&app, "finance", "add_debt", Some(id));
+    emit_entity_changed(&app, &db, "finance", "add_debt", &id, None);
     Ok(created)
 }
 
@@ -819,9 +3084,18 @@ async fn pay_debt(
     app: tauri::AppHandle,
     id: String,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Pay debt",
+    )?;
+    let old = db.snapshot("finance", Some(&id));
     db.pay_debt(&id).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "finance", "pay_debt", Some(id));
+    if let Ok((_, debts)) = db.get_finance_summary() {
+        if let Some(debt) = debts.into_iter().find(|d| d.id == id) {
+            fire_hook(&app, &db, "debt.paid", serde_json::json!(debt));
+        }
+    }
+    emit_entity_changed(&app, &db, "finance", "pay_debt", &id, old);
     Ok(())
 }
 
@@ -831,8 +3105,12 @@ async fn delete_debt(
     app: tauri::AppHandle,
     id: String,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|_| "Failed to lock db")?;
+    let db = UndoStep::begin(
+        state.db.lock().map_err(|_| "Failed to lock db")?,
+        "Delete debt",
+    )?;
+    let old = db.snapshot("finance", Some(&id));
     db.delete_debt(&id).map_err(|e| e.to_string())?;
-    emit_data_changed(&app, "finance", "delete_debt", Some(id));
+    emit_entity_changed(&app, &db, "finance", "delete_debt", &id, old);
     Ok(())
 }
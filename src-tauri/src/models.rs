@@ -1,4 +1,14 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Distinguishes a missing field (`None`) from an explicit `null` (`Some(None)`)
+/// in patch requests.
+pub fn nullable<'de, D, T>(d: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(d).map(Some)
+}
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[repr(i32)]
@@ -40,6 +50,35 @@ impl Status {
     }
 }
 
+/// Which list an open task shows in: due or scheduled by the end of today,
+/// scheduled or due later, or neither.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Agenda {
+    Today,
+    Upcoming,
+    Anytime,
+}
+
+impl Agenda {
+    pub fn parse(val: &str) -> Result<Self, String> {
+        match val {
+            "today" => Ok(Agenda::Today),
+            "upcoming" => Ok(Agenda::Upcoming),
+            "anytime" => Ok(Agenda::Anytime),
+            other => Err(format!("Invalid agenda: {}", other)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Agenda::Today => "today",
+            Agenda::Upcoming => "upcoming",
+            Agenda::Anytime => "anytime",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
     pub id: String,
@@ -49,6 +88,211 @@ pub struct Project {
     pub created_at: i64,           // UNIX ms
     pub parent_id: Option<String>, // For folder hierarchy
     pub is_folder: bool,           // True if this is a folder, false if regular project
+
+    // v9 fields (defaulted so older backups still import)
+    #[serde(default)]
+    pub sort_order: i32,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>, // emoji or icon name
+    #[serde(default)]
+    pub archived_at: Option<i64>, // UNIX ms
+    #[serde(default)]
+    pub default_priority: Option<Priority>, // applied to new tasks in this project
+    #[serde(default)]
+    pub default_tags: Vec<String>,
+}
+
+/// Partial project update; omitted fields are left unchanged and `null`
+/// clears the nullable ones.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectPatch {
+    pub name: Option<String>,
+    pub color: Option<String>,
+    pub priority: Option<Priority>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub icon: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub default_priority: Option<Option<Priority>>,
+    pub default_tags: Option<Vec<String>>,
+}
+
+/// Partial update for `update_task`: missing fields are left alone, `null`
+/// clears the nullable ones. Setting `section_id` alone also moves the task to
+/// the section's project; changing `project_id` alone drops the section.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskPatch {
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub project_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub section_id: Option<Option<String>>,
+    pub priority: Option<Priority>,
+    pub status: Option<Status>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub deadline: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub remind_at: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub scheduled_at: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub estimated_minutes: Option<Option<u32>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub actual_minutes: Option<Option<u32>>,
+    pub tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub repeat_mode: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub repeat_days_mask: Option<Option<i64>>,
+    /// The update is refused if the task is no longer at this revision.
+    pub expected_revision: Option<i64>,
+}
+
+/// Changes applied to every task of a `bulk_update_tasks` call. Missing fields
+/// are left alone; `project_id: null` moves the tasks to Inbox.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskBulkPatch {
+    pub status: Option<Status>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub project_id: Option<Option<String>>,
+    pub priority: Option<Priority>,
+    /// Moves deadlines (and pending reminders) by this many minutes; tasks
+    /// without a deadline are left alone.
+    pub deadline_shift_minutes: Option<i64>,
+    #[serde(default)]
+    pub add_tags: Vec<String>,
+    #[serde(default)]
+    pub remove_tags: Vec<String>,
+    pub archived: Option<bool>,
+    /// Moves the tasks to the trash after the other changes.
+    #[serde(default)]
+    pub delete: bool,
+}
+
+/// Outcome for one id of a bulk update.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkTaskResult {
+    pub id: String,
+    pub error: Option<String>,
+    /// Next occurrence spawned by completing a recurring task.
+    pub spawned_task_id: Option<String>,
+}
+
+/// Bulk updates are all-or-nothing: when any id fails, `applied` is false and
+/// nothing was changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkUpdateResult {
+    pub applied: bool,
+    pub results: Vec<BulkTaskResult>,
+}
+
+/// Project with its children and task counts, as returned by `get_project_tree`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectNode {
+    #[serde(flatten)]
+    pub project: Project,
+    pub task_count: i64,       // tasks directly in this project
+    pub open_task_count: i64,  // of which not done
+    pub total_task_count: i64, // including all descendants
+    pub children: Vec<ProjectNode>,
+}
+
+/// What happens to a deleted project's children and tasks.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectDeletePolicy {
+    /// Delete the project, its descendants and all their tasks.
+    Cascade,
+    /// Move children to the project's parent; its own tasks go to Inbox.
+    Reparent,
+    /// Delete the project and its descendants; all their tasks go to Inbox.
+    Inbox,
+}
+
+impl ProjectDeletePolicy {
+    pub fn parse(val: &str) -> Result<Self, String> {
+        match val {
+            "cascade" => Ok(ProjectDeletePolicy::Cascade),
+            "reparent" => Ok(ProjectDeletePolicy::Reparent),
+            "inbox" => Ok(ProjectDeletePolicy::Inbox),
+            other => Err(format!("Invalid delete policy: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ProjectDeleteSummary {
+    pub deleted_project_ids: Vec<String>,
+    pub reparented_project_ids: Vec<String>,
+    pub deleted_task_ids: Vec<String>,
+    pub moved_task_ids: Vec<String>,
+}
+
+/// Heading that groups tasks inside a project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Section {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub sort_order: i32,
+    pub archived_at: Option<i64>, // UNIX ms
+    pub created_at: i64,          // UNIX ms
+}
+
+/// Kanban board for one project. Cards are the project's tasks; each column
+/// maps to a status, so moving a card changes the task's status.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Board {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub created_at: i64, // UNIX ms
+    pub columns: Vec<BoardColumn>,
+    #[serde(default)]
+    pub cards: Vec<BoardCard>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardColumn {
+    pub id: String,
+    pub name: String,
+    pub category: Status,
+    pub wip_limit: Option<u32>,
+    pub sort_order: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BoardCard {
+    pub task_id: String,
+    pub column_id: String,
+    pub position: i32,
+}
+
+/// Column definition for `create_board` / `save_board_columns`; columns
+/// without an id are created.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardColumnInput {
+    pub id: Option<String>,
+    pub name: String,
+    pub category: Status,
+    pub wip_limit: Option<u32>,
+}
+
+/// Result of `AppDatabase::move_card`.
+#[derive(Debug, Clone)]
+pub struct CardMove {
+    pub board: Board,
+    pub status_changed: bool,
+    pub spawned_task_id: Option<String>, // next occurrence of a recurring task
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +331,26 @@ pub struct Task {
     pub is_archived: bool,
     pub sort_order: i32,
     pub subtasks: Vec<Subtask>,
+
+    #[serde(default)]
+    pub section_id: Option<String>,
+
+    // dependencies
+    #[serde(default)]
+    pub depends_on: Vec<String>, // ids of tasks that must be done first
+    #[serde(default)]
+    pub is_blocked: bool, // some task in `depends_on` is not done yet
+
+    #[serde(default)]
+    pub updated_at: i64, // UNIX ms
+    #[serde(default)]
+    pub revision: i64, // bumped on every change, for `TaskPatch::expected_revision`
+
+    // "do on" date, local: "YYYY-MM-DD" or "YYYY-MM-DDTHH:MM"
+    #[serde(default)]
+    pub scheduled_at: Option<String>,
+    #[serde(default)]
+    pub agenda: Option<Agenda>, // None once done or archived
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -107,6 +371,11 @@ pub struct NewTask {
 
     pub repeat_mode: Option<String>,
     pub repeat_days_mask: Option<i64>,
+
+    #[serde(default)]
+    pub section_id: Option<String>, // must belong to `project_id`
+    #[serde(default)]
+    pub scheduled_at: Option<String>, // see `Task::scheduled_at`
 }
 
 #[allow(dead_code)]
@@ -138,6 +407,234 @@ pub struct UserStats {
     pub points: i32,
 }
 
+/// One local day's progress toward the daily goal, measured against the goal
+/// in effect on that day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyActivity {
+    pub day: String, // YYYY-MM-DD
+    pub tasks_done: u32,
+    pub focus_minutes: u32,
+    pub goal_tasks: u32,
+    pub goal_focus_minutes: u32,
+    pub goal_met: bool,
+    /// Keeps a streak going without counting toward it.
+    pub frozen: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreakStatus {
+    pub current: u32,
+    pub best: u32,
+    pub today: DailyActivity,
+    pub freezes_left: u32, // this month
+}
+
+/// Points per ledger event, and the point totals levels start at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GamificationRules {
+    pub complete_low: i64,
+    pub complete_normal: i64,
+    pub complete_high: i64,
+    /// Extra points for completing a task by its deadline.
+    pub on_time_bonus: i64,
+    /// Per minute of a finished focus session.
+    pub focus_minute: i64,
+    /// For the first completion of each day that continues a streak.
+    pub streak_day: i64,
+    /// Points at which level 2, 3, ... start.
+    pub level_thresholds: Vec<i64>,
+}
+
+impl Default for GamificationRules {
+    fn default() -> Self {
+        GamificationRules {
+            complete_low: 10,
+            complete_normal: 20,
+            complete_high: 30,
+            on_time_bonus: 10,
+            focus_minute: 1,
+            streak_day: 5,
+            level_thresholds: vec![100, 250, 500, 1000, 2000, 3500, 5500, 8000, 11000, 15000],
+        }
+    }
+}
+
+/// One award in the points ledger.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointsEntry {
+    pub id: i64,
+    /// "complete_low" | "complete_normal" | "complete_high" | "on_time" |
+    /// "focus" | "streak_day"
+    pub event: String,
+    pub ref_id: Option<String>, // task, focus session or day
+    pub quantity: i64,          // focus minutes, streak length, else 1
+    pub points: i64,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub unlocked_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamificationStatus {
+    pub points: i64,
+    pub level: u32,
+    pub level_start: i64,           // points the current level started at
+    pub next_level_at: Option<i64>, // None at the top level
+    pub achievements: Vec<Achievement>,
+}
+
+/// How estimates compared with the time done tasks took, for one project,
+/// tag or priority. Ratios are actual / estimated minutes, so above 1 means
+/// a task took longer than estimated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EstimateAccuracy {
+    pub key: Option<String>, // project id, tag or priority; None for Inbox and untagged
+    pub label: String,
+    pub tasks: u32,
+    pub median_ratio: f64,
+    pub underestimated: u32,               // took longer than estimated
+    pub median_underestimate: Option<f64>, // actual / estimate of those
+    pub overestimated: u32,                // took less than estimated
+    pub median_overestimate: Option<f64>,  // estimate / actual of those
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EstimateReport {
+    pub overall: EstimateAccuracy,
+    pub by_project: Vec<EstimateAccuracy>,
+    pub by_tag: Vec<EstimateAccuracy>,
+    pub by_priority: Vec<EstimateAccuracy>,
+}
+
+/// What to multiply a new estimate by. Taken from the project's own history
+/// once it has enough done tasks, from all tasks before that.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EstimateCorrection {
+    pub project_id: Option<String>,
+    pub factor: f64,
+    pub samples: u32,
+    pub from_project: bool,
+}
+
+/// The tasks planned for one day, in plan order, with the planned estimates
+/// against the focus time the pomodoro settings leave for the day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyPlan {
+    pub date: String, // local "YYYY-MM-DD"
+    pub tasks: Vec<Task>,
+    pub planned_minutes: u32,   // estimates of every planned task
+    pub remaining_minutes: u32, // estimates of the unfinished ones
+    pub unestimated_count: u32, // unfinished tasks without an estimate
+    pub available_minutes: u32, // pomodoro_length * daily_pomodoros
+    pub focused_minutes: u32,   // completed focus sessions that day
+}
+
+/// A calendar slot, for a task or on its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeBlock {
+    pub id: String,
+    pub task_id: Option<String>,
+    pub title: String, // the task's title unless set
+    pub start_at: i64, // UNIX ms
+    pub end_at: i64,   // UNIX ms
+    pub completed_at: Option<i64>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeBlockCompletion {
+    pub block: TimeBlock,
+    pub focus_session_id: Option<String>, // when a session was started
+}
+
+/// Blocks made by `auto_schedule`, and the tasks that found no free slot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoScheduleResult {
+    pub blocks: Vec<TimeBlock>,
+    pub unscheduled_task_ids: Vec<String>,
+}
+
+/// Time tracked on a task, with the start/stop timer or entered by hand. At
+/// most one entry runs (has no `ended_at`) at a time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeEntry {
+    pub id: String,
+    pub task_id: String,
+    pub task_title: String,
+    pub note: Option<String>,
+    pub started_at: i64,       // UNIX ms
+    pub ended_at: Option<i64>, // UNIX ms, None while running
+    pub minutes: u32,          // up to now while running
+    pub created_at: i64,
+}
+
+/// Changes to a time entry. Missing fields are left alone; `note: null`
+/// clears the note and an `ended_at` stops a running entry.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeEntryPatch {
+    pub task_id: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub note: Option<Option<String>>,
+    pub started_at: Option<i64>,
+    pub ended_at: Option<i64>,
+}
+
+/// What tracked time is summed by in a rollup.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeRollupGroup {
+    Task,
+    Project,
+    Tag,
+    Day,
+}
+
+impl TimeRollupGroup {
+    pub fn parse(val: &str) -> Result<Self, String> {
+        match val {
+            "task" => Ok(TimeRollupGroup::Task),
+            "project" => Ok(TimeRollupGroup::Project),
+            "tag" => Ok(TimeRollupGroup::Tag),
+            "day" => Ok(TimeRollupGroup::Day),
+            other => Err(format!("Invalid rollup group: {}", other)),
+        }
+    }
+}
+
+/// Tracked minutes for one task, project, tag or day. `key` is None for
+/// tasks without a project or tags.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeRollup {
+    pub key: Option<String>,
+    pub label: String,
+    pub minutes: u32,
+    pub entries: u32,
+}
+
+/// One finished time entry or focus session, as exported in a timesheet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimesheetEntry {
+    pub source: String, // "time_entry" | "focus_session"
+    pub id: String,
+    pub task_id: String,
+    pub task_title: String,
+    pub project_id: Option<String>,
+    pub project_name: Option<String>,
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub minutes: u32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSettings {
     pub pomodoro_length: u32,
@@ -152,6 +649,62 @@ pub struct AppSettings {
     pub close_to_tray: bool,
 
     pub reminder_lead_minutes: u32,
+
+    /// Days an item stays in the trash before it is purged (0 = never).
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u32,
+
+    /// Pomodoros a day; with `pomodoro_length` this is the focus time a daily
+    /// plan is measured against.
+    #[serde(default = "default_daily_pomodoros")]
+    pub daily_pomodoros: u32,
+
+    /// Working hours for auto-scheduling time blocks, in minutes after local
+    /// midnight, on the days in `work_days_mask` (bit 0 = Monday).
+    #[serde(default = "default_work_start_minutes")]
+    pub work_start_minutes: u32,
+    #[serde(default = "default_work_end_minutes")]
+    pub work_end_minutes: u32,
+    #[serde(default = "default_work_days_mask")]
+    pub work_days_mask: i64,
+
+    /// A day meets the goal with this many completed tasks or this many
+    /// focus minutes; 0 leaves that half out.
+    #[serde(default = "default_daily_goal_tasks")]
+    pub daily_goal_tasks: u32,
+    #[serde(default)]
+    pub daily_goal_focus_minutes: u32,
+    /// Days a month that can be frozen to keep a streak going.
+    #[serde(default = "default_streak_freezes_per_month")]
+    pub streak_freezes_per_month: u32,
+}
+
+fn default_trash_retention_days() -> u32 {
+    30
+}
+
+fn default_daily_pomodoros() -> u32 {
+    8
+}
+
+fn default_work_start_minutes() -> u32 {
+    9 * 60
+}
+
+fn default_work_end_minutes() -> u32 {
+    17 * 60
+}
+
+fn default_work_days_mask() -> i64 {
+    0b0011111 // Monday to Friday
+}
+
+fn default_daily_goal_tasks() -> u32 {
+    1
+}
+
+fn default_streak_freezes_per_month() -> u32 {
+    2
 }
 
 impl Default for AppSettings {
@@ -168,9 +721,174 @@ impl Default for AppSettings {
             start_minimized: false,
             close_to_tray: true,
             reminder_lead_minutes: 30,
+            trash_retention_days: default_trash_retention_days(),
+            daily_pomodoros: default_daily_pomodoros(),
+            work_start_minutes: default_work_start_minutes(),
+            work_end_minutes: default_work_end_minutes(),
+            work_days_mask: default_work_days_mask(),
+            daily_goal_tasks: default_daily_goal_tasks(),
+            daily_goal_focus_minutes: 0,
+            streak_freezes_per_month: default_streak_freezes_per_month(),
         }
     }
 }
+/// Opt-in localhost HTTP API. Stored separately from `AppSettings` so the
+/// token is never part of a backup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    pub token: String,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 17345,
+            token: String::new(),
+        }
+    }
+}
+
+/// Outgoing hook: POSTs event JSON to a URL or pipes it to a local command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hook {
+    pub id: String,
+    pub event: String,  // "task.completed", ... or "*" for all events
+    pub kind: String,   // "http" | "command"
+    pub target: String, // URL or shell command line
+    pub enabled: bool,
+    pub created_at: i64, // UNIX ms
+}
+
+/// One delivery of an event to a hook, after all retries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookDelivery {
+    pub id: String,
+    pub hook_id: String,
+    pub event: String,
+    pub payload: String,
+    pub attempts: u32,
+    pub success: bool,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    pub created_at: i64, // UNIX ms
+}
+
+/// Backup file format shared by the app's export/import and `focusflow-cli`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportBundle {
+    pub version: u32,
+    pub exported_at: i64, // UNIX ms
+    pub projects: Vec<Project>,
+    #[serde(default)]
+    pub sections: Vec<Section>,
+    #[serde(default)]
+    pub boards: Vec<Board>,
+    pub tasks: Vec<Task>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    pub settings: AppSettings,
+}
+
+/// A task captured in a template. Dates are kept as minutes from the anchor,
+/// so instantiating against a new anchor shifts them all together.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TemplateTask {
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub priority: Priority,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub estimated_minutes: Option<u32>,
+    #[serde(default)]
+    pub repeat_mode: Option<String>,
+    #[serde(default)]
+    pub repeat_days_mask: Option<i64>,
+    #[serde(default)]
+    pub deadline_offset_minutes: Option<i64>,
+    #[serde(default)]
+    pub remind_offset_minutes: Option<i64>,
+    #[serde(default)]
+    pub subtasks: Vec<String>, // titles, in order
+    #[serde(default)]
+    pub section: Option<String>, // section name, project templates only
+    #[serde(default)]
+    pub section_index: Option<usize>, // position in TemplateProject::sections
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TemplateProject {
+    pub name: String,
+    pub color: String,
+    pub priority: Priority,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub default_priority: Option<Priority>,
+    #[serde(default)]
+    pub default_tags: Vec<String>,
+    #[serde(default)]
+    pub sections: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TemplateBody {
+    Task {
+        task: TemplateTask,
+    },
+    Project {
+        project: TemplateProject,
+        tasks: Vec<TemplateTask>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Template {
+    pub id: String,
+    pub name: String,
+    pub created_at: i64, // UNIX ms
+    #[serde(flatten)]
+    pub body: TemplateBody,
+}
+
+/// Options for `instantiate_template`. `project_id` places a task template's
+/// task; `parent_id` is the folder for a project template's project.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateInstance {
+    pub anchor: Option<i64>,  // UNIX ms, defaults to now
+    pub name: Option<String>, // overrides the task title or project name
+    pub project_id: Option<String>,
+    pub parent_id: Option<String>,
+}
+
+/// What `instantiate_template` created.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InstantiatedTemplate {
+    pub project_id: Option<String>,
+    pub task_ids: Vec<String>,
+}
+
+/// A tag with how many live tasks carry it; `open_count` leaves out done and
+/// archived ones.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    pub color: Option<String>,
+    #[serde(default)]
+    pub task_count: i64,
+    #[serde(default)]
+    pub open_count: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Transaction {
@@ -223,3 +941,74 @@ pub struct NewDebt {
     pub payment_day: Option<i32>,
     pub initial_amount: Option<f64>,
 }
+
+/// A soft-deleted item as listed in the trash. Items deleted together with a
+/// parent (tasks of a cascaded project, sub-projects) are listed once, under
+/// that parent.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrashItem {
+    /// "task" | "subtask" | "project" | "transaction" | "debt"
+    pub entity: String,
+    pub id: String,
+    pub title: String,
+    pub deleted_at: i64,
+    /// Owning task for subtasks, project for tasks, parent for projects.
+    pub parent_id: Option<String>,
+}
+
+/// What `undo` and `redo` would revert or re-apply next, by step label.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UndoStatus {
+    pub undo: Option<String>,
+    pub redo: Option<String>,
+}
+
+/// One entry of a task's history. `action` is "created" or "updated"; updates
+/// name the changed column in `field` with its values as text (enums as their
+/// stored numbers, e.g. status 2 = done). Tag changes are logged one tag at a
+/// time under "tags": a new value when added, an old value when removed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TaskEvent {
+    pub id: i64,
+    pub task_id: String,
+    pub action: String,
+    pub field: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub created_at: i64,
+}
+
+/// Position of a task or subtask, for reorder events.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SortOrder {
+    pub id: String,
+    pub sort_order: i32,
+}
+
+/// State of an entity before or after a change, as carried in `data:changed`.
+/// The event's `entity` says which variant to expect, so it is serialized
+/// untagged. Batch events (bulk edits, reorders) carry `Many`, with `None`
+/// for an item that doesn't exist on that side of the change.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Snapshot {
+    Task(Task),
+    Subtask(Subtask),
+    Project(Project),
+    Section(Section),
+    Board(Board),
+    Hook(Hook),
+    Template(Template),
+    TimeBlock(TimeBlock),
+    TimeEntry(TimeEntry),
+    DailyActivity(DailyActivity),
+    DailyPlan(DailyPlan),
+    Transaction(Transaction),
+    Debt(Debt),
+    Achievement(Achievement),
+    Settings(AppSettings),
+    ApiSettings(ApiSettings),
+    GamificationRules(GamificationRules),
+    Order(Vec<SortOrder>),
+    Many(Vec<Option<Snapshot>>),
+}
@@ -13,12 +13,6 @@ export type NewTask = tauri.NewTask;
 export type UserStats = tauri.UserStats;
 export type AppSettings = tauri.AppSettings;
 
-type DataChanged = {
-  entity: "tasks" | "projects" | "stats" | "settings";
-  action: "add" | "edit" | "delete" | "status" | "refresh";
-  id?: string | null;
-};
-
 export function useDatabase() {
   const [tasks, setTasks] = useState<Task[]>([]);
   const [projects, setProjects] = useState<Project[]>([]);
@@ -68,7 +62,7 @@ export function useDatabase() {
     (async () => {
       try {
         await loadData();
-        unlisten = await listen<DataChanged>("data:changed", () => scheduleReload());
+        unlisten = await listen<tauri.DataChanged>("data:changed", () => scheduleReload());
       } catch (e) {
         logger.error("Startup failed", e);
       }
@@ -5,12 +5,6 @@ import logger from "../lib/logger";
 
 export type { Transaction, Debt, FinanceSummary } from "../lib/tauri";
 
-type DataChanged = {
-    entity: string;
-    action: string;
-    id?: string | null;
-};
-
 export function useFinance() {
     const [finance, setFinance] = useState<tauri.FinanceSummary>({ transactions: [], debts: [] });
     const [isLoaded, setIsLoaded] = useState(false);
@@ -37,7 +31,7 @@ export function useFinance() {
         (async () => {
             try {
                 await refreshFinance();
-                unlisten = await listen<DataChanged>("data:changed", (event) => {
+                unlisten = await listen<tauri.DataChanged>("data:changed", (event) => {
                     if (event.payload.entity === "finance") {
                         refreshFinance();
                     }
@@ -3,6 +3,7 @@ import { invoke } from "@tauri-apps/api/core";
 export type Priority = "low" | "normal" | "high";
 export type Status = "todo" | "doing" | "done";
 export type RepeatMode = "daily" | "weekdays" | "custom";
+export type Agenda = "today" | "upcoming" | "anytime";
 
 export type DbHealth = {
   db_path: string;
@@ -51,6 +52,18 @@ export type Task = {
   is_archived: boolean;
   sort_order: number;
   subtasks: Subtask[];
+
+  section_id?: string | null;
+
+  // dependencies
+  depends_on: string[]; // ids of tasks that must be done first
+  is_blocked: boolean; // some task in depends_on is not done yet
+
+  updated_at: number; // ms
+  revision: number; // bumped on every change
+
+  scheduled_at?: string | null; // "do on", local "YYYY-MM-DD" or "YYYY-MM-DDTHH:MM"
+  agenda?: Agenda | null; // null once done or archived
 };
 
 export type NewTask = {
@@ -70,6 +83,9 @@ export type NewTask = {
 
   repeat_mode?: RepeatMode | null;
   repeat_days_mask?: number | null;
+
+  section_id?: string | null; // must belong to project_id
+  scheduled_at?: string | null; // see Task.scheduled_at
 };
 
 export type Project = {
@@ -80,6 +96,65 @@ export type Project = {
   created_at: number; // ms
   parent_id?: string | null; // For folder hierarchy
   is_folder: boolean; // True if this is a folder, false if regular project
+  sort_order: number;
+  description?: string | null;
+  icon?: string | null; // emoji or icon name
+  archived_at?: number | null; // ms
+  default_priority?: Priority | null; // applied to new tasks in this project
+  default_tags: string[];
+};
+
+// Heading that groups tasks inside a project
+export type Section = {
+  id: string;
+  project_id: string;
+  name: string;
+  sort_order: number;
+  archived_at?: number | null; // ms
+  created_at: number; // ms
+};
+
+// Kanban board; each column maps to a status, so moving a card changes it
+export type BoardColumn = {
+  id: string;
+  name: string;
+  category: Status;
+  wip_limit?: number | null;
+  sort_order: number;
+};
+
+export type BoardCard = {
+  task_id: string;
+  column_id: string;
+  position: number;
+};
+
+export type Board = {
+  id: string;
+  project_id: string;
+  name: string;
+  created_at: number; // ms
+  columns: BoardColumn[];
+  cards: BoardCard[];
+};
+
+// Columns without an id are created; columns left out are removed
+export type BoardColumnInput = {
+  id?: string | null;
+  name: string;
+  category: Status;
+  wip_limit?: number | null;
+};
+
+// Omitted fields are left unchanged; null clears the nullable ones
+export type ProjectPatch = {
+  name?: string;
+  color?: string;
+  priority?: Priority;
+  description?: string | null;
+  icon?: string | null;
+  default_priority?: Priority | null;
+  default_tags?: string[];
 };
 
 export type UserStats = {
@@ -99,6 +174,84 @@ export type UserStats = {
   points: number;
 };
 
+// measured against the goal in effect on that day
+export type DailyActivity = {
+  day: string; // YYYY-MM-DD
+  tasks_done: number;
+  focus_minutes: number;
+  goal_tasks: number;
+  goal_focus_minutes: number;
+  goal_met: boolean;
+  frozen: boolean; // keeps a streak going without counting toward it
+};
+
+export type StreakStatus = {
+  current: number;
+  best: number;
+  today: DailyActivity;
+  freezes_left: number; // this month
+};
+
+export type GamificationRules = {
+  complete_low: number;
+  complete_normal: number;
+  complete_high: number;
+  on_time_bonus: number; // extra for completing by the deadline
+  focus_minute: number; // per minute of a finished focus session
+  streak_day: number; // first completion of each day that continues a streak
+  level_thresholds: number[]; // points at which level 2, 3, ... start
+};
+
+export type PointsEntry = {
+  id: number;
+  event: "complete_low" | "complete_normal" | "complete_high" | "on_time" | "focus" | "streak_day";
+  ref_id?: string | null; // task, focus session or day
+  quantity: number; // focus minutes, streak length, else 1
+  points: number;
+  created_at: number;
+};
+
+export type Achievement = {
+  id: string;
+  name: string;
+  description: string;
+  unlocked_at?: number | null;
+};
+
+export type GamificationStatus = {
+  points: number;
+  level: number;
+  level_start: number;
+  next_level_at?: number | null; // null at the top level
+  achievements: Achievement[];
+};
+
+// ratios are actual / estimated minutes of done tasks; above 1 took longer
+export type EstimateAccuracy = {
+  key?: string | null; // project id, tag or priority; null for Inbox / untagged
+  label: string;
+  tasks: number;
+  median_ratio: number;
+  underestimated: number;
+  median_underestimate?: number | null; // actual / estimate
+  overestimated: number;
+  median_overestimate?: number | null; // estimate / actual
+};
+
+export type EstimateReport = {
+  overall: EstimateAccuracy;
+  by_project: EstimateAccuracy[];
+  by_tag: EstimateAccuracy[];
+  by_priority: EstimateAccuracy[];
+};
+
+export type EstimateCorrection = {
+  project_id?: string | null;
+  factor: number; // multiply new estimates by this
+  samples: number;
+  from_project: boolean; // false when all tasks were used
+};
+
 export type AppSettings = {
   pomodoro_length: number;
   short_break_length: number;
@@ -112,6 +265,117 @@ export type AppSettings = {
   close_to_tray: boolean;
 
   reminder_lead_minutes: number;
+  // days before trashed items are purged; 0 = never
+  trash_retention_days: number;
+  // with pomodoro_length, the focus time a daily plan is measured against
+  daily_pomodoros: number;
+  // working hours for auto-scheduling, in minutes after local midnight
+  work_start_minutes: number;
+  work_end_minutes: number;
+  work_days_mask: number; // bit 0 = Monday
+  // a day meets the goal with this many tasks or focus minutes; 0 = unused
+  daily_goal_tasks: number;
+  daily_goal_focus_minutes: number;
+  streak_freezes_per_month: number;
+};
+
+export type ApiSettings = {
+  enabled: boolean;
+  port: number;
+  token: string;
+};
+
+export type HookEvent =
+  | "task.created"
+  | "task.completed"
+  | "reminder.fired"
+  | "focus.finished"
+  | "debt.paid"
+  | "achievement.unlocked";
+
+export type Hook = {
+  id: string;
+  event: HookEvent | "*";
+  kind: "http" | "command";
+  target: string;
+  enabled: boolean;
+  created_at: number; // ms
+};
+
+export type HookDelivery = {
+  id: string;
+  hook_id: string;
+  event: string;
+  payload: string;
+  attempts: number;
+  success: boolean;
+  status_code: number | null;
+  error: string | null;
+  duration_ms: number;
+  created_at: number; // ms
+};
+
+/** Position of a task or subtask, as sent by "reorder" events. */
+export type SortOrder = { id: string; sort_order: number };
+
+/** Batch events ("bulk", "reorder") send one snapshot per id, `null` where
+ * the item doesn't exist on that side of the change. */
+type Batch<T> = (T | null)[];
+
+/** What `old`/`new` hold for each entity of a `data:changed` event. Entities
+ * mapped to `never` only send "refresh"-style events without snapshots. */
+export type ChangeSnapshots = {
+  tasks: Task | Batch<Task> | SortOrder[];
+  subtasks: Subtask | SortOrder[];
+  projects: Project | Batch<Project>;
+  sections: Section | Batch<Section>;
+  boards: Board;
+  hooks: Hook;
+  templates: Template;
+  time_blocks: TimeBlock;
+  time_entries: TimeEntry;
+  daily_activity: DailyActivity;
+  daily_plan: DailyPlan;
+  finance: Transaction | Debt;
+  achievements: Achievement;
+  settings: AppSettings;
+  api_settings: ApiSettings;
+  gamification_rules: GamificationRules;
+  stats: never;
+  tags: never;
+  trash: never;
+};
+
+export type ChangeEntity = keyof ChangeSnapshots;
+
+/** Payload of the `data:changed` event, narrowed by `entity`. `seq` increases
+ * by 1 per event; a gap means an event was missed and data should be
+ * reloaded. */
+export type DataChanged = {
+  [E in ChangeEntity]: {
+    seq: number;
+    entity: E;
+    action: string;
+    id: string | null;
+    old: ChangeSnapshots[E] | null;
+    new: ChangeSnapshots[E] | null;
+  };
+}[ChangeEntity];
+
+export type ProjectNode = Project & {
+  task_count: number;
+  open_task_count: number;
+  total_task_count: number; // including descendants
+  children: ProjectNode[];
+};
+
+export type ProjectDeletePolicy = "reparent" | "cascade" | "inbox";
+
+export type ProjectDeleteSummary = {
+  deleted_project_ids: string[];
+  reparented_project_ids: string[];
+  deleted_task_ids: string[];
+  moved_task_ids: string[];
 };
 
 export type ExportBundle = {
@@ -141,6 +405,11 @@ export function get_completion_series(days: number) {
   return invoke<CompletionDay[]>("get_completion_series", { days });
 }
 
+// ---- Change events ----
+export function get_change_seq() {
+  return invoke<number>("get_change_seq");
+}
+
 // ---- Settings ----
 export function get_settings() {
   return invoke<AppSettings>("get_settings");
@@ -150,6 +419,44 @@ export function save_settings(settings: AppSettings) {
   return invoke<void>("save_settings", { settings });
 }
 
+// ---- Local API ----
+export function get_api_settings() {
+  return invoke<ApiSettings>("get_api_settings");
+}
+
+export function save_api_settings(enabled: boolean, port: number) {
+  return invoke<ApiSettings>("save_api_settings", { enabled, port });
+}
+
+export function regenerate_api_token() {
+  return invoke<ApiSettings>("regenerate_api_token");
+}
+
+// ---- Hooks ----
+export function get_hooks() {
+  return invoke<Hook[]>("get_hooks");
+}
+
+export function add_hook(event: Hook["event"], kind: Hook["kind"], target: string) {
+  return invoke<Hook>("add_hook", { event, kind, target });
+}
+
+export function set_hook_enabled(id: string, enabled: boolean) {
+  return invoke<void>("set_hook_enabled", { id, enabled });
+}
+
+export function delete_hook(id: string) {
+  return invoke<void>("delete_hook", { id });
+}
+
+export function get_hook_deliveries(hookId?: string, limit?: number) {
+  return invoke<HookDelivery[]>("get_hook_deliveries", { hookId, limit });
+}
+
+export function test_hook(id: string) {
+  return invoke<void>("test_hook", { id });
+}
+
 // ---- Reminders ----
 export function set_task_remind_at(id: string, remindAt: number | null) {
   return invoke<void>("set_task_remind_at", { id, remindAt });
@@ -164,14 +471,20 @@ export function get_tasks(args: {
   limit?: number | null;
   statusFilter?: number | null;
   projectFilter?: string | null;
+  tagFilter?: string | null;
 }) {
   return invoke<Task[]>("get_tasks", {
     limit: args.limit ?? null,
     statusFilter: args.statusFilter ?? null,
     projectFilter: args.projectFilter ?? null,
+    tagFilter: args.tagFilter ?? null,
   });
 }
 
+export function get_agenda(agenda: Agenda) {
+  return invoke<Task[]>("get_agenda", { agenda });
+}
+
 export function add_task(newTask: NewTask) {
   return invoke<Task>("add_task", { newTask });
 }
@@ -200,13 +513,375 @@ export function update_task_status(taskId: string, newStatus: Status) {
   return invoke<void>("update_task_status", { taskId, newStatus });
 }
 
+// missing fields are left alone, null clears; a section implies its project
+export type TaskPatch = {
+  title?: string;
+  description?: string | null;
+  project_id?: string | null;
+  section_id?: string | null;
+  priority?: Priority;
+  status?: Status;
+  deadline?: number | null;
+  remind_at?: number | null;
+  scheduled_at?: string | null;
+  estimated_minutes?: number | null;
+  actual_minutes?: number | null;
+  tags?: string[];
+  repeat_mode?: RepeatMode | null;
+  repeat_days_mask?: number | null;
+  expected_revision?: number; // rejected with "Revision conflict" if stale
+};
+
+export function update_task(taskId: string, patch: TaskPatch) {
+  return invoke<Task>("update_task", { taskId, patch });
+}
+
 export function delete_task(taskId: string) {
   return invoke<void>("delete_task", { taskId });
 }
 
+export function duplicate_task(taskId: string) {
+  return invoke<Task>("duplicate_task", { taskId });
+}
+
+export function demote_task_to_subtask(taskId: string, parentId: string) {
+  return invoke<Subtask>("demote_task_to_subtask", { taskId, parentId });
+}
+
+// missing fields are left alone; project_id: null moves to Inbox
+export type TaskBulkPatch = {
+  status?: Status;
+  project_id?: string | null;
+  priority?: Priority;
+  deadline_shift_minutes?: number;
+  add_tags?: string[];
+  remove_tags?: string[];
+  archived?: boolean;
+  delete?: boolean;
+};
+
+// all-or-nothing: applied is false when any id has an error
+export type BulkUpdateResult = {
+  applied: boolean;
+  results: { id: string; error: string | null; spawned_task_id: string | null }[];
+};
+
+export function bulk_update_tasks(taskIds: string[], patch: TaskBulkPatch) {
+  return invoke<BulkUpdateResult>("bulk_update_tasks", { taskIds, patch });
+}
+
+// one row per creation / changed field; values are the stored text
+// (status and priority as numbers)
+export type TaskEvent = {
+  id: number;
+  task_id: string;
+  action: "created" | "updated";
+  field: string | null;
+  old_value: string | null;
+  new_value: string | null;
+  created_at: number;
+};
+
+export function get_task_history(taskId: string) {
+  return invoke<TaskEvent[]>("get_task_history", { taskId });
+}
+
+export function link_tasks(taskId: string, dependsOnId: string) {
+  return invoke<void>("link_tasks", { taskId, dependsOnId });
+}
+
+export function unlink_tasks(taskId: string, dependsOnId: string) {
+  return invoke<void>("unlink_tasks", { taskId, dependsOnId });
+}
+
+// ---- Tags ----
+// open_count leaves out done and archived tasks
+export type Tag = {
+  name: string;
+  color?: string | null;
+  task_count: number;
+  open_count: number;
+};
+
+export function get_tags() {
+  return invoke<Tag[]>("get_tags");
+}
+
+// fails if newName is taken; use merge_tags for that
+export function rename_tag(name: string, newName: string) {
+  return invoke<Tag>("rename_tag", { name, newName });
+}
+
+export function merge_tags(sources: string[], target: string) {
+  return invoke<Tag>("merge_tags", { sources, target });
+}
+
+export function set_tag_color(name: string, color: string | null) {
+  return invoke<Tag>("set_tag_color", { name, color });
+}
+
+export function delete_tag(name: string) {
+  return invoke<void>("delete_tag", { name });
+}
+
+// ---- Templates ----
+// dates are minutes from the anchor given when instantiating
+export type TemplateTask = {
+  title: string;
+  description?: string | null;
+  priority: Priority;
+  tags: string[];
+  estimated_minutes?: number | null;
+  repeat_mode?: RepeatMode | null;
+  repeat_days_mask?: number | null;
+  deadline_offset_minutes?: number | null;
+  remind_offset_minutes?: number | null;
+  subtasks: string[]; // titles
+  section?: string | null; // section name, project templates only
+  section_index?: number | null; // position in TemplateProject.sections
+};
+
+export type TemplateProject = {
+  name: string;
+  color: string;
+  priority: Priority;
+  description?: string | null;
+  icon?: string | null;
+  default_priority?: Priority | null;
+  default_tags: string[];
+  sections: string[];
+};
+
+export type Template = { id: string; name: string; created_at: number } & (
+  | { kind: "task"; task: TemplateTask }
+  | { kind: "project"; project: TemplateProject; tasks: TemplateTask[] }
+);
+
+// project_id places a task template's task, parent_id a project template's project
+export type TemplateInstance = {
+  anchor?: number | null; // ms, defaults to now
+  name?: string | null;
+  project_id?: string | null;
+  parent_id?: string | null;
+};
+
+export type InstantiatedTemplate = { project_id: string | null; task_ids: string[] };
+
+export function get_templates() {
+  return invoke<Template[]>("get_templates");
+}
+
+// anchor defaults to the task's deadline
+export function save_task_as_template(taskId: string, name: string, anchor: number | null = null) {
+  return invoke<Template>("save_task_as_template", { taskId, name, anchor });
+}
+
+// anchor defaults to the latest task deadline
+export function save_project_as_template(projectId: string, name: string, anchor: number | null = null) {
+  return invoke<Template>("save_project_as_template", { projectId, name, anchor });
+}
+
+export function instantiate_template(templateId: string, options: TemplateInstance = {}) {
+  return invoke<InstantiatedTemplate>("instantiate_template", { templateId, options });
+}
+
+export function delete_template(templateId: string) {
+  return invoke<void>("delete_template", { templateId });
+}
+
+// ---- Daily plan ----
+// dates are local "YYYY-MM-DD"; commands default to today
+export type DailyPlan = {
+  date: string;
+  tasks: Task[];
+  planned_minutes: number; // estimates of every planned task
+  remaining_minutes: number; // estimates of the unfinished ones
+  unestimated_count: number; // unfinished tasks without an estimate
+  available_minutes: number; // pomodoro_length * daily_pomodoros
+  focused_minutes: number; // completed focus sessions that day
+};
+
+export function get_daily_plan(date?: string) {
+  return invoke<DailyPlan>("get_daily_plan", { date: date ?? null });
+}
+
+export function add_to_daily_plan(taskIds: string[], date?: string) {
+  return invoke<DailyPlan>("add_to_daily_plan", { taskIds, date: date ?? null });
+}
+
+export function remove_from_daily_plan(taskId: string, date?: string) {
+  return invoke<DailyPlan>("remove_from_daily_plan", { taskId, date: date ?? null });
+}
+
+export function reorder_daily_plan(taskIds: string[], date?: string) {
+  return invoke<DailyPlan>("reorder_daily_plan", { taskIds, date: date ?? null });
+}
+
+// moves unfinished tasks to `to` (the next day); returns their ids
+export function carry_over_daily_plan(from?: string, to?: string) {
+  return invoke<string[]>("carry_over_daily_plan", { from: from ?? null, to: to ?? null });
+}
+
+// ---- Time blocks ----
+// blocks never overlap; times are ms
+export type TimeBlock = {
+  id: string;
+  task_id?: string | null;
+  title: string; // the task's title unless set
+  start_at: number;
+  end_at: number;
+  completed_at?: number | null;
+  created_at: number;
+};
+
+export type TimeBlockCompletion = {
+  block: TimeBlock;
+  focus_session_id?: string | null;
+};
+
+export type AutoScheduleResult = {
+  blocks: TimeBlock[];
+  unscheduled_task_ids: string[]; // no free slot within the horizon
+};
+
+export function get_time_blocks(from: number, to: number) {
+  return invoke<TimeBlock[]>("get_time_blocks", { from, to });
+}
+
+export function add_time_block(args: {
+  taskId?: string | null;
+  title?: string | null;
+  startAt: number;
+  endAt: number;
+}) {
+  return invoke<TimeBlock>("add_time_block", {
+    taskId: args.taskId ?? null,
+    title: args.title ?? null,
+    startAt: args.startAt,
+    endAt: args.endAt,
+  });
+}
+
+export function move_time_block(id: string, startAt: number) {
+  return invoke<TimeBlock>("move_time_block", { id, startAt });
+}
+
+export function resize_time_block(id: string, startAt: number, endAt: number) {
+  return invoke<TimeBlock>("resize_time_block", { id, startAt, endAt });
+}
+
+export function delete_time_block(id: string) {
+  return invoke<void>("delete_time_block", { id });
+}
+
+export function complete_time_block(id: string, startFocus = false) {
+  return invoke<TimeBlockCompletion>("complete_time_block", { id, startFocus });
+}
+
+// fills free working hours over the next `days` days (default 7)
+export function auto_schedule(days?: number) {
+  return invoke<AutoScheduleResult>("auto_schedule", { days: days ?? null });
+}
+
+// ---- Time tracking ----
+// days are local "YYYY-MM-DD", inclusive; times are ms
+export type TimeEntry = {
+  id: string;
+  task_id: string;
+  task_title: string;
+  note?: string | null;
+  started_at: number;
+  ended_at?: number | null; // null while the timer runs
+  minutes: number; // up to now while running
+  created_at: number;
+};
+
+// omitted fields are left alone; note: null clears the note
+export type TimeEntryPatch = {
+  task_id?: string;
+  note?: string | null;
+  started_at?: number;
+  ended_at?: number; // stops a running entry
+};
+
+export type TimeRollupGroup = "task" | "project" | "tag" | "day";
+
+export type TimeRollup = {
+  key?: string | null; // null for no project / no tags
+  label: string;
+  minutes: number;
+  entries: number;
+};
+
+export type TimesheetEntry = {
+  source: "time_entry" | "focus_session";
+  id: string;
+  task_id: string;
+  task_title: string;
+  project_id?: string | null;
+  project_name?: string | null;
+  tags: string[];
+  note?: string | null;
+  started_at: number;
+  ended_at: number;
+  minutes: number; // focused minutes for focus sessions
+};
+
+export function get_time_entries(from: string, to: string) {
+  return invoke<TimeEntry[]>("get_time_entries", { from, to });
+}
+
+export function get_running_timer() {
+  return invoke<TimeEntry | null>("get_running_timer");
+}
+
+// fails while another timer runs
+export function start_timer(taskId: string) {
+  return invoke<TimeEntry>("start_timer", { taskId });
+}
+
+export function stop_timer() {
+  return invoke<TimeEntry>("stop_timer");
+}
+
+export function add_time_entry(args: {
+  taskId: string;
+  startedAt: number;
+  endedAt: number;
+  note?: string | null;
+}) {
+  return invoke<TimeEntry>("add_time_entry", {
+    taskId: args.taskId,
+    startedAt: args.startedAt,
+    endedAt: args.endedAt,
+    note: args.note ?? null,
+  });
+}
+
+export function update_time_entry(id: string, patch: TimeEntryPatch) {
+  return invoke<TimeEntry>("update_time_entry", { id, patch });
+}
+
+export function delete_time_entry(id: string) {
+  return invoke<void>("delete_time_entry", { id });
+}
+
+export function get_time_rollup(from: string, to: string, group: TimeRollupGroup) {
+  return invoke<TimeRollup[]>("get_time_rollup", { from, to, group });
+}
+
+// finished time entries and focus sessions
+export function get_timesheet(from: string, to: string) {
+  return invoke<TimesheetEntry[]>("get_timesheet", { from, to });
+}
+
+export function export_timesheet(from: string, to: string, format: "csv" | "json") {
+  return invoke<string>("export_timesheet", { from, to, format });
+}
+
 // ---- Projects ----
-export function get_projects() {
-  return invoke<Project[]>("get_projects");
+export function get_projects(includeArchived = false) {
+  return invoke<Project[]>("get_projects", { includeArchived });
 }
 
 export function add_project(
@@ -227,8 +902,90 @@ export function update_project_priority(id: string, priority: Priority) {
   return invoke<void>("update_project_priority", { id, priority });
 }
 
-export function delete_project(id: string) {
-  return invoke<void>("delete_project", { id });
+export function update_project_details(id: string, patch: ProjectPatch) {
+  return invoke<Project>("update_project_details", { id, patch });
+}
+
+export function reorder_projects(projectIds: string[]) {
+  return invoke<void>("reorder_projects", { projectIds });
+}
+
+export function archive_project(id: string) {
+  return invoke<string[]>("archive_project", { id });
+}
+
+export function unarchive_project(id: string) {
+  return invoke<string[]>("unarchive_project", { id });
+}
+
+export function delete_project(id: string, policy?: ProjectDeletePolicy) {
+  return invoke<ProjectDeleteSummary>("delete_project", { id, policy });
+}
+
+export function get_project_tree() {
+  return invoke<ProjectNode[]>("get_project_tree");
+}
+
+export function move_project(id: string, parentId: string | null) {
+  return invoke<void>("move_project", { id, parentId });
+}
+
+// ---- Sections ----
+export function get_sections(projectId: string, includeArchived = false) {
+  return invoke<Section[]>("get_sections", { projectId, includeArchived });
+}
+
+export function add_section(projectId: string, name: string) {
+  return invoke<Section>("add_section", { projectId, name });
+}
+
+export function rename_section(id: string, name: string) {
+  return invoke<Section>("rename_section", { id, name });
+}
+
+export function reorder_sections(sectionIds: string[]) {
+  return invoke<void>("reorder_sections", { sectionIds });
+}
+
+export function archive_section(id: string) {
+  return invoke<string[]>("archive_section", { id });
+}
+
+export function unarchive_section(id: string) {
+  return invoke<string[]>("unarchive_section", { id });
+}
+
+export function move_task_to_section(taskId: string, sectionId: string | null) {
+  return invoke<Task>("move_task_to_section", { taskId, sectionId });
+}
+
+// ---- Boards ----
+export function get_boards(projectId: string) {
+  return invoke<Board[]>("get_boards", { projectId });
+}
+
+export function get_board(id: string) {
+  return invoke<Board>("get_board", { id });
+}
+
+export function create_board(projectId: string, name: string, columns?: BoardColumnInput[]) {
+  return invoke<Board>("create_board", { projectId, name, columns });
+}
+
+export function rename_board(id: string, name: string) {
+  return invoke<Board>("rename_board", { id, name });
+}
+
+export function save_board_columns(boardId: string, columns: BoardColumnInput[]) {
+  return invoke<Board>("save_board_columns", { boardId, columns });
+}
+
+export function delete_board(id: string) {
+  return invoke<void>("delete_board", { id });
+}
+
+export function move_card(boardId: string, taskId: string, columnId: string, position?: number) {
+  return invoke<Board>("move_card", { boardId, taskId, columnId, position });
 }
 
 // ---- Stats ----
@@ -236,6 +993,49 @@ export function get_stats() {
   return invoke<UserStats>("get_stats");
 }
 
+export function get_estimate_accuracy() {
+  return invoke<EstimateReport>("get_estimate_accuracy");
+}
+
+export function get_estimate_correction(projectId?: string | null) {
+  return invoke<EstimateCorrection>("get_estimate_correction", { projectId: projectId ?? null });
+}
+
+// ---- Streaks ----
+export function get_streak() {
+  return invoke<StreakStatus>("get_streak");
+}
+
+// recorded days only, oldest first
+export function get_daily_activity(from: string, to: string) {
+  return invoke<DailyActivity[]>("get_daily_activity", { from, to });
+}
+
+export function freeze_streak_day(day: string) {
+  return invoke<DailyActivity>("freeze_streak_day", { day });
+}
+
+export function unfreeze_streak_day(day: string) {
+  return invoke<DailyActivity>("unfreeze_streak_day", { day });
+}
+
+// ---- Gamification ----
+export function get_gamification() {
+  return invoke<GamificationStatus>("get_gamification");
+}
+
+export function get_points_ledger(limit?: number) {
+  return invoke<PointsEntry[]>("get_points_ledger", { limit: limit ?? null });
+}
+
+export function get_gamification_rules() {
+  return invoke<GamificationRules>("get_gamification_rules");
+}
+
+export function save_gamification_rules(rules: GamificationRules) {
+  return invoke<void>("save_gamification_rules", { rules });
+}
+
 // ---- Focus ----
 export function start_focus_session(taskId: string) {
   return invoke<string>("start_focus_session", { taskId });
@@ -266,6 +1066,10 @@ export function delete_subtask(id: string) {
   return invoke<void>("delete_subtask", { id });
 }
 
+export function promote_subtask_to_task(id: string) {
+  return invoke<Task>("promote_subtask_to_task", { id });
+}
+
 export function reorder_subtasks(subtaskIds: string[]) {
   return invoke<void>("reorder_subtasks", { subtaskIds });
 }
@@ -284,6 +1088,52 @@ export function reorder_tasks(taskIds: string[]) {
   return invoke<void>("reorder_tasks", { taskIds });
 }
 
+// ---- Trash ----
+export type TrashEntity = "task" | "subtask" | "project" | "transaction" | "debt";
+
+export type TrashItem = {
+  entity: TrashEntity;
+  id: string;
+  title: string;
+  deleted_at: number;
+  parent_id: string | null;
+};
+
+export function get_trash() {
+  return invoke<TrashItem[]>("get_trash");
+}
+
+export function restore_from_trash(entity: TrashEntity, id: string) {
+  return invoke<TrashItem[]>("restore_from_trash", { entity, id });
+}
+
+export function purge_trash_item(entity: TrashEntity, id: string) {
+  return invoke<void>("purge_trash_item", { entity, id });
+}
+
+export function empty_trash() {
+  return invoke<number>("empty_trash");
+}
+
+// ---- Undo ----
+// labels of the steps undo/redo would apply next; null when there is none
+export type UndoStatus = {
+  undo: string | null;
+  redo: string | null;
+};
+
+export function get_undo_status() {
+  return invoke<UndoStatus>("get_undo_status");
+}
+
+export function undo() {
+  return invoke<string | null>("undo");
+}
+
+export function redo() {
+  return invoke<string | null>("redo");
+}
+
 // ---- Window ----
 export function toggle_window() {
   return invoke<void>("toggle_window");
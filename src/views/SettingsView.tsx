@@ -64,6 +64,14 @@ export default function SettingsView({
       start_minimized: false,
       close_to_tray: true,
       reminder_lead_minutes: 30,
+      trash_retention_days: 30,
+      daily_pomodoros: 8,
+      work_start_minutes: 9 * 60,
+      work_end_minutes: 17 * 60,
+      work_days_mask: 0b0011111, // Monday to Friday
+      daily_goal_tasks: 1,
+      daily_goal_focus_minutes: 0,
+      streak_freezes_per_month: 2,
     });
   };
 
@@ -281,6 +289,25 @@ export default function SettingsView({
           </label>
         </section>
 
+        <section className="bg-[#0f172a]/80 rounded-2xl border border-white/5 p-5">
+          <h2 className="text-white font-bold mb-4">Trash</h2>
+
+          <label className="space-y-2 block">
+            <div className="text-sm text-slate-400">Keep deleted items for (days)</div>
+            <input
+              type="number"
+              min={0}
+              max={365}
+              value={draft.trash_retention_days}
+              onChange={setNum("trash_retention_days", 0, 365)}
+              className="w-full bg-slate-900 border border-slate-700 rounded-lg px-3 py-2 text-white outline-none focus:border-indigo-500"
+            />
+            <div className="text-xs text-slate-500">
+              Deleted tasks, projects and transactions can be restored until then. 0 keeps them forever.
+            </div>
+          </label>
+        </section>
+
         <section className="bg-[#0f172a]/80 rounded-2xl border border-white/5 p-5">
           <h2 className="text-white font-bold mb-4">Behavior</h2>
 